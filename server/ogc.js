@@ -0,0 +1,306 @@
+// A stand-in for an OGC imagery server, to try the WMS and WMTS providers
+// against without one. It draws a 10 degree checkerboard with the equator and
+// prime meridian in red, so misplaced or misprojected imagery stands out. WMTS
+// tiles are tinted by level.
+//
+// Only uses Node's built-in modules, so it runs without `npm install`:
+//
+//   node ./server/ogc.js
+//
+// then e.g. `viewer.addWmsImagery("http://localhost:3001/wms?SERVICE=WMS&REQUEST=GetCapabilities", "checkerboard")`
+// or `viewer.addWmtsImagery("http://localhost:3001/wmts?SERVICE=WMTS&REQUEST=GetCapabilities", "checkerboard")`.
+
+const http = require("http");
+const zlib = require("zlib");
+
+const port = Number(process.env.PORT) || 3001;
+
+const EARTH_RADIUS = 6378137;
+const MERCATOR_EXTENT = Math.PI * EARTH_RADIUS;
+const TILE_SIZE = 256;
+const MAX_LEVEL = 12;
+// scale denominators of level 0, for 0.28 mm pixels
+const CRS84_SCALE = 279541132.0143589;
+const MERCATOR_SCALE = 559082264.0287178;
+
+const LEVEL_TINTS = [
+  [255, 140, 0],
+  [0, 170, 255],
+  [170, 0, 255],
+  [0, 200, 90],
+];
+
+// Color of the checkerboard at a longitude and latitude in degrees.
+function color(lon, lat, tint) {
+  if (Math.abs(lat) < 0.3 || Math.abs(lon) < 0.3) {
+    return [255, 0, 0, 255];
+  }
+  const cell = Math.floor((lon + 180) / 10) + Math.floor((lat + 90) / 10);
+  return cell % 2 === 0 ? [...tint, 170] : [0, 0, 0, 0];
+}
+
+// Renders `width` x `height` pixels, `toLonLat` mapping pixel centers.
+function render(width, height, toLonLat, tint) {
+  const rows = Buffer.alloc((width * 4 + 1) * height);
+  for (let y = 0; y < height; y++) {
+    const row = y * (width * 4 + 1);
+    rows[row] = 0; // no filter
+    for (let x = 0; x < width; x++) {
+      const [lon, lat] = toLonLat(x + 0.5, y + 0.5);
+      const rgba = color(lon, lat, tint);
+      rows.set(rgba, row + 1 + x * 4);
+    }
+  }
+  return png(width, height, rows);
+}
+
+function mercatorToLonLat(x, y) {
+  const lon = (x / EARTH_RADIUS) * (180 / Math.PI);
+  const lat = (2 * Math.atan(Math.exp(y / EARTH_RADIUS)) - Math.PI / 2) * (180 / Math.PI);
+  return [lon, lat];
+}
+
+const CRC_TABLE = Array.from({ length: 256 }, (_, n) => {
+  let c = n;
+  for (let k = 0; k < 8; k++) {
+    c = c & 1 ? 0xedb88320 ^ (c >>> 1) : c >>> 1;
+  }
+  return c >>> 0;
+});
+
+function crc32(buffer) {
+  let c = 0xffffffff;
+  for (const byte of buffer) {
+    c = CRC_TABLE[(c ^ byte) & 0xff] ^ (c >>> 8);
+  }
+  return (c ^ 0xffffffff) >>> 0;
+}
+
+function chunk(type, data) {
+  const length = Buffer.alloc(4);
+  length.writeUInt32BE(data.length);
+  const body = Buffer.concat([Buffer.from(type, "ascii"), data]);
+  const crc = Buffer.alloc(4);
+  crc.writeUInt32BE(crc32(body));
+  return Buffer.concat([length, body, crc]);
+}
+
+// An 8-bit RGBA PNG from filtered scanlines.
+function png(width, height, rows) {
+  const header = Buffer.alloc(13);
+  header.writeUInt32BE(width, 0);
+  header.writeUInt32BE(height, 4);
+  header[8] = 8; // bit depth
+  header[9] = 6; // RGBA
+  return Buffer.concat([
+    Buffer.from([0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a]),
+    chunk("IHDR", header),
+    chunk("IDAT", zlib.deflateSync(rows)),
+    chunk("IEND", Buffer.alloc(0)),
+  ]);
+}
+
+function wmsCapabilities(base) {
+  return `<?xml version="1.0" encoding="UTF-8"?>
+<WMS_Capabilities version="1.3.0" xmlns="http://www.opengis.net/wms" xmlns:xlink="http://www.w3.org/1999/xlink">
+  <Service><Name>WMS</Name><Title>Hypersphere stand-in</Title></Service>
+  <Capability>
+    <Request>
+      <GetCapabilities><Format>text/xml</Format></GetCapabilities>
+      <GetMap>
+        <Format>image/png</Format>
+        <DCPType><HTTP><Get><OnlineResource xlink:type="simple" xlink:href="${base}/wms?"/></Get></HTTP></DCPType>
+      </GetMap>
+    </Request>
+    <Layer>
+      <Title>Stand-in layers</Title>
+      <CRS>CRS:84</CRS>
+      <CRS>EPSG:4326</CRS>
+      <CRS>EPSG:3857</CRS>
+      <EX_GeographicBoundingBox>
+        <westBoundLongitude>-180</westBoundLongitude>
+        <eastBoundLongitude>180</eastBoundLongitude>
+        <southBoundLatitude>-90</southBoundLatitude>
+        <northBoundLatitude>90</northBoundLatitude>
+      </EX_GeographicBoundingBox>
+      <Layer queryable="0">
+        <Name>checkerboard</Name>
+        <Title>10 degree checkerboard</Title>
+      </Layer>
+    </Layer>
+  </Capability>
+</WMS_Capabilities>`;
+}
+
+function tileMatrixSet(identifier, crs, topLeft, scale, widthAtZero) {
+  const matrices = [];
+  for (let level = 0; level <= MAX_LEVEL; level++) {
+    matrices.push(`      <TileMatrix>
+        <ows:Identifier>${level}</ows:Identifier>
+        <ScaleDenominator>${scale / 2 ** level}</ScaleDenominator>
+        <TopLeftCorner>${topLeft}</TopLeftCorner>
+        <TileWidth>${TILE_SIZE}</TileWidth>
+        <TileHeight>${TILE_SIZE}</TileHeight>
+        <MatrixWidth>${widthAtZero * 2 ** level}</MatrixWidth>
+        <MatrixHeight>${2 ** level}</MatrixHeight>
+      </TileMatrix>`);
+  }
+  return `    <TileMatrixSet>
+      <ows:Identifier>${identifier}</ows:Identifier>
+      <ows:SupportedCRS>${crs}</ows:SupportedCRS>
+${matrices.join("\n")}
+    </TileMatrixSet>`;
+}
+
+function wmtsCapabilities(base) {
+  return `<?xml version="1.0" encoding="UTF-8"?>
+<Capabilities version="1.0.0" xmlns="http://www.opengis.net/wmts/1.0" xmlns:ows="http://www.opengis.net/ows/1.1" xmlns:xlink="http://www.w3.org/1999/xlink">
+  <ows:OperationsMetadata>
+    <ows:Operation name="GetTile">
+      <ows:DCP><ows:HTTP>
+        <ows:Get xlink:href="${base}/wmts?">
+          <ows:Constraint name="GetEncoding"><ows:AllowedValues><ows:Value>KVP</ows:Value></ows:AllowedValues></ows:Constraint>
+        </ows:Get>
+      </ows:HTTP></ows:DCP>
+    </ows:Operation>
+  </ows:OperationsMetadata>
+  <Contents>
+    <Layer>
+      <ows:Title>10 degree checkerboard</ows:Title>
+      <ows:WGS84BoundingBox>
+        <ows:LowerCorner>-180 -90</ows:LowerCorner>
+        <ows:UpperCorner>180 90</ows:UpperCorner>
+      </ows:WGS84BoundingBox>
+      <ows:Identifier>checkerboard</ows:Identifier>
+      <Style isDefault="true"><ows:Identifier>default</ows:Identifier></Style>
+      <Format>image/png</Format>
+      <TileMatrixSetLink><TileMatrixSet>WorldCRS84Quad</TileMatrixSet></TileMatrixSetLink>
+      <TileMatrixSetLink><TileMatrixSet>WebMercatorQuad</TileMatrixSet></TileMatrixSetLink>
+      <ResourceURL format="image/png" resourceType="tile" template="${base}/wmts/tile/{TileMatrixSet}/{TileMatrix}/{TileRow}/{TileCol}.png"/>
+    </Layer>
+    <Layer>
+      <ows:Title>10 degree checkerboard, Web Mercator only</ows:Title>
+      <ows:Identifier>checkerboard-mercator</ows:Identifier>
+      <Style isDefault="true"><ows:Identifier>default</ows:Identifier></Style>
+      <Format>image/png</Format>
+      <TileMatrixSetLink><TileMatrixSet>WebMercatorQuad</TileMatrixSet></TileMatrixSetLink>
+    </Layer>
+${tileMatrixSet("WorldCRS84Quad", "urn:ogc:def:crs:OGC:1.3:CRS84", "-180 90", CRS84_SCALE, 2)}
+${tileMatrixSet("WebMercatorQuad", "urn:ogc:def:crs:EPSG::3857", `${-MERCATOR_EXTENT} ${MERCATOR_EXTENT}`, MERCATOR_SCALE, 1)}
+  </Contents>
+</Capabilities>`;
+}
+
+function getMap(params) {
+  const width = Math.min(Number(params.get("WIDTH")), 4096);
+  const height = Math.min(Number(params.get("HEIGHT")), 4096);
+  const crs = params.get("CRS") || params.get("SRS") || "";
+  const bbox = (params.get("BBOX") || "").split(",").map(Number);
+  if (!(width > 0 && height > 0) || bbox.length !== 4 || bbox.some(Number.isNaN)) {
+    return null;
+  }
+
+  let [minX, minY, maxX, maxY] = bbox;
+  // WMS 1.3.0 puts latitude first in EPSG:4326
+  if (crs === "EPSG:4326" && params.get("VERSION") === "1.3.0") {
+    [minX, minY, maxX, maxY] = [minY, minX, maxY, maxX];
+  }
+  const toCrs = (x, y) => [minX + (x / width) * (maxX - minX), maxY - (y / height) * (maxY - minY)];
+  if (crs === "EPSG:3857") {
+    return render(width, height, (x, y) => mercatorToLonLat(...toCrs(x, y)), LEVEL_TINTS[0]);
+  }
+  if (crs === "EPSG:4326" || crs === "CRS:84") {
+    return render(width, height, toCrs, LEVEL_TINTS[0]);
+  }
+  return null;
+}
+
+function getTile(set, level, row, column) {
+  if (!(level >= 0 && level <= MAX_LEVEL)) {
+    return null;
+  }
+  const tint = LEVEL_TINTS[level % LEVEL_TINTS.length];
+  const tiles = 2 ** level;
+  if (set === "WorldCRS84Quad") {
+    const span = 180 / tiles;
+    if (!(row >= 0 && row < tiles && column >= 0 && column < 2 * tiles)) {
+      return null;
+    }
+    return render(
+      TILE_SIZE,
+      TILE_SIZE,
+      (x, y) => [-180 + (column + x / TILE_SIZE) * span, 90 - (row + y / TILE_SIZE) * span],
+      tint,
+    );
+  }
+  if (set === "WebMercatorQuad") {
+    const span = (2 * MERCATOR_EXTENT) / tiles;
+    if (!(row >= 0 && row < tiles && column >= 0 && column < tiles)) {
+      return null;
+    }
+    return render(
+      TILE_SIZE,
+      TILE_SIZE,
+      (x, y) =>
+        mercatorToLonLat(
+          -MERCATOR_EXTENT + (column + x / TILE_SIZE) * span,
+          MERCATOR_EXTENT - (row + y / TILE_SIZE) * span,
+        ),
+      tint,
+    );
+  }
+  return null;
+}
+
+function respond(response, status, type, body) {
+  response.writeHead(status, {
+    "Content-Type": type,
+    "Access-Control-Allow-Origin": "*",
+  });
+  response.end(body);
+}
+
+const server = http.createServer((request, response) => {
+  const url = new URL(request.url, `http://${request.headers.host}`);
+  const base = `http://${request.headers.host}`;
+  // OGC parameter names are case-insensitive
+  const params = new Map([...url.searchParams].map(([key, value]) => [key.toUpperCase(), value]));
+  const requestType = (params.get("REQUEST") || "").toLowerCase();
+  let image = null;
+
+  if (url.pathname === "/wms") {
+    if (requestType === "getcapabilities") {
+      return respond(response, 200, "text/xml", wmsCapabilities(base));
+    }
+    if (requestType === "getmap") {
+      image = getMap(params);
+    }
+  } else if (url.pathname === "/wmts") {
+    if (requestType === "getcapabilities") {
+      return respond(response, 200, "text/xml", wmtsCapabilities(base));
+    }
+    if (requestType === "gettile") {
+      image = getTile(
+        params.get("TILEMATRIXSET"),
+        Number(params.get("TILEMATRIX")),
+        Number(params.get("TILEROW")),
+        Number(params.get("TILECOL")),
+      );
+    }
+  } else {
+    const tile = url.pathname.match(/^\/wmts\/tile\/([^/]+)\/(\d+)\/(\d+)\/(\d+)\.png$/);
+    if (tile) {
+      image = getTile(tile[1], Number(tile[2]), Number(tile[3]), Number(tile[4]));
+    }
+  }
+
+  if (image) {
+    respond(response, 200, "image/png", image);
+  } else {
+    respond(response, 404, "text/plain", "not found");
+  }
+});
+
+server.listen(port, () => {
+  console.log(`OGC stand-in is up on http://localhost:${port}`);
+});
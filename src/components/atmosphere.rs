@@ -0,0 +1,21 @@
+use bevy_ecs::component::Component;
+
+/// The scattering shell around the Earth. Drawn after everything else so it
+/// can tint whatever lies behind it.
+#[derive(Component)]
+pub struct AtmosphereComponent {
+    pub uniform: AtmosphereUniform,
+    pub uniform_buffer: wgpu::Buffer,
+}
+
+unsafe impl Send for AtmosphereComponent {}
+unsafe impl Sync for AtmosphereComponent {}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct AtmosphereUniform {
+    // unit vector towards the Sun in scene axes; w is unused
+    pub sun_direction: [f32; 4],
+    // x: planet radius, y: top of the atmosphere, in kilometers
+    pub radii: [f32; 4],
+}
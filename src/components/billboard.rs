@@ -0,0 +1,14 @@
+use bevy_ecs::component::Component;
+
+/// Geographic placement of a billboard entity, kept alongside its mesh so it
+/// can be picked and serialized.
+#[derive(Component)]
+pub struct BillboardComponent {
+    pub lat: f32,
+    pub lon: f32,
+    pub size: f32,
+    pub position: [f32; 3],
+    /// When it was added relative to the other billboards and polylines,
+    /// which a saved scene keeps.
+    pub order: u64,
+}
@@ -0,0 +1,17 @@
+use bevy_ecs::component::Component;
+
+/// A Sun or planet spawned by solar-system mode. Drawn with the shared
+/// `BodyPipeline` rather than a pipeline of its own.
+#[derive(Component)]
+pub struct CelestialBodyComponent {
+    pub naif_id: i32,
+    pub name: &'static str,
+    // mean IAU radius in kilometers, before exaggeration
+    pub radius: f32,
+}
+
+/// The heliocentric orbit path of a planet, drawn as a line around the Sun.
+#[derive(Component)]
+pub struct OrbitComponent {
+    pub naif_id: i32,
+}
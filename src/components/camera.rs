@@ -38,6 +38,7 @@ pub struct CameraController {
     pub is_backward_pressed: bool,
     pub is_left_pressed: bool,
     pub is_right_pressed: bool,
+    pub flight: Option<CameraFlight>,
 }
 impl CameraController {
     pub fn new(speed: f32) -> Self {
@@ -47,10 +48,19 @@ impl CameraController {
             is_backward_pressed: false,
             is_left_pressed: false,
             is_right_pressed: false,
+            flight: None,
         }
     }
 }
 
+// An animated move of the eye around the target, both ends relative to the target.
+pub struct CameraFlight {
+    pub from: cgmath::Vector3<f32>,
+    pub to: cgmath::Vector3<f32>,
+    pub duration: f32,
+    pub elapsed: f32,
+}
+
 // Needed to ensure rust compiled our data correctly for the shaders
 // Needed to store the data in a buffer without compiler rearranging
 #[repr(C)]
@@ -63,6 +73,12 @@ pub struct CameraUniform {
     pub proj_matrix: [[f32; 4]; 4],
 }
 
+impl Default for CameraUniform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl CameraUniform {
     pub fn new() -> Self {
         Self {
@@ -72,18 +88,17 @@ impl CameraUniform {
         }
     }
 
-    pub fn update_view_proj(
-        &mut self,
-        eye: cgmath::Point3<f32>,
-        target: cgmath::Point3<f32>,
-        up: cgmath::Vector3<f32>,
-        aspect: f32,
-        fovy: f32,
-        znear: f32,
-        zfar: f32,
-    ) {
+    pub fn update_view_proj(&mut self, camera: &Camera) {
         let (view_proj_matrix, view_matrix, proj_matrix) =
-            CameraSystem::build_view_projection_matrix(eye, target, up, aspect, fovy, znear, zfar);
+            CameraSystem::build_view_projection_matrix(
+                camera.eye,
+                camera.target,
+                camera.up,
+                camera.aspect,
+                camera.fovy,
+                camera.znear,
+                camera.zfar,
+            );
         self.view_proj_matrix = view_proj_matrix.into();
         self.view_matrix = view_matrix.into();
         self.proj_matrix = proj_matrix.into();
@@ -0,0 +1,13 @@
+use bevy_ecs::component::Component;
+
+/// The cloud shell just above the Earth's surface. Its cover comes from the
+/// Earth's [`EarthMap::Clouds`](super::earth::EarthMap::Clouds) layer and its
+/// rotation and shadows live in the Earth's surface uniform, so the shell and
+/// the shadows it casts always agree.
+#[derive(Component, Clone, Copy)]
+pub struct CloudComponent {
+    // eastward drift relative to the ground, radians per simulated second
+    pub drift_rate: f64,
+    pub visible: bool,
+    pub shadows: bool,
+}
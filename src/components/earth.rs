@@ -1,3 +1,4 @@
+use anise::prelude::Epoch;
 use bevy_ecs::component::Component;
 
 use super::{
@@ -13,3 +14,67 @@ pub struct EarthComponent {
 
 unsafe impl Send for EarthComponent {}
 unsafe impl Sync for EarthComponent {}
+
+/// The layers of the Earth's material. Each is a cube map, or an
+/// equirectangular image converted to one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EarthMap {
+    /// Surface color in daylight.
+    Day,
+    /// Tangent-space normals: red east, green north, blue up.
+    Normal,
+    /// A height map, converted to normals on load.
+    Bump,
+    /// Where the surface glints, e.g. an ocean mask; white is water.
+    Specular,
+    /// City lights, blended in on the night side.
+    Night,
+    /// Cloud cover, white where cloudy. Drawn on the cloud shell and used
+    /// for the shadows it casts.
+    Clouds,
+}
+
+/// The textures and lighting the Earth's material is built from, kept so one
+/// map can be swapped without reloading the others.
+#[derive(Component)]
+pub struct EarthSurfaceComponent {
+    pub day: wgpu::TextureView,
+    pub normal: wgpu::TextureView,
+    pub specular: wgpu::TextureView,
+    pub night: wgpu::TextureView,
+    pub clouds: wgpu::TextureView,
+    // time-series overlay frames, oldest first
+    pub overlay_frames: Vec<OverlayFrame>,
+    // the frames either side of the simulation time the material was last
+    // built with; none without frames
+    pub overlay_pair: Option<(usize, usize)>,
+    // bound in place of frames while there are none
+    pub no_overlay: wgpu::TextureView,
+    pub uniform: EarthSurfaceUniform,
+    pub uniform_buffer: wgpu::Buffer,
+    // shared by every map, kept so rebinding them only makes a bind group
+    pub sampler: wgpu::Sampler,
+}
+
+unsafe impl Send for EarthSurfaceComponent {}
+unsafe impl Sync for EarthSurfaceComponent {}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct EarthSurfaceUniform {
+    // unit vector towards the Sun in the Earth's model space; w is 1 when
+    // lit, 0 to show the day map everywhere
+    pub sun_direction: [f32; 4],
+    // x: how far the clouds have drifted east, radians; y: the cloud shell's
+    // radius over the Earth's; z: how dark their shadows are, 0 for none
+    pub clouds: [f32; 4],
+    // x: how far between the two overlay frames, y: the overlay's opacity
+    pub overlay: [f32; 4],
+}
+
+/// One georeferenced global image of a time series, e.g. a weather frame,
+/// shown over the day map around its timestamp.
+pub struct OverlayFrame {
+    pub epoch: Epoch,
+    pub view: wgpu::TextureView,
+}
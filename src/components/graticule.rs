@@ -0,0 +1,53 @@
+use bevy_ecs::{component::Component, entity::Entity};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraticuleAxis {
+    /// A parallel, labelled with its latitude.
+    Latitude,
+    /// A meridian, labelled with its longitude.
+    Longitude,
+}
+
+/// Text for one graticule line, placed where the line meets the edge of the
+/// view (or the globe's limb when the whole globe is in view). Parallels are
+/// labelled on the left, meridians along the bottom. Positions are in
+/// physical pixels from the top left; the host draws the text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraticuleLabel {
+    pub text: String,
+    pub axis: GraticuleAxis,
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Degrees of latitude and longitude the graticule's lines currently cover,
+/// snapped to its spacing. `east` may run past 180° when the view straddles
+/// the antimeridian.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct GraticuleWindow {
+    pub south: f64,
+    pub north: f64,
+    pub west: f64,
+    pub east: f64,
+}
+
+/// A line of the graticule and the world-space points it was drawn through.
+pub struct GraticuleLine {
+    pub axis: GraticuleAxis,
+    /// Latitude of a parallel or longitude of a meridian, in degrees.
+    pub value: f64,
+    pub positions: Vec<[f32; 3]>,
+}
+
+/// The latitude/longitude grid overlay, on the entity drawing its ordinary
+/// lines. The equator, tropics, polar circles and prime meridian are drawn
+/// brighter by `highlight_entity`.
+#[derive(Component)]
+pub struct GraticuleComponent {
+    /// Degrees between neighbouring lines; zero until first laid out.
+    pub spacing: f64,
+    pub window: GraticuleWindow,
+    pub lines: Vec<GraticuleLine>,
+    pub labels: Vec<GraticuleLabel>,
+    pub highlight_entity: Entity,
+}
@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+
+use bevy_ecs::component::Component;
+use serde::{Deserialize, Serialize};
+
+/// How many imagery layers a globe can draw at once.
+pub const MAX_IMAGERY_LAYERS: usize = 4;
+/// How many tiles a tiled layer can draw at once.
+pub const MAX_LAYER_TILES: usize = 64;
+/// Tiles are scaled to this many pixels a side in the atlas.
+pub const ATLAS_TILE_SIZE: u32 = 256;
+/// GPU memory the tile atlas may take unless told otherwise.
+pub const DEFAULT_ATLAS_BUDGET: u64 = 64 * 1024 * 1024;
+// in a layer's tile table, where a tile is missing
+pub const EMPTY_TILE: u32 = u32::MAX;
+
+/// A latitude/longitude box in degrees. A box crossing the antimeridian has
+/// `west` greater than `east`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GeoRectangle {
+    pub west: f32,
+    pub south: f32,
+    pub east: f32,
+    pub north: f32,
+}
+
+impl GeoRectangle {
+    pub const WORLD: GeoRectangle = GeoRectangle {
+        west: -180.0,
+        south: -90.0,
+        east: 180.0,
+        north: 90.0,
+    };
+
+    /// Degrees of longitude covered, across the antimeridian if need be.
+    pub fn width(&self) -> f32 {
+        let width = self.east - self.west;
+        if width <= 0.0 {
+            width + 360.0
+        } else {
+            width
+        }
+    }
+
+    pub fn contains(&self, other: &GeoRectangle) -> bool {
+        if other.south < self.south || other.north > self.north {
+            return false;
+        }
+        if self.width() >= 360.0 {
+            return true;
+        }
+        (other.west - self.west).rem_euclid(360.0) + other.width() <= self.width()
+    }
+
+    /// Grown by `fraction` of its size on every side.
+    pub fn padded(&self, fraction: f32) -> GeoRectangle {
+        let (width, height) = (self.width(), self.north - self.south);
+        let south = (self.south - height * fraction).max(-90.0);
+        let north = (self.north + height * fraction).min(90.0);
+        if width * (1.0 + 2.0 * fraction) >= 360.0 {
+            return GeoRectangle {
+                south,
+                north,
+                ..GeoRectangle::WORLD
+            };
+        }
+        let wrap = |lon: f32| (lon + 180.0).rem_euclid(360.0) - 180.0;
+        GeoRectangle {
+            west: wrap(self.west - width * fraction),
+            south,
+            east: wrap(self.east + width * fraction),
+            north,
+        }
+    }
+}
+
+/// How an imagery layer is drawn over the ones beneath it. Fields missing
+/// from a saved style take their defaults.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImageryLayerStyle {
+    /// 0 is invisible, 1 opaque.
+    pub alpha: f32,
+    /// Multiplies the color; 1 leaves it as is.
+    pub brightness: f32,
+    /// Scales the color's distance from mid-gray; 1 leaves it as is.
+    pub contrast: f32,
+    /// Rotates the hue, in degrees.
+    pub hue: f32,
+    /// 1 leaves the color as is, greater values brighten the midtones.
+    pub gamma: f32,
+    pub show: bool,
+    /// The area the image covers, stretched as an equirectangular image.
+    pub rectangle: GeoRectangle,
+}
+
+impl Default for ImageryLayerStyle {
+    fn default() -> Self {
+        ImageryLayerStyle {
+            alpha: 1.0,
+            brightness: 1.0,
+            contrast: 1.0,
+            hue: 0.0,
+            gamma: 1.0,
+            show: true,
+            rectangle: GeoRectangle::WORLD,
+        }
+    }
+}
+
+/// Identifies a layer within one globe's stack.
+pub type ImageryLayerId = u32;
+
+/// Where an imagery layer's pixels come from, kept so a saved scene can add
+/// the layer again.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ImageryLayerSource {
+    /// One image at an asset path, see [`Viewer::add_imagery_layer`](crate::Viewer::add_imagery_layer).
+    Image { path: String },
+    Wms {
+        capabilities_url: String,
+        layer: String,
+    },
+    Wmts {
+        capabilities_url: String,
+        layer: String,
+    },
+    /// An MBTiles or PMTiles archive.
+    Archive { path: String },
+}
+
+impl ImageryLayerSource {
+    /// The path or capabilities URL the layer is read from.
+    pub fn location(&self) -> &str {
+        match self {
+            ImageryLayerSource::Image { path } | ImageryLayerSource::Archive { path } => path,
+            ImageryLayerSource::Wms {
+                capabilities_url, ..
+            }
+            | ImageryLayerSource::Wmts {
+                capabilities_url, ..
+            } => capabilities_url,
+        }
+    }
+}
+
+/// Where a tiled layer's tiles lie, in radians of longitude and of latitude,
+/// or of unit sphere Mercator northing for Web Mercator tiles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileGrid {
+    pub west: f32,
+    pub top: f32,
+    pub tile_width: f32,
+    pub tile_height: f32,
+    pub columns: u32,
+    pub rows: u32,
+    pub mercator: bool,
+}
+
+/// The tiles a layer draws straight from its globe's atlas.
+pub struct LayerTiles {
+    pub grid: TileGrid,
+    // the atlas slot of each tile, row by row
+    pub slots: Vec<u32>,
+}
+
+pub struct ImageryLayer {
+    pub id: ImageryLayerId,
+    pub source: ImageryLayerSource,
+    pub style: ImageryLayerStyle,
+    // none until its image has loaded
+    pub view: Option<wgpu::TextureView>,
+    // in place of `view` for layers streamed as tiles
+    pub tiles: Option<LayerTiles>,
+}
+
+pub struct AtlasSlot {
+    pub key: Option<String>,
+    pub last_used: u64,
+    // layers drawing from it, which keep it from being evicted
+    pub users: u32,
+}
+
+/// Streamed tiles packed into the layers of one texture array, so a globe's
+/// tiled imagery needs neither a texture nor a bind group per tile. Slots no
+/// layer is drawing from are reused least recently used first.
+pub struct TileAtlas {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub slots: Vec<AtlasSlot>,
+    // the slot holding each tile, by URL
+    pub keys: HashMap<String, u32>,
+    pub tick: u64,
+}
+
+/// A globe's ordered imagery layers, composited over its base texture.
+/// Drawn through its own bind group, after the camera, material and model
+/// matrix ones.
+#[derive(Component)]
+pub struct ImageryComponent {
+    // bottom of the stack first
+    pub layers: Vec<ImageryLayer>,
+    pub next_id: ImageryLayerId,
+    // bound to the slots no layer fills
+    pub empty: wgpu::TextureView,
+    // made for the first tiled layer, bound as `empty_atlas` until then
+    pub atlas: Option<TileAtlas>,
+    pub empty_atlas: wgpu::TextureView,
+    /// Bytes of GPU memory the atlas may take, mips included.
+    pub atlas_budget: u64,
+    pub sampler: wgpu::Sampler,
+    pub uniform: ImageryUniform,
+    pub uniform_buffer: wgpu::Buffer,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+unsafe impl Send for ImageryComponent {}
+unsafe impl Sync for ImageryComponent {}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ImageryLayerUniform {
+    // west, south, east and north in radians, east unwrapped past west
+    pub rectangle: [f32; 4],
+    // brightness, contrast, hue in radians and gamma
+    pub adjustments: [f32; 4],
+    // x: alpha, 0 for an empty slot
+    pub alpha: [f32; 4],
+    // a `TileGrid`'s west, top, tile width and tile height
+    pub tile_grid: [f32; 4],
+    // columns, 0 for untiled layers, rows, 1 for Web Mercator rows, and the
+    // first of the layer's entries in `tile_slots`
+    pub tile_layout: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ImageryUniform {
+    // the drawn layers, bottom first
+    pub layers: [ImageryLayerUniform; MAX_IMAGERY_LAYERS],
+    // each tiled layer's table of atlas slots, four to an element
+    pub tile_slots: [[u32; 4]; MAX_IMAGERY_LAYERS * MAX_LAYER_TILES / 4],
+}
@@ -0,0 +1,73 @@
+use std::sync::Arc;
+
+use bevy_ecs::component::Component;
+
+use crate::loader::{ImageryCrs, TileArchive, TileMatrixSet};
+
+use super::imagery::{GeoRectangle, ImageryLayerId};
+
+/// A WMS layer, requested as one image of the view at a time.
+#[derive(Debug, Clone)]
+pub struct WmsProvider {
+    pub get_map_url: String,
+    pub version: String,
+    pub layer: String,
+    pub format: String,
+    pub crs: ImageryCrs,
+    // the code the server listed for `crs`
+    pub crs_code: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum WmtsTileUrl {
+    /// A RESTful template with `{TileMatrix}`, `{TileRow}` and `{TileCol}`.
+    Template(String),
+    /// A KVP GetTile endpoint.
+    Kvp(String),
+}
+
+/// A WMTS layer, requested tile by tile from one of its tile matrix sets.
+#[derive(Debug, Clone)]
+pub struct WmtsProvider {
+    pub layer: String,
+    pub style: String,
+    pub format: String,
+    pub tile_matrix_set: TileMatrixSet,
+    pub crs: ImageryCrs,
+    pub tile_url: WmtsTileUrl,
+}
+
+/// Web Mercator tiles read straight out of an MBTiles or PMTiles archive.
+#[derive(Debug, Clone)]
+pub struct ArchiveProvider {
+    pub archive: Arc<TileArchive>,
+    // the archive's zoom levels, as if it were a WMTS layer
+    pub tile_matrix_set: TileMatrixSet,
+}
+
+#[derive(Debug, Clone)]
+pub enum ImageryProvider {
+    Wms(WmsProvider),
+    Wmts(WmtsProvider),
+    Archive(ArchiveProvider),
+}
+
+/// The area and level of detail imagery was requested for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageryView {
+    pub rectangle: GeoRectangle,
+    /// Degrees per pixel.
+    pub resolution: f32,
+}
+
+/// Streams a provider's imagery into one of the Earth's imagery layers,
+/// requesting the region in view again as the camera moves.
+#[derive(Component)]
+pub struct ImageryProviderComponent {
+    pub layer: ImageryLayerId,
+    pub provider: ImageryProvider,
+    // what the layer currently holds
+    pub shown: Option<ImageryView>,
+    // one request at a time, so responses land in order
+    pub pending: bool,
+}
@@ -59,3 +59,30 @@ impl BillboardVertex {
         }
     }
 }
+
+// One corner of a star's quad. All four corners of a star share its direction,
+// color and size; `corner` says which way to push the vertex out.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct StarVertex {
+    pub direction: [f32; 3],
+    pub corner: [f32; 2],
+    pub color: [f32; 4],
+    // angular radius, radians
+    pub size: f32,
+}
+
+impl StarVertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 4] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2, 2 => Float32x4, 3 => Float32];
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        use std::mem;
+
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
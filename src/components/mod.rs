@@ -1,6 +1,15 @@
+pub mod atmosphere;
+pub mod billboard;
+pub mod body;
 pub mod camera;
+pub mod clouds;
 pub mod earth;
+pub mod graticule;
+pub mod imagery;
+pub mod imagery_provider;
 pub mod material;
 pub mod mesh;
 pub mod moon;
+pub mod polyline;
 pub mod render_pipelines;
+pub mod stars;
@@ -0,0 +1,17 @@
+use bevy_ecs::component::Component;
+
+/// Geographic description of a polyline entity. `coordinates` are the
+/// (lat, lon) vertices as given, `positions` the densified world-space points
+/// that were uploaded to the vertex buffer.
+#[derive(Component)]
+pub struct PolylineComponent {
+    pub coordinates: Vec<(f32, f32)>,
+    pub color: [f32; 4],
+    /// Whether the line runs back from the last vertex to the first,
+    /// outlining a polygon.
+    pub closed: bool,
+    pub positions: Vec<[f32; 3]>,
+    /// When it was added relative to the other billboards and polylines,
+    /// which a saved scene keeps.
+    pub order: u64,
+}
@@ -0,0 +1,7 @@
+use bevy_ecs::component::Component;
+
+/// The background starfield. Drawn before everything else, at infinity.
+#[derive(Component)]
+pub struct StarfieldComponent {
+    pub num_stars: usize,
+}
@@ -1,6 +1,4 @@
-use anyhow::*;
-use image::GenericImageView;
-
+#[allow(unused)]
 pub struct Texture {
     pub texture: wgpu::Texture,
     pub view: wgpu::TextureView,
@@ -0,0 +1,132 @@
+use std::fmt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+
+use super::{mbtiles::MbTiles, pmtiles, pmtiles::PmTiles, HttpSource, LoadError};
+
+/// Where a tile archive is read from, one byte range at a time.
+#[derive(Debug, Clone)]
+pub enum ArchiveLocation {
+    #[cfg(not(target_arch = "wasm32"))]
+    File(PathBuf),
+    /// Served by a static file server that honors `Range` headers.
+    Url(String),
+}
+
+impl ArchiveLocation {
+    /// A URL for `http(s)://` paths, otherwise a file natively and a URL
+    /// relative to the page on the web.
+    pub fn parse(path: &str) -> ArchiveLocation {
+        #[cfg(not(target_arch = "wasm32"))]
+        if !path.starts_with("http://") && !path.starts_with("https://") {
+            return ArchiveLocation::File(PathBuf::from(path));
+        }
+        ArchiveLocation::Url(path.to_string())
+    }
+
+    /// Reads up to `length` bytes from `offset`, fewer past the end.
+    pub async fn read(&self, offset: u64, length: u64) -> Result<Vec<u8>, LoadError> {
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            ArchiveLocation::File(path) => super::FileSource::read_range(path, offset, length),
+            ArchiveLocation::Url(url) => HttpSource::fetch_range(url.clone(), offset, length).await,
+        }
+    }
+}
+
+impl fmt::Display for ArchiveLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            ArchiveLocation::File(path) => write!(f, "{}", path.display()),
+            ArchiveLocation::Url(url) => write!(f, "{url}"),
+        }
+    }
+}
+
+enum Archive {
+    MbTiles(MbTiles),
+    PmTiles(PmTiles),
+}
+
+/// A single-file archive of Web Mercator raster tiles, MBTiles or PMTiles,
+/// with tiles read straight out of it on demand.
+pub struct TileArchive {
+    archive: Archive,
+    pub location: ArchiveLocation,
+    pub min_zoom: u8,
+    pub max_zoom: u8,
+    /// `png`, `jpg`, `webp`, ... as the archive names its tiles' format.
+    pub format: String,
+}
+
+impl TileArchive {
+    /// Reads the archive's header and index, telling the kinds apart by their
+    /// magic bytes.
+    pub async fn open(location: ArchiveLocation) -> Result<TileArchive, LoadError> {
+        let header = location.read(0, pmtiles::HEADER_SIZE).await?;
+        if header.starts_with(b"PMTiles") {
+            let pmtiles = PmTiles::open(location.clone(), &header).await?;
+            let format = match pmtiles.tile_type {
+                1 => "pbf",
+                2 => "png",
+                3 => "jpg",
+                4 => "webp",
+                5 => "avif",
+                _ => "",
+            };
+            Ok(TileArchive {
+                min_zoom: pmtiles.min_zoom,
+                max_zoom: pmtiles.max_zoom,
+                format: format.to_string(),
+                archive: Archive::PmTiles(pmtiles),
+                location,
+            })
+        } else if header.starts_with(b"SQLite format 3\0") {
+            let mbtiles = MbTiles::open(location.clone(), &header).await?;
+            let (min_zoom, max_zoom) = mbtiles.zoom_range().await?;
+            // older archives leave the format out, when it was always PNG
+            let format = mbtiles.metadata.get("format").map_or("png", String::as_str);
+            Ok(TileArchive {
+                min_zoom,
+                max_zoom,
+                format: format.to_ascii_lowercase(),
+                archive: Archive::MbTiles(mbtiles),
+                location,
+            })
+        } else {
+            Err(LoadError::Decode {
+                path: location.to_string(),
+                message: "not an MBTiles or PMTiles archive".to_string(),
+            })
+        }
+    }
+
+    /// The tile at `zoom`, `x` and `y` in the XYZ scheme, with rows counted
+    /// down from the north, or none if the archive has no such tile.
+    pub async fn tile(&self, zoom: u8, x: u32, y: u32) -> Result<Option<Vec<u8>>, LoadError> {
+        if zoom < self.min_zoom || zoom > self.max_zoom.min(30) || x >> zoom > 0 || y >> zoom > 0 {
+            return Ok(None);
+        }
+        match &self.archive {
+            Archive::MbTiles(mbtiles) => mbtiles.tile(zoom, x, y).await,
+            Archive::PmTiles(pmtiles) => pmtiles.tile(zoom, x, y).await,
+        }
+    }
+}
+
+impl fmt::Debug for TileArchive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.archive {
+            Archive::MbTiles(_) => "MBTiles",
+            Archive::PmTiles(_) => "PMTiles",
+        };
+        f.debug_struct("TileArchive")
+            .field("kind", &kind)
+            .field("location", &self.location)
+            .field("min_zoom", &self.min_zoom)
+            .field("max_zoom", &self.max_zoom)
+            .field("format", &self.format)
+            .finish()
+    }
+}
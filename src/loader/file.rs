@@ -0,0 +1,53 @@
+use std::{
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use super::{AssetSource, LoadError, LoadFuture};
+
+/// Assets read from a directory on the local filesystem.
+pub struct FileSource {
+    root: PathBuf,
+}
+
+impl FileSource {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Reads up to `length` bytes from `offset` into the file at `path`, fewer
+    /// past its end.
+    pub fn read_range(path: &Path, offset: u64, length: u64) -> Result<Vec<u8>, LoadError> {
+        let io_error = |source: std::io::Error| {
+            let path = path.display().to_string();
+            if source.kind() == std::io::ErrorKind::NotFound {
+                LoadError::NotFound { path }
+            } else {
+                LoadError::Io { path, source }
+            }
+        };
+        let mut file = std::fs::File::open(path).map_err(io_error)?;
+        file.seek(SeekFrom::Start(offset)).map_err(io_error)?;
+        let mut bytes = Vec::with_capacity(length as usize);
+        file.take(length)
+            .read_to_end(&mut bytes)
+            .map_err(io_error)?;
+        Ok(bytes)
+    }
+}
+
+impl AssetSource for FileSource {
+    fn load<'a>(&'a self, path: &'a str) -> LoadFuture<'a> {
+        Box::pin(async move {
+            let full_path = self.root.join(path);
+            std::fs::read(&full_path).map_err(|source| {
+                let path = full_path.display().to_string();
+                if source.kind() == std::io::ErrorKind::NotFound {
+                    LoadError::NotFound { path }
+                } else {
+                    LoadError::Io { path, source }
+                }
+            })
+        })
+    }
+}
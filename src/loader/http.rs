@@ -0,0 +1,131 @@
+#[cfg(target_arch = "wasm32")]
+use js_sys::Uint8Array;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::{JsCast, JsValue};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen_futures::JsFuture;
+#[cfg(target_arch = "wasm32")]
+use web_sys::{Request, RequestInit, RequestMode, Response};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::OnceLock;
+
+use super::{AssetSource, LoadError, LoadFuture};
+
+// one client for every fetch, so its connections and TLS sessions are reused
+#[cfg(not(target_arch = "wasm32"))]
+static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+/// Assets fetched over HTTP(S) relative to a base URL, with `fetch` on the web
+/// and reqwest natively.
+pub struct HttpSource {
+    base_url: String,
+}
+
+impl HttpSource {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url, path)
+    }
+
+    /// Fetches an absolute URL, for services like WMS that are addressed by
+    /// query string rather than path.
+    pub async fn fetch(url: String) -> Result<Vec<u8>, LoadError> {
+        HttpSource::get(url, None).await.map(|(_, bytes)| bytes)
+    }
+
+    /// Fetches `length` bytes from `offset` into a file with a range request,
+    /// for archives read piece by piece. Fewer bytes come back past the end of
+    /// the file.
+    pub async fn fetch_range(url: String, offset: u64, length: u64) -> Result<Vec<u8>, LoadError> {
+        let range = (offset, offset + length.max(1) - 1);
+        let (status, bytes) = HttpSource::get(url, Some(range)).await?;
+        if status == 206 {
+            return Ok(bytes);
+        }
+        // the server ignored the range and sent the whole file
+        let start = (offset as usize).min(bytes.len());
+        let end = ((offset + length) as usize).min(bytes.len());
+        Ok(bytes[start..end].to_vec())
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    async fn get(url: String, range: Option<(u64, u64)>) -> Result<(u16, Vec<u8>), LoadError> {
+        let network_error = |e: JsValue| LoadError::Network {
+            url: url.clone(),
+            message: format!("{:?}", e),
+        };
+
+        let mut opts = RequestInit::new();
+        opts.method("GET");
+        opts.mode(RequestMode::Cors);
+
+        let request = Request::new_with_str_and_init(&url, &opts).map_err(network_error)?;
+        request
+            .headers()
+            .set("Accept", "*/*")
+            .map_err(network_error)?;
+        if let Some((first, last)) = range {
+            request
+                .headers()
+                .set("Range", &format!("bytes={}-{}", first, last))
+                .map_err(network_error)?;
+        }
+
+        let window = web_sys::window().ok_or_else(|| LoadError::Network {
+            url: url.clone(),
+            message: "no window to fetch from".to_string(),
+        })?;
+        let resp: Response = JsFuture::from(window.fetch_with_request(&request))
+            .await
+            .map_err(network_error)?
+            .dyn_into()
+            .map_err(network_error)?;
+        if !resp.ok() {
+            return Err(LoadError::Http {
+                url,
+                status: resp.status(),
+            });
+        }
+
+        let buffer = JsFuture::from(resp.array_buffer().map_err(network_error)?)
+            .await
+            .map_err(network_error)?;
+        Ok((resp.status(), Uint8Array::new(&buffer).to_vec()))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn get(url: String, range: Option<(u64, u64)>) -> Result<(u16, Vec<u8>), LoadError> {
+        let network_error = |e: reqwest::Error| LoadError::Network {
+            url: url.clone(),
+            message: e.to_string(),
+        };
+
+        let mut request = CLIENT.get_or_init(reqwest::Client::new).get(&url);
+        if let Some((first, last)) = range {
+            request = request.header("Range", format!("bytes={}-{}", first, last));
+        }
+        let response = request.send().await.map_err(network_error)?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(LoadError::Http {
+                url,
+                status: status.as_u16(),
+            });
+        }
+
+        let bytes = response.bytes().await.map_err(network_error)?;
+        Ok((status.as_u16(), bytes.to_vec()))
+    }
+}
+
+impl AssetSource for HttpSource {
+    fn load<'a>(&'a self, path: &'a str) -> LoadFuture<'a> {
+        Box::pin(HttpSource::fetch(self.url(path)))
+    }
+}
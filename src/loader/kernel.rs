@@ -0,0 +1,84 @@
+use anise::{
+    naif::{BPC, SPK},
+    structure::PlanetaryDataSet,
+};
+
+use super::{AssetLoader, LoadError};
+
+// planetary ephemerides from 1849 to 2150, served next to the app
+pub const DE440S_KERNEL: &str = "kernels/de440s.bsp";
+
+/// What a kernel file holds, which decides how it is parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KernelKind {
+    /// NAIF SPK ephemeris, e.g. `de440s.bsp` or a spacecraft trajectory.
+    Spk,
+    /// NAIF binary PCK orientation, e.g. `earth_latest_high_prec.bpc` or
+    /// `moon_pa_de440_200625.bpc`.
+    Bpc,
+    /// ANISE planetary constants (`.pca`), built from text PCKs like `pck00011.tpc`.
+    PlanetaryConstants,
+}
+
+/// A SPICE kernel to load through an [`AssetLoader`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Kernel {
+    pub path: String,
+    pub kind: KernelKind,
+}
+
+impl Kernel {
+    pub fn new(path: &str, kind: KernelKind) -> Self {
+        Self {
+            path: path.to_string(),
+            kind,
+        }
+    }
+
+    /// The kernels a viewer loads unless told otherwise.
+    pub fn defaults() -> Vec<Kernel> {
+        vec![Kernel::new(DE440S_KERNEL, KernelKind::Spk)]
+    }
+
+    /// Guesses the kind from the file extension: `.bsp`, `.bpc` or `.pca`.
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = path.rsplit_once('.')?.1.to_ascii_lowercase();
+        let kind = match extension.as_str() {
+            "bsp" => KernelKind::Spk,
+            "bpc" => KernelKind::Bpc,
+            "pca" => KernelKind::PlanetaryConstants,
+            _ => return None,
+        };
+        Some(Kernel::new(path, kind))
+    }
+}
+
+/// A kernel parsed and ready to be added to an `Almanac`.
+pub enum ParsedKernel {
+    Spk(SPK),
+    Bpc(BPC),
+    PlanetaryConstants(Box<PlanetaryDataSet>),
+}
+
+impl AssetLoader {
+    /// Loads and parses a kernel.
+    pub async fn load_kernel(&self, kernel: &Kernel) -> Result<ParsedKernel, LoadError> {
+        let bytes = self.load(&kernel.path).await?;
+        let decode_error = |message: String| LoadError::Decode {
+            path: kernel.path.clone(),
+            message,
+        };
+
+        match kernel.kind {
+            KernelKind::Spk => SPK::parse(bytes)
+                .map(ParsedKernel::Spk)
+                .map_err(|e| decode_error(e.to_string())),
+            KernelKind::Bpc => BPC::parse(bytes)
+                .map(ParsedKernel::Bpc)
+                .map_err(|e| decode_error(e.to_string())),
+            KernelKind::PlanetaryConstants => PlanetaryDataSet::try_from_bytes(bytes)
+                .map(|data| ParsedKernel::PlanetaryConstants(Box::new(data)))
+                .map_err(|e| decode_error(e.to_string())),
+        }
+    }
+}
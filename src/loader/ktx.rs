@@ -0,0 +1,360 @@
+use std::io::Read;
+
+use ktx2::{Format, Reader, SupercompressionScheme};
+
+use super::{AssetLoader, LoadError};
+
+/// The mip levels of a KTX2 file, still in the GPU format they were encoded
+/// in. Upload it with `MaterialSystem::create_2d_texture` or
+/// `create_cube_map_texture`.
+#[derive(Debug, Clone)]
+pub struct Ktx2Texture {
+    pub format: wgpu::TextureFormat,
+    pub width: u32,
+    pub height: u32,
+    // 6 for a cube map, in wgpu's +X, -X, +Y, -Y, +Z, -Z order, 1 otherwise
+    pub faces: u32,
+    // largest first, each holding every face back to back
+    pub levels: Vec<Vec<u8>>,
+}
+
+impl Ktx2Texture {
+    /// Parses a KTX2 file, undoing any zstd supercompression.
+    ///
+    /// Basis Universal payloads (ETC1S/BasisLZ and UASTC) are rejected: they
+    /// need a transcoder this crate doesn't have. Transcode them to BC7, ETC2
+    /// or ASTC ahead of time with `ktx transcode` instead.
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        let reader = Reader::new(bytes).map_err(|e| e.to_string())?;
+        let header = reader.header();
+
+        if header.pixel_depth > 1 || header.layer_count > 1 {
+            return Err("3D textures and texture arrays are not supported".to_string());
+        }
+        if header.face_count != 1 && header.face_count != 6 {
+            return Err(format!("{} faces, expected 1 or 6", header.face_count));
+        }
+        let format = match header.format {
+            Some(format) => Ktx2Texture::texture_format(format)
+                .ok_or_else(|| format!("{format:?} textures are not supported"))?,
+            None => {
+                let kind = match header.supercompression_scheme {
+                    Some(SupercompressionScheme::BasisLZ) => "ETC1S",
+                    _ => "UASTC",
+                };
+                return Err(format!(
+                    "Basis Universal {kind} textures can't be transcoded; \
+                     transcode to BC7, ETC2 or ASTC with `ktx transcode` first"
+                ));
+            }
+        };
+
+        let (block_width, block_height) = format.block_dimensions();
+        let (width, height) = (header.pixel_width, header.pixel_height.max(1));
+        if width % block_width != 0 || height % block_height != 0 {
+            return Err(format!(
+                "{width}x{height} is not a whole number of {block_width}x{block_height} blocks"
+            ));
+        }
+
+        let mut levels = Vec::with_capacity(reader.levels().len());
+        for (i, level) in reader.levels().enumerate() {
+            let level = match header.supercompression_scheme {
+                None => level.to_vec(),
+                Some(SupercompressionScheme::Zstandard) => {
+                    let mut decoder =
+                        ruzstd::StreamingDecoder::new(level).map_err(|e| e.to_string())?;
+                    let mut decompressed = Vec::new();
+                    decoder
+                        .read_to_end(&mut decompressed)
+                        .map_err(|e| e.to_string())?;
+                    decompressed
+                }
+                Some(scheme) => {
+                    return Err(format!("{scheme:?} supercompression is not supported"))
+                }
+            };
+
+            let expected = Ktx2Texture::level_size(format, width, height, i as u32)
+                * header.face_count as usize;
+            if level.len() != expected {
+                return Err(format!(
+                    "level {i} holds {} bytes, expected {expected}",
+                    level.len()
+                ));
+            }
+            levels.push(level);
+        }
+
+        Ok(Ktx2Texture {
+            format,
+            width,
+            height,
+            faces: header.face_count,
+            levels,
+        })
+    }
+
+    /// Decodes the texture to RGBA8 on the CPU, signed for the snorm formats,
+    /// for adapters without its compressed format. Only the BC1 to BC5
+    /// families can be decoded; BC6H, BC7, ETC2, EAC and ASTC textures need
+    /// an adapter that samples them.
+    pub fn decompress(&self) -> Option<Ktx2Texture> {
+        use wgpu::TextureFormat::*;
+
+        let (decode_block, format): (DecodeBlock, _) = match self.format {
+            Rgba8Unorm | Rgba8UnormSrgb => return Some(self.clone()),
+            Bc1RgbaUnorm => (decode_bc1, Rgba8Unorm),
+            Bc1RgbaUnormSrgb => (decode_bc1, Rgba8UnormSrgb),
+            Bc2RgbaUnorm => (decode_bc2, Rgba8Unorm),
+            Bc2RgbaUnormSrgb => (decode_bc2, Rgba8UnormSrgb),
+            Bc3RgbaUnorm => (decode_bc3, Rgba8Unorm),
+            Bc3RgbaUnormSrgb => (decode_bc3, Rgba8UnormSrgb),
+            Bc4RUnorm => (decode_bc4, Rgba8Unorm),
+            Bc4RSnorm => (decode_bc4_signed, Rgba8Snorm),
+            Bc5RgUnorm => (decode_bc5, Rgba8Unorm),
+            Bc5RgSnorm => (decode_bc5_signed, Rgba8Snorm),
+            _ => return None,
+        };
+        let block_size = self.format.block_size(None)? as usize;
+
+        let levels = self
+            .levels
+            .iter()
+            .enumerate()
+            .map(|(level, data)| {
+                let width = (self.width >> level).max(1) as usize;
+                let height = (self.height >> level).max(1) as usize;
+                let blocks_wide = width.div_ceil(4);
+                let blocks_high = height.div_ceil(4);
+                let face_size = blocks_wide * blocks_high * block_size;
+
+                let mut rgba = vec![0; width * height * 4 * self.faces as usize];
+                for (face, face_data) in data.chunks_exact(face_size).enumerate() {
+                    let face_offset = face * width * height * 4;
+                    for (i, block) in face_data.chunks_exact(block_size).enumerate() {
+                        let texels = decode_block(block);
+                        let (block_x, block_y) = (i % blocks_wide * 4, i / blocks_wide * 4);
+                        for (j, texel) in texels.iter().enumerate() {
+                            let (x, y) = (block_x + j % 4, block_y + j / 4);
+                            // blocks overhang mips smaller than 4x4
+                            if x < width && y < height {
+                                let offset = face_offset + (y * width + x) * 4;
+                                rgba[offset..offset + 4].copy_from_slice(texel);
+                            }
+                        }
+                    }
+                }
+                rgba
+            })
+            .collect();
+
+        Some(Ktx2Texture {
+            format,
+            levels,
+            ..self.clone()
+        })
+    }
+
+    // bytes in one face of mip `level`
+    fn level_size(format: wgpu::TextureFormat, width: u32, height: u32, level: u32) -> usize {
+        let (block_width, block_height) = format.block_dimensions();
+        let blocks_wide = (width >> level).max(1).div_ceil(block_width);
+        let blocks_high = (height >> level).max(1).div_ceil(block_height);
+        let block_size = format.block_size(None).unwrap_or(0);
+        (blocks_wide * blocks_high * block_size) as usize
+    }
+
+    fn texture_format(format: Format) -> Option<wgpu::TextureFormat> {
+        use wgpu::{AstcBlock, AstcChannel, TextureFormat::*};
+
+        // the ASTC formats come in unorm/sRGB pairs, in wgpu's block order
+        let astc = Format::ASTC_4x4_UNORM_BLOCK.0.get()..=Format::ASTC_12x12_SRGB_BLOCK.0.get();
+        if astc.contains(&format.0.get()) {
+            let index = format.0.get() - astc.start();
+            let block = [
+                AstcBlock::B4x4,
+                AstcBlock::B5x4,
+                AstcBlock::B5x5,
+                AstcBlock::B6x5,
+                AstcBlock::B6x6,
+                AstcBlock::B8x5,
+                AstcBlock::B8x6,
+                AstcBlock::B8x8,
+                AstcBlock::B10x5,
+                AstcBlock::B10x6,
+                AstcBlock::B10x8,
+                AstcBlock::B10x10,
+                AstcBlock::B12x10,
+                AstcBlock::B12x12,
+            ][index as usize / 2];
+            let channel = if index.is_multiple_of(2) {
+                AstcChannel::Unorm
+            } else {
+                AstcChannel::UnormSrgb
+            };
+            return Some(Astc { block, channel });
+        }
+
+        Some(match format {
+            Format::R8G8B8A8_UNORM => Rgba8Unorm,
+            Format::R8G8B8A8_SRGB => Rgba8UnormSrgb,
+            Format::BC1_RGBA_UNORM_BLOCK | Format::BC1_RGB_UNORM_BLOCK => Bc1RgbaUnorm,
+            Format::BC1_RGBA_SRGB_BLOCK | Format::BC1_RGB_SRGB_BLOCK => Bc1RgbaUnormSrgb,
+            Format::BC2_UNORM_BLOCK => Bc2RgbaUnorm,
+            Format::BC2_SRGB_BLOCK => Bc2RgbaUnormSrgb,
+            Format::BC3_UNORM_BLOCK => Bc3RgbaUnorm,
+            Format::BC3_SRGB_BLOCK => Bc3RgbaUnormSrgb,
+            Format::BC4_UNORM_BLOCK => Bc4RUnorm,
+            Format::BC4_SNORM_BLOCK => Bc4RSnorm,
+            Format::BC5_UNORM_BLOCK => Bc5RgUnorm,
+            Format::BC5_SNORM_BLOCK => Bc5RgSnorm,
+            Format::BC6H_UFLOAT_BLOCK => Bc6hRgbUfloat,
+            Format::BC6H_SFLOAT_BLOCK => Bc6hRgbFloat,
+            Format::BC7_UNORM_BLOCK => Bc7RgbaUnorm,
+            Format::BC7_SRGB_BLOCK => Bc7RgbaUnormSrgb,
+            Format::ETC2_R8G8B8_UNORM_BLOCK => Etc2Rgb8Unorm,
+            Format::ETC2_R8G8B8_SRGB_BLOCK => Etc2Rgb8UnormSrgb,
+            Format::ETC2_R8G8B8A1_UNORM_BLOCK => Etc2Rgb8A1Unorm,
+            Format::ETC2_R8G8B8A1_SRGB_BLOCK => Etc2Rgb8A1UnormSrgb,
+            Format::ETC2_R8G8B8A8_UNORM_BLOCK => Etc2Rgba8Unorm,
+            Format::ETC2_R8G8B8A8_SRGB_BLOCK => Etc2Rgba8UnormSrgb,
+            Format::EAC_R11_UNORM_BLOCK => EacR11Unorm,
+            Format::EAC_R11_SNORM_BLOCK => EacR11Snorm,
+            Format::EAC_R11G11_UNORM_BLOCK => EacRg11Unorm,
+            Format::EAC_R11G11_SNORM_BLOCK => EacRg11Snorm,
+            _ => return None,
+        })
+    }
+}
+
+// a 4x4 block to RGBA8 texels, row by row
+type DecodeBlock = fn(&[u8]) -> [[u8; 4]; 16];
+
+// The BCn block layouts are in the Khronos Data Format Specification,
+// "S3TC Compressed Texture Image Formats" and "RGTC".
+
+// two RGB565 endpoints and 2-bit indices; `opaque` forces the 4-color mode
+fn decode_bc1_colors(block: &[u8], opaque: bool) -> [[u8; 4]; 16] {
+    let endpoint = |bytes: &[u8]| {
+        let value = u16::from_le_bytes([bytes[0], bytes[1]]);
+        (
+            value,
+            [
+                ((value >> 11) & 31) as u32 * 255 / 31,
+                ((value >> 5) & 63) as u32 * 255 / 63,
+                (value & 31) as u32 * 255 / 31,
+            ],
+        )
+    };
+    let (value0, color0) = endpoint(&block[0..2]);
+    let (value1, color1) = endpoint(&block[2..4]);
+    let mix = |a: u32, b: u32, wa: u32, wb: u32| ((a * wa + b * wb) / (wa + wb)) as u8;
+
+    let mut palette = [[0u8; 4]; 4];
+    for channel in 0..3 {
+        palette[0][channel] = color0[channel] as u8;
+        palette[1][channel] = color1[channel] as u8;
+        if opaque || value0 > value1 {
+            palette[2][channel] = mix(color0[channel], color1[channel], 2, 1);
+            palette[3][channel] = mix(color0[channel], color1[channel], 1, 2);
+        } else {
+            palette[2][channel] = mix(color0[channel], color1[channel], 1, 1);
+        }
+    }
+    palette[0][3] = 255;
+    palette[1][3] = 255;
+    palette[2][3] = 255;
+    palette[3][3] = if opaque || value0 > value1 { 255 } else { 0 };
+
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    std::array::from_fn(|i| palette[(indices >> (2 * i) & 3) as usize])
+}
+
+// two 8-bit endpoints and 3-bit indices, as used for BC3 alpha and BC4/BC5;
+// `signed` reads the endpoints as snorm, between -127 and 127
+fn decode_bc4_channel(block: &[u8], signed: bool) -> [u8; 16] {
+    let (a, b, min, max) = if signed {
+        (
+            (block[0] as i8).max(-127) as i32,
+            (block[1] as i8).max(-127) as i32,
+            -127,
+            127,
+        )
+    } else {
+        (block[0] as i32, block[1] as i32, 0, 255)
+    };
+    let mut palette = [0i32; 8];
+    palette[0] = a;
+    palette[1] = b;
+    if a > b {
+        for i in 1..7 {
+            palette[i + 1] = (a * (7 - i as i32) + b * i as i32) / 7;
+        }
+    } else {
+        for i in 1..5 {
+            palette[i + 1] = (a * (5 - i as i32) + b * i as i32) / 5;
+        }
+        palette[6] = min;
+        palette[7] = max;
+    }
+
+    let mut indices = [0u8; 8];
+    indices[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(indices);
+    std::array::from_fn(|i| palette[(indices >> (3 * i) & 7) as usize] as u8)
+}
+
+fn decode_bc1(block: &[u8]) -> [[u8; 4]; 16] {
+    decode_bc1_colors(block, false)
+}
+
+fn decode_bc2(block: &[u8]) -> [[u8; 4]; 16] {
+    let mut texels = decode_bc1_colors(&block[8..16], true);
+    for (i, texel) in texels.iter_mut().enumerate() {
+        texel[3] = (block[i / 2] >> (4 * (i % 2)) & 15) * 17;
+    }
+    texels
+}
+
+fn decode_bc3(block: &[u8]) -> [[u8; 4]; 16] {
+    let mut texels = decode_bc1_colors(&block[8..16], true);
+    let alpha = decode_bc4_channel(&block[0..8], false);
+    for (texel, alpha) in texels.iter_mut().zip(alpha) {
+        texel[3] = alpha;
+    }
+    texels
+}
+
+// single and two channel formats sample with zeroed blue/green and opaque
+// alpha, which is 127 for snorm
+fn decode_bc4(block: &[u8]) -> [[u8; 4]; 16] {
+    decode_bc4_channel(block, false).map(|red| [red, 0, 0, 255])
+}
+
+fn decode_bc4_signed(block: &[u8]) -> [[u8; 4]; 16] {
+    decode_bc4_channel(block, true).map(|red| [red, 0, 0, 127])
+}
+
+fn decode_bc5(block: &[u8]) -> [[u8; 4]; 16] {
+    let red = decode_bc4_channel(&block[0..8], false);
+    let green = decode_bc4_channel(&block[8..16], false);
+    std::array::from_fn(|i| [red[i], green[i], 0, 255])
+}
+
+fn decode_bc5_signed(block: &[u8]) -> [[u8; 4]; 16] {
+    let red = decode_bc4_channel(&block[0..8], true);
+    let green = decode_bc4_channel(&block[8..16], true);
+    std::array::from_fn(|i| [red[i], green[i], 0, 127])
+}
+
+impl AssetLoader {
+    /// Loads a KTX2 texture, 2D or cube map, without decoding its blocks.
+    pub async fn load_ktx2(&self, path: &str) -> Result<Ktx2Texture, LoadError> {
+        let bytes = self.load(path).await?;
+        Ktx2Texture::parse(&bytes).map_err(|message| LoadError::Decode {
+            path: path.to_string(),
+            message,
+        })
+    }
+}
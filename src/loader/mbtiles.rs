@@ -0,0 +1,670 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+use super::{archive::ArchiveLocation, LoadError};
+
+// SQLite b-tree page types
+const INTERIOR_INDEX: u8 = 2;
+const INTERIOR_TABLE: u8 = 5;
+const LEAF_INDEX: u8 = 10;
+const LEAF_TABLE: u8 = 13;
+
+// pages kept between lookups before the cache starts over
+const MAX_CACHED_PAGES: usize = 512;
+// far deeper than any b-tree SQLite writes, so a descent this long is going
+// round a cycle in a corrupt file
+const MAX_DEPTH: usize = 64;
+
+/// A column value, compared the way SQLite orders them in an index.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl Value {
+    fn rank(&self) -> u8 {
+        match self {
+            Value::Null => 0,
+            Value::Integer(_) | Value::Real(_) => 1,
+            Value::Text(_) => 2,
+            Value::Blob(_) => 3,
+        }
+    }
+
+    fn compare(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
+            (Value::Integer(a), Value::Real(b)) => (*a as f64).total_cmp(b),
+            (Value::Real(a), Value::Integer(b)) => a.total_cmp(&(*b as f64)),
+            (Value::Real(a), Value::Real(b)) => a.total_cmp(b),
+            (Value::Text(a), Value::Text(b)) => a.as_bytes().cmp(b.as_bytes()),
+            (Value::Blob(a), Value::Blob(b)) => a.cmp(b),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+
+    fn text(&self) -> Option<String> {
+        match self {
+            Value::Text(text) => Some(text.clone()),
+            Value::Integer(value) => Some(value.to_string()),
+            Value::Real(value) => Some(value.to_string()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Index {
+    root: u32,
+    columns: Vec<String>,
+}
+
+#[derive(Debug)]
+struct Table {
+    root: u32,
+    columns: Vec<String>,
+    indexes: Vec<Index>,
+}
+
+impl Table {
+    fn column(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|column| column == name)
+    }
+}
+
+#[derive(Debug)]
+enum TileTables {
+    /// A plain `tiles` table.
+    Tiles(Table),
+    /// The deduplicated layout behind a `tiles` view: `map` rows point at
+    /// `images` rows by `tile_id`.
+    MapImages { map: Table, images: Table },
+}
+
+/// An MBTiles archive: raster tiles in a SQLite database, read page by page
+/// with just enough of the file format to look tiles up.
+pub struct MbTiles {
+    location: ArchiveLocation,
+    page_size: u64,
+    usable_size: usize,
+    tables: TileTables,
+    pages: Mutex<HashMap<u32, Arc<Vec<u8>>>>,
+    pub metadata: HashMap<String, String>,
+}
+
+impl MbTiles {
+    /// Reads the schema and the `metadata` table. `header` is the start of the
+    /// file, at least its first 100 bytes.
+    pub async fn open(location: ArchiveLocation, header: &[u8]) -> Result<MbTiles, LoadError> {
+        if header.len() < 100 || !header.starts_with(b"SQLite format 3\0") {
+            return Err(decode_error(&location, "not a SQLite database"));
+        }
+        let page_size = match u16::from_be_bytes([header[16], header[17]]) {
+            1 => 65536,
+            size => size as u64,
+        };
+        if page_size < 512 || !page_size.is_power_of_two() {
+            return Err(decode_error(&location, "bad page size"));
+        }
+        if u32::from_be_bytes([header[56], header[57], header[58], header[59]]) > 1 {
+            return Err(decode_error(
+                &location,
+                "only UTF-8 databases are supported",
+            ));
+        }
+
+        let mut mbtiles = MbTiles {
+            page_size,
+            usable_size: page_size as usize - header[20] as usize,
+            tables: TileTables::Tiles(Table {
+                root: 0,
+                columns: Vec::new(),
+                indexes: Vec::new(),
+            }),
+            pages: Mutex::new(HashMap::new()),
+            metadata: HashMap::new(),
+            location,
+        };
+
+        // sqlite_master: type, name, tbl_name, rootpage, sql
+        let mut tables = HashMap::new();
+        let mut indexes = Vec::new();
+        let mut tiles_view = false;
+        for row in mbtiles.scan(1).await? {
+            let field = |i: usize| row.get(i).and_then(Value::text).unwrap_or_default();
+            let root = match row.get(3) {
+                Some(Value::Integer(root)) => *root as u32,
+                _ => 0,
+            };
+            match field(0).as_str() {
+                "table" => {
+                    let sql = field(4);
+                    let table = Table {
+                        root,
+                        columns: column_names(&sql),
+                        indexes: Vec::new(),
+                    };
+                    tables.insert(field(1), (table, sql));
+                }
+                "index" if root != 0 => indexes.push((field(1), field(2), root, field(4))),
+                "view" if field(1) == "tiles" => tiles_view = true,
+                _ => {}
+            }
+        }
+        for (name, table, root, sql) in indexes {
+            let Some((table, table_sql)) = tables.get_mut(&table) else {
+                continue;
+            };
+            let columns = match name.strip_prefix("sqlite_autoindex_") {
+                // made for a UNIQUE constraint, numbered from 1 after the table name
+                Some(suffix) => suffix
+                    .rsplit('_')
+                    .next()
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .and_then(|n| unique_keys(table_sql).into_iter().nth(n.wrapping_sub(1)))
+                    .unwrap_or_default(),
+                None => column_names(&sql),
+            };
+            if !columns.is_empty() {
+                table.indexes.push(Index { root, columns });
+            }
+        }
+        let mut tables: HashMap<String, Table> = tables
+            .into_iter()
+            .map(|(name, (table, _))| (name, table))
+            .collect();
+
+        let metadata = tables.remove("metadata");
+        mbtiles.tables = match (tables.remove("tiles"), tiles_view) {
+            (Some(tiles), _) => TileTables::Tiles(tiles),
+            (None, true) => match (tables.remove("map"), tables.remove("images")) {
+                (Some(map), Some(images)) => TileTables::MapImages { map, images },
+                _ => {
+                    let message = "the tiles view is not over map and images";
+                    return Err(decode_error(&mbtiles.location, message));
+                }
+            },
+            (None, false) => return Err(decode_error(&mbtiles.location, "no tiles table")),
+        };
+
+        if let Some(metadata) = metadata {
+            let (name, value) = (metadata.column("name"), metadata.column("value"));
+            for row in mbtiles.scan(metadata.root).await? {
+                let field = |i: Option<usize>| i.and_then(|i| row.get(i)).and_then(Value::text);
+                if let (Some(name), Some(value)) = (field(name), field(value)) {
+                    mbtiles.metadata.insert(name, value);
+                }
+            }
+        }
+        Ok(mbtiles)
+    }
+
+    /// The zoom levels stored, from the metadata or else the extremes of the
+    /// tiles index.
+    pub async fn zoom_range(&self) -> Result<(u8, u8), LoadError> {
+        let metadata = |name: &str| self.metadata.get(name).and_then(|z| z.trim().parse().ok());
+        if let (Some(min), Some(max)) = (metadata("minzoom"), metadata("maxzoom")) {
+            return Ok((min, max));
+        }
+        let table = match &self.tables {
+            TileTables::Tiles(table) => table,
+            TileTables::MapImages { map, .. } => map,
+        };
+        let index = table
+            .indexes
+            .iter()
+            .find(|index| index.columns.first().map(String::as_str) == Some("zoom_level"))
+            .ok_or_else(|| {
+                decode_error(&self.location, "no minzoom and maxzoom in the metadata")
+            })?;
+        let zoom = |row: Option<Vec<Value>>| match row.as_deref() {
+            Some([Value::Integer(zoom), ..]) => *zoom as u8,
+            _ => 0,
+        };
+        let min = zoom(self.extreme(index.root, false).await?);
+        let max = zoom(self.extreme(index.root, true).await?);
+        Ok((min, max))
+    }
+
+    /// The tile at `zoom`, `x` and `y`, with `y` counted down from the north
+    /// like XYZ tiles rather than up like the rows MBTiles stores.
+    pub async fn tile(&self, zoom: u8, x: u32, y: u32) -> Result<Option<Vec<u8>>, LoadError> {
+        let row = (1u32 << zoom) - 1 - y;
+        let key = [
+            ("zoom_level", Value::Integer(zoom as i64)),
+            ("tile_column", Value::Integer(x as i64)),
+            ("tile_row", Value::Integer(row as i64)),
+        ];
+        let data = match &self.tables {
+            TileTables::Tiles(tiles) => match self.find(tiles, &key).await? {
+                Some(row) => tiles
+                    .column("tile_data")
+                    .and_then(|i| row.into_iter().nth(i)),
+                None => None,
+            },
+            TileTables::MapImages { map, images } => {
+                let tile_id = match self.find(map, &key).await? {
+                    Some(row) => map.column("tile_id").and_then(|i| row.into_iter().nth(i)),
+                    None => None,
+                };
+                match tile_id {
+                    Some(tile_id) => match self.find(images, &[("tile_id", tile_id)]).await? {
+                        Some(row) => images
+                            .column("tile_data")
+                            .and_then(|i| row.into_iter().nth(i)),
+                        None => None,
+                    },
+                    None => None,
+                }
+            }
+        };
+        Ok(match data {
+            Some(Value::Blob(data)) => Some(data),
+            _ => None,
+        })
+    }
+
+    // the row whose columns equal `key`, through an index on them if there is
+    // one and a scan of the whole table otherwise
+    async fn find(
+        &self,
+        table: &Table,
+        key: &[(&str, Value)],
+    ) -> Result<Option<Vec<Value>>, LoadError> {
+        let index = table.indexes.iter().find(|index| {
+            index.columns.len() >= key.len()
+                && index.columns[..key.len()]
+                    .iter()
+                    .all(|column| key.iter().any(|(name, _)| name == column))
+        });
+        if let Some(index) = index {
+            let values: Vec<Value> = index.columns[..key.len()]
+                .iter()
+                .filter_map(|column| key.iter().find(|(name, _)| name == column))
+                .map(|(_, value)| value.clone())
+                .collect();
+            return match self.search_index(index.root, &values).await? {
+                Some(rowid) => self.row(table.root, rowid).await,
+                None => Ok(None),
+            };
+        }
+
+        let columns: Vec<(Option<usize>, &Value)> = key
+            .iter()
+            .map(|(name, value)| (table.column(name), value))
+            .collect();
+        Ok(self.scan(table.root).await?.into_iter().find(|row| {
+            columns.iter().all(|(i, value)| {
+                i.and_then(|i| row.get(i))
+                    .is_some_and(|column| column.compare(value) == Ordering::Equal)
+            })
+        }))
+    }
+
+    async fn page(&self, number: u32) -> Result<Arc<Vec<u8>>, LoadError> {
+        // pages count from 1; a 0 is a pointer read past the end of a page
+        if number == 0 {
+            return Err(self.corrupt());
+        }
+        if let Some(page) = self.pages.lock().unwrap().get(&number) {
+            return Ok(page.clone());
+        }
+        let offset = (number as u64 - 1) * self.page_size;
+        let page = Arc::new(self.location.read(offset, self.page_size).await?);
+        if page.len() < self.usable_size {
+            return Err(self.corrupt());
+        }
+        let mut pages = self.pages.lock().unwrap();
+        if pages.len() >= MAX_CACHED_PAGES {
+            pages.clear();
+        }
+        pages.insert(number, page.clone());
+        Ok(page)
+    }
+
+    fn corrupt(&self) -> LoadError {
+        decode_error(&self.location, "the database is corrupt")
+    }
+
+    // the page's type, cell offsets and right-most child
+    fn cells(&self, number: u32, page: &[u8]) -> Result<(u8, Vec<usize>, u32), LoadError> {
+        let header = if number == 1 { 100 } else { 0 };
+        let at = |i: usize| page.get(i).copied().ok_or_else(|| self.corrupt());
+        let kind = at(header)?;
+        let count = u16::from_be_bytes([at(header + 3)?, at(header + 4)?]) as usize;
+        let (right, pointers) = match kind {
+            INTERIOR_INDEX | INTERIOR_TABLE => (read_u32(page, header + 8), header + 12),
+            LEAF_INDEX | LEAF_TABLE => (0, header + 8),
+            _ => return Err(self.corrupt()),
+        };
+        let cells = (0..count)
+            .map(|i| {
+                Ok(u16::from_be_bytes([at(pointers + 2 * i)?, at(pointers + 2 * i + 1)?]) as usize)
+            })
+            .collect::<Result<_, LoadError>>()?;
+        Ok((kind, cells, right))
+    }
+
+    // the whole payload starting at `offset`, following overflow pages
+    async fn payload(&self, page: &[u8], offset: usize, table: bool) -> Result<Vec<u8>, LoadError> {
+        let (size, n) = read_varint(page, offset);
+        let mut offset = offset + n;
+        if table {
+            offset += read_varint(page, offset).1;
+        }
+        let size = size as usize;
+        let usable = self.usable_size;
+        let max_local = if table {
+            usable - 35
+        } else {
+            (usable - 12) * 64 / 255 - 23
+        };
+        let min_local = (usable - 12) * 32 / 255 - 23;
+        let local = if size <= max_local {
+            size
+        } else {
+            let local = min_local + (size - min_local) % (usable - 4);
+            if local <= max_local {
+                local
+            } else {
+                min_local
+            }
+        };
+        let mut payload = page
+            .get(offset..offset + local)
+            .ok_or_else(|| self.corrupt())?
+            .to_vec();
+        if local < size {
+            let mut next = read_u32(page, offset + local);
+            let mut visited = HashSet::new();
+            while payload.len() < size && next != 0 {
+                if !visited.insert(next) {
+                    return Err(self.corrupt());
+                }
+                let overflow = self.page(next).await?;
+                let take = (size - payload.len()).min(usable - 4);
+                payload.extend_from_slice(&overflow[4..4 + take]);
+                next = read_u32(&overflow, 0);
+            }
+        }
+        Ok(payload)
+    }
+
+    // every row of the table rooted at `root`
+    async fn scan(&self, root: u32) -> Result<Vec<Vec<Value>>, LoadError> {
+        let mut rows = Vec::new();
+        let mut stack = vec![root];
+        let mut visited = HashSet::new();
+        while let Some(number) = stack.pop() {
+            if !visited.insert(number) {
+                return Err(self.corrupt());
+            }
+            let page = self.page(number).await?;
+            let (kind, cells, right) = self.cells(number, &page)?;
+            if kind == LEAF_TABLE {
+                for cell in cells {
+                    rows.push(parse_record(&self.payload(&page, cell, true).await?));
+                }
+            } else if kind == INTERIOR_TABLE {
+                // popped last to first, so rows come out in order
+                stack.push(right);
+                stack.extend(cells.iter().rev().map(|&cell| read_u32(&page, cell)));
+            } else {
+                return Err(self.corrupt());
+            }
+        }
+        Ok(rows)
+    }
+
+    // the row with `rowid` in the table rooted at `root`
+    async fn row(&self, root: u32, rowid: i64) -> Result<Option<Vec<Value>>, LoadError> {
+        let mut number = root;
+        for _ in 0..MAX_DEPTH {
+            let page = self.page(number).await?;
+            let (kind, cells, right) = self.cells(number, &page)?;
+            match kind {
+                INTERIOR_TABLE => {
+                    number = cells
+                        .iter()
+                        .find(|&&cell| read_varint(&page, cell + 4).0 as i64 >= rowid)
+                        .map_or(right, |&cell| read_u32(&page, cell));
+                }
+                LEAF_TABLE => {
+                    for cell in cells {
+                        let n = read_varint(&page, cell).1;
+                        if read_varint(&page, cell + n).0 as i64 == rowid {
+                            return Ok(Some(parse_record(&self.payload(&page, cell, true).await?)));
+                        }
+                    }
+                    return Ok(None);
+                }
+                _ => return Err(self.corrupt()),
+            }
+        }
+        Err(self.corrupt())
+    }
+
+    // the rowid of the entry whose leading columns equal `key` in the index
+    // rooted at `root`
+    async fn search_index(&self, root: u32, key: &[Value]) -> Result<Option<i64>, LoadError> {
+        let compare = |entry: &[Value]| {
+            key.iter()
+                .zip(entry)
+                .map(|(a, b)| a.compare(b))
+                .find(|order| *order != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        };
+        let rowid = |entry: &[Value]| match entry.last() {
+            Some(Value::Integer(rowid)) => Some(*rowid),
+            _ => None,
+        };
+        let mut number = root;
+        for _ in 0..MAX_DEPTH {
+            let page = self.page(number).await?;
+            let (kind, cells, right) = self.cells(number, &page)?;
+            let interior = kind == INTERIOR_INDEX;
+            if !interior && kind != LEAF_INDEX {
+                return Err(self.corrupt());
+            }
+            let mut child = right;
+            for cell in cells {
+                let payload_at = if interior { cell + 4 } else { cell };
+                let entry = parse_record(&self.payload(&page, payload_at, false).await?);
+                match compare(&entry) {
+                    Ordering::Equal => return Ok(rowid(&entry)),
+                    Ordering::Less => {
+                        child = read_u32(&page, cell);
+                        break;
+                    }
+                    Ordering::Greater => {}
+                }
+            }
+            if !interior {
+                return Ok(None);
+            }
+            number = child;
+        }
+        Err(self.corrupt())
+    }
+
+    // the first or last entry of the index rooted at `root`
+    async fn extreme(&self, root: u32, last: bool) -> Result<Option<Vec<Value>>, LoadError> {
+        let mut number = root;
+        for _ in 0..MAX_DEPTH {
+            let page = self.page(number).await?;
+            let (kind, cells, right) = self.cells(number, &page)?;
+            match kind {
+                INTERIOR_INDEX if last => number = right,
+                INTERIOR_INDEX => match cells.first() {
+                    Some(&cell) => number = read_u32(&page, cell),
+                    None => number = right,
+                },
+                LEAF_INDEX => {
+                    let cell = if last { cells.last() } else { cells.first() };
+                    return match cell {
+                        Some(&cell) => {
+                            Ok(Some(parse_record(&self.payload(&page, cell, false).await?)))
+                        }
+                        None => Ok(None),
+                    };
+                }
+                _ => return Err(self.corrupt()),
+            }
+        }
+        Err(self.corrupt())
+    }
+}
+
+fn decode_error(location: &ArchiveLocation, message: &str) -> LoadError {
+    LoadError::Decode {
+        path: location.to_string(),
+        message: message.to_string(),
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    bytes
+        .get(offset..offset + 4)
+        .map_or(0, |b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+// a SQLite varint and how many bytes it took
+fn read_varint(bytes: &[u8], offset: usize) -> (u64, usize) {
+    let mut value = 0u64;
+    for i in 0..9 {
+        let Some(&byte) = bytes.get(offset + i) else {
+            return (value, i);
+        };
+        if i == 8 {
+            return ((value << 8) | byte as u64, 9);
+        }
+        value = (value << 7) | (byte & 0x7f) as u64;
+        if byte & 0x80 == 0 {
+            return (value, i + 1);
+        }
+    }
+    (value, 9)
+}
+
+fn parse_record(payload: &[u8]) -> Vec<Value> {
+    let (header_size, mut at) = read_varint(payload, 0);
+    let mut types = Vec::new();
+    while at < header_size as usize && at < payload.len() {
+        let (serial_type, n) = read_varint(payload, at);
+        types.push(serial_type);
+        at += n;
+    }
+
+    let mut body = header_size as usize;
+    let mut values = Vec::with_capacity(types.len());
+    for serial_type in types {
+        let size = match serial_type {
+            0 | 8 | 9 => 0,
+            1..=4 => serial_type as usize,
+            5 => 6,
+            6 | 7 => 8,
+            n if n >= 12 => (n as usize - 12) / 2,
+            _ => 0,
+        };
+        let bytes = payload.get(body..body + size).unwrap_or(&[]);
+        body += size;
+        let integer = || {
+            let sign = if bytes.first().is_some_and(|b| b & 0x80 != 0) {
+                -1i64
+            } else {
+                0
+            };
+            bytes.iter().fold(sign, |value, &b| (value << 8) | b as i64)
+        };
+        values.push(match serial_type {
+            0 => Value::Null,
+            1..=6 => Value::Integer(integer()),
+            7 => Value::Real(f64::from_bits(integer() as u64)),
+            8 => Value::Integer(0),
+            9 => Value::Integer(1),
+            n if n >= 12 && n % 2 == 0 => Value::Blob(bytes.to_vec()),
+            n if n >= 13 => Value::Text(String::from_utf8_lossy(bytes).into_owned()),
+            _ => Value::Null,
+        });
+    }
+    values
+}
+
+// the comma-separated definitions between a statement's outer parentheses
+fn definitions(sql: &str) -> Vec<&str> {
+    let (Some(open), Some(close)) = (sql.find('('), sql.rfind(')')) else {
+        return Vec::new();
+    };
+    let mut definitions = Vec::new();
+    let mut depth = 0;
+    let mut start = open + 1;
+    for (i, c) in sql[..close].char_indices().skip(open + 1) {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                definitions.push(sql[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    definitions.push(sql[start..close].trim());
+    definitions
+}
+
+fn unquote(name: &str) -> String {
+    name.trim_matches(|c| matches!(c, '"' | '`' | '[' | ']' | '\''))
+        .to_string()
+}
+
+const CONSTRAINTS: [&str; 5] = ["PRIMARY", "UNIQUE", "CHECK", "FOREIGN", "CONSTRAINT"];
+
+// a definition's column name or constraint keyword, which may run straight
+// into its parenthesis as in `UNIQUE(a, b)`
+fn first_word(definition: &str) -> &str {
+    definition
+        .split(|c: char| c.is_whitespace() || c == '(')
+        .next()
+        .unwrap_or_default()
+}
+
+// the column names in a CREATE TABLE or CREATE INDEX statement
+fn column_names(sql: &str) -> Vec<String> {
+    definitions(sql)
+        .iter()
+        .map(|definition| first_word(definition))
+        .filter(|name| !name.is_empty())
+        .filter(|name| !CONSTRAINTS.contains(&name.to_ascii_uppercase().as_str()))
+        .map(unquote)
+        .collect()
+}
+
+// the columns of each UNIQUE or PRIMARY KEY constraint in a CREATE TABLE
+// statement, in the order SQLite numbers the indexes it makes for them
+fn unique_keys(sql: &str) -> Vec<Vec<String>> {
+    let mut column_keys = Vec::new();
+    let mut table_keys = Vec::new();
+    for definition in definitions(sql) {
+        let upper = definition.to_ascii_uppercase();
+        if CONSTRAINTS.contains(&first_word(&upper)) {
+            if upper.contains("UNIQUE") || upper.contains("PRIMARY KEY") {
+                table_keys.push(column_names(definition));
+            }
+        } else if upper.contains("UNIQUE")
+            || (upper.contains("PRIMARY KEY") && !upper.contains("INTEGER PRIMARY KEY"))
+        {
+            // a primary key on an INTEGER column is the rowid and needs no index
+            column_keys.push(vec![unquote(first_word(definition))]);
+        }
+    }
+    column_keys.extend(table_keys);
+    column_keys
+}
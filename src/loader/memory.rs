@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+
+use super::{AssetSource, LoadError, LoadFuture};
+
+/// Assets held in memory, keyed by path. Useful for bytes the host already has
+/// and for the textures compiled into the crate.
+#[derive(Default)]
+pub struct MemorySource {
+    assets: HashMap<String, Vec<u8>>,
+}
+
+impl MemorySource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, path: &str, bytes: impl Into<Vec<u8>>) {
+        self.assets
+            .insert(path.trim_start_matches('/').to_string(), bytes.into());
+    }
+
+    /// The Earth and billboard textures shipped with the crate.
+    pub fn builtin_textures() -> Self {
+        let mut source = MemorySource::new();
+        source.insert(
+            "earth.jpg",
+            &include_bytes!("../assets/2k_earth_daymap.jpg")[..],
+        );
+        source.insert(
+            "billboard.png",
+            &include_bytes!("../assets/billboard.png")[..],
+        );
+        source
+    }
+}
+
+impl AssetSource for MemorySource {
+    fn load<'a>(&'a self, path: &'a str) -> LoadFuture<'a> {
+        Box::pin(async move {
+            self.assets
+                .get(path)
+                .cloned()
+                .ok_or_else(|| LoadError::NotFound {
+                    path: path.to_string(),
+                })
+        })
+    }
+}
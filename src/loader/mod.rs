@@ -0,0 +1,256 @@
+use std::{fmt, future::Future, pin::Pin};
+
+use image::RgbaImage;
+
+use crate::systems::material::{MaterialSystem, TextureSource};
+
+mod archive;
+#[cfg(not(target_arch = "wasm32"))]
+mod file;
+mod http;
+mod kernel;
+mod ktx;
+mod mbtiles;
+mod memory;
+mod ogc;
+mod pmtiles;
+mod stars;
+mod tile_cache;
+
+pub use archive::{ArchiveLocation, TileArchive};
+#[cfg(not(target_arch = "wasm32"))]
+pub use file::FileSource;
+pub use http::HttpSource;
+pub use kernel::{Kernel, KernelKind, ParsedKernel};
+pub use ktx::Ktx2Texture;
+pub use memory::MemorySource;
+pub use ogc::{
+    with_query, ImageryCrs, TileMatrix, TileMatrixSet, WmsCapabilities, WmsLayer, WmtsCapabilities,
+    WmtsLayer,
+};
+pub use pmtiles::tile_id;
+pub use stars::{Star, StarCatalogFormat};
+pub use tile_cache::{TileImageCache, DEFAULT_TILE_CACHE_BUDGET};
+
+// where `AssetLoader::default` and `with_base_url` mount each kind of asset
+pub const KERNELS_PREFIX: &str = "kernels";
+pub const TEXTURES_PREFIX: &str = "textures";
+// one equirectangular map per solar-system body, e.g. `textures/bodies/mars.jpg`
+pub const BODY_TEXTURES_PREFIX: &str = "textures/bodies";
+pub const DATA_PREFIX: &str = "data";
+
+// the dev server in `server/` listens here
+pub const DEFAULT_BASE_URL: &str = "http://localhost:3000";
+
+/// Why an asset could not be loaded.
+#[derive(Debug)]
+pub enum LoadError {
+    /// No source is mounted for the path's prefix.
+    NoSource { path: String },
+    /// The source exists but has nothing at this path.
+    NotFound { path: String },
+    /// The server answered with a non-success status.
+    Http { url: String, status: u16 },
+    /// The request never got a response (DNS, CORS, connection refused, ...).
+    Network { url: String, message: String },
+    /// Reading from the local filesystem failed.
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    /// The bytes arrived but are not a valid image, kernel, etc.
+    Decode { path: String, message: String },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::NoSource { path } => write!(f, "no asset source is mounted for {path}"),
+            LoadError::NotFound { path } => write!(f, "asset {path} was not found"),
+            LoadError::Http { url, status } => write!(f, "GET {url} returned HTTP {status}"),
+            LoadError::Network { url, message } => write!(f, "GET {url} failed: {message}"),
+            LoadError::Io { path, source } => write!(f, "could not read {path}: {source}"),
+            LoadError::Decode { path, message } => write!(f, "could not decode {path}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+// Sources are shared with loading tasks, which run on other threads natively
+// but on the single JS thread on the web where nothing is `Send`.
+#[cfg(not(target_arch = "wasm32"))]
+pub type LoadFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<u8>, LoadError>> + Send + 'a>>;
+#[cfg(target_arch = "wasm32")]
+pub type LoadFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<u8>, LoadError>> + 'a>>;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub trait MaybeSend: Send {}
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Send> MaybeSend for T {}
+#[cfg(target_arch = "wasm32")]
+pub trait MaybeSend {}
+#[cfg(target_arch = "wasm32")]
+impl<T> MaybeSend for T {}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub trait MaybeSendSync: Send + Sync {}
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Send + Sync> MaybeSendSync for T {}
+#[cfg(target_arch = "wasm32")]
+pub trait MaybeSendSync {}
+#[cfg(target_arch = "wasm32")]
+impl<T> MaybeSendSync for T {}
+
+/// Somewhere asset bytes can be fetched from.
+pub trait AssetSource: MaybeSendSync {
+    /// Fetches the raw bytes at `path`, relative to the source's root.
+    fn load<'a>(&'a self, path: &'a str) -> LoadFuture<'a>;
+}
+
+/// Runs `task` in the background: on the browser's event loop on the web, and on
+/// a tokio runtime shared by every task natively, so hosts don't need one.
+pub fn spawn(task: impl Future<Output = ()> + MaybeSend + 'static) {
+    #[cfg(target_arch = "wasm32")]
+    wasm_bindgen_futures::spawn_local(task);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        // one runtime, so tasks share worker threads and the HTTP client's
+        // connections outlive the task that opened them
+        static RUNTIME: std::sync::OnceLock<Option<tokio::runtime::Runtime>> =
+            std::sync::OnceLock::new();
+        let runtime = RUNTIME.get_or_init(|| {
+            tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .map_err(|e| tracing::error!("could not start the asset loading runtime: {e}"))
+                .ok()
+        });
+        if let Some(runtime) = runtime {
+            runtime.spawn(task);
+        }
+    }
+}
+
+/// Resolves asset paths to sources by their leading path segments.
+///
+/// A path like `kernels/de440s.bsp` is handed to whatever source is mounted at
+/// `kernels`, as `de440s.bsp`. Where mounts nest, the longest wins, so
+/// `textures/bodies/mars.jpg` goes to a source at `textures/bodies` as
+/// `mars.jpg` rather than to the one at `textures`. Kernels, textures and data layers all go through
+/// the same loader, so pointing the viewer at another server or a local
+/// directory is a matter of mounting a different source.
+pub struct AssetLoader {
+    mounts: Vec<(String, Box<dyn AssetSource>)>,
+}
+
+impl AssetLoader {
+    /// A loader with nothing mounted.
+    pub fn new() -> Self {
+        Self { mounts: Vec::new() }
+    }
+
+    /// Serves kernels and data layers from `base_url`, e.g. `base_url/de440s.bsp`,
+    /// solar-system body maps from `base_url/bodies`, e.g.
+    /// `base_url/bodies/mars.jpg`, and other textures from the ones built into
+    /// the crate.
+    pub fn with_base_url(base_url: &str) -> Self {
+        let mut loader = AssetLoader::new();
+        loader.mount(KERNELS_PREFIX, HttpSource::new(base_url));
+        loader.mount(DATA_PREFIX, HttpSource::new(base_url));
+        loader.mount(TEXTURES_PREFIX, MemorySource::builtin_textures());
+        loader.mount(
+            BODY_TEXTURES_PREFIX,
+            HttpSource::new(&format!("{}/bodies", base_url.trim_end_matches('/'))),
+        );
+        loader
+    }
+
+    /// Mounts `source` at `prefix`, replacing whatever was mounted there.
+    pub fn mount(&mut self, prefix: &str, source: impl AssetSource + 'static) {
+        let prefix = prefix.trim_matches('/').to_string();
+        self.mounts.retain(|(mounted, _)| *mounted != prefix);
+        self.mounts.push((prefix, Box::new(source)));
+    }
+
+    pub async fn load(&self, path: &str) -> Result<Vec<u8>, LoadError> {
+        let path = path.trim_start_matches('/');
+        let Some((rest, source)) = self
+            .mounts
+            .iter()
+            .filter_map(|(mounted, source)| {
+                let rest = path.strip_prefix(mounted.as_str())?;
+                // whole segments only, so `textures` doesn't match `textures2`
+                let rest = match rest.strip_prefix('/') {
+                    Some(rest) => rest,
+                    None if rest.is_empty() => rest,
+                    None => return None,
+                };
+                Some((rest, source))
+            })
+            .min_by_key(|(rest, _)| rest.len())
+        else {
+            return Err(LoadError::NoSource {
+                path: path.to_string(),
+            });
+        };
+        source.load(rest).await
+    }
+
+    /// Loads and decodes a PNG or JPEG.
+    pub async fn load_image(&self, path: &str) -> Result<RgbaImage, LoadError> {
+        let bytes = self.load(path).await?;
+        let image = image::load_from_memory(&bytes).map_err(|e| LoadError::Decode {
+            path: path.to_string(),
+            message: e.to_string(),
+        })?;
+        Ok(image.to_rgba8())
+    }
+
+    /// Loads the six faces `1.png` to `6.png` of the cube map in `directory`.
+    pub async fn load_cube_map(&self, directory: &str) -> Result<Vec<RgbaImage>, LoadError> {
+        let mut faces = Vec::with_capacity(6);
+        for face in 1..=6 {
+            faces.push(self.load_image(&format!("{directory}/{face}.png")).await?);
+        }
+        Ok(faces)
+    }
+
+    /// Loads a globe texture as a cube map: an equirectangular `.jpg` or `.png`
+    /// like the NASA and USGS global mosaics, a `.ktx2` cube map, or otherwise
+    /// a directory of six faces for [`AssetLoader::load_cube_map`].
+    pub async fn load_globe_texture(&self, path: &str) -> Result<TextureSource, LoadError> {
+        let extension = path.rsplit_once('.').map(|(_, extension)| extension);
+        match extension.map(str::to_ascii_lowercase).as_deref() {
+            Some("jpg" | "jpeg" | "png") => {
+                let image = self.load_image(path).await?;
+                Ok(MaterialSystem::equirectangular_to_cube_map(&image).into())
+            }
+            Some("ktx2") => {
+                let texture = self.load_ktx2(path).await?;
+                if texture.faces != 6 {
+                    return Err(LoadError::Decode {
+                        path: path.to_string(),
+                        message: "globe textures in KTX2 must be cube maps".to_string(),
+                    });
+                }
+                Ok(texture.into())
+            }
+            _ => Ok(self.load_cube_map(path).await?.into()),
+        }
+    }
+}
+
+impl Default for AssetLoader {
+    fn default() -> Self {
+        AssetLoader::with_base_url(DEFAULT_BASE_URL)
+    }
+}
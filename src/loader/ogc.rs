@@ -0,0 +1,483 @@
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::components::imagery::GeoRectangle;
+
+use super::LoadError;
+
+/// The projections imagery can be requested in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageryCrs {
+    /// EPSG:4326 or CRS:84, plain latitude and longitude.
+    Geographic,
+    /// EPSG:3857, the spherical Mercator of web maps.
+    WebMercator,
+}
+
+impl ImageryCrs {
+    /// Recognizes the codes and URNs servers advertise for either projection.
+    pub fn from_code(code: &str) -> Option<Self> {
+        let code = code.trim().to_ascii_uppercase();
+        let number = code.rsplit(':').next().unwrap_or_default();
+        if code.ends_with("CRS84") || code == "CRS:84" || number == "4326" {
+            Some(ImageryCrs::Geographic)
+        } else if number == "3857" || number == "900913" {
+            Some(ImageryCrs::WebMercator)
+        } else {
+            None
+        }
+    }
+}
+
+/// What a WMS server offers, from its GetCapabilities response.
+#[derive(Debug, Clone)]
+pub struct WmsCapabilities {
+    /// 1.1.1 or 1.3.0, which disagree on axis order.
+    pub version: String,
+    pub get_map_url: String,
+    pub formats: Vec<String>,
+    /// Every named layer, nested ones flattened.
+    pub layers: Vec<WmsLayer>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WmsLayer {
+    pub name: String,
+    pub title: String,
+    /// The codes it can be requested in, inherited ones included.
+    pub crs: Vec<String>,
+    pub bounds: GeoRectangle,
+}
+
+/// What a WMTS server offers, from its GetCapabilities response.
+#[derive(Debug, Clone)]
+pub struct WmtsCapabilities {
+    /// The KVP GetTile endpoint, if there is one.
+    pub get_tile_url: Option<String>,
+    pub layers: Vec<WmtsLayer>,
+    pub tile_matrix_sets: Vec<TileMatrixSet>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WmtsLayer {
+    pub identifier: String,
+    pub title: String,
+    /// The default style.
+    pub style: String,
+    pub formats: Vec<String>,
+    pub tile_matrix_sets: Vec<String>,
+    /// A RESTful tile URL template, e.g. `.../{TileMatrix}/{TileRow}/{TileCol}.png`.
+    pub resource_url: Option<String>,
+    pub bounds: GeoRectangle,
+}
+
+#[derive(Debug, Clone)]
+pub struct TileMatrixSet {
+    pub identifier: String,
+    pub crs: String,
+    /// Usually coarsest first.
+    pub matrices: Vec<TileMatrix>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TileMatrix {
+    pub identifier: String,
+    pub scale_denominator: f64,
+    /// Easting and northing of the top left corner, or longitude and latitude.
+    pub top_left: [f64; 2],
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub matrix_width: u32,
+    pub matrix_height: u32,
+}
+
+impl WmsCapabilities {
+    /// `url` is where the document came from, to resolve relative links.
+    pub fn parse(bytes: &[u8], url: &str) -> Result<Self, LoadError> {
+        WmsCapabilities::read(bytes, url).map_err(|message| decode_error(url, message))
+    }
+
+    fn read(bytes: &[u8], url: &str) -> Result<Self, String> {
+        let root = Element::parse(bytes)?;
+        if root.name != "WMS_Capabilities" && root.name != "WMT_MS_Capabilities" {
+            return Err(format!("expected WMS capabilities, found <{}>", root.name));
+        }
+        let version = root.attribute("version").unwrap_or("1.3.0").to_string();
+        let capability = root
+            .child("Capability")
+            .ok_or("capabilities without a <Capability>")?;
+        let get_map = capability
+            .path(&["Request", "GetMap"])
+            .ok_or("the server does not support GetMap")?;
+        let get_map_url = get_map
+            .path(&["DCPType", "HTTP", "Get", "OnlineResource"])
+            .and_then(|resource| resource.attribute("href"))
+            .map(|href| resolve_url(url, href))
+            .unwrap_or_else(|| url.split('?').next().unwrap_or(url).to_string());
+        let formats = get_map
+            .children("Format")
+            .map(|format| format.text().to_string())
+            .collect();
+
+        let mut layers = Vec::new();
+        for layer in capability.children("Layer") {
+            collect_wms_layers(layer, &[], GeoRectangle::WORLD, &mut layers);
+        }
+        Ok(WmsCapabilities {
+            version,
+            get_map_url,
+            formats,
+            layers,
+        })
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&WmsLayer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+}
+
+// Layers inherit their parent's projections and, lacking their own, bounds.
+fn collect_wms_layers(
+    element: &Element,
+    parent_crs: &[String],
+    parent_bounds: GeoRectangle,
+    layers: &mut Vec<WmsLayer>,
+) {
+    let mut crs = parent_crs.to_vec();
+    for code in element
+        .children("CRS")
+        .chain(element.children("SRS"))
+        .flat_map(|code| {
+            code.text()
+                .split_whitespace()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+    {
+        if !crs.contains(&code) {
+            crs.push(code);
+        }
+    }
+    let bounds = if let Some(bounds) = element.child("EX_GeographicBoundingBox") {
+        let value = |name| {
+            bounds
+                .child(name)
+                .and_then(|value| value.text().parse().ok())
+        };
+        match (
+            value("westBoundLongitude"),
+            value("southBoundLatitude"),
+            value("eastBoundLongitude"),
+            value("northBoundLatitude"),
+        ) {
+            (Some(west), Some(south), Some(east), Some(north)) => GeoRectangle {
+                west,
+                south,
+                east,
+                north,
+            },
+            _ => parent_bounds,
+        }
+    } else if let Some(bounds) = element.child("LatLonBoundingBox") {
+        let value = |name| bounds.attribute(name).and_then(|value| value.parse().ok());
+        match (value("minx"), value("miny"), value("maxx"), value("maxy")) {
+            (Some(west), Some(south), Some(east), Some(north)) => GeoRectangle {
+                west,
+                south,
+                east,
+                north,
+            },
+            _ => parent_bounds,
+        }
+    } else {
+        parent_bounds
+    };
+
+    if let Some(name) = element.child("Name") {
+        layers.push(WmsLayer {
+            name: name.text().to_string(),
+            title: element
+                .child("Title")
+                .map(Element::text)
+                .unwrap_or_default()
+                .to_string(),
+            crs: crs.clone(),
+            bounds,
+        });
+    }
+    for child in element.children("Layer") {
+        collect_wms_layers(child, &crs, bounds, layers);
+    }
+}
+
+impl WmtsCapabilities {
+    /// `url` is where the document came from, to resolve relative links.
+    pub fn parse(bytes: &[u8], url: &str) -> Result<Self, LoadError> {
+        WmtsCapabilities::read(bytes, url).map_err(|message| decode_error(url, message))
+    }
+
+    fn read(bytes: &[u8], url: &str) -> Result<Self, String> {
+        let root = Element::parse(bytes)?;
+        if root.name != "Capabilities" {
+            return Err(format!("expected WMTS capabilities, found <{}>", root.name));
+        }
+
+        // only a KVP endpoint can be queried without a template
+        let get_tile_url = root.child("OperationsMetadata").and_then(|metadata| {
+            metadata
+                .children("Operation")
+                .find(|operation| operation.attribute("name") == Some("GetTile"))?
+                .path(&["DCP", "HTTP"])?
+                .children("Get")
+                .find(|get| {
+                    get.path(&["Constraint", "AllowedValues", "Value"])
+                        .is_none_or(|value| value.text() == "KVP")
+                })?
+                .attribute("href")
+                .map(|href| resolve_url(url, href))
+        });
+
+        let contents = root
+            .child("Contents")
+            .ok_or("capabilities without <Contents>")?;
+        let layers = contents
+            .children("Layer")
+            .filter_map(|layer| {
+                let identifier = layer.child("Identifier")?.text().to_string();
+                let styles: Vec<&Element> = layer.children("Style").collect();
+                let style = styles
+                    .iter()
+                    .find(|style| style.attribute("isDefault") == Some("true"))
+                    .or(styles.first())
+                    .and_then(|style| style.child("Identifier"))
+                    .map_or("default", Element::text)
+                    .to_string();
+                let resource_url = layer
+                    .children("ResourceURL")
+                    .find(|resource| resource.attribute("resourceType") == Some("tile"))
+                    .and_then(|resource| resource.attribute("template"))
+                    .map(|template| resolve_url(url, template));
+                Some(WmtsLayer {
+                    identifier,
+                    title: layer
+                        .child("Title")
+                        .map(Element::text)
+                        .unwrap_or_default()
+                        .to_string(),
+                    style,
+                    formats: layer
+                        .children("Format")
+                        .map(|format| format.text().to_string())
+                        .collect(),
+                    tile_matrix_sets: layer
+                        .children("TileMatrixSetLink")
+                        .filter_map(|link| link.child("TileMatrixSet"))
+                        .map(|set| set.text().to_string())
+                        .collect(),
+                    resource_url,
+                    bounds: layer
+                        .child("WGS84BoundingBox")
+                        .and_then(wgs84_bounding_box)
+                        .unwrap_or(GeoRectangle::WORLD),
+                })
+            })
+            .collect();
+
+        let tile_matrix_sets = contents
+            .children("TileMatrixSet")
+            .filter_map(|set| {
+                let crs = set.child("SupportedCRS")?.text().to_string();
+                // EPSG:4326 corners are latitude first, CRS:84 and the rest easting first
+                let swap = crs.to_ascii_uppercase().ends_with("EPSG::4326")
+                    || crs.eq_ignore_ascii_case("EPSG:4326");
+                let matrices = set
+                    .children("TileMatrix")
+                    .filter_map(|matrix| {
+                        let number =
+                            |name| -> Option<f64> { matrix.child(name)?.text().parse().ok() };
+                        let corner: Vec<f64> = matrix
+                            .child("TopLeftCorner")?
+                            .text()
+                            .split_whitespace()
+                            .filter_map(|value| value.parse().ok())
+                            .collect();
+                        let &[a, b] = corner.as_slice() else {
+                            return None;
+                        };
+                        Some(TileMatrix {
+                            identifier: matrix.child("Identifier")?.text().to_string(),
+                            scale_denominator: number("ScaleDenominator")?,
+                            top_left: if swap { [b, a] } else { [a, b] },
+                            tile_width: number("TileWidth")? as u32,
+                            tile_height: number("TileHeight")? as u32,
+                            matrix_width: number("MatrixWidth")? as u32,
+                            matrix_height: number("MatrixHeight")? as u32,
+                        })
+                    })
+                    .collect();
+                Some(TileMatrixSet {
+                    identifier: set.child("Identifier")?.text().to_string(),
+                    crs,
+                    matrices,
+                })
+            })
+            .collect();
+
+        Ok(WmtsCapabilities {
+            get_tile_url,
+            layers,
+            tile_matrix_sets,
+        })
+    }
+
+    pub fn layer(&self, identifier: &str) -> Option<&WmtsLayer> {
+        self.layers
+            .iter()
+            .find(|layer| layer.identifier == identifier)
+    }
+
+    pub fn tile_matrix_set(&self, identifier: &str) -> Option<&TileMatrixSet> {
+        self.tile_matrix_sets
+            .iter()
+            .find(|set| set.identifier == identifier)
+    }
+}
+
+fn decode_error(url: &str, message: String) -> LoadError {
+    LoadError::Decode {
+        path: url.to_string(),
+        message,
+    }
+}
+
+fn wgs84_bounding_box(element: &Element) -> Option<GeoRectangle> {
+    let corner = |name| -> Option<(f32, f32)> {
+        let mut values = element.child(name)?.text().split_whitespace();
+        Some((values.next()?.parse().ok()?, values.next()?.parse().ok()?))
+    };
+    let (west, south) = corner("LowerCorner")?;
+    let (east, north) = corner("UpperCorner")?;
+    Some(GeoRectangle {
+        west,
+        south,
+        east,
+        north,
+    })
+}
+
+/// Appends `params` to a URL that may already carry a query string.
+pub fn with_query(url: &str, params: &[(&str, &str)]) -> String {
+    let mut url = url.to_string();
+    if !url.contains('?') {
+        url.push('?');
+    } else if !url.ends_with('?') && !url.ends_with('&') {
+        url.push('&');
+    }
+    let query: Vec<String> = params
+        .iter()
+        .map(|(key, value)| format!("{key}={}", encode_query_value(value)))
+        .collect();
+    url + &query.join("&")
+}
+
+// Commas are left alone, as they separate BBOX and LAYERS values.
+fn encode_query_value(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b',' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+// Links in capabilities may be relative to the document.
+fn resolve_url(base: &str, href: &str) -> String {
+    if href.contains("://") {
+        return href.to_string();
+    }
+    let base = base.split('?').next().unwrap_or(base);
+    if href.starts_with('/') {
+        let origin_end = base
+            .find("://")
+            .and_then(|scheme| base[scheme + 3..].find('/').map(|path| scheme + 3 + path))
+            .unwrap_or(base.len());
+        format!("{}{href}", &base[..origin_end])
+    } else {
+        let directory = base
+            .rsplit_once('/')
+            .map_or(base, |(directory, _)| directory);
+        format!("{directory}/{href}")
+    }
+}
+
+// Just enough of a DOM to walk capabilities documents, with namespace
+// prefixes dropped.
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    fn parse(bytes: &[u8]) -> Result<Element, String> {
+        let mut stack: Vec<Element> = Vec::new();
+        for event in EventReader::new(bytes) {
+            match event.map_err(|e| e.to_string())? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => stack.push(Element {
+                    name: name.local_name,
+                    attributes: attributes
+                        .into_iter()
+                        .map(|attribute| (attribute.name.local_name, attribute.value))
+                        .collect(),
+                    children: Vec::new(),
+                    text: String::new(),
+                }),
+                XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                    if let Some(element) = stack.last_mut() {
+                        element.text.push_str(&text);
+                    }
+                }
+                XmlEvent::EndElement { .. } => {
+                    let element = stack.pop().ok_or("unbalanced XML")?;
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(element),
+                        None => return Ok(element),
+                    }
+                }
+                _ => {}
+            }
+        }
+        Err("the document has no root element".to_string())
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    fn path(&self, names: &[&str]) -> Option<&Element> {
+        names
+            .iter()
+            .try_fold(self, |element, name| element.child(name))
+    }
+
+    fn text(&self) -> &str {
+        self.text.trim()
+    }
+}
@@ -0,0 +1,239 @@
+use std::{
+    collections::HashMap,
+    io::Read,
+    sync::{Arc, Mutex},
+};
+
+use super::{archive::ArchiveLocation, LoadError};
+
+pub const HEADER_SIZE: u64 = 127;
+
+// directories deeper than this are a loop in a corrupt archive
+const MAX_DEPTH: usize = 4;
+// leaf directories kept between lookups before the cache starts over
+const MAX_CACHED_LEAVES: usize = 64;
+
+// compression codes
+const UNKNOWN: u8 = 0;
+const NONE: u8 = 1;
+const GZIP: u8 = 2;
+const ZSTD: u8 = 4;
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    tile_id: u64,
+    offset: u64,
+    length: u64,
+    // 0 for a leaf directory, else how many consecutive tile ids share the data
+    run_length: u32,
+}
+
+/// A PMTiles (version 3) archive: tiles addressed along a Hilbert curve
+/// through a root directory and leaf directories, all read by byte range.
+pub struct PmTiles {
+    location: ArchiveLocation,
+    root: Vec<Entry>,
+    leaf_directories: u64,
+    tile_data: u64,
+    internal_compression: u8,
+    tile_compression: u8,
+    leaves: Mutex<HashMap<u64, Arc<Vec<Entry>>>>,
+    /// 1 for vector tiles, 2 PNG, 3 JPEG, 4 WebP and 5 AVIF.
+    pub tile_type: u8,
+    pub min_zoom: u8,
+    pub max_zoom: u8,
+}
+
+impl PmTiles {
+    /// Reads the root directory. `header` is the first `HEADER_SIZE` bytes of
+    /// the file.
+    pub async fn open(location: ArchiveLocation, header: &[u8]) -> Result<PmTiles, LoadError> {
+        if header.len() < HEADER_SIZE as usize || !header.starts_with(b"PMTiles") {
+            return Err(decode_error(&location, "not a PMTiles archive"));
+        }
+        if header[7] != 3 {
+            return Err(decode_error(
+                &location,
+                &format!("PMTiles version {} is not supported", header[7]),
+            ));
+        }
+        let u64_at = |i: usize| u64::from_le_bytes(header[i..i + 8].try_into().unwrap());
+
+        let mut pmtiles = PmTiles {
+            root: Vec::new(),
+            leaf_directories: u64_at(40),
+            tile_data: u64_at(56),
+            internal_compression: header[97],
+            tile_compression: header[98],
+            leaves: Mutex::new(HashMap::new()),
+            tile_type: header[99],
+            min_zoom: header[100],
+            max_zoom: header[101],
+            location,
+        };
+        pmtiles.root = pmtiles.directory(u64_at(8), u64_at(16)).await?;
+        Ok(pmtiles)
+    }
+
+    /// The tile at `zoom`, `x` and `y`, with `y` counted down from the north.
+    pub async fn tile(&self, zoom: u8, x: u32, y: u32) -> Result<Option<Vec<u8>>, LoadError> {
+        let tile_id = tile_id(zoom, x, y);
+        let mut leaf: Option<Arc<Vec<Entry>>> = None;
+        for _ in 0..MAX_DEPTH {
+            let directory = leaf.as_deref().unwrap_or(&self.root);
+            let Some(entry) = find_entry(directory, tile_id) else {
+                return Ok(None);
+            };
+            if entry.run_length > 0 {
+                let bytes = self
+                    .location
+                    .read(self.tile_data + entry.offset, entry.length)
+                    .await?;
+                return decompress(bytes, self.tile_compression)
+                    .map(Some)
+                    .map_err(|message| decode_error(&self.location, &message));
+            }
+            leaf = Some(self.leaf(entry.offset, entry.length).await?);
+        }
+        Err(decode_error(
+            &self.location,
+            "leaf directories nest too deeply",
+        ))
+    }
+
+    async fn leaf(&self, offset: u64, length: u64) -> Result<Arc<Vec<Entry>>, LoadError> {
+        if let Some(leaf) = self.leaves.lock().unwrap().get(&offset) {
+            return Ok(leaf.clone());
+        }
+        let leaf = Arc::new(
+            self.directory(self.leaf_directories + offset, length)
+                .await?,
+        );
+        let mut leaves = self.leaves.lock().unwrap();
+        if leaves.len() >= MAX_CACHED_LEAVES {
+            leaves.clear();
+        }
+        leaves.insert(offset, leaf.clone());
+        Ok(leaf)
+    }
+
+    async fn directory(&self, offset: u64, length: u64) -> Result<Vec<Entry>, LoadError> {
+        let bytes = self.location.read(offset, length).await?;
+        decompress(bytes, self.internal_compression)
+            .and_then(|bytes| parse_directory(&bytes))
+            .map_err(|message| decode_error(&self.location, &message))
+    }
+}
+
+fn decode_error(location: &ArchiveLocation, message: &str) -> LoadError {
+    LoadError::Decode {
+        path: location.to_string(),
+        message: message.to_string(),
+    }
+}
+
+fn decompress(bytes: Vec<u8>, compression: u8) -> Result<Vec<u8>, String> {
+    let mut decompressed = Vec::new();
+    match compression {
+        UNKNOWN | NONE => return Ok(bytes),
+        GZIP => {
+            flate2::read::GzDecoder::new(bytes.as_slice())
+                .read_to_end(&mut decompressed)
+                .map_err(|e| e.to_string())?;
+        }
+        ZSTD => {
+            ruzstd::StreamingDecoder::new(bytes.as_slice())
+                .map_err(|e| e.to_string())?
+                .read_to_end(&mut decompressed)
+                .map_err(|e| e.to_string())?;
+        }
+        _ => return Err(format!("compression {compression} is not supported")),
+    }
+    Ok(decompressed)
+}
+
+fn parse_directory(bytes: &[u8]) -> Result<Vec<Entry>, String> {
+    let mut at = 0;
+    let mut next = || {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = *bytes.get(at).ok_or("the directory is truncated")?;
+            at += 1;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("a directory varint is too long")
+    };
+
+    let count = next()? as usize;
+    if count > bytes.len() {
+        return Err("the directory is truncated".to_string());
+    }
+    let mut entries = vec![
+        Entry {
+            tile_id: 0,
+            offset: 0,
+            length: 0,
+            run_length: 0,
+        };
+        count
+    ];
+    let mut tile_id = 0;
+    for entry in entries.iter_mut() {
+        tile_id += next()?;
+        entry.tile_id = tile_id;
+    }
+    for entry in entries.iter_mut() {
+        entry.run_length = next()? as u32;
+    }
+    for entry in entries.iter_mut() {
+        entry.length = next()?;
+    }
+    for i in 0..count {
+        let offset = next()?;
+        entries[i].offset = if offset == 0 && i > 0 {
+            // right after the previous entry's data
+            entries[i - 1].offset + entries[i - 1].length
+        } else {
+            offset.wrapping_sub(1)
+        };
+    }
+    Ok(entries)
+}
+
+// the last entry at or before `tile_id`, if it covers it
+fn find_entry(directory: &[Entry], tile_id: u64) -> Option<Entry> {
+    let i = directory.partition_point(|entry| entry.tile_id <= tile_id);
+    let entry = *directory.get(i.checked_sub(1)?)?;
+    if entry.run_length == 0 || tile_id < entry.tile_id + entry.run_length as u64 {
+        Some(entry)
+    } else {
+        None
+    }
+}
+
+/// The PMTiles tile id of `zoom`, `x` and `y`: every tile of the shallower
+/// zoom levels comes first, then the tile's place along this level's Hilbert
+/// curve.
+pub fn tile_id(zoom: u8, x: u32, y: u32) -> u64 {
+    let first = ((1u64 << (2 * zoom as u32)) - 1) / 3;
+    let (mut x, mut y) = (x as u64, y as u64);
+    let mut d = 0;
+    let mut s = (1u64 << zoom) / 2;
+    while s > 0 {
+        let rx = (x & s > 0) as u64;
+        let ry = (y & s > 0) as u64;
+        d += s * s * ((3 * rx) ^ ry);
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - (x & (s - 1));
+                y = s - 1 - (y & (s - 1));
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    first + d
+}
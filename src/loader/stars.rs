@@ -0,0 +1,119 @@
+use super::{AssetLoader, LoadError};
+
+/// A star's J2000 position and brightness, as listed in a catalog.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Star {
+    // J2000 right ascension and declination, degrees
+    pub ra: f64,
+    pub dec: f64,
+    // visual magnitude
+    pub magnitude: f32,
+    // B-V color index
+    pub color_index: f32,
+}
+
+/// How a star catalog file is laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StarCatalogFormat {
+    /// `ra,dec,vmag,bv` rows in degrees, with an optional header, trailing
+    /// columns and `#` comments. See `assets/bright_stars.csv`.
+    Csv,
+    /// The fixed-width `catalog` file of the Yale Bright Star Catalogue, 5th
+    /// edition (VizieR V/50), with its J2000 columns.
+    YaleBsc5,
+}
+
+impl StarCatalogFormat {
+    /// `.csv` files are CSV, anything else is taken to be the BSC5 `catalog`.
+    pub fn from_path(path: &str) -> Self {
+        if path.to_ascii_lowercase().ends_with(".csv") {
+            StarCatalogFormat::Csv
+        } else {
+            StarCatalogFormat::YaleBsc5
+        }
+    }
+
+    pub fn parse(self, text: &str) -> Result<Vec<Star>, String> {
+        match self {
+            StarCatalogFormat::Csv => parse_csv(text),
+            StarCatalogFormat::YaleBsc5 => Ok(parse_bsc5(text)),
+        }
+    }
+}
+
+fn parse_csv(text: &str) -> Result<Vec<Star>, String> {
+    let mut stars = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("ra") {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let field = |index: usize| -> Result<f64, String> {
+            fields
+                .get(index)
+                .and_then(|field| field.parse().ok())
+                .ok_or_else(|| format!("line {}: expected ra,dec,vmag,bv", i + 1))
+        };
+        stars.push(Star {
+            ra: field(0)?,
+            dec: field(1)?,
+            magnitude: field(2)? as f32,
+            // unknown colors are drawn white
+            color_index: field(3).unwrap_or(0.0) as f32,
+        });
+    }
+    Ok(stars)
+}
+
+// A handful of BSC5 entries are novae or non-stellar objects with no
+// position; those are skipped.
+fn parse_bsc5(text: &str) -> Vec<Star> {
+    // 1-based, inclusive byte ranges from the catalog's ReadMe
+    let column = |line: &str, from: usize, to: usize| -> Option<f64> {
+        line.get(from - 1..to.min(line.len()))?.trim().parse().ok()
+    };
+
+    text.lines()
+        .filter_map(|line| {
+            let ra = column(line, 76, 77)?
+                + column(line, 78, 79)? / 60.0
+                + column(line, 80, 83)? / 3600.0;
+            let dec = column(line, 85, 86)?
+                + column(line, 87, 88)? / 60.0
+                + column(line, 89, 90)? / 3600.0;
+            let sign = if line.get(83..84) == Some("-") {
+                -1.0
+            } else {
+                1.0
+            };
+            Some(Star {
+                ra: ra * 15.0,
+                dec: sign * dec,
+                magnitude: column(line, 103, 107)? as f32,
+                color_index: column(line, 110, 114).unwrap_or(0.0) as f32,
+            })
+        })
+        .collect()
+}
+
+impl AssetLoader {
+    /// Loads a star catalog, in a format picked by [`StarCatalogFormat::from_path`].
+    pub async fn load_star_catalog(&self, path: &str) -> Result<Vec<Star>, LoadError> {
+        let bytes = self.load(path).await?;
+        let decode_error = |message: String| LoadError::Decode {
+            path: path.to_string(),
+            message,
+        };
+
+        let text = String::from_utf8_lossy(&bytes);
+        let stars = StarCatalogFormat::from_path(path)
+            .parse(&text)
+            .map_err(decode_error)?;
+        if stars.is_empty() {
+            return Err(decode_error("no stars found".to_string()));
+        }
+        Ok(stars)
+    }
+}
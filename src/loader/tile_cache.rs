@@ -0,0 +1,70 @@
+use std::{collections::HashMap, sync::Arc};
+
+use image::RgbaImage;
+
+/// Decoded memory the tile cache may hold unless told otherwise.
+pub const DEFAULT_TILE_CACHE_BUDGET: usize = 128 * 1024 * 1024;
+
+/// Decoded tiles by URL, so panning back over them neither fetches nor decodes
+/// them again. Past its budget the least recently used are dropped.
+pub struct TileImageCache {
+    budget: usize,
+    size: usize,
+    tick: u64,
+    images: HashMap<String, (Arc<RgbaImage>, u64)>,
+}
+
+impl TileImageCache {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            size: 0,
+            tick: 0,
+            images: HashMap::new(),
+        }
+    }
+
+    pub fn get(&mut self, key: &str) -> Option<Arc<RgbaImage>> {
+        self.tick += 1;
+        let (image, last_used) = self.images.get_mut(key)?;
+        *last_used = self.tick;
+        Some(image.clone())
+    }
+
+    pub fn insert(&mut self, key: String, image: Arc<RgbaImage>) {
+        self.tick += 1;
+        self.size += image.as_raw().len();
+        if let Some((replaced, _)) = self.images.insert(key, (image, self.tick)) {
+            self.size -= replaced.as_raw().len();
+        }
+        self.evict();
+    }
+
+    /// Bytes of decoded pixels to keep, dropping tiles now if it shrank.
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while self.size > self.budget {
+            let Some(oldest) = self
+                .images
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            if let Some((image, _)) = self.images.remove(&oldest) {
+                self.size -= image.as_raw().len();
+            }
+        }
+    }
+}
+
+impl Default for TileImageCache {
+    fn default() -> Self {
+        TileImageCache::new(DEFAULT_TILE_CACHE_BUDGET)
+    }
+}
@@ -1,5 +1,4 @@
 use hypersphere::run;
-use pollster;
 
 #[tokio::main]
 async fn main() {
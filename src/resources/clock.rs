@@ -0,0 +1,31 @@
+use anise::prelude::Epoch;
+use bevy_ecs::system::Resource;
+use chrono::{DateTime, Utc};
+
+/// Scene time, advanced every frame by the wall-clock time elapsed
+/// scaled by `multiplier`.
+#[derive(Resource)]
+pub struct SimulationClock {
+    pub epoch: Epoch,
+    pub multiplier: f64,
+    pub paused: bool,
+    pub last_tick: DateTime<Utc>,
+}
+
+impl SimulationClock {
+    pub fn new() -> Self {
+        let now = Utc::now();
+        Self {
+            epoch: Epoch::from_unix_milliseconds(now.timestamp_millis() as f64),
+            multiplier: 1.0,
+            paused: false,
+            last_tick: now,
+        }
+    }
+}
+
+impl Default for SimulationClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,11 @@
+use anise::almanac::Almanac;
+use bevy_ecs::system::Resource;
+
+/// Planetary ephemerides, inserted into the world once the kernels have loaded.
+/// Systems that place bodies by time skip their work until it exists.
+#[derive(Resource)]
+pub struct Ephemeris {
+    pub almanac: Almanac,
+    // paths of the kernels merged into `almanac`, in load order
+    pub kernels: Vec<String>,
+}
@@ -0,0 +1,86 @@
+use std::collections::HashSet;
+
+use bevy_ecs::system::Resource;
+use winit::event::{MouseButton, VirtualKeyCode};
+
+/// Something the user does to the viewer, whatever input it is bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputAction {
+    /// Circles the camera around its target while held.
+    OrbitLeft,
+    OrbitRight,
+    /// Moves the camera toward its target while held.
+    ZoomIn,
+    ZoomOut,
+    /// Drops a billboard on the globe under the pointer.
+    PlaceMarker,
+    /// Adds the globe point under the pointer to the measurement.
+    Measure,
+    /// Reports what is under the pointer as a
+    /// [`ViewerEvent::Click`](crate::ViewerEvent::Click), then measures or
+    /// drops a billboard there if the viewer is set to.
+    Select,
+}
+
+impl InputAction {
+    /// Whether the action lasts as long as its input is held, rather than
+    /// happening once when it is pressed.
+    pub fn is_held(&self) -> bool {
+        matches!(
+            self,
+            InputAction::OrbitLeft
+                | InputAction::OrbitRight
+                | InputAction::ZoomIn
+                | InputAction::ZoomOut
+        )
+    }
+}
+
+/// A key, mouse button or touch that can trigger actions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputTrigger {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+    /// A finger on the surface, any finger. Touch acts like a button at the
+    /// pointer: it can select, measure or place a marker, or hold an action
+    /// while a finger is down, but there are no drag or pinch gestures, so
+    /// it can't steer the camera by itself.
+    Touch,
+}
+
+/// Which actions each input triggers. An input can trigger several actions
+/// and an action be triggered by several inputs.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct InputBindings {
+    pub bindings: Vec<(InputTrigger, InputAction)>,
+}
+
+impl Default for InputBindings {
+    // W/S or up/down to zoom, A/D or left/right to orbit, and a left click or
+    // a tap to select
+    fn default() -> Self {
+        use InputAction::*;
+        use InputTrigger::*;
+        InputBindings {
+            bindings: vec![
+                (Key(VirtualKeyCode::W), ZoomIn),
+                (Key(VirtualKeyCode::Up), ZoomIn),
+                (Key(VirtualKeyCode::S), ZoomOut),
+                (Key(VirtualKeyCode::Down), ZoomOut),
+                (Key(VirtualKeyCode::A), OrbitLeft),
+                (Key(VirtualKeyCode::Left), OrbitLeft),
+                (Key(VirtualKeyCode::D), OrbitRight),
+                (Key(VirtualKeyCode::Right), OrbitRight),
+                (Mouse(MouseButton::Left), Select),
+                (Touch, Select),
+            ],
+        }
+    }
+}
+
+/// Which inputs are holding down each held action. An action held by several
+/// inputs at once is released only when the last of them is.
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct HeldInputs {
+    pub held: HashSet<(InputTrigger, InputAction)>,
+}
@@ -0,0 +1,4 @@
+pub mod clock;
+pub mod ephemeris;
+pub mod input;
+pub mod solar_system;
@@ -0,0 +1,17 @@
+use bevy_ecs::system::Resource;
+
+use crate::components::render_pipelines::RenderPipelineComponent;
+
+/// Present while solar-system mode is on.
+#[derive(Resource)]
+pub struct SolarSystem {
+    // multiplies planet radii so they stay visible at AU distances
+    pub radius_scale: f32,
+    pub show_orbits: bool,
+    // orbits need the ephemeris, which may arrive after the mode is enabled
+    pub orbits_built: bool,
+}
+
+/// The one pipeline every `CelestialBodyComponent` is drawn with.
+#[derive(Resource)]
+pub struct BodyPipeline(pub RenderPipelineComponent);
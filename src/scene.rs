@@ -0,0 +1,152 @@
+use std::{fmt, str::FromStr};
+
+use anise::prelude::Epoch;
+use serde::{Deserialize, Serialize};
+
+use crate::components::imagery::{ImageryLayerSource, ImageryLayerStyle};
+
+/// The document version [`Viewer::save_scene`](crate::Viewer::save_scene)
+/// writes and the newest [`Viewer::load_scene`](crate::Viewer::load_scene)
+/// reads.
+pub const SCENE_VERSION: u32 = 1;
+
+/// Everything the host put into a viewer, as plain data that round-trips
+/// through JSON or RON: the camera pose, simulation time, billboards, lines,
+/// polygons and imagery layers with their styles. Textures and tiles are not
+/// embedded; layers name the image, archive or server they were read from
+/// and are fetched again on load.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneDocument {
+    pub version: u32,
+    pub camera: SceneCamera,
+    pub time: SceneTime,
+    #[serde(default)]
+    pub billboards: Vec<SceneBillboard>,
+    #[serde(default)]
+    pub polylines: Vec<ScenePolyline>,
+    #[serde(default)]
+    pub polygons: Vec<ScenePolyline>,
+    /// Bottom of each globe's stack first.
+    #[serde(default)]
+    pub imagery_layers: Vec<SceneImageryLayer>,
+}
+
+/// Where the camera is and what it looks at, in world kilometers.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SceneCamera {
+    pub eye: [f32; 3],
+    pub target: [f32; 3],
+    pub up: [f32; 3],
+    /// Vertical field of view in degrees.
+    pub fovy: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SceneTime {
+    /// Written like `2024-03-20T03:06:00 UTC`.
+    #[serde(with = "epoch_text")]
+    pub epoch: Epoch,
+    pub multiplier: f64,
+    pub paused: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SceneBillboard {
+    pub lat: f32,
+    pub lon: f32,
+}
+
+/// A polyline, or the ring of a polygon, through (lat, lon) vertices.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScenePolyline {
+    pub coordinates: Vec<(f32, f32)>,
+    pub color: [f32; 4],
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneImageryLayer {
+    /// The globe the layer is draped over, as named by
+    /// [`Viewer::globe_entity`](crate::Viewer::globe_entity).
+    pub globe: String,
+    pub source: ImageryLayerSource,
+    pub style: ImageryLayerStyle,
+}
+
+/// Why a scene could not be written out or read back.
+#[derive(Debug)]
+pub enum SceneError {
+    /// The text is not a valid document in the format it was read as.
+    Format {
+        format: &'static str,
+        message: String,
+    },
+    /// The document was saved by a newer version of the viewer.
+    UnsupportedVersion { version: u32 },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Format { format, message } => {
+                write!(f, "invalid {format} scene: {message}")
+            }
+            SceneError::UnsupportedVersion { version } => write!(
+                f,
+                "scene version {version} is newer than the supported {SCENE_VERSION}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl SceneDocument {
+    pub fn to_json(&self) -> Result<String, SceneError> {
+        serde_json::to_string_pretty(self).map_err(|e| SceneError::Format {
+            format: "JSON",
+            message: e.to_string(),
+        })
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, SceneError> {
+        serde_json::from_str(text).map_err(|e| SceneError::Format {
+            format: "JSON",
+            message: e.to_string(),
+        })
+    }
+
+    pub fn to_ron(&self) -> Result<String, SceneError> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).map_err(|e| {
+            SceneError::Format {
+                format: "RON",
+                message: e.to_string(),
+            }
+        })
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self, SceneError> {
+        ron::from_str(text).map_err(|e| SceneError::Format {
+            format: "RON",
+            message: e.to_string(),
+        })
+    }
+}
+
+// epochs as the text `Epoch` displays and parses, so documents stay readable
+mod epoch_text {
+    use super::*;
+
+    pub fn serialize<S: serde::Serializer>(
+        epoch: &Epoch,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(epoch)
+    }
+
+    pub fn deserialize<'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Epoch, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        Epoch::from_str(&text).map_err(serde::de::Error::custom)
+    }
+}
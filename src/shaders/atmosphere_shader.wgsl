@@ -0,0 +1,161 @@
+const PI: f32 = 3.141592653589793;
+
+// samples along the view ray, and along each sample's ray to the Sun
+const VIEW_SAMPLES: i32 = 16;
+const LIGHT_SAMPLES: i32 = 8;
+
+// sea-level coefficients per kilometer (Bruneton & Neyret 2008)
+const RAYLEIGH_SCATTERING: vec3<f32> = vec3<f32>(5.802e-3, 13.558e-3, 33.1e-3);
+const MIE_SCATTERING: f32 = 3.996e-3;
+const MIE_EXTINCTION: f32 = 4.44e-3;
+// density falls off by e every scale height, kilometers
+const RAYLEIGH_SCALE_HEIGHT: f32 = 8.0;
+const MIE_SCALE_HEIGHT: f32 = 1.2;
+// how strongly aerosols scatter forwards
+const MIE_G: f32 = 0.8;
+const SUN_INTENSITY: f32 = 20.0;
+
+struct CameraUniform {
+    view_proj_matrix: mat4x4<f32>,
+    view_matrix: mat4x4<f32>,
+};
+
+struct AtmosphereUniform {
+    sun_direction: vec4<f32>,
+    radii: vec4<f32>,
+};
+
+struct ModelUniform {
+    model: mat4x4<f32>,
+};
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+    @location(1) eye: vec3<f32>,
+};
+
+@group(0) @binding(0) var<uniform> camera: CameraUniform;
+@group(1) @binding(0) var<uniform> atmosphere: AtmosphereUniform;
+@group(2) @binding(0) var<uniform> model_uniform: ModelUniform;
+
+@vertex
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    let world_position = model_uniform.model * vec4<f32>(model.position, 1.0);
+    out.clip_position = camera.view_proj_matrix * world_position;
+    out.world_position = world_position.xyz;
+
+    // The camera is only visible to vertex shaders, so find the eye here
+    let view_rotation = mat3x3<f32>(
+        camera.view_matrix[0].xyz,
+        camera.view_matrix[1].xyz,
+        camera.view_matrix[2].xyz
+    );
+    out.eye = -(transpose(view_rotation) * camera.view_matrix[3].xyz);
+
+    return out;
+}
+
+// Distances along the ray to where it enters and leaves a sphere at the
+// origin; x > y when it misses.
+fn ray_sphere(origin: vec3<f32>, direction: vec3<f32>, radius: f32) -> vec2<f32> {
+    let b = dot(origin, direction);
+    let c = dot(origin, origin) - radius * radius;
+    let discriminant = b * b - c;
+    if discriminant < 0.0 {
+        return vec2<f32>(1e9, -1e9);
+    }
+    let s = sqrt(discriminant);
+    return vec2<f32>(-b - s, -b + s);
+}
+
+// Rayleigh and Mie optical depth from `point` to the Sun, or -1 in the
+// planet's shadow.
+fn light_optical_depth(point: vec3<f32>, sun: vec3<f32>) -> vec2<f32> {
+    let planet = ray_sphere(point, sun, atmosphere.radii.x);
+    if planet.x < planet.y && planet.x > 0.0 {
+        return vec2<f32>(-1.0);
+    }
+
+    let distance = ray_sphere(point, sun, atmosphere.radii.y).y;
+    let segment = distance / f32(LIGHT_SAMPLES);
+    var depth = vec2<f32>(0.0);
+    for (var i = 0; i < LIGHT_SAMPLES; i++) {
+        let sample = point + sun * segment * (f32(i) + 0.5);
+        let height = length(sample) - atmosphere.radii.x;
+        depth += vec2<f32>(
+            exp(-height / RAYLEIGH_SCALE_HEIGHT),
+            exp(-height / MIE_SCALE_HEIGHT)
+        ) * segment;
+    }
+    return depth;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // The shell is drawn from the inside, so every pixel the atmosphere
+    // covers is shaded once whether the camera is in space or on the ground
+    let eye = in.eye;
+    let direction = normalize(in.world_position - eye);
+    let sun = atmosphere.sun_direction.xyz;
+
+    let shell = ray_sphere(eye, direction, atmosphere.radii.y);
+    if shell.x > shell.y || shell.y < 0.0 {
+        discard;
+    }
+    let start = max(shell.x, 0.0);
+    var end = shell.y;
+    let ground = ray_sphere(eye, direction, atmosphere.radii.x);
+    if ground.x < ground.y && ground.x > 0.0 {
+        end = min(end, ground.x);
+    }
+
+    let segment = (end - start) / f32(VIEW_SAMPLES);
+    var view_depth = vec2<f32>(0.0);
+    var rayleigh = vec3<f32>(0.0);
+    var mie = vec3<f32>(0.0);
+    for (var i = 0; i < VIEW_SAMPLES; i++) {
+        let sample = eye + direction * (start + segment * (f32(i) + 0.5));
+        let height = length(sample) - atmosphere.radii.x;
+        let density = vec2<f32>(
+            exp(-height / RAYLEIGH_SCALE_HEIGHT),
+            exp(-height / MIE_SCALE_HEIGHT)
+        ) * segment;
+        view_depth += density;
+
+        let light_depth = light_optical_depth(sample, sun);
+        if light_depth.x < 0.0 {
+            continue;
+        }
+        let total_depth = view_depth + light_depth;
+        let attenuation = exp(
+            -(RAYLEIGH_SCATTERING * total_depth.x + MIE_EXTINCTION * total_depth.y)
+        );
+        rayleigh += density.x * attenuation;
+        mie += density.y * attenuation;
+    }
+
+    let mu = dot(direction, sun);
+    let rayleigh_phase = 3.0 / (16.0 * PI) * (1.0 + mu * mu);
+    let g2 = MIE_G * MIE_G;
+    let mie_phase = 3.0 / (8.0 * PI) * ((1.0 - g2) * (1.0 + mu * mu))
+        / ((2.0 + g2) * pow(1.0 + g2 - 2.0 * MIE_G * mu, 1.5));
+
+    let scattered = SUN_INTENSITY
+        * (rayleigh * RAYLEIGH_SCATTERING * rayleigh_phase + mie * MIE_SCATTERING * mie_phase);
+    let transmittance = exp(
+        -(RAYLEIGH_SCATTERING * view_depth.x + MIE_EXTINCTION * view_depth.y)
+    );
+
+    // Premultiplied: add the scattered light, dim what's behind by the
+    // transmittance
+    let color = 1.0 - exp(-scattered);
+    let alpha = 1.0 - (transmittance.r + transmittance.g + transmittance.b) / 3.0;
+    return vec4<f32>(color, alpha);
+}
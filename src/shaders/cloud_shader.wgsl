@@ -0,0 +1,78 @@
+// kept faintly visible on the night side, like the ground below
+const AMBIENT: f32 = 0.02;
+
+struct CameraUniform {
+    view_proj_matrix: mat4x4<f32>,
+    view_matrix: mat4x4<f32>,
+};
+
+// the Earth's surface uniform, shared so the shell matches its shadows
+struct EarthSurfaceUniform {
+    sun_direction: vec4<f32>,
+    clouds: vec4<f32>,
+    overlay: vec4<f32>,
+};
+
+struct ModelUniform {
+    model: mat4x4<f32>,
+};
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec3<f32>,
+};
+
+@group(0) @binding(0) var<uniform> camera: CameraUniform;
+@group(2) @binding(0) var<uniform> model_uniform: ModelUniform;
+
+@vertex
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    let world_position = model_uniform.model * vec4<f32>(model.position, 1.0);
+    out.clip_position = camera.view_proj_matrix * world_position;
+    out.tex_coords = normalize(model.position);
+
+    return out;
+}
+
+// fragment
+@group(1) @binding(0) var cloudTexture: texture_cube<f32>;
+@group(1) @binding(1) var cloudSampler: sampler;
+@group(1) @binding(2) var<uniform> surface: EarthSurfaceUniform;
+
+// Turns a direction west by `angle` about the pole, to look up clouds that
+// have drifted east by that much
+fn undrift(direction: vec3<f32>, angle: f32) -> vec3<f32> {
+    let c = cos(angle);
+    let s = sin(angle);
+    return vec3<f32>(
+        c * direction.x - s * direction.z,
+        direction.y,
+        s * direction.x + c * direction.z
+    );
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let cover = textureSample(
+        cloudTexture,
+        cloudSampler,
+        undrift(in.tex_coords, surface.clouds.x)
+    ).r;
+
+    var light = 1.0;
+    if surface.sun_direction.w != 0.0 {
+        let normal = normalize(in.tex_coords);
+        let sun = surface.sun_direction.xyz;
+        let daylight = smoothstep(-0.1, 0.1, dot(normal, sun));
+        light = AMBIENT + (1.0 - AMBIENT) * max(dot(normal, sun), 0.0) * daylight;
+    }
+
+    // premultiplied white, as opaque as the cover is thick
+    return vec4<f32>(vec3<f32>(light * cover), cover);
+}
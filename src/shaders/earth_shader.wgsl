@@ -1,57 +1,159 @@
-const PI: f32 = 3.141592653589793;
+// kept faintly visible on the night side
+const AMBIENT: f32 = 0.02;
+const SPECULAR_STRENGTH: f32 = 0.6;
+const SHININESS: f32 = 40.0;
+const NIGHT_LIGHTS_INTENSITY: f32 = 1.5;
 
 struct CameraUniform {
     view_proj_matrix: mat4x4<f32>,
     view_matrix: mat4x4<f32>,
 };
 
+struct EarthSurfaceUniform {
+    sun_direction: vec4<f32>,
+    // x: eastward drift in radians, y: shell radius over the Earth's,
+    // z: shadow strength
+    clouds: vec4<f32>,
+    // x: how far between the two overlay frames, y: overlay opacity
+    overlay: vec4<f32>,
+};
+
 struct ModelUniform {
     model: mat4x4<f32>,
 };
 
-
 struct VertexInput {
     @location(0) position: vec3<f32>,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
-    @location(0) tex_coords: vec3<f32>, // Texture coordinates
+    @location(0) tex_coords: vec3<f32>,
+    @location(1) world_position: vec3<f32>,
+    @location(2) eye: vec3<f32>,
 };
 
-
 @group(0) @binding(0) var<uniform> camera: CameraUniform;
-@group(2) @binding(0) var<uniform> model_uniform: ModelUniform; 
+@group(2) @binding(0) var<uniform> model_uniform: ModelUniform;
 
 @vertex
 fn vs_main(model: VertexInput) -> VertexOutput {
     var out: VertexOutput;
 
-    // Apply the model matrix to transform the vertex position to world space
     let world_position = model_uniform.model * vec4<f32>(model.position, 1.0);
-
-    // Transform the position from world space to clip space
     out.clip_position = camera.view_proj_matrix * world_position;
+    out.world_position = world_position.xyz;
 
-    // Rotate the texture coordinates by 180 degrees around the Y-axis
-    // YOU WILL NEED TO REVIST THIS IF YOU EVER CHANGE YOU CUBE MAP
-    let rotated_tex_coords = vec3<f32>(-model.position.x, model.position.y, -model.position.z);
-
-    // Use the normalized rotated position as direction vector for cube texture coordinates
-    out.tex_coords = normalize(rotated_tex_coords);
+    // Cube maps are laid out in model space (see
+    // MaterialSystem::equirectangular_to_cube_map), so the model position is
+    // the lookup direction
+    out.tex_coords = normalize(model.position);
 
+    // The camera is only visible to vertex shaders, so find the eye here
+    let view_rotation = mat3x3<f32>(
+        camera.view_matrix[0].xyz,
+        camera.view_matrix[1].xyz,
+        camera.view_matrix[2].xyz
+    );
+    out.eye = -(transpose(view_rotation) * camera.view_matrix[3].xyz);
 
     return out;
 }
 
 // fragment
-@group(1) @binding(0) var globeTexture: texture_cube<f32>;
-@group(1) @binding(1) var globeSampler: sampler;
+@group(1) @binding(0) var dayTexture: texture_cube<f32>;
+@group(1) @binding(1) var normalTexture: texture_cube<f32>;
+@group(1) @binding(2) var specularTexture: texture_cube<f32>;
+@group(1) @binding(3) var nightTexture: texture_cube<f32>;
+@group(1) @binding(4) var surfaceSampler: sampler;
+@group(1) @binding(5) var<uniform> surface: EarthSurfaceUniform;
+@group(1) @binding(6) var cloudTexture: texture_cube<f32>;
+@group(1) @binding(7) var overlayFromTexture: texture_cube<f32>;
+@group(1) @binding(8) var overlayToTexture: texture_cube<f32>;
+
+// Turns a direction west by `angle` about the pole, to look up clouds that
+// have drifted east by that much
+fn undrift(direction: vec3<f32>, angle: f32) -> vec3<f32> {
+    let c = cos(angle);
+    let s = sin(angle);
+    return vec3<f32>(
+        c * direction.x - s * direction.z,
+        direction.y,
+        s * direction.x + c * direction.z
+    );
+}
+
+// Where the ray from a point on the unit sphere towards the Sun leaves the
+// cloud shell
+fn cloud_shadow_direction(normal: vec3<f32>, sun: vec3<f32>, shell_radius: f32) -> vec3<f32> {
+    let b = dot(normal, sun);
+    let t = -b + sqrt(max(b * b - 1.0 + shell_radius * shell_radius, 0.0));
+    return normalize(normal + sun * t);
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    // Sample the texture using the texture coordinates
-    let texture_color = textureSample(globeTexture, globeSampler, in.tex_coords);
-
-    // Return the sampled color
-    return texture_color;
-}
\ No newline at end of file
+    // sampled up front: texture lookups need uniform control flow
+    // imagery layers (see imagery.wgsl) go over the day map, the overlay
+    // over both
+    let base = composite_imagery(
+        textureSample(dayTexture, surfaceSampler, in.tex_coords).rgb,
+        in.tex_coords
+    );
+    let overlay = mix(
+        textureSample(overlayFromTexture, surfaceSampler, in.tex_coords),
+        textureSample(overlayToTexture, surfaceSampler, in.tex_coords),
+        surface.overlay.x
+    );
+    // the overlay is part of the surface, so it is lit along with it
+    let day = mix(base, overlay.rgb, overlay.a * surface.overlay.y);
+    let tangent_normal = textureSample(normalTexture, surfaceSampler, in.tex_coords).xyz * 2.0 - 1.0;
+    let glint_mask = textureSample(specularTexture, surfaceSampler, in.tex_coords).r;
+    let night = textureSample(nightTexture, surfaceSampler, in.tex_coords).rgb;
+    let shadow_direction = cloud_shadow_direction(
+        normalize(in.tex_coords),
+        surface.sun_direction.xyz,
+        surface.clouds.y
+    );
+    let cloud_cover = textureSample(
+        cloudTexture,
+        surfaceSampler,
+        undrift(shadow_direction, surface.clouds.x)
+    ).r;
+
+    if surface.sun_direction.w == 0.0 {
+        return vec4<f32>(day, 1.0);
+    }
+
+    // The Earth's model matrix is the identity, so model space directions
+    // are world space too
+    let normal = normalize(in.tex_coords);
+    var east = vec3<f32>(normal.z, 0.0, -normal.x);
+    if length(east) < 1e-5 {
+        // at the poles any horizontal direction will do
+        east = vec3<f32>(-1.0, 0.0, 0.0);
+    }
+    east = normalize(east);
+    let north = cross(normal, east);
+    let surface_normal = normalize(
+        east * tangent_normal.x + north * tangent_normal.y + normal * tangent_normal.z
+    );
+
+    let sun = surface.sun_direction.xyz;
+    // soften the terminator, and keep relief from lighting the night side
+    let daylight = smoothstep(-0.1, 0.1, dot(normal, sun))
+        * (1.0 - cloud_cover * surface.clouds.z);
+    let diffuse = max(dot(surface_normal, sun), 0.0) * daylight;
+
+    // oceans are flat, so the glint uses the sphere's normal
+    let view = normalize(in.eye - in.world_position);
+    let halfway = normalize(sun + view);
+    let glint = glint_mask * SPECULAR_STRENGTH * pow(max(dot(normal, halfway), 0.0), SHININESS)
+        * daylight;
+
+    let darkness = 1.0 - smoothstep(-0.2, 0.05, dot(normal, sun));
+    let color = day * (AMBIENT + (1.0 - AMBIENT) * diffuse)
+        + vec3<f32>(glint)
+        + night * NIGHT_LIGHTS_INTENSITY * darkness;
+    return vec4<f32>(color, 1.0);
+}
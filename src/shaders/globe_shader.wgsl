@@ -1,5 +1,3 @@
-const PI: f32 = 3.141592653589793;
-
 struct CameraUniform {
     view_proj_matrix: mat4x4<f32>,
     view_matrix: mat4x4<f32>,
@@ -19,7 +17,7 @@ struct VertexOutput {
 };
 
 @group(0) @binding(0) var<uniform> camera: CameraUniform;
-@group(2) @binding(0) var<uniform> model_uniform: ModelUniform; 
+@group(2) @binding(0) var<uniform> model_uniform: ModelUniform;
 
 @vertex
 fn vs_main(model: VertexInput) -> VertexOutput {
@@ -31,11 +29,10 @@ fn vs_main(model: VertexInput) -> VertexOutput {
     // Transform the position from world space to clip space
     out.clip_position = camera.view_proj_matrix * world_position;
 
-    // Texture coordinate rotation should be revisited if the cube map logic changes
-    let rotated_tex_coords = vec3<f32>(-model.position.x, model.position.y, -model.position.z);
-
-    // Use the normalized rotated position as direction vector for cube texture coordinates
-    out.tex_coords = normalize(rotated_tex_coords);
+    // Cube maps are laid out in model space (see
+    // MaterialSystem::equirectangular_to_cube_map), so the model position is
+    // the lookup direction
+    out.tex_coords = normalize(model.position);
 
     return out;
 }
@@ -48,5 +45,6 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     // Sample the texture using the texture coordinates
     let texture_color = textureSample(globeTexture, globeSampler, in.tex_coords);
 
-    return vec4<f32>(1.0, 1.0, 1.0, 1.0); // White color
-}
\ No newline at end of file
+    // Draw the imagery layers over it (see imagery.wgsl)
+    return vec4<f32>(composite_imagery(texture_color.rgb, in.tex_coords), texture_color.a);
+}
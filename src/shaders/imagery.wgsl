@@ -0,0 +1,147 @@
+// Imagery layers composited over a globe's base texture. Shared by the globe
+// and Earth shaders, which ImagerySystem::create_shader_module prefixes with it.
+
+const IMAGERY_TAU: f32 = 6.283185307179586;
+// Web Mercator tiles stop short of the poles, where northing runs off to infinity
+const MAX_TILE_LAT: f32 = 1.5707;
+const EMPTY_TILE: u32 = 0xffffffffu;
+
+struct ImageryLayer {
+    // west, south, east and north in radians
+    rectangle: vec4<f32>,
+    // brightness, contrast, hue and gamma
+    adjustments: vec4<f32>,
+    alpha: vec4<f32>,
+    // tiled layers: west, top, tile width and tile height, in radians or
+    // unit sphere Mercator northing
+    tile_grid: vec4<f32>,
+    // columns (0 if not tiled), rows, 1 for Mercator rows, first table entry
+    tile_layout: vec4<f32>,
+};
+
+struct Imagery {
+    layers: array<ImageryLayer, 4>,
+    // the atlas slot of each tiled layer's tiles, row by row
+    tile_slots: array<vec4<u32>, 64>,
+};
+
+@group(3) @binding(0) var imageryTexture0: texture_2d<f32>;
+@group(3) @binding(1) var imageryTexture1: texture_2d<f32>;
+@group(3) @binding(2) var imageryTexture2: texture_2d<f32>;
+@group(3) @binding(3) var imageryTexture3: texture_2d<f32>;
+@group(3) @binding(4) var imagerySampler: sampler;
+@group(3) @binding(5) var<uniform> imagery: Imagery;
+@group(3) @binding(6) var imageryAtlas: texture_2d_array<f32>;
+
+fn adjust_imagery(color: vec3<f32>, adjustments: vec4<f32>) -> vec3<f32> {
+    var adjusted = color * adjustments.x;
+    adjusted = mix(vec3<f32>(0.5), adjusted, adjustments.y);
+
+    // hue turns the color about the gray axis
+    let axis = vec3<f32>(0.57735026);
+    let c = cos(adjustments.z);
+    let s = sin(adjustments.z);
+    adjusted = adjusted * c + cross(axis, adjusted) * s + axis * dot(axis, adjusted) * (1.0 - c);
+
+    return pow(max(adjusted, vec3<f32>(0.0)), vec3<f32>(1.0 / adjustments.w));
+}
+
+// Draws one layer over `color` wherever its rectangle covers. `gradients`
+// holds the longitude and latitude derivatives along x and y.
+fn blend_imagery_layer(
+    color: vec3<f32>,
+    layer_texture: texture_2d<f32>,
+    layer: ImageryLayer,
+    lat_lon: vec2<f32>,
+    gradients: vec4<f32>
+) -> vec3<f32> {
+    if layer.tile_layout.x > 0.0 {
+        return blend_tiled_imagery_layer(color, layer, lat_lon, gradients);
+    }
+    let rectangle = layer.rectangle;
+    var lon = lat_lon.y;
+    if lon < rectangle.x {
+        // the rectangle may run past the antimeridian
+        lon += IMAGERY_TAU;
+    }
+    let size = rectangle.zw - rectangle.xy;
+    let uv = vec2<f32>((lon - rectangle.x) / size.x, (rectangle.w - lat_lon.x) / size.y);
+
+    // explicit gradients, so mipmapping doesn't break where longitude wraps
+    let sample = textureSampleGrad(
+        layer_texture,
+        imagerySampler,
+        uv,
+        vec2<f32>(gradients.x / size.x, -gradients.z / size.y),
+        vec2<f32>(gradients.y / size.x, -gradients.w / size.y)
+    );
+
+    let inside = all(uv >= vec2<f32>(0.0)) && all(uv <= vec2<f32>(1.0));
+    let alpha = select(0.0, sample.a * layer.alpha.x, inside);
+    return select(color, mix(color, adjust_imagery(sample.rgb, layer.adjustments), alpha), alpha > 0.0);
+}
+
+// Like blend_imagery_layer, for a layer drawn tile by tile from the atlas.
+fn blend_tiled_imagery_layer(
+    color: vec3<f32>,
+    layer: ImageryLayer,
+    lat_lon: vec2<f32>,
+    gradients: vec4<f32>
+) -> vec3<f32> {
+    let grid = layer.tile_grid;
+    let tiles = layer.tile_layout;
+    var x = lat_lon.y;
+    if x < grid.x {
+        x += IMAGERY_TAU;
+    }
+    var y = lat_lon.x;
+    var y_gradients = gradients.zw;
+    if tiles.z > 0.5 {
+        let lat = clamp(lat_lon.x, -MAX_TILE_LAT, MAX_TILE_LAT);
+        y = log(tan(0.25 * IMAGERY_TAU + 0.5 * lat));
+        y_gradients = y_gradients / cos(lat);
+    }
+
+    let position = vec2<f32>((x - grid.x) / grid.z, (grid.y - y) / grid.w);
+    let cell = floor(position);
+    let inside = all(cell >= vec2<f32>(0.0)) && all(cell < tiles.xy);
+    let clamped = clamp(cell, vec2<f32>(0.0), tiles.xy - vec2<f32>(1.0));
+    let entry = u32(tiles.w) + u32(clamped.y) * u32(tiles.x) + u32(clamped.x);
+    let slot = imagery.tile_slots[entry / 4u][entry % 4u];
+
+    let sample = textureSampleGrad(
+        imageryAtlas,
+        imagerySampler,
+        position - cell,
+        i32(min(slot, 0x7fffffffu)),
+        vec2<f32>(gradients.x / grid.z, -y_gradients.x / grid.w),
+        vec2<f32>(gradients.y / grid.z, -y_gradients.y / grid.w)
+    );
+
+    let alpha = select(0.0, sample.a * layer.alpha.x, inside && slot != EMPTY_TILE);
+    return select(color, mix(color, adjust_imagery(sample.rgb, layer.adjustments), alpha), alpha > 0.0);
+}
+
+fn wrap_angle(angle: f32) -> f32 {
+    return angle - IMAGERY_TAU * round(angle / IMAGERY_TAU);
+}
+
+// `direction` is a model space direction, as used for cube map lookups
+fn composite_imagery(base: vec3<f32>, direction: vec3<f32>) -> vec3<f32> {
+    let normal = normalize(direction);
+    // the same convention as MeshSystem::cartesian_to_lat_lon
+    let lat_lon = vec2<f32>(asin(clamp(normal.y, -1.0, 1.0)), atan2(-normal.x, -normal.z));
+    let gradients = vec4<f32>(
+        wrap_angle(dpdx(lat_lon.y)),
+        wrap_angle(dpdy(lat_lon.y)),
+        dpdx(lat_lon.x),
+        dpdy(lat_lon.x)
+    );
+
+    var color = base;
+    color = blend_imagery_layer(color, imageryTexture0, imagery.layers[0], lat_lon, gradients);
+    color = blend_imagery_layer(color, imageryTexture1, imagery.layers[1], lat_lon, gradients);
+    color = blend_imagery_layer(color, imageryTexture2, imagery.layers[2], lat_lon, gradients);
+    color = blend_imagery_layer(color, imageryTexture3, imagery.layers[3], lat_lon, gradients);
+    return color;
+}
@@ -0,0 +1,60 @@
+struct CameraUniform {
+    view_proj_matrix: mat4x4<f32>,
+    view_matrix: mat4x4<f32>,
+    proj_matrix: mat4x4<f32>,
+};
+
+struct ModelUniform {
+    model: mat4x4<f32>,
+};
+
+struct VertexInput {
+    @location(0) direction: vec3<f32>,
+    @location(1) corner: vec2<f32>,
+    @location(2) color: vec4<f32>,
+    @location(3) size: f32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) corner: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> camera: CameraUniform;
+@group(1) @binding(0) var<uniform> tint: vec4<f32>;
+@group(2) @binding(0) var<uniform> model_uniform: ModelUniform;
+
+@vertex
+fn vs_main(star: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    // Stars are at infinity: only the camera's rotation moves them
+    let view_rotation: mat3x3<f32> = mat3x3<f32>(
+        camera.view_matrix[0].xyz,
+        camera.view_matrix[1].xyz,
+        camera.view_matrix[2].xyz
+    );
+    // Directions are J2000; the model matrix turns them into the scene
+    let direction = (model_uniform.model * vec4<f32>(star.direction, 0.0)).xyz;
+    let view_direction = view_rotation * direction;
+
+    // Push the corner out across the line of sight, one unit away, so the
+    // quad always faces the camera and keeps its angular size
+    let view_position = view_direction + vec3<f32>(star.corner * star.size, 0.0);
+    out.clip_position = camera.proj_matrix * vec4<f32>(view_position, 1.0);
+
+    // Just inside the far plane, behind anything else in the scene
+    out.clip_position.z = out.clip_position.w * 0.99999;
+
+    out.corner = star.corner;
+    out.color = star.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Round, soft-edged points
+    let falloff = 1.0 - smoothstep(0.3, 1.0, length(in.corner));
+    return vec4<f32>(in.color.rgb * tint.rgb, in.color.a * tint.a * falloff);
+}
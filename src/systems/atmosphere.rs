@@ -0,0 +1,180 @@
+use cgmath::{InnerSpace, SquareMatrix, Vector3};
+use wgpu::util::DeviceExt;
+
+use crate::{
+    components::{
+        atmosphere::{AtmosphereComponent, AtmosphereUniform},
+        camera::CameraComponent,
+        material::MaterialComponent,
+        mesh::MeshComponent,
+        render_pipelines::RenderPipelineComponent,
+    },
+    matrix4_to_array, WGS84_A,
+};
+
+use super::{mesh::MeshSystem, pipelines::AtmosphereRenderPipelineSystem};
+
+// Above this the air is too thin to scatter anything visible, kilometers
+const ATMOSPHERE_HEIGHT: f32 = 100.0;
+// The shell mesh is a little larger than the atmosphere so its flat facets
+// never cut into it; the shader intersects the true sphere.
+const SHELL_MARGIN: f32 = 1.01;
+
+// the March equinox direction, used until an ephemeris has loaded
+const DEFAULT_SUN_DIRECTION: [f32; 4] = [0.0, 0.0, 1.0, 0.0];
+
+pub struct AtmosphereSystem {}
+
+impl AtmosphereSystem {
+    pub fn create_atmosphere(
+        device: &wgpu::Device,
+        texture_format: wgpu::TextureFormat,
+        camera_component: &CameraComponent,
+    ) -> (
+        AtmosphereComponent,
+        MeshComponent,
+        MaterialComponent,
+        RenderPipelineComponent,
+    ) {
+        let uniform = AtmosphereUniform {
+            sun_direction: DEFAULT_SUN_DIRECTION,
+            radii: [WGS84_A, WGS84_A + ATMOSPHERE_HEIGHT, 0.0, 0.0],
+        };
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Atmosphere Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[uniform]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let mesh_component = AtmosphereSystem::generate_mesh(device, uniform.radii[1]);
+        let material_component = AtmosphereSystem::generate_material(device, &uniform_buffer);
+        let render_pipeline_component = AtmosphereSystem::generate_render_pipeline(
+            device,
+            texture_format,
+            camera_component,
+            &mesh_component,
+            &material_component,
+        );
+
+        (
+            AtmosphereComponent {
+                uniform,
+                uniform_buffer,
+            },
+            mesh_component,
+            material_component,
+            render_pipeline_component,
+        )
+    }
+
+    // `sun_direction` points at the Sun in the Earth's model space, the same
+    // as `EarthSystem::sun_direction`, so the glow follows the terminator.
+    pub fn update_sun_direction(
+        queue: &wgpu::Queue,
+        atmosphere: &mut AtmosphereComponent,
+        sun_direction: Vector3<f64>,
+    ) {
+        let sun_direction = sun_direction.normalize().cast::<f32>().unwrap();
+        atmosphere.uniform.sun_direction = sun_direction.extend(0.0).into();
+        queue.write_buffer(
+            &atmosphere.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[atmosphere.uniform]),
+        );
+    }
+
+    fn generate_mesh(device: &wgpu::Device, atmosphere_radius: f32) -> MeshComponent {
+        let atmosphere_matrix = matrix4_to_array(cgmath::Matrix4::identity());
+        let atmosphere_matrix_bind_group_layout =
+            MeshSystem::create_model_matrix_bind_group_layout(device);
+        let atmosphere_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Mesh Buffer"),
+            contents: bytemuck::cast_slice(&[atmosphere_matrix]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let atmosphere_matrix_bind_group = MeshSystem::create_model_matrix_bind_group(
+            device,
+            &atmosphere_matrix_bind_group_layout,
+            &atmosphere_buffer,
+        );
+        let (atmosphere_vertices_vec, atmosphere_indices_vec) =
+            MeshSystem::generate_sphere_mesh(atmosphere_radius * SHELL_MARGIN);
+
+        MeshComponent {
+            vertex_buffer: MeshSystem::create_vertex_buffer(
+                device,
+                atmosphere_vertices_vec.as_slice(),
+            ),
+            index_buffer: MeshSystem::create_index_buffer(
+                device,
+                atmosphere_indices_vec.as_slice(),
+            ),
+            num_indices: atmosphere_indices_vec.len() as u32,
+            model_matrix_bind_group_layout: atmosphere_matrix_bind_group_layout,
+            model_matrix_bind_group: atmosphere_matrix_bind_group,
+            model_matrix_buffer: atmosphere_buffer,
+            model_matrix: atmosphere_matrix,
+        }
+    }
+
+    fn generate_material(
+        device: &wgpu::Device,
+        uniform_buffer: &wgpu::Buffer,
+    ) -> MaterialComponent {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Atmosphere Uniform bind group layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+            label: Some("Atmosphere Uniform bind group"),
+        });
+
+        MaterialComponent {
+            bind_group,
+            bind_group_layout,
+            uniforms: None,
+            shader: device
+                .create_shader_module(wgpu::include_wgsl!("../shaders/atmosphere_shader.wgsl")),
+        }
+    }
+
+    fn generate_render_pipeline(
+        device: &wgpu::Device,
+        texture_format: wgpu::TextureFormat,
+        camera_component: &CameraComponent,
+        mesh_component: &MeshComponent,
+        material_component: &MaterialComponent,
+    ) -> RenderPipelineComponent {
+        let atmosphere_pipeline_layouts: &[&wgpu::BindGroupLayout] = &[
+            &camera_component.camera_bind_group_layout,
+            &material_component.bind_group_layout,
+            &mesh_component.model_matrix_bind_group_layout,
+        ];
+        let atmosphere_render_pipeline_layout =
+            AtmosphereRenderPipelineSystem::layout_desc(device, atmosphere_pipeline_layouts);
+        let atmosphere_render_pipeline = AtmosphereRenderPipelineSystem::pipeline_desc(
+            device,
+            &atmosphere_render_pipeline_layout,
+            &material_component.shader,
+            texture_format,
+        );
+        RenderPipelineComponent {
+            render_pipeline: atmosphere_render_pipeline,
+            render_pipeline_layout: atmosphere_render_pipeline_layout,
+        }
+    }
+}
@@ -1,11 +1,12 @@
 use crate::{
     components::{
-        camera::CameraComponent, material::MaterialComponent, mesh::MeshComponent,
-        render_pipelines::RenderPipelineComponent,
+        billboard::BillboardComponent, camera::CameraComponent, material::MaterialComponent,
+        mesh::MeshComponent, render_pipelines::RenderPipelineComponent,
     },
     matrix4_to_array,
 };
 use cgmath::{InnerSpace, Matrix4, Vector3};
+use image::RgbaImage;
 use wgpu::util::DeviceExt;
 
 use super::{material::MaterialSystem, mesh::MeshSystem, pipelines::BillboardRenderPipelineSystem};
@@ -13,13 +14,25 @@ use super::{material::MaterialSystem, mesh::MeshSystem, pipelines::BillboardRend
 pub struct BillboardSystem {}
 
 impl BillboardSystem {
-    pub fn create_billboard_mesh(
-        device: &wgpu::Device,
+    pub fn create_billboard_component(
         size: f32,
         lat: f32,
         lon: f32,
         globe_radius: f32,
-    ) -> MeshComponent {
+        order: u64,
+    ) -> BillboardComponent {
+        let position = BillboardSystem::billboard_position(lat, lon, globe_radius);
+        BillboardComponent {
+            lat,
+            lon,
+            size,
+            position: position.into(),
+            order,
+        }
+    }
+
+    // world-space anchor of a billboard placed at lat/lon
+    pub fn billboard_position(lat: f32, lon: f32, globe_radius: f32) -> Vector3<f32> {
         // Convert latitude and longitude to Cartesian coordinates at globe surface
         let (x, y, z) = MeshSystem::lat_lon_to_cartesian(lat, lon, globe_radius);
 
@@ -31,19 +44,29 @@ impl BillboardSystem {
 
         // Offset the position slightly above the surface
         let offset_distance = 0.03 * globe_radius; // Adjust this value based on your needs
-        let offset_position = position + normalized_position * offset_distance;
+        position + normalized_position * offset_distance
+    }
+
+    pub fn create_billboard_mesh(
+        device: &wgpu::Device,
+        size: f32,
+        lat: f32,
+        lon: f32,
+        globe_radius: f32,
+    ) -> MeshComponent {
+        let offset_position = BillboardSystem::billboard_position(lat, lon, globe_radius);
 
         // Use the offset position for translation
         let billboard_matrix = matrix4_to_array(Matrix4::from_translation(offset_position));
         let billboard_matrix_bind_group_layout =
-            MeshSystem::create_model_matrix_bind_group_layout(&device);
+            MeshSystem::create_model_matrix_bind_group_layout(device);
         let billboard_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Mesh Buffer"),
             contents: bytemuck::cast_slice(&[billboard_matrix]),
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
         let billboard_matrix_bind_group = MeshSystem::create_model_matrix_bind_group(
-            &device,
+            device,
             &billboard_matrix_bind_group_layout,
             &billboard_buffer,
         );
@@ -52,13 +75,10 @@ impl BillboardSystem {
 
         MeshComponent {
             vertex_buffer: MeshSystem::create_vertex_buffer(
-                &device,
-                &billboard_vertices_vec.as_slice(),
-            ),
-            index_buffer: MeshSystem::create_index_buffer(
-                &device,
-                &billboard_indices_vec.as_slice(),
+                device,
+                billboard_vertices_vec.as_slice(),
             ),
+            index_buffer: MeshSystem::create_index_buffer(device, billboard_indices_vec.as_slice()),
             num_indices: billboard_indices_vec.len() as u32,
             model_matrix_bind_group_layout: billboard_matrix_bind_group_layout,
             model_matrix_bind_group: billboard_matrix_bind_group,
@@ -70,13 +90,10 @@ impl BillboardSystem {
     pub fn create_billboard_material(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
+        billboard_image: RgbaImage,
     ) -> MaterialComponent {
-        let billboard_image_data = include_bytes!("../assets/billboard.png");
-        let billboard_dyn_image = image::load_from_memory(billboard_image_data)
-            .expect("Failed to load image from memory");
-        let billboard_image_buffer = billboard_dyn_image.to_rgba8();
         let (materal_bind_group, material_bind_group_layout) =
-            MaterialSystem::create_2d_texture(&device, &queue, billboard_image_buffer);
+            MaterialSystem::create_2d_texture(device, queue, billboard_image.into());
         MaterialComponent {
             bind_group: materal_bind_group,
             bind_group_layout: material_bind_group_layout,
@@ -99,9 +116,9 @@ impl BillboardSystem {
             &mesh.model_matrix_bind_group_layout,
         ];
         let billboard_render_pipeline_layout =
-            BillboardRenderPipelineSystem::layout_desc(&device, billboard_pipeline_layouts);
+            BillboardRenderPipelineSystem::layout_desc(device, billboard_pipeline_layouts);
         let billboard_render_pipeline = BillboardRenderPipelineSystem::pipeline_desc(
-            &device,
+            device,
             &billboard_render_pipeline_layout,
             &material.shader,
             texture_format,
@@ -0,0 +1,306 @@
+use anise::{
+    almanac::Almanac,
+    astro::Aberration,
+    constants::{celestial_objects, orientations},
+    prelude::*,
+};
+use cgmath::{Matrix3, Matrix4, SquareMatrix, Vector3};
+use wgpu::util::DeviceExt;
+
+use crate::{
+    components::{
+        body::CelestialBodyComponent, camera::CameraComponent, material::MaterialComponent,
+        mesh::MeshComponent, render_pipelines::RenderPipelineComponent,
+    },
+    matrix4_to_array,
+};
+
+use super::{
+    imagery::ImagerySystem,
+    material::{MaterialSystem, TextureSource},
+    mesh::MeshSystem,
+    orientation::OrientationSystem,
+    pipelines::EarthRenderPipelineSystem,
+};
+
+// points sampled along each orbit path
+const ORBIT_SAMPLES: u32 = 360;
+
+/// A body solar-system mode can spawn.
+pub struct Body {
+    pub naif_id: i32,
+    pub name: &'static str,
+    // IAU mean radius, kilometers
+    pub radius: f32,
+    // sidereal period, days; None for the Sun
+    pub orbital_period: Option<f64>,
+    // drawn until (or instead of) a texture
+    pub color: [u8; 4],
+}
+
+// Planets are placed at their system barycenters, which every DE kernel
+// carries and which are close enough at these scales. Earth and the Moon
+// have their own systems.
+pub const BODIES: [Body; 9] = [
+    Body {
+        naif_id: celestial_objects::SUN,
+        name: "sun",
+        radius: 695_700.0,
+        orbital_period: None,
+        color: [255, 214, 120, 255],
+    },
+    Body {
+        naif_id: celestial_objects::MERCURY,
+        name: "mercury",
+        radius: 2_439.4,
+        orbital_period: Some(87.969),
+        color: [150, 145, 140, 255],
+    },
+    Body {
+        naif_id: celestial_objects::VENUS,
+        name: "venus",
+        radius: 6_051.8,
+        orbital_period: Some(224.701),
+        color: [220, 200, 150, 255],
+    },
+    Body {
+        naif_id: celestial_objects::MARS_BARYCENTER,
+        name: "mars",
+        radius: 3_389.5,
+        orbital_period: Some(686.980),
+        color: [190, 100, 60, 255],
+    },
+    Body {
+        naif_id: celestial_objects::JUPITER_BARYCENTER,
+        name: "jupiter",
+        radius: 69_911.0,
+        orbital_period: Some(4_332.59),
+        color: [200, 170, 130, 255],
+    },
+    Body {
+        naif_id: celestial_objects::SATURN_BARYCENTER,
+        name: "saturn",
+        radius: 58_232.0,
+        orbital_period: Some(10_759.22),
+        color: [220, 200, 150, 255],
+    },
+    Body {
+        naif_id: celestial_objects::URANUS_BARYCENTER,
+        name: "uranus",
+        radius: 25_362.0,
+        orbital_period: Some(30_688.5),
+        color: [160, 210, 220, 255],
+    },
+    Body {
+        naif_id: celestial_objects::NEPTUNE_BARYCENTER,
+        name: "neptune",
+        radius: 24_622.0,
+        orbital_period: Some(60_195.0),
+        color: [80, 110, 200, 255],
+    },
+    Body {
+        naif_id: celestial_objects::PLUTO_BARYCENTER,
+        name: "pluto",
+        radius: 1_188.3,
+        orbital_period: Some(90_560.0),
+        color: [190, 170, 150, 255],
+    },
+];
+
+pub struct BodySystem {}
+
+impl BodySystem {
+    pub fn create_body(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        body: &Body,
+    ) -> (CelestialBodyComponent, MeshComponent, MaterialComponent) {
+        let body_component = CelestialBodyComponent {
+            naif_id: body.naif_id,
+            name: body.name,
+            radius: body.radius,
+        };
+        let mesh_component = BodySystem::create_mesh(device);
+        let material_component =
+            BodySystem::create_material(device, queue, MaterialSystem::solid_cube_map(body.color));
+        (body_component, mesh_component, material_component)
+    }
+
+    // a unit sphere, sized by the model matrix so the radius scale can change
+    fn create_mesh(device: &wgpu::Device) -> MeshComponent {
+        let body_matrix = matrix4_to_array(Matrix4::identity());
+        let body_matrix_bind_group_layout =
+            MeshSystem::create_model_matrix_bind_group_layout(device);
+        let body_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Mesh Buffer"),
+            contents: bytemuck::cast_slice(&[body_matrix]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let body_matrix_bind_group = MeshSystem::create_model_matrix_bind_group(
+            device,
+            &body_matrix_bind_group_layout,
+            &body_buffer,
+        );
+        let (body_vertices_vec, body_indices_vec) = MeshSystem::generate_sphere_mesh(1.0);
+
+        MeshComponent {
+            vertex_buffer: MeshSystem::create_vertex_buffer(device, body_vertices_vec.as_slice()),
+            index_buffer: MeshSystem::create_index_buffer(device, body_indices_vec.as_slice()),
+            num_indices: body_indices_vec.len() as u32,
+            model_matrix_bind_group_layout: body_matrix_bind_group_layout,
+            model_matrix_bind_group: body_matrix_bind_group,
+            model_matrix_buffer: body_buffer,
+            model_matrix: body_matrix,
+        }
+    }
+
+    pub fn create_material(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        cube_map: TextureSource,
+    ) -> MaterialComponent {
+        let (materal_bind_group, material_bind_group_layout) =
+            MaterialSystem::create_cube_map_texture(device, queue, cube_map);
+        MaterialComponent {
+            bind_group: materal_bind_group,
+            bind_group_layout: material_bind_group_layout,
+            uniforms: None,
+            shader: ImagerySystem::create_shader_module(
+                device,
+                "globe_shader.wgsl",
+                include_str!("../shaders/globe_shader.wgsl"),
+            ),
+        }
+    }
+
+    // Built once from any body's material and mesh; every body shares the layouts.
+    pub fn create_render_pipeline(
+        device: &wgpu::Device,
+        texture_format: wgpu::TextureFormat,
+        camera_component: &CameraComponent,
+        mesh_component: &MeshComponent,
+        material_component: &MaterialComponent,
+    ) -> RenderPipelineComponent {
+        let imagery_bind_group_layout = ImagerySystem::create_bind_group_layout(device);
+        let body_pipeline_layouts: &[&wgpu::BindGroupLayout] = &[
+            &camera_component.camera_bind_group_layout,
+            &material_component.bind_group_layout,
+            &mesh_component.model_matrix_bind_group_layout,
+            &imagery_bind_group_layout,
+        ];
+        let body_render_pipeline_layout =
+            EarthRenderPipelineSystem::layout_desc(device, body_pipeline_layouts);
+        let body_render_pipeline = EarthRenderPipelineSystem::pipeline_desc(
+            device,
+            &body_render_pipeline_layout,
+            &material_component.shader,
+            texture_format,
+        );
+        RenderPipelineComponent {
+            render_pipeline: body_render_pipeline,
+            render_pipeline_layout: body_render_pipeline_layout,
+        }
+    }
+
+    // Scene position of `naif_id` relative to the Earth, or None outside the
+    // kernels' coverage.
+    pub fn scene_position(almanac: &Almanac, naif_id: i32, epoch: Epoch) -> Option<Vector3<f64>> {
+        let position =
+            BodySystem::relative_position(almanac, naif_id, celestial_objects::EARTH, epoch)?;
+        Some(OrientationSystem::j2000_to_scene(Some(almanac), epoch) * position)
+    }
+
+    // Position of `target` seen from `observer` in J2000 axes.
+    fn relative_position(
+        almanac: &Almanac,
+        target: i32,
+        observer: i32,
+        epoch: Epoch,
+    ) -> Option<Vector3<f64>> {
+        let state = almanac
+            .translate_from_to(
+                Frame::from_ephem_orient(target, orientations::J2000),
+                Frame::from_ephem_orient(observer, orientations::J2000),
+                epoch,
+                Aberration::None,
+            )
+            .ok()?;
+        let position = state.to_cartesian_pos_vel();
+        Some(Vector3::new(position[0], position[1], position[2]))
+    }
+
+    pub fn update_position(
+        queue: &wgpu::Queue,
+        body_mesh: &MeshComponent,
+        body: &CelestialBodyComponent,
+        radius_scale: f32,
+        almanac: &Almanac,
+        epoch: Epoch,
+    ) {
+        let Some(position) = BodySystem::scene_position(almanac, body.naif_id, epoch) else {
+            return;
+        };
+
+        // exaggerating the Sun as well would swallow the inner planets
+        let radius_scale = if body.naif_id == celestial_objects::SUN {
+            1.0
+        } else {
+            radius_scale
+        };
+        let body_matrix: [[f32; 4]; 4] = (Matrix4::from_translation(position.cast().unwrap())
+            * Matrix4::from_scale(body.radius * radius_scale))
+        .into();
+        queue.write_buffer(
+            &body_mesh.model_matrix_buffer,
+            0,
+            bytemuck::cast_slice(&[body_matrix]),
+        );
+    }
+
+    // One sidereal period of heliocentric positions centred on `epoch`, in
+    // J2000 axes. Epochs the kernels don't cover are skipped.
+    pub fn orbit_positions(almanac: &Almanac, body: &Body, epoch: Epoch) -> Vec<[f32; 3]> {
+        let Some(period) = body.orbital_period else {
+            return Vec::new();
+        };
+
+        let mut positions: Vec<[f32; 3]> = (0..=ORBIT_SAMPLES)
+            .filter_map(|i| {
+                let offset = period * (i as f64 / ORBIT_SAMPLES as f64 - 0.5);
+                let sample_epoch = epoch + Unit::Day * offset;
+                BodySystem::relative_position(
+                    almanac,
+                    body.naif_id,
+                    celestial_objects::SUN,
+                    sample_epoch,
+                )
+            })
+            .map(|position| position.cast::<f32>().unwrap().into())
+            .collect();
+        // close the loop when the whole period was covered
+        if positions.len() as u32 == ORBIT_SAMPLES + 1 {
+            positions[ORBIT_SAMPLES as usize] = positions[0];
+        }
+        positions
+    }
+
+    // Orbits are heliocentric, so they follow the Sun around the Earth-centred
+    // scene, and turn with the rest of the sky.
+    pub fn update_orbit(
+        queue: &wgpu::Queue,
+        orbit_mesh: &MeshComponent,
+        sun_position: Vector3<f64>,
+        j2000_to_scene: Matrix3<f64>,
+    ) {
+        let orbit_matrix: [[f32; 4]; 4] = (Matrix4::from_translation(sun_position)
+            * Matrix4::from(j2000_to_scene))
+        .cast::<f32>()
+        .unwrap()
+        .into();
+        queue.write_buffer(
+            &orbit_mesh.model_matrix_buffer,
+            0,
+            bytemuck::cast_slice(&[orbit_matrix]),
+        );
+    }
+}
@@ -1,8 +1,13 @@
 use bevy_ecs::world::Mut;
 use wgpu::util::DeviceExt;
-use winit::event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent};
+use winit::event::{ElementState, KeyboardInput, WindowEvent};
 
-use crate::components::camera::{Camera, CameraComponent, CameraController, CameraUniform};
+use crate::{
+    components::camera::{Camera, CameraComponent, CameraController, CameraFlight, CameraUniform},
+    resources::input::{InputAction, InputBindings, InputTrigger},
+};
+
+use super::input::InputSystem;
 
 pub struct CameraSystem {}
 
@@ -27,15 +32,7 @@ impl CameraSystem {
         };
 
         let mut camera_uniform = CameraUniform::new();
-        camera_uniform.update_view_proj(
-            camera.eye,
-            camera.target,
-            camera.up,
-            camera.aspect,
-            camera.fovy,
-            camera.znear,
-            camera.zfar,
-        );
+        camera_uniform.update_view_proj(&camera);
 
         let camera_buffer = CameraSystem::create_uniform_buffer(device, &camera_uniform);
         let camera_bind_group_layout =
@@ -84,7 +81,7 @@ impl CameraSystem {
         layout: &wgpu::BindGroupLayout,
     ) -> wgpu::BindGroup {
         device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: layout,
+            layout,
             entries: &[wgpu::BindGroupEntry {
                 binding: 0,
                 resource: buffer.as_entire_binding(),
@@ -93,7 +90,11 @@ impl CameraSystem {
         })
     }
 
-    pub fn process_key_events(cam_controller: &mut CameraController, event: &WindowEvent) -> bool {
+    pub fn process_key_events(
+        cam_controller: &mut CameraController,
+        bindings: &InputBindings,
+        event: &WindowEvent,
+    ) -> bool {
         match event {
             WindowEvent::KeyboardInput {
                 input:
@@ -104,37 +105,107 @@ impl CameraSystem {
                     },
                 ..
             } => {
-                let is_pressed = *state == ElementState::Pressed;
-                match keycode {
-                    VirtualKeyCode::W | VirtualKeyCode::Up => {
-                        cam_controller.is_forward_pressed = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::A | VirtualKeyCode::Left => {
-                        cam_controller.is_left_pressed = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::S | VirtualKeyCode::Down => {
-                        cam_controller.is_backward_pressed = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::D | VirtualKeyCode::Right => {
-                        cam_controller.is_right_pressed = is_pressed;
-                        true
-                    }
-                    _ => false,
+                let mut handled = false;
+                for action in InputSystem::actions(bindings, InputTrigger::Key(*keycode)) {
+                    let is_pressed = *state == ElementState::Pressed;
+                    handled |= CameraSystem::process_action(cam_controller, action, is_pressed);
                 }
+                handled
+            }
+            _ => false,
+        }
+    }
+
+    /// Starts or stops moving the camera. Returns false for actions that are
+    /// not the camera's.
+    pub fn process_action(
+        cam_controller: &mut CameraController,
+        action: InputAction,
+        is_pressed: bool,
+    ) -> bool {
+        match action {
+            InputAction::ZoomIn => {
+                cam_controller.is_forward_pressed = is_pressed;
+                true
+            }
+            InputAction::OrbitLeft => {
+                cam_controller.is_left_pressed = is_pressed;
+                true
+            }
+            InputAction::ZoomOut => {
+                cam_controller.is_backward_pressed = is_pressed;
+                true
+            }
+            InputAction::OrbitRight => {
+                cam_controller.is_right_pressed = is_pressed;
+                true
             }
             _ => false,
         }
     }
 
-    pub fn update_camera(queue: &wgpu::Queue, mut cam_component: Mut<'_, CameraComponent>) {
+    // Starts a flight from the current eye position to `destination`.
+    pub fn fly_to(
+        cam_component: &mut CameraComponent,
+        destination: cgmath::Point3<f32>,
+        duration_seconds: f32,
+    ) {
+        let target = cam_component.camera.target;
+        cam_component.camera_controller.flight = Some(CameraFlight {
+            from: cam_component.camera.eye - target,
+            to: destination - target,
+            duration: duration_seconds.max(0.0),
+            elapsed: 0.0,
+        });
+    }
+
+    // Moves the eye along the flight path. The direction is slerped around the
+    // target and the distance interpolated separately so the camera arcs over
+    // the globe instead of cutting through it.
+    fn advance_flight(cam_component: &mut CameraComponent, dt: f32) {
+        use cgmath::{InnerSpace, VectorSpace};
+        let Some(flight) = cam_component.camera_controller.flight.as_mut() else {
+            return;
+        };
+
+        flight.elapsed += dt;
+        let t = if flight.duration > 0.0 {
+            (flight.elapsed / flight.duration).min(1.0)
+        } else {
+            1.0
+        };
+        // smoothstep easing
+        let eased = t * t * (3.0 - 2.0 * t);
+
+        let (from_dir, to_dir) = (flight.from.normalize(), flight.to.normalize());
+        let angle = from_dir.dot(to_dir).clamp(-1.0, 1.0).acos();
+        let direction = if angle.sin().abs() < 1e-6 {
+            from_dir.lerp(to_dir, eased).normalize()
+        } else {
+            (from_dir * ((1.0 - eased) * angle).sin() + to_dir * (eased * angle).sin())
+                / angle.sin()
+        };
+        let distance =
+            flight.from.magnitude() + (flight.to.magnitude() - flight.from.magnitude()) * eased;
+
+        cam_component.camera.eye = cam_component.camera.target + direction * distance;
+        if t >= 1.0 {
+            cam_component.camera_controller.flight = None;
+        }
+    }
+
+    pub fn update_camera(
+        queue: &wgpu::Queue,
+        mut cam_component: Mut<'_, CameraComponent>,
+        dt: f32,
+    ) {
         use cgmath::InnerSpace;
+        CameraSystem::advance_flight(&mut cam_component, dt);
+
         let forward = cam_component.camera.target - cam_component.camera.eye;
         let forward_norm = forward.normalize();
         let forward_mag = forward.magnitude();
-        let speed = cam_component.camera_controller.speed.clone();
+        let speed = cam_component.camera_controller.speed;
 
         // Prevents glitching when camera gets too close to the
         // center of the scene.
@@ -168,17 +239,10 @@ impl CameraSystem {
                 cam_component.camera.target + rotation_matrix * relative_position;
         }
 
-        let eye = cam_component.camera.eye;
-        let target = cam_component.camera.target;
-        let up = cam_component.camera.up;
-        let aspect = cam_component.camera.aspect;
-        let fovy = cam_component.camera.fovy;
-        let znear = cam_component.camera.znear;
-        let zfar = cam_component.camera.zfar;
-
+        let cam_component = &mut *cam_component;
         cam_component
             .camera_uniform
-            .update_view_proj(eye, target, up, aspect, fovy, znear, zfar);
+            .update_view_proj(&cam_component.camera);
         queue.write_buffer(
             &cam_component.camera_buffer,
             0,
@@ -220,7 +284,7 @@ impl CameraSystem {
         {
             let view = cgmath::Matrix4::look_at_rh(eye, target, up);
             let proj = cgmath::perspective(cgmath::Deg(fovy), aspect, znear, zfar);
-            return (proj * view, view.clone(), proj.clone());
+            (proj * view, view, proj)
         }
     }
 }
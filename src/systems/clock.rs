@@ -0,0 +1,25 @@
+use anise::prelude::{Epoch, Unit};
+use chrono::Utc;
+
+use crate::resources::clock::SimulationClock;
+
+pub struct ClockSystem {}
+
+impl ClockSystem {
+    /// Advances the clock and returns the wall-clock seconds since the last tick.
+    pub fn tick(clock: &mut SimulationClock) -> f64 {
+        let now = Utc::now();
+        let elapsed_seconds = (now - clock.last_tick).num_microseconds().unwrap_or(0) as f64 / 1e6;
+        clock.last_tick = now;
+
+        if !clock.paused {
+            clock.epoch += Unit::Second * (elapsed_seconds * clock.multiplier);
+        }
+        elapsed_seconds
+    }
+
+    pub fn set_epoch(clock: &mut SimulationClock, epoch: Epoch) {
+        clock.epoch = epoch;
+        clock.last_tick = Utc::now();
+    }
+}
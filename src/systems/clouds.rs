@@ -0,0 +1,243 @@
+use std::f64::consts::TAU;
+
+use anise::prelude::Epoch;
+use cgmath::SquareMatrix;
+use wgpu::util::DeviceExt;
+
+use crate::{
+    components::{
+        camera::CameraComponent, clouds::CloudComponent, earth::EarthSurfaceComponent,
+        material::MaterialComponent, mesh::MeshComponent,
+        render_pipelines::RenderPipelineComponent,
+    },
+    matrix4_to_array, WGS84_A,
+};
+
+use super::{mesh::MeshSystem, pipelines::CloudRenderPipelineSystem};
+
+// height of the cloud shell above the surface, kilometers
+const CLOUD_ALTITUDE: f32 = 12.0;
+// how much sunlight full cover keeps off the ground
+const SHADOW_STRENGTH: f32 = 0.8;
+// roughly the speed of mid-latitude weather, degrees of longitude per hour
+pub const DEFAULT_DRIFT_RATE: f64 = 0.25;
+
+pub struct CloudSystem {}
+
+impl CloudSystem {
+    pub fn create_clouds(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture_format: wgpu::TextureFormat,
+        camera_component: &CameraComponent,
+        surface_component: &mut EarthSurfaceComponent,
+    ) -> (
+        CloudComponent,
+        MeshComponent,
+        MaterialComponent,
+        RenderPipelineComponent,
+    ) {
+        let cloud_component = CloudComponent {
+            drift_rate: CloudSystem::drift_rate(DEFAULT_DRIFT_RATE),
+            visible: true,
+            shadows: true,
+        };
+        surface_component.uniform.clouds = [
+            0.0,
+            (WGS84_A + CLOUD_ALTITUDE) / WGS84_A,
+            SHADOW_STRENGTH,
+            0.0,
+        ];
+        queue.write_buffer(
+            &surface_component.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[surface_component.uniform]),
+        );
+
+        let mesh_component = CloudSystem::generate_mesh(device);
+        let (material_component, render_pipeline_component) = CloudSystem::retexture(
+            device,
+            texture_format,
+            camera_component,
+            &mesh_component,
+            surface_component,
+        );
+        (
+            cloud_component,
+            mesh_component,
+            material_component,
+            render_pipeline_component,
+        )
+    }
+
+    // Rebuilds the shell's material after the Earth's cloud map changed.
+    pub fn retexture(
+        device: &wgpu::Device,
+        texture_format: wgpu::TextureFormat,
+        camera_component: &CameraComponent,
+        mesh_component: &MeshComponent,
+        surface_component: &EarthSurfaceComponent,
+    ) -> (MaterialComponent, RenderPipelineComponent) {
+        let material_component = CloudSystem::generate_material(device, surface_component);
+        let render_pipeline_component = CloudSystem::generate_render_pipeline(
+            device,
+            texture_format,
+            camera_component,
+            mesh_component,
+            &material_component,
+        );
+        (material_component, render_pipeline_component)
+    }
+
+    /// Degrees of longitude per hour to radians per second.
+    pub fn drift_rate(degrees_per_hour: f64) -> f64 {
+        degrees_per_hour.to_radians() / 3600.0
+    }
+
+    // The drift is a function of the simulation time rather than accumulated
+    // per frame, so jumping the clock puts the clouds where they'd have been.
+    pub fn update(
+        queue: &wgpu::Queue,
+        cloud_component: &CloudComponent,
+        surface_component: &mut EarthSurfaceComponent,
+        epoch: Epoch,
+    ) {
+        let drift = (cloud_component.drift_rate * epoch.to_tdb_seconds()).rem_euclid(TAU);
+        let shadows = cloud_component.visible && cloud_component.shadows;
+        surface_component.uniform.clouds[0] = drift as f32;
+        surface_component.uniform.clouds[2] = if shadows { SHADOW_STRENGTH } else { 0.0 };
+        queue.write_buffer(
+            &surface_component.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[surface_component.uniform]),
+        );
+    }
+
+    fn generate_mesh(device: &wgpu::Device) -> MeshComponent {
+        let cloud_matrix = matrix4_to_array(cgmath::Matrix4::identity());
+        let cloud_matrix_bind_group_layout =
+            MeshSystem::create_model_matrix_bind_group_layout(device);
+        let cloud_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Mesh Buffer"),
+            contents: bytemuck::cast_slice(&[cloud_matrix]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let cloud_matrix_bind_group = MeshSystem::create_model_matrix_bind_group(
+            device,
+            &cloud_matrix_bind_group_layout,
+            &cloud_buffer,
+        );
+        let (cloud_vertices_vec, cloud_indices_vec) =
+            MeshSystem::generate_sphere_mesh(WGS84_A + CLOUD_ALTITUDE);
+
+        MeshComponent {
+            vertex_buffer: MeshSystem::create_vertex_buffer(device, cloud_vertices_vec.as_slice()),
+            index_buffer: MeshSystem::create_index_buffer(device, cloud_indices_vec.as_slice()),
+            num_indices: cloud_indices_vec.len() as u32,
+            model_matrix_bind_group_layout: cloud_matrix_bind_group_layout,
+            model_matrix_bind_group: cloud_matrix_bind_group,
+            model_matrix_buffer: cloud_buffer,
+            model_matrix: cloud_matrix,
+        }
+    }
+
+    fn generate_material(
+        device: &wgpu::Device,
+        surface_component: &EarthSurfaceComponent,
+    ) -> MaterialComponent {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Cloud Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Cloud bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::Cube,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&surface_component.clouds),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: surface_component.uniform_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("Cloud bind group"),
+        });
+
+        MaterialComponent {
+            bind_group,
+            bind_group_layout,
+            uniforms: None,
+            shader: device
+                .create_shader_module(wgpu::include_wgsl!("../shaders/cloud_shader.wgsl")),
+        }
+    }
+
+    fn generate_render_pipeline(
+        device: &wgpu::Device,
+        texture_format: wgpu::TextureFormat,
+        camera_component: &CameraComponent,
+        mesh_component: &MeshComponent,
+        material_component: &MaterialComponent,
+    ) -> RenderPipelineComponent {
+        let cloud_pipeline_layouts: &[&wgpu::BindGroupLayout] = &[
+            &camera_component.camera_bind_group_layout,
+            &material_component.bind_group_layout,
+            &mesh_component.model_matrix_bind_group_layout,
+        ];
+        let cloud_render_pipeline_layout =
+            CloudRenderPipelineSystem::layout_desc(device, cloud_pipeline_layouts);
+        let cloud_render_pipeline = CloudRenderPipelineSystem::pipeline_desc(
+            device,
+            &cloud_render_pipeline_layout,
+            &material_component.shader,
+            texture_format,
+        );
+        RenderPipelineComponent {
+            render_pipeline: cloud_render_pipeline,
+            render_pipeline_layout: cloud_render_pipeline_layout,
+        }
+    }
+}
@@ -1,27 +1,95 @@
-use cgmath::SquareMatrix;
+use anise::{almanac::Almanac, constants::celestial_objects, prelude::Epoch};
+use cgmath::{InnerSpace, SquareMatrix, Vector3};
 use wgpu::util::DeviceExt;
 
 use crate::{
     components::{
-        camera::CameraComponent, material::MaterialComponent, mesh::MeshComponent,
+        camera::CameraComponent,
+        earth::{EarthMap, EarthSurfaceComponent, EarthSurfaceUniform, OverlayFrame},
+        material::MaterialComponent,
+        mesh::MeshComponent,
         render_pipelines::RenderPipelineComponent,
     },
     matrix4_to_array, WGS84_A,
 };
 
-use super::{material::MaterialSystem, mesh::MeshSystem, pipelines::EarthRenderPipelineSystem};
+use super::{
+    body::BodySystem,
+    imagery::ImagerySystem,
+    material::{MaterialSystem, TextureSource},
+    mesh::MeshSystem,
+    pipelines::EarthRenderPipelineSystem,
+};
+
+// what each optional map shows until one is loaded: flat ground, no glint,
+// no lights, clear skies and no overlay
+const FLAT_NORMAL: [u8; 4] = [128, 128, 255, 255];
+const NO_SPECULAR: [u8; 4] = [0, 0, 0, 255];
+const NO_LIGHTS: [u8; 4] = [0, 0, 0, 255];
+const NO_CLOUDS: [u8; 4] = [0, 0, 0, 255];
+const NO_OVERLAY: [u8; 4] = [0, 0, 0, 0];
+
+// the March equinox direction, used until an ephemeris has loaded
+const DEFAULT_SUN_DIRECTION: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
 
 pub struct EarthSystem {}
 
 impl EarthSystem {
-    pub fn new(
+    pub fn create_earth(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         texture_format: wgpu::TextureFormat,
         camera_component: &CameraComponent,
-    ) -> (MeshComponent, MaterialComponent, RenderPipelineComponent) {
+        cube_map: TextureSource,
+    ) -> (
+        MeshComponent,
+        MaterialComponent,
+        RenderPipelineComponent,
+        EarthSurfaceComponent,
+    ) {
+        let solid = |color| {
+            MaterialSystem::create_cube_map_view(
+                device,
+                queue,
+                MaterialSystem::solid_cube_map(color),
+                false,
+            )
+        };
+        let uniform = EarthSurfaceUniform {
+            sun_direction: DEFAULT_SUN_DIRECTION,
+            // set up by CloudSystem::create_clouds
+            clouds: [0.0, 1.0, 0.0, 0.0],
+            overlay: [0.0, 1.0, 0.0, 0.0],
+        };
+        let surface_component = EarthSurfaceComponent {
+            day: MaterialSystem::create_cube_map_view(device, queue, cube_map, true),
+            normal: solid(FLAT_NORMAL),
+            specular: solid(NO_SPECULAR),
+            night: solid(NO_LIGHTS),
+            clouds: solid(NO_CLOUDS),
+            overlay_frames: Vec::new(),
+            overlay_pair: None,
+            no_overlay: solid(NO_OVERLAY),
+            uniform,
+            uniform_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Earth Surface Uniform Buffer"),
+                contents: bytemuck::cast_slice(&[uniform]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }),
+            sampler: device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("Earth Surface Sampler"),
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                address_mode_w: wgpu::AddressMode::ClampToEdge,
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                mipmap_filter: wgpu::FilterMode::Linear,
+                ..Default::default()
+            }),
+        };
+
         let mesh_component = EarthSystem::generate_mesh(device);
-        let material_component = EarthSystem::generate_material(device, queue);
+        let material_component = EarthSystem::generate_material(device, &surface_component);
         let earth_render_pipeline_component = EarthSystem::generate_render_pipeline(
             device,
             texture_format,
@@ -33,9 +101,161 @@ impl EarthSystem {
             mesh_component,
             material_component,
             earth_render_pipeline_component,
+            surface_component,
         )
     }
 
+    // Swaps in one of the Earth's maps, keeping the others. Bump maps are
+    // expected to have been converted to normals already.
+    pub fn set_map(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        surface_component: &mut EarthSurfaceComponent,
+        material_component: &mut MaterialComponent,
+        map: EarthMap,
+        cube_map: TextureSource,
+    ) {
+        let srgb = matches!(map, EarthMap::Day | EarthMap::Night);
+        let view = MaterialSystem::create_cube_map_view(device, queue, cube_map, srgb);
+        match map {
+            EarthMap::Day => surface_component.day = view,
+            EarthMap::Normal | EarthMap::Bump => surface_component.normal = view,
+            EarthMap::Specular => surface_component.specular = view,
+            EarthMap::Night => surface_component.night = view,
+            EarthMap::Clouds => surface_component.clouds = view,
+        }
+
+        EarthSystem::rebuild_material(device, surface_component, material_component);
+    }
+
+    // Rebinds the surface's textures, after they or the overlay frames in
+    // use have changed. The layout and pipeline don't depend on which
+    // textures are bound, so only the bind group is made again.
+    pub fn rebuild_material(
+        device: &wgpu::Device,
+        surface_component: &EarthSurfaceComponent,
+        material_component: &mut MaterialComponent,
+    ) {
+        material_component.bind_group = EarthSystem::create_bind_group(
+            device,
+            &material_component.bind_group_layout,
+            surface_component,
+        );
+    }
+
+    // Adds a frame to the time-series overlay, replacing any frame with the
+    // same timestamp. Takes effect on the next `update_overlay`.
+    pub fn add_overlay_frame(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        surface_component: &mut EarthSurfaceComponent,
+        epoch: Epoch,
+        cube_map: TextureSource,
+    ) {
+        let frame = OverlayFrame {
+            epoch,
+            view: MaterialSystem::create_cube_map_view(device, queue, cube_map, true),
+        };
+        let frames = &mut surface_component.overlay_frames;
+        match frames.binary_search_by(|other| other.epoch.cmp(&epoch)) {
+            Ok(i) => frames[i] = frame,
+            Err(i) => frames.insert(i, frame),
+        }
+        // indices have shifted, so whatever is bound is stale
+        surface_component.overlay_pair = None;
+    }
+
+    // Removes every overlay frame. The material still binds the old ones
+    // until it is rebuilt.
+    pub fn clear_overlay(surface_component: &mut EarthSurfaceComponent) {
+        surface_component.overlay_frames.clear();
+        surface_component.overlay_pair = None;
+    }
+
+    pub fn set_overlay_opacity(
+        queue: &wgpu::Queue,
+        surface_component: &mut EarthSurfaceComponent,
+        opacity: f32,
+    ) {
+        surface_component.uniform.overlay[1] = opacity.clamp(0.0, 1.0);
+        queue.write_buffer(
+            &surface_component.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[surface_component.uniform]),
+        );
+    }
+
+    /// Picks the overlay frames either side of `epoch` and cross-fades
+    /// between them; outside the series the nearest frame is held. Returns
+    /// true when a different pair is needed, and with it
+    /// [`EarthSystem::rebuild_material`].
+    pub fn update_overlay(
+        queue: &wgpu::Queue,
+        surface_component: &mut EarthSurfaceComponent,
+        epoch: Epoch,
+    ) -> bool {
+        let frames = &surface_component.overlay_frames;
+        let next = frames.partition_point(|frame| frame.epoch <= epoch);
+        let (pair, blend) = if frames.is_empty() {
+            (None, 0.0)
+        } else if next == 0 {
+            (Some((0, 0)), 0.0)
+        } else if next == frames.len() {
+            (Some((next - 1, next - 1)), 0.0)
+        } else {
+            let (from, to) = (&frames[next - 1], &frames[next]);
+            let blend = (epoch - from.epoch).to_seconds() / (to.epoch - from.epoch).to_seconds();
+            (Some((next - 1, next)), blend as f32)
+        };
+
+        surface_component.uniform.overlay[0] = blend;
+        queue.write_buffer(
+            &surface_component.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[surface_component.uniform]),
+        );
+
+        let changed = pair != surface_component.overlay_pair;
+        surface_component.overlay_pair = pair;
+        changed
+    }
+
+    /// The direction of the Sun in the Earth's model space. The scene shares
+    /// its axes, so the terminator faces the Sun drawn in solar-system mode.
+    pub fn sun_direction(almanac: &Almanac, epoch: Epoch) -> Option<Vector3<f64>> {
+        let sun_scene = BodySystem::scene_position(almanac, celestial_objects::SUN, epoch)?;
+        Some(sun_scene.normalize())
+    }
+
+    pub fn update_sun_direction(
+        queue: &wgpu::Queue,
+        surface_component: &mut EarthSurfaceComponent,
+        sun_direction: Vector3<f64>,
+    ) {
+        let lit = surface_component.uniform.sun_direction[3];
+        let sun_direction = sun_direction.cast::<f32>().unwrap();
+        surface_component.uniform.sun_direction = sun_direction.extend(lit).into();
+        queue.write_buffer(
+            &surface_component.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[surface_component.uniform]),
+        );
+    }
+
+    // Unlit, the day map is shown everywhere as before the Sun was known.
+    pub fn set_lighting(
+        queue: &wgpu::Queue,
+        surface_component: &mut EarthSurfaceComponent,
+        enabled: bool,
+    ) {
+        surface_component.uniform.sun_direction[3] = if enabled { 1.0 } else { 0.0 };
+        queue.write_buffer(
+            &surface_component.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[surface_component.uniform]),
+        );
+    }
+
     fn generate_mesh(device: &wgpu::Device) -> MeshComponent {
         let earth_matrix = matrix4_to_array(cgmath::Matrix4::identity());
         let earth_matrix_bind_group_layout =
@@ -46,7 +266,7 @@ impl EarthSystem {
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
         let earth_matrix_bind_group = MeshSystem::create_model_matrix_bind_group(
-            &device,
+            device,
             &earth_matrix_bind_group_layout,
             &earth_buffer,
         );
@@ -55,11 +275,8 @@ impl EarthSystem {
         let (earth_vertices_vec, earth_indices_vec) = MeshSystem::generate_sphere_mesh(WGS84_A);
 
         MeshComponent {
-            vertex_buffer: MeshSystem::create_vertex_buffer(
-                &device,
-                &earth_vertices_vec.as_slice(),
-            ),
-            index_buffer: MeshSystem::create_index_buffer(&device, &earth_indices_vec.as_slice()),
+            vertex_buffer: MeshSystem::create_vertex_buffer(device, earth_vertices_vec.as_slice()),
+            index_buffer: MeshSystem::create_index_buffer(device, earth_indices_vec.as_slice()),
             num_indices: earth_indices_vec.len() as u32,
             model_matrix_bind_group_layout: earth_matrix_bind_group_layout,
             model_matrix_bind_group: earth_matrix_bind_group,
@@ -68,26 +285,122 @@ impl EarthSystem {
         }
     }
 
-    fn generate_material(device: &wgpu::Device, queue: &wgpu::Queue) -> MaterialComponent {
-        let earth_image_data = MaterialSystem::cube_map_buffer_from_urls(vec![
-            include_bytes!("../assets/1.png"),
-            include_bytes!("../assets/2.png"),
-            include_bytes!("../assets/3.png"),
-            include_bytes!("../assets/4.png"),
-            include_bytes!("../assets/5.png"),
-            include_bytes!("../assets/6.png"),
-        ]);
-        let (materal_bind_group, material_bind_group_layout) =
-            MaterialSystem::create_cube_map_texture(&device, &queue, earth_image_data.clone());
+    fn generate_material(
+        device: &wgpu::Device,
+        surface_component: &EarthSurfaceComponent,
+    ) -> MaterialComponent {
+        let cube_map_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::Cube,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Earth Surface bind group layout"),
+            entries: &[
+                // day, normal, specular and night maps
+                cube_map_entry(0),
+                cube_map_entry(1),
+                cube_map_entry(2),
+                cube_map_entry(3),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // cloud cover, for the shadows
+                cube_map_entry(6),
+                // the overlay frames either side of the simulation time
+                cube_map_entry(7),
+                cube_map_entry(8),
+            ],
+        });
+        let bind_group =
+            EarthSystem::create_bind_group(device, &bind_group_layout, surface_component);
+
         MaterialComponent {
-            bind_group: materal_bind_group,
-            bind_group_layout: material_bind_group_layout,
+            bind_group,
+            bind_group_layout,
             uniforms: None,
-            shader: device
-                .create_shader_module(wgpu::include_wgsl!("../shaders/earth_shader.wgsl")),
+            shader: ImagerySystem::create_shader_module(
+                device,
+                "earth_shader.wgsl",
+                include_str!("../shaders/earth_shader.wgsl"),
+            ),
         }
     }
 
+    fn create_bind_group(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        surface_component: &EarthSurfaceComponent,
+    ) -> wgpu::BindGroup {
+        let (overlay_from, overlay_to) = match surface_component.overlay_pair {
+            Some((from, to)) => (
+                &surface_component.overlay_frames[from].view,
+                &surface_component.overlay_frames[to].view,
+            ),
+            None => (&surface_component.no_overlay, &surface_component.no_overlay),
+        };
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&surface_component.day),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&surface_component.normal),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&surface_component.specular),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&surface_component.night),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::Sampler(&surface_component.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: surface_component.uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: wgpu::BindingResource::TextureView(&surface_component.clouds),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 7,
+                    resource: wgpu::BindingResource::TextureView(overlay_from),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 8,
+                    resource: wgpu::BindingResource::TextureView(overlay_to),
+                },
+            ],
+            label: Some("Earth Surface bind group"),
+        })
+    }
+
     fn generate_render_pipeline(
         device: &wgpu::Device,
         texture_format: wgpu::TextureFormat,
@@ -95,15 +408,17 @@ impl EarthSystem {
         mesh_component: &MeshComponent,
         material_component: &MaterialComponent,
     ) -> RenderPipelineComponent {
+        let imagery_bind_group_layout = ImagerySystem::create_bind_group_layout(device);
         let earth_pipeline_layouts: &[&wgpu::BindGroupLayout] = &[
             &camera_component.camera_bind_group_layout,
             &material_component.bind_group_layout,
             &mesh_component.model_matrix_bind_group_layout,
+            &imagery_bind_group_layout,
         ];
         let earth_render_pipeline_layout =
-            EarthRenderPipelineSystem::layout_desc(&device, earth_pipeline_layouts);
+            EarthRenderPipelineSystem::layout_desc(device, earth_pipeline_layouts);
         let earth_render_pipeline = EarthRenderPipelineSystem::pipeline_desc(
-            &device,
+            device,
             &earth_render_pipeline_layout,
             &material_component.shader,
             texture_format,
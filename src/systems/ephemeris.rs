@@ -0,0 +1,28 @@
+use anise::almanac::Almanac;
+
+use crate::{loader::ParsedKernel, resources::ephemeris::Ephemeris};
+
+pub struct EphemerisSystem {}
+
+impl EphemerisSystem {
+    /// Adds `kernel` on top of whatever is already loaded, or starts a fresh
+    /// ephemeris if nothing is. Later kernels take precedence where they overlap.
+    pub fn add_kernel(
+        ephemeris: Option<&Ephemeris>,
+        path: &str,
+        kernel: ParsedKernel,
+    ) -> Result<Ephemeris, String> {
+        let default_almanac = Almanac::default();
+        let almanac = ephemeris.map_or(&default_almanac, |ephemeris| &ephemeris.almanac);
+
+        let almanac = match kernel {
+            ParsedKernel::Spk(spk) => almanac.with_spk(spk).map_err(|e| e.to_string())?,
+            ParsedKernel::Bpc(bpc) => almanac.with_bpc(bpc).map_err(|e| e.to_string())?,
+            ParsedKernel::PlanetaryConstants(data) => almanac.with_planetary_data(*data),
+        };
+
+        let mut kernels = ephemeris.map_or_else(Vec::new, |ephemeris| ephemeris.kernels.clone());
+        kernels.push(path.to_string());
+        Ok(Ephemeris { almanac, kernels })
+    }
+}
@@ -1,3 +1,24 @@
+use super::{mgrs::MgrsSystem, utm::UtmSystem};
+
+/// How positions are written out for people, e.g. the one under the cursor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoordinateFormat {
+    /// `48.85837, 2.29448` with this many decimals.
+    DecimalDegrees { decimals: usize },
+    /// `48°51'30.1"N 2°17'40.1"E` with this many decimals of seconds.
+    Dms { decimals: usize },
+    /// `31N 448251 5411952`, or UPS near the poles, with this many decimals of meters.
+    Utm { decimals: usize },
+    /// `31U DQ 48250 11951` with this many digits per axis, 0 to 5.
+    Mgrs { precision: usize },
+}
+
+impl Default for CoordinateFormat {
+    fn default() -> Self {
+        CoordinateFormat::DecimalDegrees { decimals: 5 }
+    }
+}
+
 pub struct CoordinatesSystem {}
 
 impl CoordinatesSystem {
@@ -11,4 +32,36 @@ impl CoordinatesSystem {
 
         [x, y, z]
     }
+
+    /// Writes a latitude and longitude in degrees out in `format`.
+    pub fn format(lat: f64, lon: f64, format: CoordinateFormat) -> String {
+        let lon = (lon + 180.0).rem_euclid(360.0) - 180.0;
+        match format {
+            CoordinateFormat::DecimalDegrees { decimals } => {
+                format!("{lat:.decimals$}, {lon:.decimals$}")
+            }
+            CoordinateFormat::Dms { decimals } => format!(
+                "{}{} {}{}",
+                CoordinatesSystem::dms(lat, decimals),
+                if lat < 0.0 { 'S' } else { 'N' },
+                CoordinatesSystem::dms(lon, decimals),
+                if lon < 0.0 { 'W' } else { 'E' },
+            ),
+            CoordinateFormat::Utm { decimals } => {
+                UtmSystem::format(&UtmSystem::from_lat_lon(lat, lon), decimals)
+            }
+            CoordinateFormat::Mgrs { precision } => MgrsSystem::format(lat, lon, precision),
+        }
+    }
+
+    // degrees, minutes and seconds of an angle's magnitude, rounded as a
+    // whole so 59.99" never shows as 60"
+    fn dms(angle: f64, decimals: usize) -> String {
+        let unit = 10u64.pow(decimals as u32);
+        let total = (angle.abs() * 3600.0 * unit as f64).round() as u64;
+        let (degrees, minutes) = (total / (3600 * unit), total / (60 * unit) % 60);
+        let seconds = (total % (60 * unit)) as f64 / unit as f64;
+        let width = if decimals > 0 { decimals + 3 } else { 2 };
+        format!("{degrees}°{minutes:02}'{seconds:0width$.decimals$}\"")
+    }
 }
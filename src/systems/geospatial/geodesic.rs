@@ -0,0 +1,461 @@
+use std::f64::consts::PI;
+
+/// WGS84 semi-major axis in meters.
+pub const WGS84_SEMI_MAJOR_AXIS: f64 = 6_378_137.0;
+/// WGS84 flattening.
+pub const WGS84_FLATTENING: f64 = 1.0 / 298.257_223_563;
+
+// Vincenty's iteration limit and the change in longitude on the auxiliary
+// sphere that counts as converged (about 0.006 mm)
+const MAX_ITERATIONS: usize = 200;
+const CONVERGENCE: f64 = 1e-12;
+// halvings of the azimuth range for antipodal points, down to ~1e-18 rad
+const BISECTION_ITERATIONS: usize = 60;
+// longest edge piece polygon areas are summed over, short enough that the
+// authalic great circle and the geodesic agree
+const AREA_EDGE_SPACING: f64 = 10_000.0;
+// most pieces `intermediate_points` splits a geodesic into, whatever spacing
+// it's asked for
+const MAX_INTERMEDIATE_STEPS: usize = 1 << 16;
+
+/// Solution of the inverse problem between two points on the WGS84 ellipsoid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeodesicInverse {
+    /// Length of the geodesic in meters.
+    pub distance: f64,
+    /// Azimuth at the first point, degrees clockwise from north in 0..360.
+    pub initial_bearing: f64,
+    /// Azimuth at the second point, degrees clockwise from north in 0..360.
+    pub final_bearing: f64,
+}
+
+/// Solution of the direct problem: where a geodesic from a point ends up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeodesicDirect {
+    /// Latitude of the destination in degrees.
+    pub lat: f64,
+    /// Longitude of the destination in degrees, in -180..180.
+    pub lon: f64,
+    /// Azimuth at the destination, degrees clockwise from north in 0..360.
+    pub final_bearing: f64,
+}
+
+/// Geodesics on the WGS84 ellipsoid, all in degrees and meters. Vincenty's
+/// formulae are accurate to well under a millimeter; the nearly antipodal pairs
+/// where his inverse iteration fails are solved by bisecting on the azimuth
+/// instead.
+pub struct GeodesicSystem {}
+
+impl GeodesicSystem {
+    /// Distance and azimuths of the shortest path between two (lat, lon) points.
+    pub fn inverse(from: (f64, f64), to: (f64, f64)) -> GeodesicInverse {
+        GeodesicSystem::vincenty_inverse(from, to)
+            .unwrap_or_else(|| GeodesicSystem::antipodal_inverse(from, to))
+    }
+
+    /// Destination `distance` meters from (lat, lon) `from`, setting off on
+    /// `bearing` degrees clockwise from north.
+    pub fn direct(from: (f64, f64), bearing: f64, distance: f64) -> GeodesicDirect {
+        let f = WGS84_FLATTENING;
+        let b = WGS84_SEMI_MAJOR_AXIS * (1.0 - f);
+
+        let (sin_u1, cos_u1) = reduced_latitude(from.0);
+        let (sin_alpha1, cos_alpha1) = bearing.to_radians().sin_cos();
+        // arc from the equator crossing to the start on the auxiliary sphere
+        let sigma1 = sin_u1.atan2(cos_u1 * cos_alpha1);
+        let sin_alpha = cos_u1 * sin_alpha1;
+        let cos2_alpha = 1.0 - sin_alpha * sin_alpha;
+        let (big_a, big_b) = series_coefficients(cos2_alpha);
+
+        let mut sigma = distance / (b * big_a);
+        for _ in 0..MAX_ITERATIONS {
+            let (sin_sigma, cos_sigma) = sigma.sin_cos();
+            let cos_2sigma_m = (2.0 * sigma1 + sigma).cos();
+            let next =
+                distance / (b * big_a) + delta_sigma(big_b, sin_sigma, cos_sigma, cos_2sigma_m);
+            let converged = (next - sigma).abs() < CONVERGENCE;
+            sigma = next;
+            if converged {
+                break;
+            }
+        }
+
+        let (sin_sigma, cos_sigma) = sigma.sin_cos();
+        let cos_2sigma_m = (2.0 * sigma1 + sigma).cos();
+        let x = sin_u1 * sin_sigma - cos_u1 * cos_sigma * cos_alpha1;
+        let lat = (sin_u1 * cos_sigma + cos_u1 * sin_sigma * cos_alpha1)
+            .atan2((1.0 - f) * (sin_alpha * sin_alpha + x * x).sqrt());
+        let lambda =
+            (sin_sigma * sin_alpha1).atan2(cos_u1 * cos_sigma - sin_u1 * sin_sigma * cos_alpha1);
+        let l = lambda
+            - longitude_correction(
+                cos2_alpha,
+                sin_alpha,
+                sigma,
+                sin_sigma,
+                cos_sigma,
+                cos_2sigma_m,
+            );
+        let final_bearing = sin_alpha.atan2(-x);
+
+        GeodesicDirect {
+            lat: lat.to_degrees(),
+            lon: wrap_radians(from.1.to_radians() + l).to_degrees(),
+            final_bearing: normalize_degrees(final_bearing.to_degrees()),
+        }
+    }
+
+    /// The point `fraction` of the way along the geodesic from `from` to `to`.
+    pub fn intermediate(from: (f64, f64), to: (f64, f64), fraction: f64) -> (f64, f64) {
+        let inverse = GeodesicSystem::inverse(from, to);
+        let point =
+            GeodesicSystem::direct(from, inverse.initial_bearing, inverse.distance * fraction);
+        (point.lat, point.lon)
+    }
+
+    /// The point halfway along the geodesic from `from` to `to`.
+    pub fn midpoint(from: (f64, f64), to: (f64, f64)) -> (f64, f64) {
+        GeodesicSystem::intermediate(from, to, 0.5)
+    }
+
+    /// Points evenly spaced along the geodesic from `from` to `to`, no more than
+    /// `max_spacing` meters apart, both ends included. Spacings that would
+    /// split it into more than 65536 pieces, including zero, negative and NaN
+    /// ones, are widened to that.
+    pub fn intermediate_points(
+        from: (f64, f64),
+        to: (f64, f64),
+        max_spacing: f64,
+    ) -> Vec<(f64, f64)> {
+        let inverse = GeodesicSystem::inverse(from, to);
+        // `max` drops a NaN spacing, and the 0/0 of a path of no length
+        let spacing = max_spacing.max(inverse.distance / MAX_INTERMEDIATE_STEPS as f64);
+        let steps = (inverse.distance / spacing)
+            .ceil()
+            .max(1.0)
+            .min(MAX_INTERMEDIATE_STEPS as f64) as usize;
+
+        let mut points = vec![from];
+        for i in 1..steps {
+            let distance = inverse.distance * i as f64 / steps as f64;
+            let point = GeodesicSystem::direct(from, inverse.initial_bearing, distance);
+            points.push((point.lat, point.lon));
+        }
+        points.push(to);
+        points
+    }
+
+    /// Area in square meters of the polygon through the (lat, lon) vertices in
+    /// degrees, joined by geodesics. The ring closes itself and is measured on
+    /// the authalic sphere, which preserves area. Of the two regions the ring
+    /// bounds, the smaller is returned.
+    pub fn polygon_area(vertices: &[(f64, f64)]) -> f64 {
+        if vertices.len() < 3 {
+            return 0.0;
+        }
+        let e = (WGS84_FLATTENING * (2.0 - WGS84_FLATTENING)).sqrt();
+        let q_pole = authalic_q(1.0, e);
+        let radius = WGS84_SEMI_MAJOR_AXIS * (q_pole / 2.0).sqrt();
+        let ring: Vec<(f64, f64)> = (0..vertices.len())
+            .flat_map(|i| {
+                let next = vertices[(i + 1) % vertices.len()];
+                let mut edge =
+                    GeodesicSystem::intermediate_points(vertices[i], next, AREA_EDGE_SPACING);
+                edge.pop();
+                edge
+            })
+            .collect();
+
+        // signed excess of the strip between each edge and the equator, and
+        // how far the ring winds around the poles
+        let mut excess = 0.0;
+        let mut winding = 0.0;
+        for (i, &(lat1, lon1)) in ring.iter().enumerate() {
+            let (lat2, lon2) = ring[(i + 1) % ring.len()];
+            let beta1 = (authalic_q(lat1.to_radians().sin(), e) / q_pole)
+                .clamp(-1.0, 1.0)
+                .asin();
+            let beta2 = (authalic_q(lat2.to_radians().sin(), e) / q_pole)
+                .clamp(-1.0, 1.0)
+                .asin();
+            let delta_lon = wrap_radians((lon2 - lon1).to_radians());
+
+            let (t1, t2) = ((beta1 / 2.0).tan(), (beta2 / 2.0).tan());
+            excess += 2.0 * ((delta_lon / 2.0).tan() * (t1 + t2)).atan2(1.0 + t1 * t2);
+            winding += delta_lon;
+        }
+
+        // a ring around a pole splits the sphere into a hemisphere plus or
+        // minus the strips rather than the strips and the rest
+        let sphere = 4.0 * PI;
+        let area = if winding.abs() > PI {
+            sphere / 2.0 - excess.abs()
+        } else {
+            excess.abs()
+        }
+        .rem_euclid(sphere);
+        area.min(sphere - area) * radius * radius
+    }
+
+    // Vincenty's inverse, or `None` for the nearly antipodal points where the
+    // iteration on the longitude diverges.
+    fn vincenty_inverse(from: (f64, f64), to: (f64, f64)) -> Option<GeodesicInverse> {
+        let f = WGS84_FLATTENING;
+        let b = WGS84_SEMI_MAJOR_AXIS * (1.0 - f);
+
+        let (sin_u1, cos_u1) = reduced_latitude(from.0);
+        let (sin_u2, cos_u2) = reduced_latitude(to.0);
+        let l = wrap_radians((to.1 - from.1).to_radians());
+
+        let mut lambda = l;
+        for _ in 0..MAX_ITERATIONS {
+            let (sin_lambda, cos_lambda) = lambda.sin_cos();
+            let sin_sigma = ((cos_u2 * sin_lambda).powi(2)
+                + (cos_u1 * sin_u2 - sin_u1 * cos_u2 * cos_lambda).powi(2))
+            .sqrt();
+            if sin_sigma == 0.0 {
+                // coincident points
+                return Some(GeodesicInverse {
+                    distance: 0.0,
+                    initial_bearing: 0.0,
+                    final_bearing: 0.0,
+                });
+            }
+            let cos_sigma = sin_u1 * sin_u2 + cos_u1 * cos_u2 * cos_lambda;
+            let sigma = sin_sigma.atan2(cos_sigma);
+            let sin_alpha = cos_u1 * cos_u2 * sin_lambda / sin_sigma;
+            let cos2_alpha = 1.0 - sin_alpha * sin_alpha;
+            // zero on an equatorial line
+            let cos_2sigma_m = if cos2_alpha != 0.0 {
+                cos_sigma - 2.0 * sin_u1 * sin_u2 / cos2_alpha
+            } else {
+                0.0
+            };
+            let previous = lambda;
+            lambda = l + longitude_correction(
+                cos2_alpha,
+                sin_alpha,
+                sigma,
+                sin_sigma,
+                cos_sigma,
+                cos_2sigma_m,
+            );
+
+            if lambda.abs() > PI {
+                return None;
+            }
+            if (lambda - previous).abs() > CONVERGENCE {
+                continue;
+            }
+
+            let (big_a, big_b) = series_coefficients(cos2_alpha);
+            let distance =
+                b * big_a * (sigma - delta_sigma(big_b, sin_sigma, cos_sigma, cos_2sigma_m));
+            let (sin_lambda, cos_lambda) = lambda.sin_cos();
+            let initial =
+                (cos_u2 * sin_lambda).atan2(cos_u1 * sin_u2 - sin_u1 * cos_u2 * cos_lambda);
+            let fin = (cos_u1 * sin_lambda).atan2(-sin_u1 * cos_u2 + cos_u1 * sin_u2 * cos_lambda);
+            return Some(GeodesicInverse {
+                distance,
+                initial_bearing: normalize_degrees(initial.to_degrees()),
+                final_bearing: normalize_degrees(fin.to_degrees()),
+            });
+        }
+
+        None
+    }
+
+    // Nearly antipodal points. With the first point south of the equator and
+    // at least as far from it as the second, and the second to the east,
+    // the longitude at which a geodesic leaving the first point climbs through
+    // the second's latitude grows steadily with its starting azimuth (Karney,
+    // "Algorithms for geodesics", 2013), so bisect on that azimuth.
+    fn antipodal_inverse(from: (f64, f64), to: (f64, f64)) -> GeodesicInverse {
+        let swap = from.0.abs() < to.0.abs();
+        let (mut first, mut second) = if swap { (to, from) } else { (from, to) };
+        let flip_lat = first.0 > 0.0;
+        if flip_lat {
+            first.0 = -first.0;
+            second.0 = -second.0;
+        }
+        let l = wrap_radians((second.1 - first.1).to_radians());
+        let flip_lon = l < 0.0;
+        let l = l.abs();
+
+        let (sin_u1, cos_u1) = reduced_latitude(first.0);
+        let (sin_u2, _) = reduced_latitude(second.0);
+        // both on the equator, where the path over the pole crosses it going
+        // south and its longitude shrinks as the azimuth grows instead
+        let equatorial = sin_u1 == 0.0;
+
+        let (mut low, mut high) = if equatorial {
+            (0.0, PI / 2.0)
+        } else {
+            (0.0, PI)
+        };
+        for _ in 0..BISECTION_ITERATIONS {
+            let middle = (low + high) / 2.0;
+            let path = AuxiliaryPath::new(middle, sin_u1, cos_u1, sin_u2, equatorial);
+            if (path.longitude > l) != equatorial {
+                high = middle;
+            } else {
+                low = middle;
+            }
+        }
+        let azimuth = (low + high) / 2.0;
+        let path = AuxiliaryPath::new(azimuth, sin_u1, cos_u1, sin_u2, equatorial);
+
+        let b = WGS84_SEMI_MAJOR_AXIS * (1.0 - WGS84_FLATTENING);
+        let (big_a, big_b) = series_coefficients(path.cos2_alpha);
+        let (sin_sigma, cos_sigma) = path.sigma.sin_cos();
+        let distance =
+            b * big_a * (path.sigma - delta_sigma(big_b, sin_sigma, cos_sigma, path.cos_2sigma_m));
+
+        // undo the arrangement, last step first
+        let (mut initial, mut fin) = (azimuth, path.final_azimuth);
+        if flip_lon {
+            (initial, fin) = (-initial, -fin);
+        }
+        if flip_lat {
+            (initial, fin) = (PI - initial, PI - fin);
+        }
+        if swap {
+            (initial, fin) = (fin + PI, initial + PI);
+        }
+        GeodesicInverse {
+            distance,
+            initial_bearing: normalize_degrees(initial.to_degrees()),
+            final_bearing: normalize_degrees(fin.to_degrees()),
+        }
+    }
+}
+
+// q(φ) of the authalic latitude, from sin φ and the first eccentricity
+fn authalic_q(sin_lat: f64, e: f64) -> f64 {
+    let e_sin = e * sin_lat;
+    (1.0 - e * e)
+        * (sin_lat / (1.0 - e_sin * e_sin) - ((1.0 - e_sin) / (1.0 + e_sin)).ln() / (2.0 * e))
+}
+
+fn wrap_radians(angle: f64) -> f64 {
+    (angle + PI).rem_euclid(2.0 * PI) - PI
+}
+
+// in 0..360; `rem_euclid` rounds tiny negative angles up to 360 itself
+fn normalize_degrees(angle: f64) -> f64 {
+    let angle = angle.rem_euclid(360.0);
+    if angle < 360.0 {
+        angle
+    } else {
+        0.0
+    }
+}
+
+// A geodesic followed on the auxiliary sphere from its start until it climbs
+// through the target reduced latitude.
+struct AuxiliaryPath {
+    // ellipsoidal longitude gained, radians
+    longitude: f64,
+    // arc length on the auxiliary sphere
+    sigma: f64,
+    cos2_alpha: f64,
+    cos_2sigma_m: f64,
+    final_azimuth: f64,
+}
+
+impl AuxiliaryPath {
+    fn new(azimuth: f64, sin_u1: f64, cos_u1: f64, sin_u2: f64, equatorial: bool) -> Self {
+        let (sin_azimuth, cos_azimuth) = azimuth.sin_cos();
+        // azimuth where the geodesic crosses the equator
+        let sin_alpha = cos_u1 * sin_azimuth;
+        let cos2_alpha = 1.0 - sin_alpha * sin_alpha;
+        let cos_alpha = cos2_alpha.sqrt();
+
+        // arcs from the northward equator crossing
+        let sigma1 = sin_u1.atan2(cos_u1 * cos_azimuth);
+        let sigma2 = if equatorial {
+            PI
+        } else {
+            let mut sigma2 = (sin_u2 / cos_alpha).clamp(-1.0, 1.0).asin();
+            while sigma2 < sigma1 {
+                sigma2 += 2.0 * PI;
+            }
+            sigma2
+        };
+        // longitude on the auxiliary sphere, unwrapped across whole turns
+        let omega = |sigma: f64| {
+            let turns = ((sigma + PI) / (2.0 * PI)).floor() * 2.0 * PI;
+            let rest = sigma - turns;
+            turns + (sin_alpha * rest.sin()).atan2(rest.cos())
+        };
+
+        let sigma = sigma2 - sigma1;
+        let cos_2sigma_m = (sigma1 + sigma2).cos();
+        let longitude = omega(sigma2)
+            - omega(sigma1)
+            - longitude_correction(
+                cos2_alpha,
+                sin_alpha,
+                sigma,
+                sigma.sin(),
+                sigma.cos(),
+                cos_2sigma_m,
+            );
+
+        AuxiliaryPath {
+            longitude,
+            sigma,
+            cos2_alpha,
+            cos_2sigma_m,
+            final_azimuth: sin_alpha.atan2(cos_alpha * sigma2.cos()),
+        }
+    }
+}
+
+// sine and cosine of the reduced latitude
+fn reduced_latitude(lat: f64) -> (f64, f64) {
+    let u = ((1.0 - WGS84_FLATTENING) * lat.to_radians().tan()).atan();
+    u.sin_cos()
+}
+
+// Vincenty's A and B from cos² of the equatorial azimuth
+fn series_coefficients(cos2_alpha: f64) -> (f64, f64) {
+    let a = WGS84_SEMI_MAJOR_AXIS;
+    let b = a * (1.0 - WGS84_FLATTENING);
+    let u2 = cos2_alpha * (a * a - b * b) / (b * b);
+    let big_a = 1.0 + u2 / 16384.0 * (4096.0 + u2 * (-768.0 + u2 * (320.0 - 175.0 * u2)));
+    let big_b = u2 / 1024.0 * (256.0 + u2 * (-128.0 + u2 * (74.0 - 47.0 * u2)));
+    (big_a, big_b)
+}
+
+// difference between the auxiliary arc and the ellipsoidal distance over b·A
+fn delta_sigma(big_b: f64, sin_sigma: f64, cos_sigma: f64, cos_2sigma_m: f64) -> f64 {
+    big_b
+        * sin_sigma
+        * (cos_2sigma_m
+            + big_b / 4.0
+                * (cos_sigma * (-1.0 + 2.0 * cos_2sigma_m * cos_2sigma_m)
+                    - big_b / 6.0
+                        * cos_2sigma_m
+                        * (-3.0 + 4.0 * sin_sigma * sin_sigma)
+                        * (-3.0 + 4.0 * cos_2sigma_m * cos_2sigma_m)))
+}
+
+// longitude on the auxiliary sphere less the longitude on the ellipsoid
+fn longitude_correction(
+    cos2_alpha: f64,
+    sin_alpha: f64,
+    sigma: f64,
+    sin_sigma: f64,
+    cos_sigma: f64,
+    cos_2sigma_m: f64,
+) -> f64 {
+    let f = WGS84_FLATTENING;
+    let c = f / 16.0 * cos2_alpha * (4.0 + f * (4.0 - 3.0 * cos2_alpha));
+    (1.0 - c)
+        * f
+        * sin_alpha
+        * (sigma
+            + c * sin_sigma
+                * (cos_2sigma_m + c * cos_sigma * (-1.0 + 2.0 * cos_2sigma_m * cos_2sigma_m)))
+}
@@ -0,0 +1,198 @@
+use super::{
+    utm::{Hemisphere, UtmCoordinate, UtmSystem, UTM_MIN_LATITUDE},
+    ParseCoordinateError,
+};
+
+/// Most digits per axis an MGRS reference carries, for 1 m squares.
+pub const MAX_MGRS_PRECISION: usize = 5;
+
+// 8° latitude bands from 80°S, with X stretched to 84°N
+const LATITUDE_BANDS: &[u8] = b"CDEFGHJKLMNPQRSTUVWX";
+// 100 km column letters, repeating every three zones
+const UTM_COLUMNS: [&[u8]; 3] = [b"ABCDEFGH", b"JKLMNPQR", b"STUVWXYZ"];
+// 100 km row letters, shifted by five in even zones
+const UTM_ROWS: &[u8] = b"ABCDEFGHJKLMNPQRSTUV";
+// the four UPS grids, west and east of the 0°/180° meridian in the south
+// then the north, with their column and row letters
+const UPS_BANDS: &[u8] = b"ABYZ";
+const UPS_COLUMNS: [&[u8]; 4] = [b"JKLPQRSTUXYZ", b"ABCFGHJKLPQR", b"RSTUXYZ", b"ABCFGHJ"];
+const UPS_ROWS: [&[u8]; 2] = [b"ABCDEFGHJKLMNPQRSTUVWXYZ", b"ABCDEFGHJKLMNP"];
+// index of the first 100 km square of the western grids, south and north,
+// and of the eastern grids in both
+const UPS_WEST_INDEX: [usize; 2] = [8, 13];
+const UPS_EAST_INDEX: usize = 20;
+const SQUARE_SIZE: f64 = 100_000.0;
+// northings repeat their row letters every 2000 km
+const ROW_CYCLE: f64 = 2_000_000.0;
+
+/// Military Grid Reference System references on top of the UTM and UPS grids.
+pub struct MgrsSystem {}
+
+impl MgrsSystem {
+    /// `"31U DQ 48250 11951"` for a point, with `precision` digits per axis
+    /// from 0 (100 km squares) to 5 (1 m squares). Digits are truncated, so
+    /// the reference names the square the point falls in.
+    pub fn format(lat: f64, lon: f64, precision: usize) -> String {
+        let precision = precision.min(MAX_MGRS_PRECISION);
+        let coordinate = UtmSystem::from_lat_lon(lat, lon);
+        let column = (coordinate.easting / SQUARE_SIZE).floor().max(0.0) as usize;
+        let row = (coordinate.northing / SQUARE_SIZE).floor().max(0.0) as usize;
+
+        let (zone, letters) = if coordinate.is_ups() {
+            let north = coordinate.hemisphere == Hemisphere::North;
+            let east = column >= UPS_EAST_INDEX;
+            let grid = 2 * north as usize + east as usize;
+            let first_column = if east {
+                UPS_EAST_INDEX
+            } else {
+                UPS_WEST_INDEX[north as usize]
+            };
+            let columns = UPS_COLUMNS[grid];
+            let rows = UPS_ROWS[north as usize];
+            let letters = [
+                UPS_BANDS[grid],
+                columns[column.saturating_sub(first_column).min(columns.len() - 1)],
+                rows[row
+                    .saturating_sub(UPS_WEST_INDEX[north as usize])
+                    .min(rows.len() - 1)],
+            ];
+            (String::new(), letters)
+        } else {
+            let band = ((lat - UTM_MIN_LATITUDE) / 8.0).floor().clamp(0.0, 19.0) as usize;
+            let columns = UTM_COLUMNS[(coordinate.zone as usize - 1) % 3];
+            let row_shift = if coordinate.zone.is_multiple_of(2) {
+                5
+            } else {
+                0
+            };
+            let letters = [
+                LATITUDE_BANDS[band],
+                columns[column.clamp(1, columns.len()) - 1],
+                UTM_ROWS[(row + row_shift) % UTM_ROWS.len()],
+            ];
+            (coordinate.zone.to_string(), letters)
+        };
+
+        let [band, column_letter, row_letter] = letters.map(char::from);
+        let mut text = format!("{zone}{band} {column_letter}{row_letter}");
+        if precision > 0 {
+            let scale = 10f64.powi((MAX_MGRS_PRECISION - precision) as i32);
+            let digits = |meters: f64| (meters.rem_euclid(SQUARE_SIZE) / scale).floor() as u32;
+            text += &format!(
+                " {:0precision$} {:0precision$}",
+                digits(coordinate.easting),
+                digits(coordinate.northing)
+            );
+        }
+        text
+    }
+
+    /// The south-west corner of the square an MGRS reference names, e.g.
+    /// `"31U DQ 48250 11951"`, `"31UDQ4825011951"` or `"ZGC 2000 3000"`.
+    pub fn parse(text: &str) -> Result<UtmCoordinate, ParseCoordinateError> {
+        let compact: String = text
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+        let zone_length = compact
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(|| ParseCoordinateError::new(text, "missing the grid letters"))?;
+        let (zone, rest) = compact.split_at(zone_length);
+        let letters = rest.as_bytes();
+        if letters.len() < 3 || !letters[..3].iter().all(u8::is_ascii_alphabetic) {
+            return Err(ParseCoordinateError::new(
+                text,
+                "expected a band and two square letters",
+            ));
+        }
+
+        let digits = &rest[3..];
+        if !digits.len().is_multiple_of(2)
+            || digits.len() > 2 * MAX_MGRS_PRECISION
+            || !digits.bytes().all(|c| c.is_ascii_digit())
+        {
+            return Err(ParseCoordinateError::new(
+                text,
+                "expected an even number of digits, at most ten",
+            ));
+        }
+        let precision = digits.len() / 2;
+        let scale = 10f64.powi((MAX_MGRS_PRECISION - precision) as i32);
+        let offset = |digits: &str| digits.parse::<f64>().unwrap_or(0.0) * scale;
+        let (easting_offset, northing_offset) =
+            (offset(&digits[..precision]), offset(&digits[precision..]));
+        let letter = |set: &[u8], letter: u8, what: &str| {
+            set.iter().position(|&c| c == letter).ok_or_else(|| {
+                ParseCoordinateError::new(text, format!("{} is not a valid {what}", letter as char))
+            })
+        };
+
+        if zone.is_empty() {
+            let grid = letter(UPS_BANDS, letters[0], "polar band")?;
+            let north = grid >= 2;
+            let east = grid % 2 == 1;
+            let first_column = if east {
+                UPS_EAST_INDEX
+            } else {
+                UPS_WEST_INDEX[north as usize]
+            };
+            let column = letter(UPS_COLUMNS[grid], letters[1], "column for this polar band")?;
+            let row = letter(
+                UPS_ROWS[north as usize],
+                letters[2],
+                "row for this polar band",
+            )?;
+
+            return Ok(UtmCoordinate {
+                zone: 0,
+                hemisphere: if north {
+                    Hemisphere::North
+                } else {
+                    Hemisphere::South
+                },
+                easting: (first_column + column) as f64 * SQUARE_SIZE + easting_offset,
+                northing: (UPS_WEST_INDEX[north as usize] + row) as f64 * SQUARE_SIZE
+                    + northing_offset,
+            });
+        }
+
+        let zone = zone
+            .parse::<u8>()
+            .ok()
+            .filter(|zone| (1..=60).contains(zone))
+            .ok_or_else(|| ParseCoordinateError::new(text, "zones run from 1 to 60"))?;
+        let band = letter(LATITUDE_BANDS, letters[0], "latitude band")?;
+        let column = letter(
+            UTM_COLUMNS[(zone as usize - 1) % 3],
+            letters[1],
+            "column for this zone",
+        )?;
+        let row_shift = if zone.is_multiple_of(2) { 5 } else { 0 };
+        let row = (letter(UTM_ROWS, letters[2], "row letter")? + UTM_ROWS.len() - row_shift)
+            % UTM_ROWS.len();
+
+        // the row letters only give the northing modulo 2000 km; take the
+        // first repeat that reaches the band, less a square of slack for
+        // parallels curving away from the central meridian
+        let band_south = UTM_MIN_LATITUDE + 8.0 * band as f64;
+        let central_meridian = zone as f64 * 6.0 - 183.0;
+        let band_northing = UtmSystem::to_zone(band_south, central_meridian, zone).northing;
+        let band_northing = (band_northing / SQUARE_SIZE).floor() * SQUARE_SIZE - SQUARE_SIZE;
+        let mut northing = row as f64 * SQUARE_SIZE + northing_offset;
+        while northing < band_northing {
+            northing += ROW_CYCLE;
+        }
+
+        Ok(UtmCoordinate {
+            zone,
+            hemisphere: if band_south >= 0.0 {
+                Hemisphere::North
+            } else {
+                Hemisphere::South
+            },
+            easting: (column + 1) as f64 * SQUARE_SIZE + easting_offset,
+            northing,
+        })
+    }
+}
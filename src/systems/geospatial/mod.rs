@@ -0,0 +1,30 @@
+use std::fmt;
+
+pub mod coordinates;
+pub mod geodesic;
+pub mod mgrs;
+pub mod utm;
+
+/// Text that is not a valid grid reference.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseCoordinateError {
+    pub text: String,
+    pub message: String,
+}
+
+impl ParseCoordinateError {
+    fn new(text: &str, message: impl Into<String>) -> Self {
+        ParseCoordinateError {
+            text: text.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseCoordinateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "could not parse {:?}: {}", self.text, self.message)
+    }
+}
+
+impl std::error::Error for ParseCoordinateError {}
@@ -0,0 +1,341 @@
+use std::f64::consts::FRAC_PI_4;
+
+use super::{
+    geodesic::{WGS84_FLATTENING, WGS84_SEMI_MAJOR_AXIS},
+    ParseCoordinateError,
+};
+
+/// Southern and northern limits of the UTM grid; UPS covers the poles beyond.
+pub const UTM_MIN_LATITUDE: f64 = -80.0;
+pub const UTM_MAX_LATITUDE: f64 = 84.0;
+
+// scale on the central meridian and the false origin, meters
+const UTM_SCALE: f64 = 0.9996;
+const UTM_FALSE_EASTING: f64 = 500_000.0;
+const UTM_FALSE_NORTHING_SOUTH: f64 = 10_000_000.0;
+// scale at the pole and the false origin on both axes, meters
+const UPS_SCALE: f64 = 0.994;
+const UPS_FALSE_ORIGIN: f64 = 2_000_000.0;
+// change in tan(latitude) that ends the inverse iterations
+const CONVERGENCE: f64 = 1e-12;
+const MAX_ITERATIONS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hemisphere {
+    North,
+    South,
+}
+
+/// A position on the UTM grid, or on the UPS grid around a pole when `zone`
+/// is 0.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UtmCoordinate {
+    /// UTM zone in 1..=60, or 0 for UPS.
+    pub zone: u8,
+    pub hemisphere: Hemisphere,
+    /// Meters, false easting included.
+    pub easting: f64,
+    /// Meters, false northing included.
+    pub northing: f64,
+}
+
+impl UtmCoordinate {
+    pub fn is_ups(&self) -> bool {
+        self.zone == 0
+    }
+}
+
+/// Conversions between WGS84 latitude and longitude in degrees and the UTM
+/// and UPS grids. The transverse Mercator uses Krüger's series to sixth order
+/// in n (Karney, "Transverse Mercator with an accuracy of a few nanometers",
+/// 2011).
+pub struct UtmSystem {}
+
+impl UtmSystem {
+    /// The UTM zone containing a point, honouring the Norway and Svalbard
+    /// exceptions, or 0 where UPS applies.
+    pub fn zone(lat: f64, lon: f64) -> u8 {
+        if !(UTM_MIN_LATITUDE..UTM_MAX_LATITUDE).contains(&lat) {
+            return 0;
+        }
+        let lon = (lon + 180.0).rem_euclid(360.0) - 180.0;
+        if (56.0..64.0).contains(&lat) && (3.0..12.0).contains(&lon) {
+            return 32;
+        }
+        if lat >= 72.0 && (0.0..42.0).contains(&lon) {
+            return match lon {
+                lon if lon < 9.0 => 31,
+                lon if lon < 21.0 => 33,
+                lon if lon < 33.0 => 35,
+                _ => 37,
+            };
+        }
+        ((lon + 180.0) / 6.0).floor() as u8 % 60 + 1
+    }
+
+    /// The UTM coordinate of a point, or its UPS coordinate near the poles.
+    pub fn from_lat_lon(lat: f64, lon: f64) -> UtmCoordinate {
+        match UtmSystem::zone(lat, lon) {
+            0 => UtmSystem::to_ups(lat, lon),
+            zone => UtmSystem::to_zone(lat, lon, zone),
+        }
+    }
+
+    /// Projects a point into a given UTM zone, even one it lies outside of.
+    pub fn to_zone(lat: f64, lon: f64, zone: u8) -> UtmCoordinate {
+        let (big_a, alpha, _) = kruger_series();
+        let e = eccentricity();
+        let central_meridian = zone as f64 * 6.0 - 183.0;
+        let lambda = ((lon - central_meridian + 180.0).rem_euclid(360.0) - 180.0).to_radians();
+        let (sin_lambda, cos_lambda) = lambda.sin_cos();
+
+        // conformal latitude, as its tangent
+        let tau = lat.to_radians().tan();
+        let tau_prime = conformal_tangent(tau, e);
+
+        let xi_prime = tau_prime.atan2(cos_lambda);
+        let eta_prime =
+            (sin_lambda / (tau_prime * tau_prime + cos_lambda * cos_lambda).sqrt()).asinh();
+        let (mut xi, mut eta) = (xi_prime, eta_prime);
+        for (j, alpha) in alpha.iter().enumerate() {
+            let k = 2.0 * (j + 1) as f64;
+            xi += alpha * (k * xi_prime).sin() * (k * eta_prime).cosh();
+            eta += alpha * (k * xi_prime).cos() * (k * eta_prime).sinh();
+        }
+
+        let hemisphere = if lat < 0.0 {
+            Hemisphere::South
+        } else {
+            Hemisphere::North
+        };
+        let false_northing = match hemisphere {
+            Hemisphere::North => 0.0,
+            Hemisphere::South => UTM_FALSE_NORTHING_SOUTH,
+        };
+        UtmCoordinate {
+            zone,
+            hemisphere,
+            easting: UTM_SCALE * big_a * eta + UTM_FALSE_EASTING,
+            northing: UTM_SCALE * big_a * xi + false_northing,
+        }
+    }
+
+    /// Latitude and longitude in degrees of a UTM or UPS coordinate.
+    pub fn to_lat_lon(coordinate: &UtmCoordinate) -> (f64, f64) {
+        if coordinate.is_ups() {
+            return UtmSystem::from_ups(coordinate);
+        }
+
+        let (big_a, _, beta) = kruger_series();
+        let e = eccentricity();
+        let false_northing = match coordinate.hemisphere {
+            Hemisphere::North => 0.0,
+            Hemisphere::South => UTM_FALSE_NORTHING_SOUTH,
+        };
+        let eta = (coordinate.easting - UTM_FALSE_EASTING) / (UTM_SCALE * big_a);
+        let xi = (coordinate.northing - false_northing) / (UTM_SCALE * big_a);
+
+        let (mut xi_prime, mut eta_prime) = (xi, eta);
+        for (j, beta) in beta.iter().enumerate() {
+            let k = 2.0 * (j + 1) as f64;
+            xi_prime -= beta * (k * xi).sin() * (k * eta).cosh();
+            eta_prime -= beta * (k * xi).cos() * (k * eta).sinh();
+        }
+
+        let (sin_xi, cos_xi) = xi_prime.sin_cos();
+        let sinh_eta = eta_prime.sinh();
+        let tau_prime = sin_xi / (sinh_eta * sinh_eta + cos_xi * cos_xi).sqrt();
+        let tau = geodetic_tangent(tau_prime, e);
+
+        let central_meridian = coordinate.zone as f64 * 6.0 - 183.0;
+        let lon = central_meridian + sinh_eta.atan2(cos_xi).to_degrees();
+        (
+            tau.atan().to_degrees(),
+            (lon + 180.0).rem_euclid(360.0) - 180.0,
+        )
+    }
+
+    /// Reads `"33N 500000 4649776"` (or `"33 N ..."`) for UTM and
+    /// `"N 2000000 2000000"` for UPS. The letter is the hemisphere, not an
+    /// MGRS latitude band.
+    pub fn parse(text: &str) -> Result<UtmCoordinate, ParseCoordinateError> {
+        let compact: String = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let split = compact
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(|| ParseCoordinateError::new(text, "missing the hemisphere"))?;
+        let (zone, rest) = compact.split_at(split);
+        let zone = match zone {
+            "" => 0,
+            zone => zone
+                .parse::<u8>()
+                .ok()
+                .filter(|zone| (1..=60).contains(zone))
+                .ok_or_else(|| ParseCoordinateError::new(text, "zones run from 1 to 60"))?,
+        };
+
+        let mut parts = rest.split_whitespace();
+        let hemisphere = match parts
+            .next()
+            .map(|part| part.to_ascii_uppercase())
+            .as_deref()
+        {
+            Some("N") => Hemisphere::North,
+            Some("S") => Hemisphere::South,
+            _ => return Err(ParseCoordinateError::new(text, "expected N or S")),
+        };
+        let mut meters = || {
+            parts
+                .next()
+                .and_then(|part| part.parse::<f64>().ok())
+                .filter(|meters| meters.is_finite() && *meters >= 0.0)
+                .ok_or_else(|| {
+                    ParseCoordinateError::new(text, "expected an easting and a northing")
+                })
+        };
+        let (easting, northing) = (meters()?, meters()?);
+        if parts.next().is_some() {
+            return Err(ParseCoordinateError::new(
+                text,
+                "unexpected text after the northing",
+            ));
+        }
+
+        Ok(UtmCoordinate {
+            zone,
+            hemisphere,
+            easting,
+            northing,
+        })
+    }
+
+    /// `"33N 500000 4649776"`, or `"N 2000000 2000000"` for UPS, with
+    /// `decimals` digits of meters.
+    pub fn format(coordinate: &UtmCoordinate, decimals: usize) -> String {
+        let hemisphere = match coordinate.hemisphere {
+            Hemisphere::North => 'N',
+            Hemisphere::South => 'S',
+        };
+        let zone = if coordinate.is_ups() {
+            String::new()
+        } else {
+            coordinate.zone.to_string()
+        };
+        format!(
+            "{zone}{hemisphere} {:.decimals$} {:.decimals$}",
+            coordinate.easting, coordinate.northing
+        )
+    }
+
+    // Polar stereographic on the pole of the point's hemisphere.
+    fn to_ups(lat: f64, lon: f64) -> UtmCoordinate {
+        let e = eccentricity();
+        let hemisphere = if lat < 0.0 {
+            Hemisphere::South
+        } else {
+            Hemisphere::North
+        };
+        // worked in the northern aspect, mirrored for the south
+        let phi = lat.abs().to_radians();
+        let e_sin = e * phi.sin();
+        let t = (FRAC_PI_4 - phi / 2.0).tan() / ((1.0 - e_sin) / (1.0 + e_sin)).powf(e / 2.0);
+        let rho = 2.0 * WGS84_SEMI_MAJOR_AXIS * UPS_SCALE * t / polar_factor(e);
+
+        let (sin_lambda, cos_lambda) = lon.to_radians().sin_cos();
+        let northing = match hemisphere {
+            Hemisphere::North => UPS_FALSE_ORIGIN - rho * cos_lambda,
+            Hemisphere::South => UPS_FALSE_ORIGIN + rho * cos_lambda,
+        };
+        UtmCoordinate {
+            zone: 0,
+            hemisphere,
+            easting: UPS_FALSE_ORIGIN + rho * sin_lambda,
+            northing,
+        }
+    }
+
+    fn from_ups(coordinate: &UtmCoordinate) -> (f64, f64) {
+        let e = eccentricity();
+        let dx = coordinate.easting - UPS_FALSE_ORIGIN;
+        let dy = coordinate.northing - UPS_FALSE_ORIGIN;
+        let rho = dx.hypot(dy);
+        let t = rho * polar_factor(e) / (2.0 * WGS84_SEMI_MAJOR_AXIS * UPS_SCALE);
+
+        let mut phi = std::f64::consts::FRAC_PI_2 - 2.0 * t.atan();
+        for _ in 0..MAX_ITERATIONS {
+            let e_sin = e * phi.sin();
+            let next = std::f64::consts::FRAC_PI_2
+                - 2.0 * (t * ((1.0 - e_sin) / (1.0 + e_sin)).powf(e / 2.0)).atan();
+            let converged = (next - phi).abs() < CONVERGENCE;
+            phi = next;
+            if converged {
+                break;
+            }
+        }
+
+        match coordinate.hemisphere {
+            Hemisphere::North => (phi.to_degrees(), dx.atan2(-dy).to_degrees()),
+            Hemisphere::South => (-phi.to_degrees(), dx.atan2(dy).to_degrees()),
+        }
+    }
+}
+
+fn eccentricity() -> f64 {
+    (WGS84_FLATTENING * (2.0 - WGS84_FLATTENING)).sqrt()
+}
+
+// sqrt((1 + e)^(1 + e) (1 - e)^(1 - e)), which ties t to ρ at the pole
+fn polar_factor(e: f64) -> f64 {
+    ((1.0 + e).powf(1.0 + e) * (1.0 - e).powf(1.0 - e)).sqrt()
+}
+
+// rectifying radius A and Krüger's α and β coefficients
+fn kruger_series() -> (f64, [f64; 6], [f64; 6]) {
+    let n = WGS84_FLATTENING / (2.0 - WGS84_FLATTENING);
+    let (n2, n3, n4, n5, n6) = (n * n, n.powi(3), n.powi(4), n.powi(5), n.powi(6));
+
+    let big_a = WGS84_SEMI_MAJOR_AXIS / (1.0 + n) * (1.0 + n2 / 4.0 + n4 / 64.0 + n6 / 256.0);
+    let alpha = [
+        n / 2.0 - 2.0 * n2 / 3.0 + 5.0 * n3 / 16.0 + 41.0 * n4 / 180.0 - 127.0 * n5 / 288.0
+            + 7891.0 * n6 / 37800.0,
+        13.0 * n2 / 48.0 - 3.0 * n3 / 5.0 + 557.0 * n4 / 1440.0 + 281.0 * n5 / 630.0
+            - 1983433.0 * n6 / 1935360.0,
+        61.0 * n3 / 240.0 - 103.0 * n4 / 140.0 + 15061.0 * n5 / 26880.0 + 167603.0 * n6 / 181440.0,
+        49561.0 * n4 / 161280.0 - 179.0 * n5 / 168.0 + 6601661.0 * n6 / 7257600.0,
+        34729.0 * n5 / 80640.0 - 3418889.0 * n6 / 1995840.0,
+        212378941.0 * n6 / 319334400.0,
+    ];
+    let beta = [
+        n / 2.0 - 2.0 * n2 / 3.0 + 37.0 * n3 / 96.0 - n4 / 360.0 - 81.0 * n5 / 512.0
+            + 96199.0 * n6 / 604800.0,
+        n2 / 48.0 + n3 / 15.0 - 437.0 * n4 / 1440.0 + 46.0 * n5 / 105.0
+            - 1118711.0 * n6 / 3870720.0,
+        17.0 * n3 / 480.0 - 37.0 * n4 / 840.0 - 209.0 * n5 / 4480.0 + 5569.0 * n6 / 90720.0,
+        4397.0 * n4 / 161280.0 - 11.0 * n5 / 504.0 - 830251.0 * n6 / 7257600.0,
+        4583.0 * n5 / 161280.0 - 108847.0 * n6 / 3991680.0,
+        20648693.0 * n6 / 638668800.0,
+    ];
+    (big_a, alpha, beta)
+}
+
+// tan of the conformal latitude from tan of the geodetic latitude
+fn conformal_tangent(tau: f64, e: f64) -> f64 {
+    let sigma = (e * (e * tau / (1.0 + tau * tau).sqrt()).atanh()).sinh();
+    tau * (1.0 + sigma * sigma).sqrt() - sigma * (1.0 + tau * tau).sqrt()
+}
+
+// inverse of `conformal_tangent` by Newton's method
+fn geodetic_tangent(tau_prime: f64, e: f64) -> f64 {
+    let e2 = e * e;
+    let mut tau = tau_prime;
+    for _ in 0..MAX_ITERATIONS {
+        let tau_i = conformal_tangent(tau, e);
+        let delta = (tau_prime - tau_i) / (1.0 + tau_i * tau_i).sqrt()
+            * (1.0 + (1.0 - e2) * tau * tau)
+            / ((1.0 - e2) * (1.0 + tau * tau).sqrt());
+        tau += delta;
+        if delta.abs() < CONVERGENCE {
+            break;
+        }
+    }
+    tau
+}
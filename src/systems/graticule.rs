@@ -0,0 +1,256 @@
+use cgmath::{InnerSpace, Vector3};
+
+use crate::components::{
+    camera::CameraComponent,
+    graticule::{GraticuleAxis, GraticuleLabel, GraticuleLine, GraticuleWindow},
+};
+
+use super::{mesh::MeshSystem, window::WindowSystem};
+
+// spacings to choose from, in degrees, from 30° down to one arc-minute
+const SPACINGS: [f64; 12] = [
+    30.0,
+    15.0,
+    10.0,
+    5.0,
+    2.0,
+    1.0,
+    30.0 / 60.0,
+    15.0 / 60.0,
+    10.0 / 60.0,
+    5.0 / 60.0,
+    2.0 / 60.0,
+    1.0 / 60.0,
+];
+// most lines wanted across the patch of globe straight below the camera
+const LINES_ACROSS_VIEW: f64 = 8.0;
+// how many times that patch's radius lines are laid out to, so tilted views
+// toward the horizon still have lines
+const WINDOW_MARGIN: f64 = 3.0;
+// height lines are lifted off the globe, as a fraction of their spacing, so
+// they clear it without floating above a camera close to the ground
+const LIFT: f64 = 0.01;
+// longest step between two uploaded vertices, in degrees
+const MAX_STEP: f64 = 1.0;
+
+// the tropics sit at the Earth's obliquity, the polar circles at its complement
+const TROPIC_LATITUDE: f64 = 23.436;
+const POLAR_CIRCLE_LATITUDE: f64 = 90.0 - TROPIC_LATITUDE;
+
+pub struct GraticuleSystem {}
+
+impl GraticuleSystem {
+    /// Line spacing in degrees for a camera at `eye`, and the window of lines
+    /// to lay out around the point below it.
+    pub fn layout(eye: Vector3<f32>, fovy: f32, globe_radius: f32) -> (f64, GraticuleWindow) {
+        let radius = globe_radius as f64;
+        let distance = (eye.magnitude() as f64).max(radius * (1.0 + 1e-6));
+        let altitude = distance - radius;
+        // angular radius of the cap up to the horizon, and of the patch the
+        // view spans looking straight down
+        let horizon = (radius / distance).acos().to_degrees();
+        let view = (altitude * (fovy as f64 / 2.0).to_radians().tan() / radius)
+            .to_degrees()
+            .min(horizon);
+
+        let spacing = SPACINGS
+            .iter()
+            .rev()
+            .copied()
+            .find(|spacing| 2.0 * view / spacing <= LINES_ACROSS_VIEW)
+            .unwrap_or(SPACINGS[0]);
+
+        let reach = horizon.min(view * WINDOW_MARGIN).max(spacing);
+        let (lat, lon) = MeshSystem::cartesian_to_lat_lon(eye);
+        let (lat, lon) = (lat as f64, lon as f64);
+        let (south, north) = ((lat - reach).max(-90.0), (lat + reach).min(90.0));
+        // widest longitude span of a cap, unless it holds a pole
+        let ratio = reach.to_radians().sin() / lat.to_radians().cos();
+        let (west, east) = if south <= -90.0 || north >= 90.0 || ratio >= 1.0 {
+            (-180.0, 180.0)
+        } else {
+            let half_width = ratio.asin().to_degrees();
+            (lon - half_width, lon + half_width)
+        };
+
+        let snap_down = |degrees: f64| (degrees / spacing).floor() * spacing;
+        let snap_up = |degrees: f64| (degrees / spacing).ceil() * spacing;
+        let window = GraticuleWindow {
+            south: snap_down(south).max(-90.0),
+            north: snap_up(north).min(90.0),
+            west: snap_down(west),
+            east: snap_up(east),
+        };
+        (spacing, window)
+    }
+
+    /// The ordinary lines of a window at `spacing`, and the equator, tropics,
+    /// polar circles and prime meridian wherever they cross it.
+    pub fn lines(
+        window: GraticuleWindow,
+        spacing: f64,
+        globe_radius: f32,
+    ) -> (Vec<GraticuleLine>, Vec<GraticuleLine>) {
+        let radius = globe_radius as f64 * (1.0 + spacing.to_radians() * LIFT);
+        let step = (spacing / 2.0).min(MAX_STEP);
+        let sample = |from: f64, to: f64| {
+            let count = ((to - from) / step).ceil().max(1.0) as usize;
+            (0..=count).map(move |i| from + (to - from) * i as f64 / count as f64)
+        };
+        let point = |lat: f64, lon: f64| {
+            let (x, y, z) = MeshSystem::lat_lon_to_cartesian(lat as f32, lon as f32, radius as f32);
+            [x, y, z]
+        };
+        let parallel = |lat: f64| GraticuleLine {
+            axis: GraticuleAxis::Latitude,
+            value: lat,
+            positions: sample(window.west, window.east)
+                .map(|lon| point(lat, lon))
+                .collect(),
+        };
+        let meridian = |lon: f64| GraticuleLine {
+            axis: GraticuleAxis::Longitude,
+            value: lon,
+            positions: sample(window.south, window.north)
+                .map(|lat| point(lat, lon))
+                .collect(),
+        };
+
+        // the poles are points, and a full turn would draw its seam twice
+        let full_turn = window.east - window.west >= 360.0;
+        let multiples = |from: f64, to: f64, spacing: f64| {
+            ((from / spacing).ceil() as i64..=(to / spacing).floor() as i64)
+                .map(move |k| k as f64 * spacing)
+        };
+        let mut lines: Vec<GraticuleLine> = multiples(window.south, window.north, spacing)
+            .filter(|lat| lat.abs() < 90.0)
+            .map(parallel)
+            .collect();
+        lines.extend(
+            multiples(window.west, window.east, spacing)
+                .filter(|&lon| !(full_turn && lon >= window.east))
+                .map(meridian),
+        );
+
+        let mut highlights: Vec<GraticuleLine> = [
+            -POLAR_CIRCLE_LATITUDE,
+            -TROPIC_LATITUDE,
+            0.0,
+            TROPIC_LATITUDE,
+            POLAR_CIRCLE_LATITUDE,
+        ]
+        .into_iter()
+        .filter(|lat| (window.south..=window.north).contains(lat))
+        .map(parallel)
+        .collect();
+        highlights.extend(
+            multiples(window.west, window.east, 360.0)
+                .filter(|&lon| !(full_turn && lon >= window.east))
+                .map(meridian),
+        );
+
+        (lines, highlights)
+    }
+
+    /// Where each line meets the left (parallels) or bottom (meridians) of the
+    /// view, or its outermost visible point if it never reaches that edge.
+    pub fn labels(
+        lines: &[GraticuleLine],
+        camera_component: &CameraComponent,
+        screen_width: f32,
+        screen_height: f32,
+    ) -> Vec<GraticuleLabel> {
+        let eye = Vector3::new(
+            camera_component.camera.eye.x,
+            camera_component.camera.eye.y,
+            camera_component.camera.eye.z,
+        );
+        let inside = |(x, y): (f32, f32)| {
+            (0.0..=screen_width).contains(&x) && (0.0..=screen_height).contains(&y)
+        };
+
+        let mut labels = Vec::new();
+        for line in lines {
+            let latitude = line.axis == GraticuleAxis::Latitude;
+            // signed distance past the edge the label belongs on
+            let past_edge = |(x, y): (f32, f32)| {
+                if latitude {
+                    -x
+                } else {
+                    y - screen_height
+                }
+            };
+
+            let mut best: Option<(f32, f32)> = None;
+            let mut offer = |candidate: (f32, f32)| {
+                if best.is_none_or(|best| past_edge(candidate) > past_edge(best)) {
+                    best = Some(candidate);
+                }
+            };
+            let mut previous: Option<(f32, f32)> = None;
+            for &position in &line.positions {
+                let point = Vector3::from(position);
+                // on the near side of the horizon
+                let screen = if point.dot(eye) > point.magnitude2() {
+                    WindowSystem::world_to_screen(
+                        screen_width,
+                        screen_height,
+                        point,
+                        camera_component,
+                    )
+                } else {
+                    None
+                };
+
+                if let (Some(a), Some(b)) = (previous, screen) {
+                    let (distance_a, distance_b) = (past_edge(a), past_edge(b));
+                    if (distance_a > 0.0) != (distance_b > 0.0) {
+                        let t = distance_a / (distance_a - distance_b);
+                        let crossing = (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t);
+                        if inside(crossing) {
+                            offer(crossing);
+                        }
+                    }
+                }
+                if let Some(screen) = screen.filter(|&screen| inside(screen)) {
+                    offer(screen);
+                }
+                previous = screen;
+            }
+
+            if let Some((x, y)) = best {
+                labels.push(GraticuleLabel {
+                    text: GraticuleSystem::label_text(line.axis, line.value),
+                    axis: line.axis,
+                    x,
+                    y,
+                });
+            }
+        }
+        labels
+    }
+
+    // `45°N`, `122°30'W`; the equator, prime meridian and antimeridian have
+    // no hemisphere
+    fn label_text(axis: GraticuleAxis, value: f64) -> String {
+        let value = match axis {
+            GraticuleAxis::Latitude => value,
+            GraticuleAxis::Longitude => (value + 180.0).rem_euclid(360.0) - 180.0,
+        };
+        let total_minutes = (value.abs() * 60.0).round() as i64;
+        let (degrees, minutes) = (total_minutes / 60, total_minutes % 60);
+        let hemisphere = match axis {
+            _ if total_minutes == 0 => "",
+            GraticuleAxis::Longitude if degrees == 180 && minutes == 0 => "",
+            GraticuleAxis::Latitude if value > 0.0 => "N",
+            GraticuleAxis::Latitude => "S",
+            GraticuleAxis::Longitude if value > 0.0 => "E",
+            GraticuleAxis::Longitude => "W",
+        };
+        if minutes == 0 {
+            format!("{degrees}°{hemisphere}")
+        } else {
+            format!("{degrees}°{minutes:02}'{hemisphere}")
+        }
+    }
+}
@@ -0,0 +1,526 @@
+use std::{collections::HashMap, sync::Arc};
+
+use image::{imageops, Rgba, RgbaImage};
+use wgpu::util::DeviceExt;
+
+use crate::components::imagery::{
+    AtlasSlot, GeoRectangle, ImageryComponent, ImageryLayer, ImageryLayerId, ImageryLayerSource,
+    ImageryLayerStyle, ImageryLayerUniform, ImageryUniform, LayerTiles, TileAtlas, TileGrid,
+    ATLAS_TILE_SIZE, DEFAULT_ATLAS_BUDGET, EMPTY_TILE, MAX_IMAGERY_LAYERS, MAX_LAYER_TILES,
+};
+
+use super::material::{MaterialSystem, TextureSource};
+
+// fills the slots no layer is drawn in
+const EMPTY_SLOT: ImageryLayerUniform = ImageryLayerUniform {
+    rectangle: [
+        -std::f32::consts::PI,
+        -std::f32::consts::FRAC_PI_2,
+        std::f32::consts::PI,
+        std::f32::consts::FRAC_PI_2,
+    ],
+    adjustments: [1.0, 1.0, 0.0, 1.0],
+    alpha: [0.0; 4],
+    tile_grid: [0.0; 4],
+    tile_layout: [0.0; 4],
+};
+const EMPTY_TILE_SLOTS: [[u32; 4]; MAX_IMAGERY_LAYERS * MAX_LAYER_TILES / 4] =
+    [[EMPTY_TILE; 4]; MAX_IMAGERY_LAYERS * MAX_LAYER_TILES / 4];
+
+pub struct ImagerySystem {}
+
+impl ImagerySystem {
+    /// An empty layer stack, drawing nothing over the globe's own texture.
+    pub fn create_imagery(device: &wgpu::Device, queue: &wgpu::Queue) -> ImageryComponent {
+        let empty = MaterialSystem::create_2d_view(
+            device,
+            queue,
+            RgbaImage::from_pixel(1, 1, Rgba([0, 0, 0, 0])).into(),
+            true,
+        );
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Imagery Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        // WebGL takes textures with a single layer for plain 2D ones
+        let (_, empty_atlas) = ImagerySystem::create_atlas_texture(device, 1, 2);
+        let uniform = ImageryUniform {
+            layers: [EMPTY_SLOT; MAX_IMAGERY_LAYERS],
+            tile_slots: EMPTY_TILE_SLOTS,
+        };
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Imagery Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[uniform]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group_layout = ImagerySystem::create_bind_group_layout(device);
+        let bind_group = ImagerySystem::create_bind_group(
+            device,
+            &bind_group_layout,
+            [&empty; MAX_IMAGERY_LAYERS],
+            &empty_atlas,
+            &sampler,
+            &uniform_buffer,
+        );
+
+        ImageryComponent {
+            layers: Vec::new(),
+            next_id: 0,
+            empty,
+            atlas: None,
+            empty_atlas,
+            atlas_budget: DEFAULT_ATLAS_BUDGET,
+            sampler,
+            uniform,
+            uniform_buffer,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    // Every globe pipeline takes this layout as its fourth bind group.
+    pub fn create_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        };
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Imagery bind group layout"),
+            entries: &[
+                texture_entry(0),
+                texture_entry(1),
+                texture_entry(2),
+                texture_entry(3),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 6,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+            ],
+        })
+    }
+
+    // WGSL has no includes, so globe shaders are prefixed with the
+    // compositing code they call.
+    pub fn create_shader_module(
+        device: &wgpu::Device,
+        label: &str,
+        source: &str,
+    ) -> wgpu::ShaderModule {
+        device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(label),
+            source: wgpu::ShaderSource::Wgsl(
+                format!("{}\n{}", include_str!("../shaders/imagery.wgsl"), source).into(),
+            ),
+        })
+    }
+
+    /// Puts a layer on top of the stack. It is drawn once its image is set.
+    pub fn add_layer(
+        imagery: &mut ImageryComponent,
+        source: ImageryLayerSource,
+        style: ImageryLayerStyle,
+    ) -> ImageryLayerId {
+        let id = imagery.next_id;
+        imagery.next_id += 1;
+        imagery.layers.push(ImageryLayer {
+            id,
+            source,
+            style,
+            view: None,
+            tiles: None,
+        });
+        id
+    }
+
+    // The image is stretched over the layer's rectangle as an
+    // equirectangular image.
+    pub fn set_layer_image(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        imagery: &mut ImageryComponent,
+        id: ImageryLayerId,
+        image: TextureSource,
+    ) -> bool {
+        let Some(layer) = imagery.layers.iter_mut().find(|layer| layer.id == id) else {
+            return false;
+        };
+        layer.view = Some(MaterialSystem::create_2d_view(device, queue, image, true));
+        let tiles = layer.tiles.take();
+        ImagerySystem::release_tiles(imagery, tiles);
+        ImagerySystem::rebind(device, queue, imagery);
+        true
+    }
+
+    /// Draws a layer from `tiles`, row by row over `grid`, keyed by URL and
+    /// None where there is no tile. Tiles already in the atlas aren't
+    /// uploaded again; when every slot is in use the rest are left out.
+    pub fn set_layer_tiles(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        imagery: &mut ImageryComponent,
+        id: ImageryLayerId,
+        grid: TileGrid,
+        tiles: Vec<(String, Option<Arc<RgbaImage>>)>,
+    ) -> bool {
+        let Some(index) = imagery.layers.iter().position(|layer| layer.id == id) else {
+            return false;
+        };
+        let budget = imagery.atlas_budget;
+        let atlas = imagery
+            .atlas
+            .get_or_insert_with(|| ImagerySystem::create_atlas(device, budget));
+
+        let mut slots = Vec::with_capacity(tiles.len());
+        let mut dropped = 0;
+        for (key, image) in tiles.into_iter().take(MAX_LAYER_TILES) {
+            let Some(image) = image else {
+                slots.push(EMPTY_TILE);
+                continue;
+            };
+            let slot = match atlas.keys.get(&key) {
+                Some(&slot) => slot,
+                None => match ImagerySystem::allocate_slot(atlas, key) {
+                    Some(slot) => {
+                        let image = if image.dimensions() == (ATLAS_TILE_SIZE, ATLAS_TILE_SIZE) {
+                            (*image).clone()
+                        } else {
+                            imageops::resize(
+                                &*image,
+                                ATLAS_TILE_SIZE,
+                                ATLAS_TILE_SIZE,
+                                imageops::FilterType::Triangle,
+                            )
+                        };
+                        MaterialSystem::write_mip_chain(queue, &atlas.texture, slot, image);
+                        slot
+                    }
+                    None => {
+                        dropped += 1;
+                        slots.push(EMPTY_TILE);
+                        continue;
+                    }
+                },
+            };
+            atlas.tick += 1;
+            let atlas_slot = &mut atlas.slots[slot as usize];
+            atlas_slot.last_used = atlas.tick;
+            atlas_slot.users += 1;
+            slots.push(slot);
+        }
+        if dropped > 0 {
+            tracing::warn!("the tile atlas is full, {dropped} tiles were left out");
+        }
+
+        let layer = &mut imagery.layers[index];
+        layer.view = None;
+        let tiles = layer.tiles.replace(LayerTiles { grid, slots });
+        ImagerySystem::release_tiles(imagery, tiles);
+        ImagerySystem::rebind(device, queue, imagery);
+        true
+    }
+
+    /// Sets how much GPU memory the tile atlas may take. The atlas is emptied,
+    /// along with every tiled layer, to be made again at the new size.
+    pub fn set_atlas_budget(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        imagery: &mut ImageryComponent,
+        budget: u64,
+    ) {
+        imagery.atlas_budget = budget;
+        imagery.atlas = None;
+        for layer in imagery.layers.iter_mut() {
+            layer.tiles = None;
+        }
+        ImagerySystem::rebind(device, queue, imagery);
+    }
+
+    pub fn set_layer_style(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        imagery: &mut ImageryComponent,
+        id: ImageryLayerId,
+        style: ImageryLayerStyle,
+    ) -> bool {
+        let Some(layer) = imagery.layers.iter_mut().find(|layer| layer.id == id) else {
+            return false;
+        };
+        layer.style = style;
+        ImagerySystem::rebind(device, queue, imagery);
+        true
+    }
+
+    /// Moves a layer to `index` in the stack, 0 being the bottom. Indices
+    /// past the top move it to the top.
+    pub fn move_layer(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        imagery: &mut ImageryComponent,
+        id: ImageryLayerId,
+        index: usize,
+    ) -> bool {
+        let Some(from) = imagery.layers.iter().position(|layer| layer.id == id) else {
+            return false;
+        };
+        let layer = imagery.layers.remove(from);
+        let index = index.min(imagery.layers.len());
+        imagery.layers.insert(index, layer);
+        ImagerySystem::rebind(device, queue, imagery);
+        true
+    }
+
+    pub fn remove_layer(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        imagery: &mut ImageryComponent,
+        id: ImageryLayerId,
+    ) -> bool {
+        let Some(index) = imagery.layers.iter().position(|layer| layer.id == id) else {
+            return false;
+        };
+        let layer = imagery.layers.remove(index);
+        ImagerySystem::release_tiles(imagery, layer.tiles);
+        ImagerySystem::rebind(device, queue, imagery);
+        true
+    }
+
+    // Binds the topmost shown, loaded layers, up to the shader's limit.
+    fn rebind(device: &wgpu::Device, queue: &wgpu::Queue, imagery: &mut ImageryComponent) {
+        let mut drawn: Vec<&ImageryLayer> = imagery
+            .layers
+            .iter()
+            .rev()
+            .filter(|layer| layer.style.show && (layer.view.is_some() || layer.tiles.is_some()))
+            .take(MAX_IMAGERY_LAYERS)
+            .collect();
+        drawn.reverse();
+
+        let mut views = [&imagery.empty; MAX_IMAGERY_LAYERS];
+        let mut uniform = ImageryUniform {
+            layers: [EMPTY_SLOT; MAX_IMAGERY_LAYERS],
+            tile_slots: EMPTY_TILE_SLOTS,
+        };
+        for (slot, layer) in drawn.iter().enumerate() {
+            uniform.layers[slot] = ImagerySystem::layer_uniform(&layer.style);
+            match (&layer.view, &layer.tiles) {
+                (Some(view), _) => views[slot] = view,
+                (None, Some(tiles)) => {
+                    let first = slot * MAX_LAYER_TILES;
+                    let grid = tiles.grid;
+                    uniform.layers[slot].tile_grid =
+                        [grid.west, grid.top, grid.tile_width, grid.tile_height];
+                    uniform.layers[slot].tile_layout = [
+                        grid.columns as f32,
+                        grid.rows as f32,
+                        if grid.mercator { 1.0 } else { 0.0 },
+                        first as f32,
+                    ];
+                    for (i, &tile_slot) in tiles.slots.iter().enumerate() {
+                        uniform.tile_slots[(first + i) / 4][(first + i) % 4] = tile_slot;
+                    }
+                }
+                (None, None) => {}
+            }
+        }
+
+        let atlas = imagery
+            .atlas
+            .as_ref()
+            .map_or(&imagery.empty_atlas, |atlas| &atlas.view);
+        let bind_group = ImagerySystem::create_bind_group(
+            device,
+            &imagery.bind_group_layout,
+            views,
+            atlas,
+            &imagery.sampler,
+            &imagery.uniform_buffer,
+        );
+        queue.write_buffer(&imagery.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+        imagery.uniform = uniform;
+        imagery.bind_group = bind_group;
+    }
+
+    fn layer_uniform(style: &ImageryLayerStyle) -> ImageryLayerUniform {
+        let GeoRectangle {
+            west,
+            south,
+            east,
+            north,
+        } = style.rectangle;
+        // crossing the antimeridian, east is carried on past 180
+        let east = if east <= west { east + 360.0 } else { east };
+        ImageryLayerUniform {
+            rectangle: [
+                west.to_radians(),
+                south.to_radians(),
+                east.to_radians(),
+                north.to_radians(),
+            ],
+            adjustments: [
+                style.brightness,
+                style.contrast,
+                style.hue.to_radians(),
+                style.gamma.max(f32::EPSILON),
+            ],
+            alpha: [style.alpha.clamp(0.0, 1.0), 0.0, 0.0, 0.0],
+            ..EMPTY_SLOT
+        }
+    }
+
+    // As many slots as fit in `budget`, though always enough for one layer.
+    fn create_atlas(device: &wgpu::Device, budget: u64) -> TileAtlas {
+        let tile_size = ATLAS_TILE_SIZE as u64;
+        // mips add a third
+        let slot_size = tile_size * tile_size * 4 * 4 / 3;
+        let count = (budget / slot_size)
+            .min(device.limits().max_texture_array_layers as u64)
+            .max(MAX_LAYER_TILES as u64) as u32;
+        let (texture, view) = ImagerySystem::create_atlas_texture(device, ATLAS_TILE_SIZE, count);
+        TileAtlas {
+            texture,
+            view,
+            slots: (0..count)
+                .map(|_| AtlasSlot {
+                    key: None,
+                    last_used: 0,
+                    users: 0,
+                })
+                .collect(),
+            keys: HashMap::new(),
+            tick: 0,
+        }
+    }
+
+    fn create_atlas_texture(
+        device: &wgpu::Device,
+        size: u32,
+        layers: u32,
+    ) -> (wgpu::Texture, wgpu::TextureView) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: layers,
+            },
+            mip_level_count: MaterialSystem::mip_level_count((size, size)),
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            label: Some("Tile Atlas"),
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Tile Atlas View"),
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        (texture, view)
+    }
+
+    // A free slot, or else the least recently used one no layer is drawing
+    // from, now holding `key`.
+    fn allocate_slot(atlas: &mut TileAtlas, key: String) -> Option<u32> {
+        let (slot, _) = atlas
+            .slots
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| slot.users == 0)
+            .min_by_key(|(_, slot)| (slot.key.is_some(), slot.last_used))?;
+        if let Some(evicted) = atlas.slots[slot].key.replace(key.clone()) {
+            atlas.keys.remove(&evicted);
+        }
+        atlas.keys.insert(key, slot as u32);
+        Some(slot as u32)
+    }
+
+    // Lets the atlas reuse the slots a layer no longer draws from.
+    fn release_tiles(imagery: &mut ImageryComponent, tiles: Option<LayerTiles>) {
+        let (Some(atlas), Some(tiles)) = (imagery.atlas.as_mut(), tiles) else {
+            return;
+        };
+        for slot in tiles.slots {
+            if let Some(slot) = atlas.slots.get_mut(slot as usize) {
+                slot.users = slot.users.saturating_sub(1);
+            }
+        }
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        views: [&wgpu::TextureView; MAX_IMAGERY_LAYERS],
+        atlas: &wgpu::TextureView,
+        sampler: &wgpu::Sampler,
+        uniform_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(views[0]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(views[1]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(views[2]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(views[3]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: wgpu::BindingResource::TextureView(atlas),
+                },
+            ],
+            label: Some("Imagery bind group"),
+        })
+    }
+}
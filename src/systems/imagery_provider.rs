@@ -0,0 +1,712 @@
+use std::sync::Arc;
+
+use cgmath::InnerSpace;
+use image::{imageops, RgbaImage};
+
+use crate::{
+    components::{
+        camera::CameraComponent,
+        imagery::{GeoRectangle, TileGrid, MAX_LAYER_TILES},
+        imagery_provider::{
+            ArchiveProvider, ImageryProvider, ImageryProviderComponent, ImageryView, WmsProvider,
+            WmtsProvider, WmtsTileUrl,
+        },
+    },
+    loader::{
+        with_query, ImageryCrs, TileArchive, TileMatrix, TileMatrixSet, WmsCapabilities,
+        WmtsCapabilities,
+    },
+};
+
+use super::{mesh::MeshSystem, window::WindowSystem};
+
+// the largest texture WebGL 2 guarantees
+const MAX_IMAGE_SIZE: u32 = 2048;
+// Web Mercator's square world ends here
+const MAX_MERCATOR_LAT: f64 = 85.051_128_78;
+const MERCATOR_RADIUS: f64 = 6_378_137.0;
+// WMTS scale denominators assume 0.28 mm pixels
+const STANDARD_PIXEL_SIZE: f64 = 0.00028;
+// of the single tile at zoom 0 in Web Mercator tile sets
+const MERCATOR_ZOOM_0_SCALE: f64 = 559_082_264.028_717_8;
+const ARCHIVE_TILE_SIZE: u32 = 256;
+// screen points sampled along each side when finding the region in view
+const VIEW_SAMPLES: usize = 8;
+// requests reach this far past the view, so small pans don't refetch
+const VIEW_PADDING: f32 = 0.25;
+
+/// An image assembled from one or more fetched pieces.
+pub struct ImageryRequest {
+    pub crs: ImageryCrs,
+    // longitudes of the left and right edges, east unwrapped past west
+    pub west: f64,
+    pub east: f64,
+    // latitudes, or Mercator northings
+    pub top: f64,
+    pub bottom: f64,
+    pub width: u32,
+    pub height: u32,
+    pub pieces: Vec<ImageryPiece>,
+    /// Where pieces with a `tile` are read from, rather than fetched by URL.
+    pub archive: Option<Arc<TileArchive>>,
+    /// For tiles, which are drawn from the atlas rather than assembled.
+    pub grid: Option<TileGrid>,
+}
+
+/// A GetMap image or a tile, placed at `x`, `y` in the assembled image.
+pub struct ImageryPiece {
+    /// For archive tiles, the archive and the tile's path within it.
+    pub url: String,
+    /// The zoom, column and row of an archive tile.
+    pub tile: Option<(u8, u32, u32)>,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+pub struct ImageryProviderSystem {}
+
+impl ImageryProviderSystem {
+    pub fn wms_provider(
+        capabilities: &WmsCapabilities,
+        layer: &str,
+    ) -> Result<ImageryProvider, String> {
+        let layer = capabilities
+            .layer(layer)
+            .ok_or_else(|| format!("the server has no WMS layer {layer}"))?;
+        // geographic images need no reprojecting, so they win when offered
+        let (crs, crs_code) = [ImageryCrs::Geographic, ImageryCrs::WebMercator]
+            .into_iter()
+            .find_map(|crs| {
+                layer
+                    .crs
+                    .iter()
+                    .find(|code| ImageryCrs::from_code(code) == Some(crs))
+                    .map(|code| (crs, code.clone()))
+            })
+            .ok_or_else(|| {
+                format!(
+                    "WMS layer {} offers neither EPSG:4326 nor EPSG:3857",
+                    layer.name
+                )
+            })?;
+
+        Ok(ImageryProvider::Wms(WmsProvider {
+            get_map_url: capabilities.get_map_url.clone(),
+            version: capabilities.version.clone(),
+            layer: layer.name.clone(),
+            format: ImageryProviderSystem::preferred_format(&capabilities.formats),
+            crs,
+            crs_code,
+        }))
+    }
+
+    pub fn wmts_provider(
+        capabilities: &WmtsCapabilities,
+        layer: &str,
+    ) -> Result<ImageryProvider, String> {
+        let layer = capabilities
+            .layer(layer)
+            .ok_or_else(|| format!("the server has no WMTS layer {layer}"))?;
+        let (tile_matrix_set, crs) = [ImageryCrs::Geographic, ImageryCrs::WebMercator]
+            .into_iter()
+            .find_map(|crs| {
+                layer
+                    .tile_matrix_sets
+                    .iter()
+                    .filter_map(|identifier| capabilities.tile_matrix_set(identifier))
+                    .find(|set| {
+                        ImageryCrs::from_code(&set.crs) == Some(crs) && !set.matrices.is_empty()
+                    })
+                    .map(|set| (set.clone(), crs))
+            })
+            .ok_or_else(|| {
+                format!(
+                    "WMTS layer {} has no EPSG:4326 or EPSG:3857 tile matrix set",
+                    layer.identifier
+                )
+            })?;
+        let tile_url = match (&layer.resource_url, &capabilities.get_tile_url) {
+            (Some(template), _) => WmtsTileUrl::Template(template.clone()),
+            (None, Some(url)) => WmtsTileUrl::Kvp(url.clone()),
+            (None, None) => {
+                return Err(format!(
+                    "WMTS layer {} has neither a tile template nor a GetTile endpoint",
+                    layer.identifier
+                ))
+            }
+        };
+
+        Ok(ImageryProvider::Wmts(WmtsProvider {
+            layer: layer.identifier.clone(),
+            style: layer.style.clone(),
+            format: ImageryProviderSystem::preferred_format(&layer.formats),
+            tile_matrix_set,
+            crs,
+            tile_url,
+        }))
+    }
+
+    /// Serves `archive`'s tiles, so long as they are images that can be decoded.
+    pub fn archive_provider(archive: TileArchive) -> Result<ImageryProvider, String> {
+        if !matches!(archive.format.as_str(), "png" | "jpg" | "jpeg") {
+            return Err(format!(
+                "{} holds {} tiles, not PNG or JPEG imagery",
+                archive.location, archive.format
+            ));
+        }
+        let matrices = (archive.min_zoom..=archive.max_zoom.min(30))
+            .map(|zoom| TileMatrix {
+                identifier: zoom.to_string(),
+                scale_denominator: MERCATOR_ZOOM_0_SCALE / (1u64 << zoom) as f64,
+                top_left: [
+                    -MERCATOR_RADIUS * std::f64::consts::PI,
+                    MERCATOR_RADIUS * std::f64::consts::PI,
+                ],
+                tile_width: ARCHIVE_TILE_SIZE,
+                tile_height: ARCHIVE_TILE_SIZE,
+                matrix_width: 1 << zoom,
+                matrix_height: 1 << zoom,
+            })
+            .collect();
+        Ok(ImageryProvider::Archive(ArchiveProvider {
+            tile_matrix_set: TileMatrixSet {
+                identifier: "GoogleMapsCompatible".to_string(),
+                crs: "EPSG:3857".to_string(),
+                matrices,
+            },
+            archive: Arc::new(archive),
+        }))
+    }
+
+    // PNG keeps transparency for layers drawn over others
+    fn preferred_format(formats: &[String]) -> String {
+        ["image/png", "image/jpeg"]
+            .into_iter()
+            .find(|preferred| formats.iter().any(|format| format == preferred))
+            .or(formats.first().map(String::as_str))
+            .unwrap_or("image/png")
+            .to_string()
+    }
+
+    /// The part of a globe centered on the origin that the camera sees, and
+    /// the degrees per pixel at the middle of the screen.
+    pub fn visible_view(
+        screen_width: f32,
+        screen_height: f32,
+        radius: f32,
+        camera_component: &CameraComponent,
+    ) -> Option<ImageryView> {
+        let eye = cgmath::Vector3::new(
+            camera_component.camera.eye.x,
+            camera_component.camera.eye.y,
+            camera_component.camera.eye.z,
+        );
+        let distance = eye.magnitude();
+        if distance <= radius {
+            return None;
+        }
+
+        let mut hits = Vec::new();
+        let mut horizon_in_view = false;
+        for i in 0..=VIEW_SAMPLES {
+            for j in 0..=VIEW_SAMPLES {
+                let x = screen_width * i as f32 / VIEW_SAMPLES as f32;
+                let y = screen_height * j as f32 / VIEW_SAMPLES as f32;
+                let (origin, direction) =
+                    WindowSystem::cursor_ray(screen_width, screen_height, x, y, camera_component);
+                match WindowSystem::ray_sphere_intersection(
+                    origin,
+                    direction,
+                    cgmath::Vector3::new(0.0, 0.0, 0.0),
+                    radius,
+                ) {
+                    Some(t) => hits.push(MeshSystem::cartesian_to_lat_lon(origin + direction * t)),
+                    None => horizon_in_view = true,
+                }
+            }
+        }
+        if hits.is_empty() {
+            return None;
+        }
+
+        let (center_lat, center_lon) = MeshSystem::cartesian_to_lat_lon(eye);
+        let mut rectangle = if horizon_in_view {
+            // anything up to the horizon may be in view
+            let horizon = (radius / distance).acos().to_degrees();
+            let (south, north) = (center_lat - horizon, center_lat + horizon);
+            if south <= -90.0 || north >= 90.0 {
+                GeoRectangle {
+                    south: south.max(-90.0),
+                    north: north.min(90.0),
+                    ..GeoRectangle::WORLD
+                }
+            } else {
+                let half_width = (horizon.to_radians().sin() / center_lat.to_radians().cos())
+                    .min(1.0)
+                    .asin()
+                    .to_degrees();
+                GeoRectangle {
+                    west: center_lon - half_width,
+                    south,
+                    east: center_lon + half_width,
+                    north,
+                }
+            }
+        } else {
+            // longitudes relative to the center, which the view can't wrap around
+            let mut rectangle = GeoRectangle {
+                west: 180.0,
+                south: 90.0,
+                east: -180.0,
+                north: -90.0,
+            };
+            for (lat, lon) in hits {
+                let lon = (lon - center_lon + 180.0).rem_euclid(360.0) - 180.0;
+                rectangle.west = rectangle.west.min(lon);
+                rectangle.east = rectangle.east.max(lon);
+                rectangle.south = rectangle.south.min(lat);
+                rectangle.north = rectangle.north.max(lat);
+            }
+            rectangle.west += center_lon;
+            rectangle.east += center_lon;
+            rectangle
+        };
+
+        // a pole on screen is surrounded by every longitude
+        for pole in [90.0_f32, -90.0] {
+            let point = cgmath::Vector3::new(0.0, radius * pole.signum(), 0.0);
+            let facing = eye.dot(point) > radius * radius;
+            let on_screen =
+                WindowSystem::world_to_screen(screen_width, screen_height, point, camera_component)
+                    .is_some_and(|(x, y)| {
+                        (0.0..=screen_width).contains(&x) && (0.0..=screen_height).contains(&y)
+                    });
+            if facing && on_screen {
+                rectangle.west = -180.0;
+                rectangle.east = 180.0;
+                rectangle.south = rectangle.south.min(pole);
+                rectangle.north = rectangle.north.max(pole);
+            }
+        }
+        if rectangle.east - rectangle.west < 360.0 {
+            let wrap = |lon: f32| (lon + 180.0).rem_euclid(360.0) - 180.0;
+            rectangle.west = wrap(rectangle.west);
+            rectangle.east = wrap(rectangle.east);
+        }
+
+        let pixel_angle = camera_component.camera.fovy.to_radians() / screen_height;
+        let resolution = ((distance - radius) * pixel_angle / radius).to_degrees();
+        Some(ImageryView {
+            rectangle,
+            resolution,
+        })
+    }
+
+    /// Whether the layer should be fetched again to cover `view`, and what to
+    /// ask for if so.
+    pub fn next_request(
+        component: &ImageryProviderComponent,
+        view: &ImageryView,
+    ) -> Option<ImageryView> {
+        if component.pending {
+            return None;
+        }
+        if let Some(shown) = component.shown {
+            let detailed_enough = shown.resolution <= view.resolution * 2.0;
+            let not_too_detailed = shown.resolution >= view.resolution / 4.0;
+            if shown.rectangle.contains(&view.rectangle) && detailed_enough && not_too_detailed {
+                return None;
+            }
+        }
+        Some(ImageryView {
+            rectangle: view.rectangle.padded(VIEW_PADDING),
+            resolution: view.resolution,
+        })
+    }
+
+    /// What to fetch to cover `view`, or None if the provider has nothing there.
+    pub fn plan(provider: &ImageryProvider, view: &ImageryView) -> Option<ImageryRequest> {
+        match provider {
+            ImageryProvider::Wms(wms) => ImageryProviderSystem::plan_wms(wms, view),
+            ImageryProvider::Wmts(wmts) => ImageryProviderSystem::plan_tiles(
+                &wmts.tile_matrix_set,
+                wmts.crs,
+                view,
+                |matrix, row, column| {
+                    let url = ImageryProviderSystem::tile_url(wmts, matrix, row, column);
+                    (url, None)
+                },
+            ),
+            ImageryProvider::Archive(archive) => ImageryProviderSystem::plan_tiles(
+                &archive.tile_matrix_set,
+                ImageryCrs::WebMercator,
+                view,
+                |matrix, row, column| {
+                    let zoom = matrix.identifier.parse().unwrap_or_default();
+                    let url = format!("{}/{zoom}/{column}/{row}", archive.archive.location);
+                    (url, Some((zoom, column as u32, row as u32)))
+                },
+            )
+            .map(|request| ImageryRequest {
+                archive: Some(archive.archive.clone()),
+                ..request
+            }),
+        }
+    }
+
+    fn plan_wms(wms: &WmsProvider, view: &ImageryView) -> Option<ImageryRequest> {
+        let rectangle = view.rectangle;
+        let west = rectangle.west as f64;
+        let east = west + rectangle.width() as f64;
+        let (mut south, mut north) = (rectangle.south as f64, rectangle.north as f64);
+        let (top, bottom, projected_resolution) = match wms.crs {
+            ImageryCrs::Geographic => (north, south, view.resolution as f64),
+            ImageryCrs::WebMercator => {
+                south = south.max(-MAX_MERCATOR_LAT);
+                north = north.min(MAX_MERCATOR_LAT);
+                let middle = ((south + north) / 2.0).to_radians();
+                (
+                    mercator_y(north),
+                    mercator_y(south),
+                    (view.resolution as f64).to_radians() * MERCATOR_RADIUS / middle.cos(),
+                )
+            }
+        };
+        if north <= south {
+            return None;
+        }
+
+        let width = (east - west) / view.resolution as f64;
+        let height = (top - bottom) / projected_resolution;
+        let scale = (MAX_IMAGE_SIZE as f64 / width)
+            .min(MAX_IMAGE_SIZE as f64 / height)
+            .min(1.0);
+        let width = ((width * scale).round() as u32).max(1);
+        let height = ((height * scale).round() as u32).max(1);
+
+        // one image either side of the antimeridian
+        let spans = if east > 180.0 {
+            vec![(west, 180.0), (-180.0, east - 360.0)]
+        } else {
+            vec![(west, east)]
+        };
+        let mut pieces = Vec::new();
+        let mut x = 0;
+        for (i, &(span_west, span_east)) in spans.iter().enumerate() {
+            let piece_width = if i + 1 == spans.len() {
+                width - x
+            } else {
+                ((width as f64 * (span_east - span_west) / (east - west)).round() as u32).min(width)
+            };
+            if piece_width == 0 {
+                continue;
+            }
+            let bbox = match wms.crs {
+                // WMS 1.3.0 takes EPSG:4326 latitude first
+                ImageryCrs::Geographic
+                    if wms.version.starts_with("1.3") && wms.crs_code.ends_with("4326") =>
+                {
+                    format!("{south},{span_west},{north},{span_east}")
+                }
+                ImageryCrs::Geographic => format!("{span_west},{south},{span_east},{north}"),
+                ImageryCrs::WebMercator => format!(
+                    "{},{bottom},{},{top}",
+                    span_west.to_radians() * MERCATOR_RADIUS,
+                    span_east.to_radians() * MERCATOR_RADIUS
+                ),
+            };
+            let crs_key = if wms.version.starts_with("1.3") {
+                "CRS"
+            } else {
+                "SRS"
+            };
+            let (width_param, height_param) = (piece_width.to_string(), height.to_string());
+            let url = with_query(
+                &wms.get_map_url,
+                &[
+                    ("SERVICE", "WMS"),
+                    ("VERSION", &wms.version),
+                    ("REQUEST", "GetMap"),
+                    ("LAYERS", &wms.layer),
+                    ("STYLES", ""),
+                    ("FORMAT", &wms.format),
+                    ("TRANSPARENT", "TRUE"),
+                    (crs_key, &wms.crs_code),
+                    ("BBOX", &bbox),
+                    ("WIDTH", &width_param),
+                    ("HEIGHT", &height_param),
+                ],
+            );
+            pieces.push(ImageryPiece {
+                url,
+                tile: None,
+                x,
+                y: 0,
+                width: piece_width,
+                height,
+            });
+            x += piece_width;
+        }
+
+        Some(ImageryRequest {
+            crs: wms.crs,
+            west,
+            east,
+            top,
+            bottom,
+            width,
+            height,
+            pieces,
+            archive: None,
+            grid: None,
+        })
+    }
+
+    // The tiles of the best level of `tile_matrix_set` for `view`, each named
+    // by `piece` from its matrix, row and column.
+    fn plan_tiles(
+        tile_matrix_set: &TileMatrixSet,
+        crs: ImageryCrs,
+        view: &ImageryView,
+        piece: impl Fn(&TileMatrix, i64, i64) -> (String, Option<(u8, u32, u32)>),
+    ) -> Option<ImageryRequest> {
+        let rectangle = view.rectangle;
+        let west = rectangle.west as f64;
+        let east = west + rectangle.width() as f64;
+        let (south, north) = (rectangle.south as f64, rectangle.north as f64);
+        let (units_per_degree, x_range, y_range, target) = match crs {
+            ImageryCrs::Geographic => (1.0, (west, east), (north, south), view.resolution as f64),
+            ImageryCrs::WebMercator => {
+                let (south, north) = (south.max(-MAX_MERCATOR_LAT), north.min(MAX_MERCATOR_LAT));
+                let middle = ((south + north) / 2.0).to_radians();
+                let units_per_degree = MERCATOR_RADIUS.to_radians();
+                (
+                    units_per_degree,
+                    (west * units_per_degree, east * units_per_degree),
+                    (mercator_y(north), mercator_y(south)),
+                    view.resolution as f64 * units_per_degree / middle.cos(),
+                )
+            }
+        };
+        // scale denominators are in meters, even for geographic sets
+        let meters_per_unit = match crs {
+            ImageryCrs::Geographic => MERCATOR_RADIUS.to_radians(),
+            ImageryCrs::WebMercator => 1.0,
+        };
+        let resolution =
+            |matrix: &TileMatrix| matrix.scale_denominator * STANDARD_PIXEL_SIZE / meters_per_unit;
+
+        let mut matrices: Vec<&TileMatrix> = tile_matrix_set.matrices.iter().collect();
+        matrices.sort_by(|a, b| b.scale_denominator.total_cmp(&a.scale_denominator));
+        // the least detailed level that is detailed enough, backing off to
+        // coarser ones while there are too many tiles
+        let mut level = matrices
+            .iter()
+            .rposition(|matrix| resolution(matrix) >= target * 0.75)
+            .unwrap_or(0);
+        loop {
+            let matrix = matrices[level];
+            let resolution = resolution(matrix);
+            let (span_x, span_y) = (
+                matrix.tile_width as f64 * resolution,
+                matrix.tile_height as f64 * resolution,
+            );
+            let [left, top] = matrix.top_left;
+            let world_width = 360.0 * units_per_degree;
+            let wraps = (matrix.matrix_width as f64 * span_x - world_width).abs() < span_x / 2.0;
+
+            let mut first_column = ((x_range.0 - left) / span_x).floor() as i64;
+            let mut last_column = ((x_range.1 - left) / span_x).ceil() as i64 - 1;
+            if !wraps {
+                first_column = first_column.max(0);
+                last_column = last_column.min(matrix.matrix_width as i64 - 1);
+            }
+            let first_row = (((top - y_range.0) / span_y).floor() as i64).max(0);
+            let last_row = (((top - y_range.1) / span_y).ceil() as i64 - 1)
+                .min(matrix.matrix_height as i64 - 1);
+            if last_column < first_column || last_row < first_row {
+                return None;
+            }
+
+            let (columns, rows) = (
+                (last_column - first_column + 1) as u32,
+                (last_row - first_row + 1) as u32,
+            );
+            let (width, height) = (columns * matrix.tile_width, rows * matrix.tile_height);
+            let fits = (columns * rows) as usize <= MAX_LAYER_TILES
+                && width <= MAX_IMAGE_SIZE
+                && height <= MAX_IMAGE_SIZE;
+            if !fits && level > 0 {
+                level -= 1;
+                continue;
+            }
+            if !fits {
+                // archives may start too deep to cover a wide view, so wait
+                // for the camera to come closer
+                return None;
+            }
+
+            let mut pieces = Vec::new();
+            for row in first_row..=last_row {
+                for column in first_column..=last_column {
+                    let tile_column = column.rem_euclid(matrix.matrix_width as i64);
+                    let (url, tile) = piece(matrix, row, tile_column);
+                    pieces.push(ImageryPiece {
+                        url,
+                        tile,
+                        x: (column - first_column) as u32 * matrix.tile_width,
+                        y: (row - first_row) as u32 * matrix.tile_height,
+                        width: matrix.tile_width,
+                        height: matrix.tile_height,
+                    });
+                }
+            }
+            let west = (left + first_column as f64 * span_x) / units_per_degree;
+            let grid_top = top - first_row as f64 * span_y;
+            // the shader's units: radians, or Mercator on the unit sphere
+            let to_shader = match crs {
+                ImageryCrs::Geographic => 1.0_f64.to_radians(),
+                ImageryCrs::WebMercator => 1.0 / MERCATOR_RADIUS,
+            };
+            let grid = TileGrid {
+                west: ((west + 180.0).rem_euclid(360.0) - 180.0).to_radians() as f32,
+                top: (grid_top * to_shader) as f32,
+                tile_width: (span_x / units_per_degree).to_radians() as f32,
+                tile_height: (span_y * to_shader) as f32,
+                columns,
+                rows,
+                mercator: crs == ImageryCrs::WebMercator,
+            };
+            return Some(ImageryRequest {
+                crs,
+                west,
+                east: (left + (last_column + 1) as f64 * span_x) / units_per_degree,
+                top: grid_top,
+                bottom: top - (last_row + 1) as f64 * span_y,
+                width,
+                height,
+                pieces,
+                archive: None,
+                grid: Some(grid),
+            });
+        }
+    }
+
+    fn tile_url(wmts: &WmtsProvider, matrix: &TileMatrix, row: i64, column: i64) -> String {
+        match &wmts.tile_url {
+            WmtsTileUrl::Template(template) => template
+                .replace("{TileMatrixSet}", &wmts.tile_matrix_set.identifier)
+                .replace("{TileMatrix}", &matrix.identifier)
+                .replace("{TileRow}", &row.to_string())
+                .replace("{TileCol}", &column.to_string())
+                .replace("{Style}", &wmts.style),
+            WmtsTileUrl::Kvp(url) => with_query(
+                url,
+                &[
+                    ("SERVICE", "WMTS"),
+                    ("REQUEST", "GetTile"),
+                    ("VERSION", "1.0.0"),
+                    ("LAYER", &wmts.layer),
+                    ("STYLE", &wmts.style),
+                    ("FORMAT", &wmts.format),
+                    ("TILEMATRIXSET", &wmts.tile_matrix_set.identifier),
+                    ("TILEMATRIX", &matrix.identifier),
+                    ("TILEROW", &row.to_string()),
+                    ("TILECOL", &column.to_string()),
+                ],
+            ),
+        }
+    }
+
+    /// Stitches the fetched pieces, None where one failed, into an
+    /// equirectangular image and the area it covers.
+    pub fn assemble(
+        request: &ImageryRequest,
+        images: Vec<Option<RgbaImage>>,
+    ) -> (GeoRectangle, RgbaImage) {
+        let mut canvas = RgbaImage::new(request.width, request.height);
+        for (piece, image) in request.pieces.iter().zip(images) {
+            let Some(mut image) = image else {
+                continue;
+            };
+            if image.dimensions() != (piece.width, piece.height) {
+                image = imageops::resize(
+                    &image,
+                    piece.width,
+                    piece.height,
+                    imageops::FilterType::Triangle,
+                );
+            }
+            imageops::replace(&mut canvas, &image, piece.x as i64, piece.y as i64);
+        }
+
+        let rectangle = ImageryProviderSystem::rectangle(request);
+        let (top, bottom) = (request.top, request.bottom);
+        let (north, south) = (rectangle.north as f64, rectangle.south as f64);
+        let height = request.height as f64;
+        let image = match request.crs {
+            ImageryCrs::Geographic => {
+                let row = |lat: f64| ((top - lat) / (top - bottom) * height).round() as u32;
+                let first_row = row(north).min(request.height - 1);
+                let rows = (row(south) - first_row).max(1);
+                imageops::crop_imm(&canvas, 0, first_row, request.width, rows).to_image()
+            }
+            ImageryCrs::WebMercator => {
+                let mut image = RgbaImage::new(request.width, request.height);
+                for row in 0..request.height {
+                    let lat = north - (row as f64 + 0.5) / height * (north - south);
+                    let source = ((top - mercator_y(lat)) / (top - bottom) * height - 0.5)
+                        .clamp(0.0, height - 1.0);
+                    let (above, t) = (source.floor() as u32, source.fract() as f32);
+                    let below = (above + 1).min(request.height - 1);
+                    for x in 0..request.width {
+                        let (a, b) = (canvas.get_pixel(x, above), canvas.get_pixel(x, below));
+                        let pixel = image.get_pixel_mut(x, row);
+                        for channel in 0..4 {
+                            pixel[channel] = (a[channel] as f32 * (1.0 - t) + b[channel] as f32 * t)
+                                .round() as u8;
+                        }
+                    }
+                }
+                image
+            }
+        };
+        (rectangle, image)
+    }
+
+    /// The area a request's images cover.
+    pub fn rectangle(request: &ImageryRequest) -> GeoRectangle {
+        let (north, south) = match request.crs {
+            // tile grids may run past the poles
+            ImageryCrs::Geographic => (request.top.min(90.0), request.bottom.max(-90.0)),
+            ImageryCrs::WebMercator => (mercator_lat(request.top), mercator_lat(request.bottom)),
+        };
+        if request.east - request.west >= 360.0 {
+            GeoRectangle {
+                south: south as f32,
+                north: north as f32,
+                ..GeoRectangle::WORLD
+            }
+        } else {
+            let west = (request.west + 180.0).rem_euclid(360.0) - 180.0;
+            let east = west + (request.east - request.west);
+            GeoRectangle {
+                west: west as f32,
+                south: south as f32,
+                east: if east > 180.0 { east - 360.0 } else { east } as f32,
+                north: north as f32,
+            }
+        }
+    }
+}
+
+fn mercator_y(lat: f64) -> f64 {
+    let lat = lat.clamp(-MAX_MERCATOR_LAT, MAX_MERCATOR_LAT).to_radians();
+    MERCATOR_RADIUS * (std::f64::consts::FRAC_PI_4 + lat / 2.0).tan().ln()
+}
+
+fn mercator_lat(y: f64) -> f64 {
+    (2.0 * (y / MERCATOR_RADIUS).exp().atan() - std::f64::consts::FRAC_PI_2).to_degrees()
+}
@@ -0,0 +1,50 @@
+use crate::resources::input::{HeldInputs, InputAction, InputBindings, InputTrigger};
+
+pub struct InputSystem {}
+
+impl InputSystem {
+    /// The actions `trigger` is bound to, in the order they were bound.
+    pub fn actions(bindings: &InputBindings, trigger: InputTrigger) -> Vec<InputAction> {
+        bindings
+            .bindings
+            .iter()
+            .filter(|(bound, _)| *bound == trigger)
+            .map(|&(_, action)| action)
+            .collect()
+    }
+
+    /// Binds `trigger` to `action` as well as whatever it already triggers.
+    /// Returns false if it already was.
+    pub fn bind(bindings: &mut InputBindings, trigger: InputTrigger, action: InputAction) -> bool {
+        if bindings.bindings.contains(&(trigger, action)) {
+            return false;
+        }
+        bindings.bindings.push((trigger, action));
+        true
+    }
+
+    /// Unbinds `trigger` from every action, returning the actions it was bound to.
+    pub fn unbind(bindings: &mut InputBindings, trigger: InputTrigger) -> Vec<InputAction> {
+        let actions = InputSystem::actions(bindings, trigger);
+        bindings.bindings.retain(|(bound, _)| *bound != trigger);
+        actions
+    }
+
+    /// Records `trigger` holding `action` down. Returns true if nothing was
+    /// holding it yet, so the action should start.
+    pub fn hold(held: &mut HeldInputs, trigger: InputTrigger, action: InputAction) -> bool {
+        let started = !InputSystem::is_held(held, action);
+        held.held.insert((trigger, action));
+        started
+    }
+
+    /// Records `trigger` letting go of `action`. Returns true if that was the
+    /// last input holding it, so the action should stop.
+    pub fn release(held: &mut HeldInputs, trigger: InputTrigger, action: InputAction) -> bool {
+        held.held.remove(&(trigger, action)) && !InputSystem::is_held(held, action)
+    }
+
+    pub fn is_held(held: &HeldInputs, action: InputAction) -> bool {
+        held.held.iter().any(|&(_, holding)| holding == action)
+    }
+}
@@ -1,78 +1,185 @@
-use image::{ImageBuffer, Rgba};
+use cgmath::{InnerSpace, Vector3};
+use image::{Rgba, RgbaImage};
+use wgpu::util::DeviceExt;
+
+use crate::loader::Ktx2Texture;
+
+// 2048² faces hold an 8k mosaic at full resolution
+const MAX_CUBE_FACE_SIZE: u32 = 2048;
+
+/// The pixels of a 2D texture or the six faces of a cube map.
+#[derive(Debug, Clone)]
+pub enum TextureSource {
+    /// Decoded images, mipmapped on upload.
+    Images(Vec<RgbaImage>),
+    /// A KTX2 file's levels, uploaded as they are when the adapter supports
+    /// their format.
+    Ktx2(Ktx2Texture),
+}
+
+impl From<RgbaImage> for TextureSource {
+    fn from(image: RgbaImage) -> Self {
+        TextureSource::Images(vec![image])
+    }
+}
+
+impl From<Vec<RgbaImage>> for TextureSource {
+    fn from(faces: Vec<RgbaImage>) -> Self {
+        TextureSource::Images(faces)
+    }
+}
+
+impl From<Ktx2Texture> for TextureSource {
+    fn from(texture: Ktx2Texture) -> Self {
+        TextureSource::Ktx2(texture)
+    }
+}
 
 pub struct MaterialSystem {}
 
 impl MaterialSystem {
-    pub fn cube_map_buffer_from_urls(urls: Vec<&[u8]>) -> Vec<ImageBuffer<Rgba<u8>, Vec<u8>>> {
-        urls.iter()
-            .map(|&cube_bytes| {
-                let cube_image =
-                    image::load_from_memory(cube_bytes).expect("Failed to load image from memory");
-                cube_image.to_rgba8()
+    // 1x1 faces of a single color, drawn until the real textures arrive
+    pub fn solid_cube_map(color: [u8; 4]) -> TextureSource {
+        (0..6)
+            .map(|_| RgbaImage::from_pixel(1, 1, Rgba(color)))
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    /// Checks that `device` can sample `source`, decoding a compressed KTX2
+    /// texture to RGBA8 when the adapter lacks its format.
+    pub fn prepare_texture(
+        device: &wgpu::Device,
+        source: TextureSource,
+    ) -> Result<TextureSource, String> {
+        match source {
+            TextureSource::Ktx2(texture)
+                if !device
+                    .features()
+                    .contains(texture.format.required_features()) =>
+            {
+                texture
+                    .decompress()
+                    .map(TextureSource::Ktx2)
+                    .ok_or_else(|| {
+                        format!(
+                            "this GPU can't sample {:?}, and only BC1 to BC5 can be decoded \
+                             without it",
+                            texture.format
+                        )
+                    })
+            }
+            source => Ok(source),
+        }
+    }
+
+    /// Resamples an equirectangular (plate carrée) map, 90°N at the top and
+    /// 180°W at the left edge, into six cube faces in the order
+    /// `create_cube_map_texture` expects.
+    ///
+    /// The faces are laid out for sampling by model-space direction, the frame
+    /// `OrientationSystem::body_fixed_to_mesh` maps to: north is +y, 0° lon
+    /// faces -z and 90°E faces -x.
+    pub fn equirectangular_to_cube_map(image: &RgbaImage) -> Vec<RgbaImage> {
+        // a face spans 90° of longitude, as many texels as the source has
+        let face_size = (image.width() / 4).clamp(1, MAX_CUBE_FACE_SIZE);
+
+        (0..6)
+            .map(|face| {
+                RgbaImage::from_fn(face_size, face_size, |x, y| {
+                    // texel centres in [-1, 1], t pointing down the face
+                    let s = 2.0 * (x as f32 + 0.5) / face_size as f32 - 1.0;
+                    let t = 2.0 * (y as f32 + 0.5) / face_size as f32 - 1.0;
+                    let direction = MaterialSystem::cube_face_direction(face, s, t);
+                    MaterialSystem::sample_equirectangular(image, direction)
+                })
             })
             .collect()
     }
 
-    pub fn create_cube_map_texture(
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
-        image_data: Vec<ImageBuffer<Rgba<u8>, Vec<u8>>>,
-    ) -> (wgpu::BindGroup, wgpu::BindGroupLayout) {
-        let dimensions = image_data[0].dimensions();
-        let face_size = wgpu::Extent3d {
-            width: dimensions.0,
-            height: dimensions.1,
-            depth_or_array_layers: 1, // Single layer for each face
+    /// Turns an equirectangular height map, brighter being higher, into a
+    /// tangent-space normal map in the same projection: x east, y north and z
+    /// up, packed into 0..255. `relief` is the height of a white texel over a
+    /// black one and `radius` the body's, both in kilometers.
+    pub fn bump_to_normal_map(image: &RgbaImage, relief: f32, radius: f32) -> RgbaImage {
+        let (width, height) = image.dimensions();
+        let elevation = |x: i64, y: i64| {
+            let x = x.rem_euclid(width as i64) as u32;
+            let y = y.clamp(0, height as i64 - 1) as u32;
+            image.get_pixel(x, y).0[0] as f32 / 255.0 * relief
         };
+        // ground distance between texel centres
+        let north_step = std::f32::consts::PI * radius / height as f32;
 
-        let cube_map_texture = device.create_texture(&wgpu::TextureDescriptor {
-            size: wgpu::Extent3d {
-                width: dimensions.0,
-                height: dimensions.1,
-                depth_or_array_layers: 6, // 6 layers for cube texture
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            label: Some("Cube Map Texture"),
-            view_formats: &[],
-        });
+        RgbaImage::from_fn(width, height, |x, y| {
+            let lat = std::f32::consts::PI * (0.5 - (y as f32 + 0.5) / height as f32);
+            let east_step = (std::f32::consts::TAU * radius * lat.cos() / width as f32).max(1e-3);
+            let (x, y) = (x as i64, y as i64);
+            let slope_east = (elevation(x + 1, y) - elevation(x - 1, y)) / (2.0 * east_step);
+            // rows run southwards
+            let slope_north = (elevation(x, y - 1) - elevation(x, y + 1)) / (2.0 * north_step);
 
-        // Copy each face into the cube texture
-        for (i, data) in image_data.iter().enumerate() {
-            queue.write_texture(
-                wgpu::ImageCopyTexture {
-                    texture: &cube_map_texture,
-                    mip_level: 0,
-                    origin: wgpu::Origin3d {
-                        x: 0,
-                        y: 0,
-                        z: i as u32, // Specifies which layer of the cube map to copy to
-                    },
-                    aspect: wgpu::TextureAspect::All,
-                },
-                bytemuck::cast_slice(&data),
-                wgpu::ImageDataLayout {
-                    offset: 0,
-                    bytes_per_row: Some((dimensions.0 * 4) as u32), // 4 bytes per pixel for RGBA
-                    rows_per_image: Some(dimensions.1 as u32),
-                },
-                face_size,
-            );
+            let normal = Vector3::new(-slope_east, -slope_north, 1.0).normalize();
+            Rgba([
+                ((normal.x * 0.5 + 0.5) * 255.0).round() as u8,
+                ((normal.y * 0.5 + 0.5) * 255.0).round() as u8,
+                ((normal.z * 0.5 + 0.5) * 255.0).round() as u8,
+                255,
+            ])
+        })
+    }
+
+    // The direction through (s, t) on a face, in the +X, -X, +Y, -Y, +Z, -Z
+    // face order and orientation wgpu uses for cube textures.
+    fn cube_face_direction(face: usize, s: f32, t: f32) -> Vector3<f32> {
+        match face {
+            0 => Vector3::new(1.0, -t, -s),
+            1 => Vector3::new(-1.0, -t, s),
+            2 => Vector3::new(s, 1.0, t),
+            3 => Vector3::new(s, -1.0, -t),
+            4 => Vector3::new(s, -t, 1.0),
+            _ => Vector3::new(-s, -t, -1.0),
         }
+        .normalize()
+    }
 
-        let cube_map_material_view = cube_map_texture.create_view(&wgpu::TextureViewDescriptor {
-            label: Some("Cube Map Material View"),
-            dimension: Some(wgpu::TextureViewDimension::Cube),
-            format: Some(wgpu::TextureFormat::Rgba8UnormSrgb),
-            aspect: wgpu::TextureAspect::All,
-            base_mip_level: 0,
-            mip_level_count: Some(1),
-            base_array_layer: 0,
-            array_layer_count: Some(6),
-        });
+    // bilinear, wrapping around the antimeridian
+    fn sample_equirectangular(image: &RgbaImage, direction: Vector3<f32>) -> Rgba<u8> {
+        let lat = direction.y.clamp(-1.0, 1.0).asin();
+        let lon = (-direction.x).atan2(-direction.z);
+
+        let (width, height) = image.dimensions();
+        let u = (lon / std::f32::consts::TAU + 0.5) * width as f32 - 0.5;
+        let v = (0.5 - lat / std::f32::consts::PI) * height as f32 - 0.5;
+
+        let (x0, y0) = (u.floor(), v.floor());
+        let (fx, fy) = (u - x0, v - y0);
+        let texel = |x: f32, y: f32| {
+            let x = (x as i64).rem_euclid(width as i64) as u32;
+            let y = (y.max(0.0) as u32).min(height - 1);
+            image.get_pixel(x, y).0.map(f32::from)
+        };
+        let (a, b, c, d) = (
+            texel(x0, y0),
+            texel(x0 + 1.0, y0),
+            texel(x0, y0 + 1.0),
+            texel(x0 + 1.0, y0 + 1.0),
+        );
+
+        Rgba(std::array::from_fn(|i| {
+            let top = a[i] + (b[i] - a[i]) * fx;
+            let bottom = c[i] + (d[i] - c[i]) * fx;
+            (top + (bottom - top) * fy).round() as u8
+        }))
+    }
+
+    pub fn create_cube_map_texture(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        source: TextureSource,
+    ) -> (wgpu::BindGroup, wgpu::BindGroupLayout) {
+        let cube_map_material_view =
+            MaterialSystem::create_cube_map_view(device, queue, source, true);
 
         let cube_map_material_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             label: Some("Cube Map Material Sampler"),
@@ -132,55 +239,56 @@ impl MaterialSystem {
         )
     }
 
-    pub fn create_2d_texture(
+    /// Uploads a cube map for a material with its own bind group. `srgb` is
+    /// false for data like normal maps, so decoded images aren't gamma
+    /// corrected; KTX2 files keep the format they were encoded in.
+    pub fn create_cube_map_view(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-        image_data: ImageBuffer<Rgba<u8>, Vec<u8>>,
-    ) -> (wgpu::BindGroup, wgpu::BindGroupLayout) {
-        let dimensions = image_data.dimensions();
-        let texture_size = wgpu::Extent3d {
-            width: dimensions.0,
-            height: dimensions.1,
-            depth_or_array_layers: 1, // Single layer for 2D texture
-        };
+        source: TextureSource,
+        srgb: bool,
+    ) -> wgpu::TextureView {
+        let cube_map_texture =
+            MaterialSystem::upload_texture(device, queue, source, 6, srgb, "Cube Map Texture");
 
-        let texture = device.create_texture(&wgpu::TextureDescriptor {
-            size: texture_size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            label: Some("2D Texture"),
-            view_formats: &[],
-        });
+        cube_map_texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Cube Map Material View"),
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            format: Some(cube_map_texture.format()),
+            aspect: wgpu::TextureAspect::All,
+            base_mip_level: 0,
+            mip_level_count: Some(cube_map_texture.mip_level_count()),
+            base_array_layer: 0,
+            array_layer_count: Some(6),
+        })
+    }
 
-        queue.write_texture(
-            wgpu::ImageCopyTexture {
-                texture: &texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            bytemuck::cast_slice(&image_data),
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some((dimensions.0 * 4) as u32), // 4 bytes per pixel for RGBA
-                rows_per_image: Some(dimensions.1 as u32),
-            },
-            texture_size,
-        );
+    pub fn create_2d_view(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        source: TextureSource,
+        srgb: bool,
+    ) -> wgpu::TextureView {
+        let texture = MaterialSystem::upload_texture(device, queue, source, 1, srgb, "2D Texture");
 
-        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor {
+        texture.create_view(&wgpu::TextureViewDescriptor {
             label: Some("2D Texture View"),
             dimension: Some(wgpu::TextureViewDimension::D2),
-            format: Some(wgpu::TextureFormat::Rgba8UnormSrgb),
+            format: Some(texture.format()),
             aspect: wgpu::TextureAspect::All,
             base_mip_level: 0,
-            mip_level_count: Some(1),
+            mip_level_count: Some(texture.mip_level_count()),
             base_array_layer: 0,
             array_layer_count: None,
-        });
+        })
+    }
+
+    pub fn create_2d_texture(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        source: TextureSource,
+    ) -> (wgpu::BindGroup, wgpu::BindGroupLayout) {
+        let texture_view = MaterialSystem::create_2d_view(device, queue, source, true);
 
         let texture_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             label: Some("2D Texture Sampler"),
@@ -235,4 +343,271 @@ impl MaterialSystem {
 
         (texture_bind_group, texture_bind_group_layout)
     }
+
+    // Creates a texture with `layers` faces and fills every mip level. A
+    // texture the adapter can't sample is replaced by a magenta placeholder.
+    fn upload_texture(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        source: TextureSource,
+        layers: u32,
+        srgb: bool,
+        label: &str,
+    ) -> wgpu::Texture {
+        let source = match MaterialSystem::prepare_texture(device, source) {
+            Ok(TextureSource::Ktx2(texture)) if texture.faces != layers => {
+                tracing::error!("{label}: expected {layers} faces, got {}", texture.faces);
+                MaterialSystem::placeholder(layers)
+            }
+            Ok(source) => source,
+            Err(error) => {
+                tracing::error!("{label}: {error}");
+                MaterialSystem::placeholder(layers)
+            }
+        };
+
+        match source {
+            TextureSource::Images(images) => {
+                let dimensions = images[0].dimensions();
+                let texture = device.create_texture(&wgpu::TextureDescriptor {
+                    size: wgpu::Extent3d {
+                        width: dimensions.0,
+                        height: dimensions.1,
+                        depth_or_array_layers: layers,
+                    },
+                    mip_level_count: MaterialSystem::mip_level_count(dimensions),
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: if srgb {
+                        wgpu::TextureFormat::Rgba8UnormSrgb
+                    } else {
+                        wgpu::TextureFormat::Rgba8Unorm
+                    },
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                    label: Some(label),
+                    view_formats: &[],
+                });
+
+                // Copy each face, and its mip chain, into the texture
+                for (i, image) in images.into_iter().enumerate() {
+                    MaterialSystem::write_mip_chain(queue, &texture, i as u32, image);
+                }
+                texture
+            }
+            TextureSource::Ktx2(ktx2_texture) => {
+                MaterialSystem::upload_ktx2(device, queue, ktx2_texture, label)
+            }
+        }
+    }
+
+    fn placeholder(layers: u32) -> TextureSource {
+        TextureSource::Images(
+            (0..layers)
+                .map(|_| RgbaImage::from_pixel(1, 1, Rgba([255, 0, 255, 255])))
+                .collect(),
+        )
+    }
+
+    // Uploads the file's own mips, or generates them for uncompressed
+    // textures that came with only the base level.
+    fn upload_ktx2(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        ktx2_texture: Ktx2Texture,
+        label: &str,
+    ) -> wgpu::Texture {
+        let size = wgpu::Extent3d {
+            width: ktx2_texture.width,
+            height: ktx2_texture.height,
+            depth_or_array_layers: ktx2_texture.faces,
+        };
+        // decoded snorm textures keep the mips they came with
+        let rgba8 = matches!(
+            ktx2_texture.format,
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb
+        );
+        let mip_level_count = if rgba8 && ktx2_texture.levels.len() == 1 {
+            MaterialSystem::mip_level_count((size.width, size.height))
+        } else {
+            ktx2_texture.levels.len() as u32
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            size,
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: ktx2_texture.format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            label: Some(label),
+            view_formats: &[],
+        });
+
+        if mip_level_count as usize > ktx2_texture.levels.len() {
+            let face_size = (size.width * size.height * 4) as usize;
+            for (i, face) in ktx2_texture.levels[0].chunks_exact(face_size).enumerate() {
+                let image = RgbaImage::from_raw(size.width, size.height, face.to_vec()).unwrap();
+                MaterialSystem::write_mip_chain(queue, &texture, i as u32, image);
+            }
+            return texture;
+        }
+
+        let (block_width, block_height) = ktx2_texture.format.block_dimensions();
+        let block_size = ktx2_texture.format.block_size(None).unwrap();
+        for (mip_level, data) in ktx2_texture.levels.iter().enumerate() {
+            // blocks overhang the mips smaller than a block
+            let level_size = size
+                .mip_level_size(mip_level as u32, wgpu::TextureDimension::D2)
+                .physical_size(ktx2_texture.format);
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: mip_level as u32,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                data,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(level_size.width / block_width * block_size),
+                    rows_per_image: Some(level_size.height / block_height),
+                },
+                level_size,
+            );
+        }
+        texture
+    }
+
+    // down to 1x1
+    pub fn mip_level_count(dimensions: (u32, u32)) -> u32 {
+        32 - dimensions.0.max(dimensions.1).max(1).leading_zeros()
+    }
+
+    // Mips are downsampled on the CPU rather than with a render pass per
+    // level, so WebGL2 gets exactly the same chain as native.
+    pub fn write_mip_chain(
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+        layer: u32,
+        image: RgbaImage,
+    ) {
+        let srgb = texture.format().is_srgb();
+        let mut level = image;
+        for mip_level in 0..texture.mip_level_count() {
+            if mip_level > 0 {
+                level = MaterialSystem::downsample(&level, srgb);
+            }
+            let (width, height) = level.dimensions();
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture,
+                    mip_level,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: layer, // Specifies which layer of the texture to copy to
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                bytemuck::cast_slice(&level),
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(width * 4), // 4 bytes per pixel for RGBA
+                    rows_per_image: Some(height),
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+    }
+
+    // Halves each side with a 2x2 box filter, averaging sRGB color in linear
+    // light so the smaller mips don't darken. An odd last row or column is
+    // dropped.
+    fn downsample(image: &RgbaImage, srgb: bool) -> RgbaImage {
+        let (width, height) = image.dimensions();
+        let to_linear: Vec<f32> = (0..=255u8)
+            .map(|value| {
+                let value = value as f32 / 255.0;
+                if !srgb {
+                    value
+                } else if value <= 0.04045 {
+                    value / 12.92
+                } else {
+                    ((value + 0.055) / 1.055).powf(2.4)
+                }
+            })
+            .collect();
+        let to_srgb = |value: f32| {
+            let value = if !srgb {
+                value
+            } else if value <= 0.003_130_8 {
+                value * 12.92
+            } else {
+                1.055 * value.powf(1.0 / 2.4) - 0.055
+            };
+            (value * 255.0).round().clamp(0.0, 255.0) as u8
+        };
+
+        RgbaImage::from_fn((width / 2).max(1), (height / 2).max(1), |x, y| {
+            let xs = [(2 * x).min(width - 1), (2 * x + 1).min(width - 1)];
+            let ys = [(2 * y).min(height - 1), (2 * y + 1).min(height - 1)];
+            let mut sum = [0.0f32; 4];
+            for &sy in ys.iter() {
+                for &sx in xs.iter() {
+                    let texel = image.get_pixel(sx, sy).0;
+                    for channel in 0..3 {
+                        sum[channel] += to_linear[texel[channel] as usize];
+                    }
+                    sum[3] += texel[3] as f32 / 255.0;
+                }
+            }
+            Rgba([
+                to_srgb(sum[0] / 4.0),
+                to_srgb(sum[1] / 4.0),
+                to_srgb(sum[2] / 4.0),
+                (sum[3] / 4.0 * 255.0).round() as u8,
+            ])
+        })
+    }
+
+    pub fn create_color_uniform(
+        device: &wgpu::Device,
+        color: [f32; 4],
+    ) -> (wgpu::BindGroup, wgpu::BindGroupLayout) {
+        let color_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Color Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[color]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let color_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Color Uniform bind group layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+
+        let color_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &color_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: color_buffer.as_entire_binding(),
+            }],
+            label: Some("Color Uniform bind group"),
+        });
+
+        (color_bind_group, color_bind_group_layout)
+    }
 }
@@ -143,13 +143,25 @@ impl MeshSystem {
         (vertices, indices)
     }
 
+    // Model-space position of a geodetic lat/lon, in the frame globe textures
+    // are laid out in: north is +y, 0° lon faces -z and 90°E faces -x.
     pub fn lat_lon_to_cartesian(lat: f32, lon: f32, radius: f32) -> (f32, f32, f32) {
-        let phi = (90.0 - lat).to_radians(); // Convert latitude to radians and start from the north pole
-        let theta = lon.to_radians(); // Convert longitude to radians
+        let (lat, lon) = (lat.to_radians(), lon.to_radians());
 
-        let x = radius * phi.sin() * theta.cos();
-        let y = radius * phi.cos();
-        let z = radius * phi.sin() * theta.sin();
+        let x = -radius * lat.cos() * lon.sin();
+        let y = radius * lat.sin();
+        let z = -radius * lat.cos() * lon.cos();
         (x, y, z)
     }
+
+    // Inverse of `lat_lon_to_cartesian`, in degrees.
+    pub fn cartesian_to_lat_lon(point: cgmath::Vector3<f32>) -> (f32, f32) {
+        use cgmath::InnerSpace;
+        let normalized_point = point.normalize();
+        let lat = normalized_point.y.clamp(-1.0, 1.0).asin().to_degrees();
+        let lon = (-normalized_point.x)
+            .atan2(-normalized_point.z)
+            .to_degrees();
+        (lat, lon)
+    }
 }
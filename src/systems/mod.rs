@@ -1,8 +1,21 @@
+pub mod atmosphere;
 pub mod billboard;
+pub mod body;
 pub mod camera;
+pub mod clock;
+pub mod clouds;
 pub mod earth;
+pub mod ephemeris;
+pub mod geospatial;
+pub mod graticule;
+pub mod imagery;
+pub mod imagery_provider;
+pub mod input;
 pub mod material;
 pub mod mesh;
 pub mod moon;
+pub mod orientation;
 pub mod pipelines;
+pub mod polyline;
+pub mod stars;
 pub mod window;
@@ -0,0 +1,173 @@
+use anise::{
+    almanac::Almanac,
+    constants::{celestial_objects::LUNA, frames},
+    prelude::*,
+};
+use cgmath::{Matrix, Matrix3, Rad};
+
+// NAIF frame ids of the lunar principal-axis frames realized by the
+// moon_pa_de440 and moon_pa_de421 BPCs
+const MOON_PA_DE440: i32 = 31008;
+const MOON_PA_DE421: i32 = 31006;
+
+// Constant PA -> ME rotation for DE440, from moon_de440_220930.tf, in
+// arcseconds about z, y, x. Textures and selenographic coordinates use ME.
+const MOON_PA_TO_ME_ARCSECONDS: [f64; 3] = [67.8526, 78.6944, 0.2785];
+
+pub struct OrientationSystem {}
+
+impl OrientationSystem {
+    /// Rotation from J2000 to the scene at `epoch`.
+    ///
+    /// The scene is the Earth's model space: the globe and everything laid on
+    /// it are drawn body-fixed and unrotated, so the Moon, the stars and in
+    /// solar-system mode the Sun, planets and orbits are turned into the
+    /// Earth's frame instead, and the sky wheels around the globe once a
+    /// sidereal day. Without an almanac the IAU rotation model is used.
+    pub fn j2000_to_scene(almanac: Option<&Almanac>, epoch: Epoch) -> Matrix3<f64> {
+        let earth_to_j2000 = match almanac {
+            Some(almanac) => OrientationSystem::earth_body_fixed_to_j2000(almanac, epoch),
+            None => OrientationSystem::iau_earth_to_j2000(epoch),
+        };
+        OrientationSystem::body_fixed_to_mesh() * earth_to_j2000.transpose()
+    }
+
+    // Body-fixed (x through 0° lon, z through the north pole) to the model
+    // space the globe shaders sample their cube maps in: north is +y, 0° lon
+    // faces -z and 90°E faces -x.
+    pub fn body_fixed_to_mesh() -> Matrix3<f64> {
+        Matrix3::new(0.0, 0.0, -1.0, -1.0, 0.0, 0.0, 0.0, 1.0, 0.0)
+    }
+
+    /// Rotation from the Moon's mean-Earth body-fixed frame to J2000.
+    ///
+    /// Uses a lunar principal-axis BPC when one has been merged into `almanac`,
+    /// which includes physical libration, and the IAU rotation model otherwise.
+    pub fn moon_body_fixed_to_j2000(almanac: &Almanac, epoch: Epoch) -> Matrix3<f64> {
+        for pa_frame in [MOON_PA_DE440, MOON_PA_DE421] {
+            let from = Frame::from_ephem_orient(LUNA, pa_frame);
+            if let Ok(dcm) = almanac.rotate_from_to(from, frames::LUNA_J2000, epoch) {
+                let pa_to_j2000 = OrientationSystem::to_cgmath(&dcm.rot_mat);
+                return pa_to_j2000 * OrientationSystem::moon_pa_to_me().transpose();
+            }
+        }
+        OrientationSystem::iau_moon_to_j2000(epoch)
+    }
+
+    /// Rotation from the Earth's body-fixed frame to J2000.
+    ///
+    /// Uses ITRF93 when a high-precision Earth BPC has been merged into
+    /// `almanac`, and the IAU rotation model otherwise, which ignores
+    /// precession and nutation but stays within a degree or so this century.
+    pub fn earth_body_fixed_to_j2000(almanac: &Almanac, epoch: Epoch) -> Matrix3<f64> {
+        match almanac.rotate_from_to(frames::EARTH_ITRF93, frames::EARTH_J2000, epoch) {
+            Ok(dcm) => OrientationSystem::to_cgmath(&dcm.rot_mat),
+            Err(_) => OrientationSystem::iau_earth_to_j2000(epoch),
+        }
+    }
+
+    // IAU WGCCRE 2009 Earth rotation model
+    fn iau_earth_to_j2000(epoch: Epoch) -> Matrix3<f64> {
+        let d = epoch.to_tdb_days_since_j2000();
+        let t = epoch.to_tdb_centuries_since_j2000();
+
+        let ra = -0.641 * t;
+        let dec = 90.0 - 0.557 * t;
+        let w = 190.147 + 360.985_623_5 * d;
+
+        let j2000_to_body = OrientationSystem::frame_rotation_z(w.to_radians())
+            * OrientationSystem::frame_rotation_x((90.0 - dec).to_radians())
+            * OrientationSystem::frame_rotation_z((90.0 + ra).to_radians());
+        j2000_to_body.transpose()
+    }
+
+    fn moon_pa_to_me() -> Matrix3<f64> {
+        let [z, y, x] =
+            MOON_PA_TO_ME_ARCSECONDS.map(|arcseconds| (arcseconds / 3600.0).to_radians());
+        OrientationSystem::frame_rotation_x(x)
+            * OrientationSystem::frame_rotation_y(y)
+            * OrientationSystem::frame_rotation_z(z)
+    }
+
+    // IAU WGCCRE 2009 lunar rotation model, which approximates the ME frame.
+    fn iau_moon_to_j2000(epoch: Epoch) -> Matrix3<f64> {
+        let d = epoch.to_tdb_days_since_j2000();
+        let t = epoch.to_tdb_centuries_since_j2000();
+
+        let e = [
+            125.045 - 0.0529921 * d,
+            250.089 - 0.1059842 * d,
+            260.008 + 13.0120009 * d,
+            176.625 + 13.3407154 * d,
+            357.529 + 0.9856003 * d,
+            311.589 + 26.4057084 * d,
+            134.963 + 13.0649930 * d,
+            276.617 + 0.3287146 * d,
+            34.226 + 1.7484877 * d,
+            15.134 - 0.1589763 * d,
+            119.743 + 0.0036096 * d,
+            239.961 + 0.1643573 * d,
+            25.053 + 12.9590088 * d,
+        ]
+        .map(f64::to_radians);
+        let (s, c) = (e.map(f64::sin), e.map(f64::cos));
+
+        let ra = 269.9949 + 0.0031 * t - 3.8787 * s[0] - 0.1204 * s[1] + 0.0700 * s[2]
+            - 0.0172 * s[3]
+            + 0.0072 * s[5]
+            - 0.0052 * s[9]
+            + 0.0043 * s[12];
+        let dec = 66.5392 + 0.0130 * t + 1.5419 * c[0] + 0.0239 * c[1] - 0.0278 * c[2]
+            + 0.0068 * c[3]
+            - 0.0029 * c[5]
+            + 0.0009 * c[6]
+            + 0.0008 * c[9]
+            - 0.0009 * c[12];
+        let w = 38.3213 + 13.17635815 * d - 1.4e-12 * d * d + 3.5610 * s[0] + 0.1208 * s[1]
+            - 0.0642 * s[2]
+            + 0.0158 * s[3]
+            + 0.0252 * s[4]
+            - 0.0066 * s[5]
+            - 0.0047 * s[6]
+            - 0.0046 * s[7]
+            + 0.0028 * s[8]
+            + 0.0052 * s[9]
+            + 0.0040 * s[10]
+            + 0.0019 * s[11]
+            - 0.0044 * s[12];
+
+        let j2000_to_body = OrientationSystem::frame_rotation_z(w.to_radians())
+            * OrientationSystem::frame_rotation_x((90.0 - dec).to_radians())
+            * OrientationSystem::frame_rotation_z((90.0 + ra).to_radians());
+        j2000_to_body.transpose()
+    }
+
+    // SPICE-style frame rotations: they rotate the axes by `angle`, so vectors
+    // appear to turn the other way.
+    fn frame_rotation_x(angle: f64) -> Matrix3<f64> {
+        Matrix3::from_angle_x(Rad(-angle))
+    }
+
+    fn frame_rotation_y(angle: f64) -> Matrix3<f64> {
+        Matrix3::from_angle_y(Rad(-angle))
+    }
+
+    fn frame_rotation_z(angle: f64) -> Matrix3<f64> {
+        Matrix3::from_angle_z(Rad(-angle))
+    }
+
+    fn to_cgmath(m: &anise::math::Matrix3) -> Matrix3<f64> {
+        // cgmath takes columns
+        Matrix3::new(
+            m[(0, 0)],
+            m[(1, 0)],
+            m[(2, 0)],
+            m[(0, 1)],
+            m[(1, 1)],
+            m[(2, 1)],
+            m[(0, 2)],
+            m[(1, 2)],
+            m[(2, 2)],
+        )
+    }
+}
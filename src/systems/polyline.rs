@@ -0,0 +1,169 @@
+use cgmath::SquareMatrix;
+use wgpu::util::DeviceExt;
+
+use crate::{
+    components::{
+        camera::CameraComponent,
+        material::MaterialComponent,
+        mesh::{MeshComponent, Vertex},
+        polyline::PolylineComponent,
+        render_pipelines::RenderPipelineComponent,
+    },
+    matrix4_to_array,
+};
+
+use super::{
+    geospatial::geodesic::GeodesicSystem, material::MaterialSystem, mesh::MeshSystem,
+    pipelines::PolylineRenderPipelineSystem,
+};
+
+// fraction of the globe radius lines are lifted off the surface
+// so they don't fight with the globe
+const POLYLINE_ALTITUDE: f32 = 0.002;
+/// Index that ends one line strip and starts the next in a polyline mesh.
+pub const STRIP_RESTART: u32 = u32::MAX;
+
+// longest geodesic step between two uploaded vertices, in meters
+const MAX_SEGMENT_LENGTH: f64 = 100_000.0;
+
+pub struct PolylineSystem {}
+
+impl PolylineSystem {
+    pub fn create_polyline_component(
+        coordinates: Vec<(f32, f32)>,
+        color: [f32; 4],
+        closed: bool,
+        globe_radius: f32,
+        order: u64,
+    ) -> PolylineComponent {
+        let positions = if closed && coordinates.len() > 2 {
+            let mut ring = coordinates.clone();
+            ring.push(coordinates[0]);
+            PolylineSystem::densify(&ring, globe_radius)
+        } else {
+            PolylineSystem::densify(&coordinates, globe_radius)
+        };
+        PolylineComponent {
+            coordinates,
+            color,
+            closed,
+            positions,
+            order,
+        }
+    }
+
+    // Walks each segment along its WGS84 geodesic so long lines hug the globe
+    // instead of cutting through it, and match what the measure tools report.
+    pub fn densify(coordinates: &[(f32, f32)], globe_radius: f32) -> Vec<[f32; 3]> {
+        let radius = globe_radius * (1.0 + POLYLINE_ALTITUDE);
+        let position = |(lat, lon): (f64, f64)| {
+            let (x, y, z) = MeshSystem::lat_lon_to_cartesian(lat as f32, lon as f32, radius);
+            [x, y, z]
+        };
+
+        let mut positions = Vec::new();
+        for pair in coordinates.windows(2) {
+            let (from, to) = (
+                (pair[0].0 as f64, pair[0].1 as f64),
+                (pair[1].0 as f64, pair[1].1 as f64),
+            );
+            let mut points = GeodesicSystem::intermediate_points(from, to, MAX_SEGMENT_LENGTH);
+            points.pop();
+            positions.extend(points.into_iter().map(position));
+        }
+        if let Some(&(lat, lon)) = coordinates.last() {
+            positions.push(position((lat as f64, lon as f64)));
+        }
+
+        positions
+    }
+
+    pub fn create_polyline_mesh(device: &wgpu::Device, positions: &[[f32; 3]]) -> MeshComponent {
+        PolylineSystem::create_polyline_strips_mesh(device, &[positions])
+    }
+
+    /// One mesh drawing several separate lines, each strip ended by a
+    /// [`STRIP_RESTART`] index.
+    pub fn create_polyline_strips_mesh(
+        device: &wgpu::Device,
+        strips: &[&[[f32; 3]]],
+    ) -> MeshComponent {
+        let polyline_matrix = matrix4_to_array(cgmath::Matrix4::identity());
+        let polyline_matrix_bind_group_layout =
+            MeshSystem::create_model_matrix_bind_group_layout(device);
+        let polyline_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Mesh Buffer"),
+            contents: bytemuck::cast_slice(&[polyline_matrix]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let polyline_matrix_bind_group = MeshSystem::create_model_matrix_bind_group(
+            device,
+            &polyline_matrix_bind_group_layout,
+            &polyline_buffer,
+        );
+
+        let mut polyline_vertices_vec: Vec<Vertex> = Vec::new();
+        let mut polyline_indices_vec: Vec<u32> = Vec::new();
+        for strip in strips {
+            if !polyline_indices_vec.is_empty() {
+                polyline_indices_vec.push(STRIP_RESTART);
+            }
+            let first = polyline_vertices_vec.len() as u32;
+            polyline_indices_vec.extend(first..first + strip.len() as u32);
+            polyline_vertices_vec.extend(strip.iter().map(|&position| Vertex { position }));
+        }
+        // wgpu rejects empty buffers, and a lone vertex draws nothing
+        if polyline_vertices_vec.is_empty() {
+            polyline_vertices_vec.push(Vertex { position: [0.0; 3] });
+            polyline_indices_vec.push(0);
+        }
+
+        MeshComponent {
+            vertex_buffer: MeshSystem::create_vertex_buffer(device, &polyline_vertices_vec),
+            index_buffer: MeshSystem::create_index_buffer(device, &polyline_indices_vec),
+            num_indices: polyline_indices_vec.len() as u32,
+            model_matrix_bind_group_layout: polyline_matrix_bind_group_layout,
+            model_matrix_bind_group: polyline_matrix_bind_group,
+            model_matrix_buffer: polyline_buffer,
+            model_matrix: polyline_matrix,
+        }
+    }
+
+    pub fn create_polyline_material(device: &wgpu::Device, color: [f32; 4]) -> MaterialComponent {
+        let (material_bind_group, material_bind_group_layout) =
+            MaterialSystem::create_color_uniform(device, color);
+        MaterialComponent {
+            bind_group: material_bind_group,
+            bind_group_layout: material_bind_group_layout,
+            uniforms: Some(vec![color]),
+            shader: device
+                .create_shader_module(wgpu::include_wgsl!("../shaders/polyline_shader.wgsl")),
+        }
+    }
+
+    pub fn create_render_pipeline(
+        device: &wgpu::Device,
+        camera: &CameraComponent,
+        material: &MaterialComponent,
+        mesh: &MeshComponent,
+        texture_format: &wgpu::TextureFormat,
+    ) -> RenderPipelineComponent {
+        let polyline_pipeline_layouts: &[&wgpu::BindGroupLayout] = &[
+            &camera.camera_bind_group_layout,
+            &material.bind_group_layout,
+            &mesh.model_matrix_bind_group_layout,
+        ];
+        let polyline_render_pipeline_layout =
+            PolylineRenderPipelineSystem::layout_desc(device, polyline_pipeline_layouts);
+        let polyline_render_pipeline = PolylineRenderPipelineSystem::pipeline_desc(
+            device,
+            &polyline_render_pipeline_layout,
+            &material.shader,
+            texture_format,
+        );
+        RenderPipelineComponent {
+            render_pipeline: polyline_render_pipeline,
+            render_pipeline_layout: polyline_render_pipeline_layout,
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use cgmath::{Matrix3, Matrix4, SquareMatrix};
+use wgpu::util::DeviceExt;
+
+use crate::{
+    components::{
+        camera::CameraComponent,
+        material::MaterialComponent,
+        mesh::{MeshComponent, StarVertex},
+        render_pipelines::RenderPipelineComponent,
+        stars::StarfieldComponent,
+    },
+    loader::{Star, StarCatalogFormat},
+    matrix4_to_array,
+};
+
+use super::{material::MaterialSystem, mesh::MeshSystem, pipelines::StarRenderPipelineSystem};
+
+// naked-eye limit; the Bright Star Catalogue stops around here too
+const MAGNITUDE_LIMIT: f32 = 6.5;
+// angular radius of a star at the magnitude limit, radians
+const FAINTEST_STAR_SIZE: f32 = 0.0015;
+// multiplies every star's color and opacity
+const STARFIELD_TINT: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+const QUAD_CORNERS: [[f32; 2]; 4] = [[-1.0, -1.0], [1.0, -1.0], [1.0, 1.0], [-1.0, 1.0]];
+
+pub struct StarSystem {}
+
+impl StarSystem {
+    /// The bright stars compiled into the crate, drawn until a fuller catalog
+    /// is loaded.
+    pub fn bright_stars() -> Vec<Star> {
+        StarCatalogFormat::Csv
+            .parse(include_str!("../assets/bright_stars.csv"))
+            .expect("the built-in star list is valid")
+    }
+
+    pub fn create_starfield(
+        device: &wgpu::Device,
+        stars: &[Star],
+    ) -> (StarfieldComponent, MeshComponent) {
+        let visible_stars: Vec<&Star> = stars
+            .iter()
+            .filter(|star| star.magnitude <= MAGNITUDE_LIMIT)
+            .collect();
+
+        let mut star_vertices_vec = Vec::with_capacity(visible_stars.len() * 4);
+        let mut star_indices_vec = Vec::with_capacity(visible_stars.len() * 6);
+        for star in visible_stars.iter() {
+            let direction = StarSystem::direction(star);
+            let color = StarSystem::color(star);
+            let size = StarSystem::size(star);
+
+            let first = star_vertices_vec.len() as u32;
+            star_vertices_vec.extend(QUAD_CORNERS.iter().map(|&corner| StarVertex {
+                direction,
+                corner,
+                color,
+                size,
+            }));
+            star_indices_vec.extend([0, 1, 2, 0, 2, 3].map(|i| first + i));
+        }
+
+        // turns the J2000 directions into the scene, see `update_orientation`
+        let star_matrix = matrix4_to_array(cgmath::Matrix4::identity());
+        let star_matrix_bind_group_layout =
+            MeshSystem::create_model_matrix_bind_group_layout(device);
+        let star_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Mesh Buffer"),
+            contents: bytemuck::cast_slice(&[star_matrix]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let star_matrix_bind_group = MeshSystem::create_model_matrix_bind_group(
+            device,
+            &star_matrix_bind_group_layout,
+            &star_buffer,
+        );
+
+        (
+            StarfieldComponent {
+                num_stars: visible_stars.len(),
+            },
+            MeshComponent {
+                vertex_buffer: MeshSystem::create_vertex_buffer(
+                    device,
+                    star_vertices_vec.as_slice(),
+                ),
+                index_buffer: MeshSystem::create_index_buffer(device, star_indices_vec.as_slice()),
+                num_indices: star_indices_vec.len() as u32,
+                model_matrix_bind_group_layout: star_matrix_bind_group_layout,
+                model_matrix_bind_group: star_matrix_bind_group,
+                model_matrix_buffer: star_buffer,
+                model_matrix: star_matrix,
+            },
+        )
+    }
+
+    pub fn create_material(device: &wgpu::Device) -> MaterialComponent {
+        let (material_bind_group, material_bind_group_layout) =
+            MaterialSystem::create_color_uniform(device, STARFIELD_TINT);
+        MaterialComponent {
+            bind_group: material_bind_group,
+            bind_group_layout: material_bind_group_layout,
+            uniforms: Some(vec![STARFIELD_TINT]),
+            shader: device.create_shader_module(wgpu::include_wgsl!("../shaders/star_shader.wgsl")),
+        }
+    }
+
+    pub fn create_render_pipeline(
+        device: &wgpu::Device,
+        camera: &CameraComponent,
+        material: &MaterialComponent,
+        mesh: &MeshComponent,
+        texture_format: &wgpu::TextureFormat,
+    ) -> RenderPipelineComponent {
+        let star_pipeline_layouts: &[&wgpu::BindGroupLayout] = &[
+            &camera.camera_bind_group_layout,
+            &material.bind_group_layout,
+            &mesh.model_matrix_bind_group_layout,
+        ];
+        let star_render_pipeline_layout =
+            StarRenderPipelineSystem::layout_desc(device, star_pipeline_layouts);
+        let star_render_pipeline = StarRenderPipelineSystem::pipeline_desc(
+            device,
+            &star_render_pipeline_layout,
+            &material.shader,
+            texture_format,
+        );
+        RenderPipelineComponent {
+            render_pipeline: star_render_pipeline,
+            render_pipeline_layout: star_render_pipeline_layout,
+        }
+    }
+
+    /// Turns the starfield from J2000 into the scene, which rotates with the
+    /// Earth.
+    pub fn update_orientation(
+        queue: &wgpu::Queue,
+        starfield_mesh: &MeshComponent,
+        j2000_to_scene: Matrix3<f64>,
+    ) {
+        let star_matrix: [[f32; 4]; 4] =
+            Matrix4::from(j2000_to_scene).cast::<f32>().unwrap().into();
+        queue.write_buffer(
+            &starfield_mesh.model_matrix_buffer,
+            0,
+            bytemuck::cast_slice(&[star_matrix]),
+        );
+    }
+
+    // unit vector towards the star, in J2000 axes
+    fn direction(star: &Star) -> [f32; 3] {
+        let (ra, dec) = (star.ra.to_radians(), star.dec.to_radians());
+        [dec.cos() * ra.cos(), dec.cos() * ra.sin(), dec.sin()].map(|c| c as f32)
+    }
+
+    // Each magnitude step is ~2.5x in flux; grow the disc more gently than
+    // that so the brightest stars don't turn into blobs.
+    fn size(star: &Star) -> f32 {
+        let magnitude = star.magnitude.max(-1.5);
+        FAINTEST_STAR_SIZE * 10f32.powf(0.1 * (MAGNITUDE_LIMIT - magnitude))
+    }
+
+    // B-V to temperature (Ballesteros 2012), then temperature to RGB with
+    // Tanner Helland's blackbody fit. Fainter stars are more transparent.
+    fn color(star: &Star) -> [f32; 4] {
+        let bv = star.color_index.clamp(-0.4, 2.0);
+        let temperature = 4600.0 * (1.0 / (0.92 * bv + 1.7) + 1.0 / (0.92 * bv + 0.62));
+
+        let t = temperature / 100.0;
+        let red = if t <= 66.0 {
+            255.0
+        } else {
+            329.698_73 * (t - 60.0).powf(-0.133_204_76)
+        };
+        let green = if t <= 66.0 {
+            99.470_8 * t.ln() - 161.119_57
+        } else {
+            288.122_17 * (t - 60.0).powf(-0.075_514_85)
+        };
+        let blue = if t >= 66.0 {
+            255.0
+        } else if t <= 19.0 {
+            0.0
+        } else {
+            138.517_73 * (t - 10.0).ln() - 305.044_8
+        };
+
+        let alpha = (0.25 + 0.75 * (MAGNITUDE_LIMIT - star.magnitude) / 5.0).clamp(0.25, 1.0);
+        [
+            (red / 255.0).clamp(0.0, 1.0),
+            (green / 255.0).clamp(0.0, 1.0),
+            (blue / 255.0).clamp(0.0, 1.0),
+            alpha,
+        ]
+    }
+}
@@ -1,5 +1,7 @@
 use crate::components::camera::CameraComponent;
-use cgmath::{InnerSpace, SquareMatrix};
+
+use super::mesh::MeshSystem;
+use cgmath::InnerSpace;
 
 pub struct WindowSystem {}
 
@@ -12,71 +14,102 @@ impl WindowSystem {
         globe_radius: f32,
         camera_component: &CameraComponent,
     ) -> Option<(f32, f32)> {
-        let view_proj_matrix =
-            cgmath::Matrix4::from(camera_component.camera_uniform.view_proj_matrix);
-
-        let mouse_pos_clip_near = cgmath::Vector4::new(
-            (position_x * 2.0) / screen_width - 1.0,
-            1.0 - (2.0 * position_y) / screen_height,
-            camera_component.camera.znear,
-            1.0,
+        let (ray_origin, ray_direction) = WindowSystem::cursor_ray(
+            screen_width,
+            screen_height,
+            position_x,
+            position_y,
+            camera_component,
         );
 
-        let mouse_pos_clip_far = cgmath::Vector4::new(
-            (position_x * 2.0) / screen_width - 1.0,
-            1.0 - (2.0 * position_y) / screen_height,
-            camera_component.camera.zfar,
-            1.0,
-        );
+        // intersection
+        let t = WindowSystem::ray_sphere_intersection(
+            ray_origin,
+            ray_direction,
+            cgmath::Vector3::new(0.0, 0.0, 0.0),
+            globe_radius,
+        )?;
+        let intersection_point = ray_origin + ray_direction * t;
+
+        Some(MeshSystem::cartesian_to_lat_lon(intersection_point))
+    }
 
-        // Transform these points to world space
-        let mouse_pos_world_near = (view_proj_matrix).invert().unwrap() * mouse_pos_clip_near;
-        let mouse_pos_world_far = (view_proj_matrix).invert().unwrap() * mouse_pos_clip_far;
+    // Ray from the camera eye through the cursor, in world space. Built from
+    // the camera's frame rather than by unprojecting, which the far plane's
+    // distance makes too imprecise in f32.
+    pub fn cursor_ray(
+        screen_width: f32,
+        screen_height: f32,
+        position_x: f32,
+        position_y: f32,
+        camera_component: &CameraComponent,
+    ) -> (cgmath::Vector3<f32>, cgmath::Vector3<f32>) {
+        let camera = &camera_component.camera;
+        let forward = (camera.target - camera.eye).normalize();
+        let right = forward.cross(camera.up).normalize();
+        let up = right.cross(forward);
 
-        // Convert from homogeneous to Cartesian coordinates
-        let mouse_pos_world_near = mouse_pos_world_near.truncate() / mouse_pos_world_near.w;
-        let mouse_pos_world_far = mouse_pos_world_far.truncate() / mouse_pos_world_far.w;
+        let half_height = (camera.fovy.to_radians() / 2.0).tan();
+        let half_width = half_height * screen_width / screen_height;
+        let ndc_x = (position_x * 2.0) / screen_width - 1.0;
+        let ndc_y = 1.0 - (2.0 * position_y) / screen_height;
 
-        // Create the ray
-        // needs to be a matrix4
-        let ray_origin = cgmath::Vector3::new(
-            camera_component.camera.eye.x,
-            camera_component.camera.eye.y,
-            camera_component.camera.eye.z,
-        );
-        let ray_direction = (mouse_pos_world_far - mouse_pos_world_near).normalize();
+        let ray_origin = cgmath::Vector3::new(camera.eye.x, camera.eye.y, camera.eye.z);
+        let ray_direction =
+            (forward + right * (ndc_x * half_width) + up * (ndc_y * half_height)).normalize();
+
+        (ray_origin, ray_direction)
+    }
 
-        let oc = ray_origin - cgmath::Vector3::new(0.0, 0.0, 0.0);
+    // Distance along the ray to the nearest hit in front of the origin, if any.
+    pub fn ray_sphere_intersection(
+        ray_origin: cgmath::Vector3<f32>,
+        ray_direction: cgmath::Vector3<f32>,
+        center: cgmath::Vector3<f32>,
+        radius: f32,
+    ) -> Option<f32> {
+        let oc = ray_origin - center;
         let a = ray_direction.dot(ray_direction);
         let b = 2.0 * oc.dot(ray_direction);
-        let c = oc.dot(oc) - globe_radius * globe_radius;
+        let c = oc.dot(oc) - radius * radius;
         let discriminant = b * b - 4.0 * a * c;
 
-        // intersection
-        if discriminant >= 0.0 {
-            let discriminant_sqrt = discriminant.sqrt();
-            let t1 = (-b - discriminant_sqrt) / (2.0 * a);
-            let t2 = (-b + discriminant_sqrt) / (2.0 * a);
-
-            let t = if t1 > 0.0 && (t2 < 0.0 || t1 < t2) {
-                t1
-            } else {
-                t2
-            };
-
-            let intersection_point = ray_origin + ray_direction * t;
-
-            // Calculate Latitude (φ) and Longitude (λ)
-            let normalized_point = intersection_point.normalize(); // Make sure it's on the unit sphere
+        if discriminant < 0.0 {
+            // No intersection with the sphere
+            return None;
+        }
 
-            let latitude = normalized_point.y.asin().to_degrees(); // Convert radians to degrees
-            let longitude = normalized_point.z.atan2(normalized_point.x).to_degrees(); // Convert radians to degrees
+        let discriminant_sqrt = discriminant.sqrt();
+        let t1 = (-b - discriminant_sqrt) / (2.0 * a);
+        let t2 = (-b + discriminant_sqrt) / (2.0 * a);
 
-            println!("lat: {:?}, lon: {:?}", latitude, longitude);
-            Some((latitude, longitude))
+        let t = if t1 > 0.0 && (t2 < 0.0 || t1 < t2) {
+            t1
         } else {
-            None
-            // No intersection with the sphere
+            t2
+        };
+        (t > 0.0).then_some(t)
+    }
+
+    // Projects a world-space point to pixel coordinates, or None if it is behind the camera.
+    pub fn world_to_screen(
+        screen_width: f32,
+        screen_height: f32,
+        point: cgmath::Vector3<f32>,
+        camera_component: &CameraComponent,
+    ) -> Option<(f32, f32)> {
+        let view_proj_matrix =
+            cgmath::Matrix4::from(camera_component.camera_uniform.view_proj_matrix);
+        let clip = view_proj_matrix * point.extend(1.0);
+        if clip.w <= 0.0 {
+            return None;
         }
+
+        let ndc_x = clip.x / clip.w;
+        let ndc_y = clip.y / clip.w;
+        Some((
+            (ndc_x + 1.0) * 0.5 * screen_width,
+            (1.0 - ndc_y) * 0.5 * screen_height,
+        ))
     }
 }
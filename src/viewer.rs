@@ -0,0 +1,2664 @@
+use std::{
+    collections::HashSet,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use anise::{constants::celestial_objects, prelude::*};
+use bevy_ecs::{
+    entity::Entity,
+    query::{With, Without},
+    world::{Mut, World},
+};
+use cgmath::InnerSpace;
+use futures_channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use image::RgbaImage;
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{ElementState, KeyboardInput, MouseButton, TouchPhase, VirtualKeyCode, WindowEvent},
+};
+
+use crate::{
+    components::{
+        atmosphere::AtmosphereComponent,
+        billboard::BillboardComponent,
+        body::{CelestialBodyComponent, OrbitComponent},
+        camera::CameraComponent,
+        clouds::CloudComponent,
+        earth::{EarthMap, EarthSurfaceComponent},
+        graticule::{GraticuleComponent, GraticuleLabel, GraticuleLine, GraticuleWindow},
+        imagery::{
+            GeoRectangle, ImageryComponent, ImageryLayerId, ImageryLayerSource, ImageryLayerStyle,
+            TileGrid,
+        },
+        imagery_provider::{ImageryProvider, ImageryProviderComponent, ImageryView},
+        material::MaterialComponent,
+        mesh::MeshComponent,
+        polyline::PolylineComponent,
+        render_pipelines::RenderPipelineComponent,
+        stars::StarfieldComponent,
+    },
+    depth_buffer::Texture,
+    loader::{
+        self, ArchiveLocation, AssetLoader, HttpSource, Kernel, LoadError, MaybeSend, ParsedKernel,
+        Star, TileArchive, TileImageCache, WmsCapabilities, WmtsCapabilities, BODY_TEXTURES_PREFIX,
+    },
+    resources::{
+        clock::SimulationClock,
+        ephemeris::Ephemeris,
+        input::{HeldInputs, InputAction, InputBindings, InputTrigger},
+        solar_system::{BodyPipeline, SolarSystem},
+    },
+    scene::{
+        SceneBillboard, SceneCamera, SceneDocument, SceneError, SceneImageryLayer, ScenePolyline,
+        SceneTime, SCENE_VERSION,
+    },
+    systems::{
+        atmosphere::AtmosphereSystem,
+        billboard::BillboardSystem,
+        body::{BodySystem, BODIES},
+        camera::CameraSystem,
+        clock::ClockSystem,
+        clouds::CloudSystem,
+        earth::EarthSystem,
+        ephemeris::EphemerisSystem,
+        geospatial::{
+            coordinates::{CoordinateFormat, CoordinatesSystem},
+            geodesic::{GeodesicInverse, GeodesicSystem},
+        },
+        graticule::GraticuleSystem,
+        imagery::ImagerySystem,
+        imagery_provider::{ImageryProviderSystem, ImageryRequest},
+        input::InputSystem,
+        material::{MaterialSystem, TextureSource},
+        mesh::MeshSystem,
+        moon::MoonSystem,
+        orientation::OrientationSystem,
+        polyline::PolylineSystem,
+        stars::StarSystem,
+        window::WindowSystem,
+    },
+    WGS84_A,
+};
+
+// where the viewer's own assets live, relative to the loader's mounts
+const EARTH_TEXTURE: &str = "textures/earth.jpg";
+const BILLBOARD_TEXTURE: &str = "textures/billboard.png";
+
+// the textures `load_assets` fetches before any kernels
+const STARTUP_TEXTURE_COUNT: usize = 2;
+
+// a white texel in the usual Earth bump maps is the top of Everest, km
+const BUMP_RELIEF: f32 = 8.848;
+
+// drawn until the real textures arrive
+const EARTH_PLACEHOLDER_COLOR: [u8; 4] = [24, 48, 96, 255];
+const MOON_PLACEHOLDER_COLOR: [u8; 4] = [96, 96, 96, 255];
+
+// the default far plane only reaches past the Moon; Pluto is ~50 AU out
+const SCENE_ZFAR: f32 = 100_000_000.0;
+const SOLAR_SYSTEM_ZFAR: f32 = 10_000_000_000.0;
+
+// orbit paths, drawn fainter than host polylines
+const ORBIT_COLOR: [f32; 4] = [0.5, 0.6, 0.8, 0.6];
+
+// how close, in pixels, the cursor has to be to a polyline to pick it
+const POLYLINE_PICK_TOLERANCE: f32 = 6.0;
+
+// path drawn through the points of a measurement
+const MEASUREMENT_COLOR: [f32; 4] = [1.0, 0.55, 0.1, 1.0];
+
+// graticule lines, with the equator, tropics, polar circles and prime
+// meridian stronger
+const GRATICULE_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.35];
+const GRATICULE_HIGHLIGHT_COLOR: [f32; 4] = [1.0, 0.85, 0.4, 0.8];
+
+/// Something that happened in the scene, queued for the host to drain with
+/// [`Viewer::drain_events`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ViewerEvent {
+    /// The left mouse button was pressed over the globe or an entity.
+    Click {
+        lat: f32,
+        lon: f32,
+        entity: Option<Entity>,
+    },
+    /// The cursor moved to another point of the globe or onto another
+    /// entity. Only queued while [`Viewer::set_hover_events`] is on.
+    Hover {
+        lat: f32,
+        lon: f32,
+        entity: Option<Entity>,
+    },
+    /// An asset finished loading. `completed` counts failed assets too.
+    AssetLoaded {
+        path: String,
+        completed: usize,
+        total: usize,
+    },
+    /// An asset could not be loaded. The viewer keeps running without it.
+    AssetFailed {
+        path: String,
+        error: String,
+        completed: usize,
+        total: usize,
+    },
+    /// A point was added to the measurement, see [`Viewer::set_measure_mode`].
+    Measured(Measurement),
+}
+
+/// Geodesic distances and area between the points clicked in measure mode,
+/// measured on the WGS84 ellipsoid.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Measurement {
+    /// (lat, lon) of each clicked point, in degrees.
+    pub points: Vec<(f32, f32)>,
+    /// The geodesic from each point to the next.
+    pub segments: Vec<GeodesicInverse>,
+    /// Length of the whole path in meters.
+    pub distance: f64,
+    /// Area in square meters of the polygon closing the path, zero below three points.
+    pub area: f64,
+}
+
+enum LoadedAsset {
+    Kernel(ParsedKernel),
+    // the map is always `Day` for anything but the Earth
+    GlobeTexture(Entity, EarthMap, TextureSource),
+    ImageryImage(Entity, ImageryLayerId, TextureSource),
+    ImageryProvider(ImageryLayerId, ImageryProvider),
+    // carries its own result, so the provider hears of failures too
+    ProviderImage(Entity, ImageryView, Result<ProviderImagery, LoadError>),
+    OverlayFrame(Epoch, TextureSource),
+    BillboardImage(RgbaImage),
+    StarCatalog(Vec<Star>),
+}
+
+enum ProviderImagery {
+    // stitched into one image
+    Image(GeoRectangle, TextureSource),
+    // to be drawn from the tile atlas, row by row and keyed by URL
+    Tiles(
+        GeoRectangle,
+        TileGrid,
+        Vec<(String, Option<Arc<RgbaImage>>)>,
+    ),
+}
+
+// an asset path and how loading it went
+type AssetLoad = (String, Result<LoadedAsset, LoadError>);
+
+// `MoonSystem::retexture` and the like
+type RetextureFn = fn(
+    &wgpu::Device,
+    &wgpu::Queue,
+    wgpu::TextureFormat,
+    &CameraComponent,
+    &MeshComponent,
+    TextureSource,
+) -> (MaterialComponent, RenderPipelineComponent);
+
+/// An embeddable globe scene.
+///
+/// The viewer owns the wgpu device, queue and surface it renders with, plus the
+/// bevy `World` holding every entity in the scene. It does not own a window or an
+/// event loop: the host feeds it events through [`Viewer::handle_event`] and drives
+/// frames with [`Viewer::update`] and [`Viewer::render`]. Anything spawned into the
+/// world with a `MeshComponent`, `MaterialComponent` and `RenderPipelineComponent`
+/// is drawn alongside the built-in entities.
+pub struct Viewer {
+    // renderer
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    config: wgpu::SurfaceConfiguration,
+    _depth_texture: Texture,
+    screen_coords: Option<PhysicalPosition<f64>>,
+
+    // geospatial
+    earth_radius: f32,
+
+    // assets
+    billboard_image: RgbaImage,
+    loader: Arc<AssetLoader>,
+    loading_sender: UnboundedSender<AssetLoad>,
+    loading: UnboundedReceiver<AssetLoad>,
+    loading_completed: usize,
+    loading_total: usize,
+    tile_images: Arc<Mutex<TileImageCache>>,
+
+    // scene
+    world: World,
+    earth_entity: Entity,
+    moon_entity: Entity,
+    // the moon mesh starts at the origin, inside the globe, so it's only drawn
+    // once an ephemeris has put it in its orbit
+    moon_placed: bool,
+    camera_entity: Entity,
+    starfield_entity: Entity,
+    atmosphere_entity: Entity,
+    show_atmosphere: bool,
+    cloud_entity: Entity,
+
+    // host interaction
+    events: Vec<ViewerEvent>,
+    place_billboard_on_click: bool,
+    // picking on every cursor move is wasted without a listener
+    hover_events: bool,
+    // what the last `Hover` reported, so an unmoved pointer reports nothing
+    last_hover: Option<(f32, f32, Option<Entity>)>,
+    measure_mode: bool,
+    measurement: Measurement,
+    measurement_entity: Option<Entity>,
+    coordinate_format: CoordinateFormat,
+    graticule_entity: Option<Entity>,
+    // handed to each billboard and polyline in turn; entities can't order
+    // them, as bevy reuses the indices of despawned ones
+    annotations_added: u64,
+}
+
+impl Viewer {
+    /// Builds a viewer on top of an already configured surface, loading the
+    /// default kernels and textures from the default [`AssetLoader`].
+    pub fn new(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        surface: wgpu::Surface,
+        config: wgpu::SurfaceConfiguration,
+    ) -> Self {
+        Viewer::with_loader(
+            device,
+            queue,
+            surface,
+            config,
+            AssetLoader::default(),
+            Kernel::defaults(),
+        )
+    }
+
+    /// Builds a viewer on top of an already configured surface, loading its
+    /// textures and `kernels` through `loader`. The kernels are merged into one
+    /// ephemeris in order, later ones taking precedence where they overlap.
+    ///
+    /// Returns right away: the globe is drawn in placeholder colors until its
+    /// textures arrive, and the moon stays hidden until an ephemeris covering
+    /// it has loaded. Progress and failures are reported as [`ViewerEvent`]s.
+    pub fn with_loader(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        surface: wgpu::Surface,
+        config: wgpu::SurfaceConfiguration,
+        loader: AssetLoader,
+        kernels: Vec<Kernel>,
+    ) -> Self {
+        let mut world = World::new();
+        world.insert_resource(SimulationClock::new());
+        world.insert_resource(InputBindings::default());
+        world.insert_resource(HeldInputs::default());
+
+        // init components via systems
+        let camera_component = CameraSystem::create_camera(&device, config.width, config.height);
+        let (
+            earth_mesh_component,
+            earth_material_component,
+            earth_render_pipeline_component,
+            mut earth_surface_component,
+        ) = EarthSystem::create_earth(
+            &device,
+            &queue,
+            config.format,
+            &camera_component,
+            MaterialSystem::solid_cube_map(EARTH_PLACEHOLDER_COLOR),
+        );
+        let (moon_mesh_component, moon_material_component, moon_render_pipeline_component) =
+            MoonSystem::create_moon(
+                &device,
+                &queue,
+                config.format,
+                &camera_component,
+                MaterialSystem::solid_cube_map(MOON_PLACEHOLDER_COLOR),
+            );
+
+        let (starfield_component, starfield_mesh_component) =
+            StarSystem::create_starfield(&device, &StarSystem::bright_stars());
+        let starfield_material_component = StarSystem::create_material(&device);
+        let starfield_render_pipeline_component = StarSystem::create_render_pipeline(
+            &device,
+            &camera_component,
+            &starfield_material_component,
+            &starfield_mesh_component,
+            &config.format,
+        );
+
+        let atmosphere_components =
+            AtmosphereSystem::create_atmosphere(&device, config.format, &camera_component);
+        let cloud_components = CloudSystem::create_clouds(
+            &device,
+            &queue,
+            config.format,
+            &camera_component,
+            &mut earth_surface_component,
+        );
+
+        // init entities
+        let starfield_entity = world
+            .spawn((
+                starfield_component,
+                starfield_mesh_component,
+                starfield_material_component,
+                starfield_render_pipeline_component,
+            ))
+            .id();
+        let atmosphere_entity = world.spawn(atmosphere_components).id();
+        let cloud_entity = world.spawn(cloud_components).id();
+        let camera_entity = world.spawn(camera_component).id();
+        let earth_entity = world
+            .spawn((
+                earth_mesh_component,
+                earth_material_component,
+                earth_render_pipeline_component,
+                earth_surface_component,
+                ImagerySystem::create_imagery(&device, &queue),
+            ))
+            .id();
+        let moon_entity = world
+            .spawn((
+                moon_mesh_component,
+                moon_material_component,
+                moon_render_pipeline_component,
+                ImagerySystem::create_imagery(&device, &queue),
+            ))
+            .id();
+
+        // wasm only
+        let _depth_texture = Texture::create_depth_texture(&device, &config, "depth texture");
+
+        // sources are only shared across threads natively
+        #[cfg_attr(target_arch = "wasm32", allow(clippy::arc_with_non_send_sync))]
+        let loader = Arc::new(loader);
+        let loading_total = STARTUP_TEXTURE_COUNT + kernels.len();
+        let (loading_sender, loading) = mpsc::unbounded();
+        loader::spawn(Viewer::load_assets(
+            loader.clone(),
+            earth_entity,
+            kernels,
+            loading_sender.clone(),
+        ));
+
+        Self {
+            // wgpu-specific
+            surface,
+            device,
+            queue,
+            config,
+            _depth_texture,
+
+            // screen
+            screen_coords: None,
+
+            // math
+            earth_radius: WGS84_A,
+
+            // assets
+            billboard_image: RgbaImage::from_pixel(1, 1, image::Rgba([255, 255, 255, 255])),
+            loader,
+            loading_sender,
+            loading,
+            loading_completed: 0,
+            loading_total,
+            tile_images: Arc::new(Mutex::new(TileImageCache::default())),
+
+            // visualization
+            world,
+            earth_entity,
+            moon_entity,
+            moon_placed: false,
+            camera_entity,
+            starfield_entity,
+            atmosphere_entity,
+            show_atmosphere: true,
+            cloud_entity,
+
+            events: Vec::new(),
+            place_billboard_on_click: true,
+            hover_events: false,
+            last_hover: None,
+            measure_mode: false,
+            measurement: Measurement::default(),
+            measurement_entity: None,
+            coordinate_format: CoordinateFormat::default(),
+            graticule_entity: None,
+            annotations_added: 0,
+        }
+    }
+
+    // Fetches and decodes everything the built-in scene needs, in the
+    // background, handing each asset back as soon as it is ready.
+    async fn load_assets(
+        loader: Arc<AssetLoader>,
+        earth_entity: Entity,
+        kernels: Vec<Kernel>,
+        sender: UnboundedSender<AssetLoad>,
+    ) {
+        let send = |path: &str, result: Result<LoadedAsset, LoadError>| {
+            // the viewer was dropped, nobody is waiting for the rest
+            sender.unbounded_send((path.to_string(), result)).is_ok()
+        };
+
+        let earth = loader.load_globe_texture(EARTH_TEXTURE).await;
+        if !send(
+            EARTH_TEXTURE,
+            earth.map(|faces| LoadedAsset::GlobeTexture(earth_entity, EarthMap::Day, faces)),
+        ) {
+            return;
+        }
+        let billboard = loader.load_image(BILLBOARD_TEXTURE).await;
+        if !send(
+            BILLBOARD_TEXTURE,
+            billboard.map(LoadedAsset::BillboardImage),
+        ) {
+            return;
+        }
+
+        for kernel in kernels {
+            let parsed = loader.load_kernel(&kernel).await;
+            if !send(&kernel.path, parsed.map(LoadedAsset::Kernel)) {
+                return;
+            }
+        }
+    }
+
+    /// Loads another kernel in the background and merges it into the ephemeris,
+    /// e.g. a spacecraft SPK. Reported like the startup assets.
+    pub fn load_kernel(&mut self, kernel: Kernel) {
+        self.loading_total += 1;
+        let loader = self.loader.clone();
+        let sender = self.loading_sender.clone();
+        loader::spawn(async move {
+            let parsed = loader.load_kernel(&kernel).await;
+            let _ = sender.unbounded_send((kernel.path, parsed.map(LoadedAsset::Kernel)));
+        });
+    }
+
+    /// Retextures the Earth, the Moon or a solar-system body from `path`: an
+    /// equirectangular image covering -180..180 longitude and 90..-90
+    /// latitude, a KTX2 cube map, or a directory of six cube map faces. The
+    /// Moon starts out in a flat color until it is given one.
+    pub fn load_globe_texture(&mut self, entity: Entity, path: &str) {
+        self.spawn_globe_texture(entity, EarthMap::Day, path);
+    }
+
+    /// Loads one layer of the Earth's material, in any of the layouts
+    /// [`Viewer::load_globe_texture`] takes. Bump maps have to be
+    /// equirectangular images; their heights are turned into normals.
+    pub fn load_earth_map(&mut self, map: EarthMap, path: &str) {
+        self.spawn_globe_texture(self.earth_entity, map, path);
+    }
+
+    fn spawn_globe_texture(&mut self, entity: Entity, map: EarthMap, path: &str) {
+        self.loading_total += 1;
+        let path = path.to_string();
+        let loader = self.loader.clone();
+        let sender = self.loading_sender.clone();
+        loader::spawn(async move {
+            let faces = if map == EarthMap::Bump {
+                loader.load_image(&path).await.map(|image| {
+                    let normals = MaterialSystem::bump_to_normal_map(&image, BUMP_RELIEF, WGS84_A);
+                    MaterialSystem::equirectangular_to_cube_map(&normals).into()
+                })
+            } else {
+                loader.load_globe_texture(&path).await
+            };
+            let _ = sender.unbounded_send((
+                path,
+                faces.map(|faces| LoadedAsset::GlobeTexture(entity, map, faces)),
+            ));
+        });
+    }
+
+    /// The entity of the globe called `name`: `"earth"`, `"moon"`, or one of
+    /// the solar-system bodies while they are shown, e.g. `"mars"`.
+    pub fn globe_entity(&mut self, name: &str) -> Option<Entity> {
+        match name {
+            "earth" => Some(self.earth_entity),
+            "moon" => Some(self.moon_entity),
+            _ => self
+                .world
+                .query::<(Entity, &CelestialBodyComponent)>()
+                .iter(&self.world)
+                .find(|(_, body)| body.name == name)
+                .map(|(entity, _)| entity),
+        }
+    }
+
+    /// Puts an imagery layer on top of a globe's stack, drawn over its texture
+    /// once the image at `path` has loaded. The image is stretched over
+    /// `style.rectangle` as an equirectangular image. At most
+    /// [`MAX_IMAGERY_LAYERS`](crate::components::imagery::MAX_IMAGERY_LAYERS)
+    /// are drawn at once, the topmost shown ones. Returns None if `entity` is
+    /// not a globe.
+    pub fn add_imagery_layer(
+        &mut self,
+        entity: Entity,
+        path: &str,
+        style: ImageryLayerStyle,
+    ) -> Option<ImageryLayerId> {
+        let mut imagery = self.world.get_mut::<ImageryComponent>(entity)?;
+        let source = ImageryLayerSource::Image {
+            path: path.to_string(),
+        };
+        let id = ImagerySystem::add_layer(&mut imagery, source, style);
+
+        self.loading_total += 1;
+        let path = path.to_string();
+        let loader = self.loader.clone();
+        let sender = self.loading_sender.clone();
+        loader::spawn(async move {
+            let image = loader.load_image(&path).await;
+            let _ = sender.unbounded_send((
+                path,
+                image.map(|image| LoadedAsset::ImageryImage(entity, id, image.into())),
+            ));
+        });
+        Some(id)
+    }
+
+    /// Restyles a layer: opacity, color adjustments, visibility and extent.
+    pub fn set_imagery_layer_style(
+        &mut self,
+        entity: Entity,
+        id: ImageryLayerId,
+        style: ImageryLayerStyle,
+    ) -> bool {
+        let Some(mut imagery) = self.world.get_mut::<ImageryComponent>(entity) else {
+            return false;
+        };
+        ImagerySystem::set_layer_style(&self.device, &self.queue, &mut imagery, id, style)
+    }
+
+    /// Moves a layer to `index` in its stack, 0 being the bottom.
+    pub fn move_imagery_layer(&mut self, entity: Entity, id: ImageryLayerId, index: usize) -> bool {
+        let Some(mut imagery) = self.world.get_mut::<ImageryComponent>(entity) else {
+            return false;
+        };
+        ImagerySystem::move_layer(&self.device, &self.queue, &mut imagery, id, index)
+    }
+
+    pub fn remove_imagery_layer(&mut self, entity: Entity, id: ImageryLayerId) -> bool {
+        if entity == self.earth_entity {
+            // and stop whatever was streaming into it
+            let providers: Vec<Entity> = self
+                .world
+                .query::<(Entity, &ImageryProviderComponent)>()
+                .iter(&self.world)
+                .filter(|(_, provider)| provider.layer == id)
+                .map(|(provider, _)| provider)
+                .collect();
+            for provider in providers {
+                self.world.despawn(provider);
+            }
+        }
+        let Some(mut imagery) = self.world.get_mut::<ImageryComponent>(entity) else {
+            return false;
+        };
+        ImagerySystem::remove_layer(&self.device, &self.queue, &mut imagery, id)
+    }
+
+    /// A globe's imagery layers and their styles, bottom first.
+    pub fn imagery_layers(&self, entity: Entity) -> Vec<(ImageryLayerId, ImageryLayerStyle)> {
+        self.world
+            .get::<ImageryComponent>(entity)
+            .map(|imagery| {
+                imagery
+                    .layers
+                    .iter()
+                    .map(|layer| (layer.id, layer.style))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// How much memory streamed imagery tiles may take: `gpu_bytes` in the
+    /// Earth's tile atlas, mips included, and `memory_bytes` of decoded tiles
+    /// kept to be uploaded again without fetching them. Past either, the least
+    /// recently used tiles are dropped. Changing the GPU budget remakes the
+    /// atlas, so tiled layers are fetched again.
+    pub fn set_tile_cache_budget(&mut self, gpu_bytes: u64, memory_bytes: usize) {
+        self.tile_images.lock().unwrap().set_budget(memory_bytes);
+
+        let mut imagery = self
+            .world
+            .get_mut::<ImageryComponent>(self.earth_entity)
+            .unwrap();
+        if imagery.atlas_budget == gpu_bytes {
+            return;
+        }
+        ImagerySystem::set_atlas_budget(&self.device, &self.queue, &mut imagery, gpu_bytes);
+        for mut provider in self
+            .world
+            .query::<&mut ImageryProviderComponent>()
+            .iter_mut(&mut self.world)
+        {
+            provider.shown = None;
+        }
+    }
+
+    /// Drapes a layer from a WMS server over the Earth, as a new imagery layer
+    /// drawn once the GetCapabilities document at `capabilities_url` has been
+    /// read. It is requested in EPSG:4326 where the server offers it and
+    /// EPSG:3857 otherwise, and fetched again for the region in view as the
+    /// camera moves, which keeps replacing `style.rectangle`.
+    pub fn add_wms_imagery(
+        &mut self,
+        capabilities_url: &str,
+        layer: &str,
+        style: ImageryLayerStyle,
+    ) -> ImageryLayerId {
+        let source = ImageryLayerSource::Wms {
+            capabilities_url: capabilities_url.to_string(),
+            layer: layer.to_string(),
+        };
+        let (url, layer) = (capabilities_url.to_string(), layer.to_string());
+        self.add_provider_imagery(source, style, async move {
+            let bytes = HttpSource::fetch(url.clone()).await?;
+            let capabilities = WmsCapabilities::parse(&bytes, &url)?;
+            ImageryProviderSystem::wms_provider(&capabilities, &layer)
+                .map_err(|message| LoadError::Decode { path: url, message })
+        })
+    }
+
+    /// Like [`Viewer::add_wms_imagery`] for a WMTS layer, fetched as tiles from
+    /// the level of its tile matrix set that best matches the screen.
+    pub fn add_wmts_imagery(
+        &mut self,
+        capabilities_url: &str,
+        layer: &str,
+        style: ImageryLayerStyle,
+    ) -> ImageryLayerId {
+        let source = ImageryLayerSource::Wmts {
+            capabilities_url: capabilities_url.to_string(),
+            layer: layer.to_string(),
+        };
+        let (url, layer) = (capabilities_url.to_string(), layer.to_string());
+        self.add_provider_imagery(source, style, async move {
+            let bytes = HttpSource::fetch(url.clone()).await?;
+            let capabilities = WmtsCapabilities::parse(&bytes, &url)?;
+            ImageryProviderSystem::wmts_provider(&capabilities, &layer)
+                .map_err(|message| LoadError::Decode { path: url, message })
+        })
+    }
+
+    /// Drapes the raster tiles of an MBTiles or PMTiles archive over the Earth,
+    /// read straight out of the file as the camera moves, like
+    /// [`Viewer::add_wmts_imagery`]. `path` is a local file natively, and on the
+    /// web a URL, relative to the page or absolute, on a server that honors
+    /// range requests, like the one in `server/`.
+    pub fn add_archive_imagery(&mut self, path: &str, style: ImageryLayerStyle) -> ImageryLayerId {
+        let location = ArchiveLocation::parse(path);
+        let source = ImageryLayerSource::Archive {
+            path: path.to_string(),
+        };
+        self.add_provider_imagery(source, style, async move {
+            let archive = TileArchive::open(location.clone()).await?;
+            ImageryProviderSystem::archive_provider(archive).map_err(|message| LoadError::Decode {
+                path: location.to_string(),
+                message,
+            })
+        })
+    }
+
+    // Adds the layer `provider` will fill once it resolves. The source's URL or
+    // path names it in loading events.
+    fn add_provider_imagery(
+        &mut self,
+        source: ImageryLayerSource,
+        style: ImageryLayerStyle,
+        provider: impl Future<Output = Result<ImageryProvider, LoadError>> + MaybeSend + 'static,
+    ) -> ImageryLayerId {
+        let mut imagery = self
+            .world
+            .get_mut::<ImageryComponent>(self.earth_entity)
+            .unwrap();
+        let path = source.location().to_string();
+        let layer = ImagerySystem::add_layer(&mut imagery, source, style);
+
+        self.loading_total += 1;
+        let sender = self.loading_sender.clone();
+        loader::spawn(async move {
+            let provider = provider.await;
+            let _ = sender.unbounded_send((
+                path,
+                provider.map(|provider| LoadedAsset::ImageryProvider(layer, provider)),
+            ));
+        });
+        layer
+    }
+
+    // Asks providers whose layer no longer covers the view for one that does.
+    fn update_imagery_providers(&mut self) {
+        let mut providers = self
+            .world
+            .query::<(Entity, &mut ImageryProviderComponent)>();
+        if providers.iter(&self.world).next().is_none() {
+            return;
+        }
+        let Some(view) = self
+            .world
+            .get::<CameraComponent>(self.camera_entity)
+            .and_then(|camera_component| {
+                ImageryProviderSystem::visible_view(
+                    self.config.width as f32,
+                    self.config.height as f32,
+                    self.earth_radius,
+                    camera_component,
+                )
+            })
+        else {
+            return;
+        };
+
+        let mut requests = Vec::new();
+        for (entity, mut provider) in providers.iter_mut(&mut self.world) {
+            let Some(view) = ImageryProviderSystem::next_request(&provider, &view) else {
+                continue;
+            };
+            match ImageryProviderSystem::plan(&provider.provider, &view) {
+                Some(request) => {
+                    provider.pending = true;
+                    requests.push((entity, view, request));
+                }
+                // nothing there to show
+                None => provider.shown = Some(view),
+            }
+        }
+
+        for (entity, view, request) in requests {
+            self.loading_total += 1;
+            let path = request.pieces[0].url.clone();
+            let sender = self.loading_sender.clone();
+            let tile_images = self.tile_images.clone();
+            loader::spawn(async move {
+                let image = Viewer::fetch_imagery(request, tile_images).await;
+                let _ = sender
+                    .unbounded_send((path, Ok(LoadedAsset::ProviderImage(entity, view, image))));
+            });
+        }
+    }
+
+    // Fetches a request's pieces concurrently and stitches them together,
+    // or for tiles leaves that to the atlas. Pieces that fail are left
+    // transparent, unless they all do. Tiles are looked up in `tile_images`
+    // first.
+    async fn fetch_imagery(
+        request: ImageryRequest,
+        tile_images: Arc<Mutex<TileImageCache>>,
+    ) -> Result<ProviderImagery, LoadError> {
+        let pieces = request.pieces.iter().map(|piece| {
+            let url = piece.url.clone();
+            let tile = piece.tile.zip(request.archive.clone());
+            let tile_images = request.grid.map(|_| tile_images.clone());
+            async move {
+                if let Some(tile_images) = &tile_images {
+                    if let Some(image) = tile_images.lock().unwrap().get(&url) {
+                        return Ok(Some(image));
+                    }
+                }
+                let bytes = match tile {
+                    Some(((zoom, x, y), archive)) => archive.tile(zoom, x, y).await,
+                    None => HttpSource::fetch(url.clone()).await.map(Some),
+                };
+                // archives leave out tiles with nothing in them
+                let image = bytes.and_then(|bytes| {
+                    bytes
+                        .map(|bytes| {
+                            image::load_from_memory(&bytes)
+                                .map(|image| Arc::new(image.to_rgba8()))
+                                .map_err(|e| LoadError::Decode {
+                                    path: url.clone(),
+                                    message: e.to_string(),
+                                })
+                        })
+                        .transpose()
+                });
+                if let (Ok(Some(image)), Some(tile_images)) = (&image, tile_images) {
+                    tile_images.lock().unwrap().insert(url, image.clone());
+                }
+                image
+            }
+        });
+
+        let mut images = Vec::with_capacity(request.pieces.len());
+        let mut error = None;
+        for piece in futures::future::join_all(pieces).await {
+            match piece {
+                Ok(image) => images.push(image),
+                Err(e) => {
+                    error.get_or_insert(e);
+                    images.push(None);
+                }
+            }
+        }
+        if let (true, Some(error)) = (images.iter().all(Option::is_none), error) {
+            return Err(error);
+        }
+        if let Some(grid) = request.grid {
+            let rectangle = ImageryProviderSystem::rectangle(&request);
+            let keys = request.pieces.into_iter().map(|piece| piece.url);
+            return Ok(ProviderImagery::Tiles(
+                rectangle,
+                grid,
+                keys.zip(images).collect(),
+            ));
+        }
+        let images = images
+            .into_iter()
+            .map(|image| image.map(Arc::unwrap_or_clone))
+            .collect();
+        let (rectangle, image) = ImageryProviderSystem::assemble(&request, images);
+        Ok(ProviderImagery::Image(rectangle, image.into()))
+    }
+
+    /// Adds a frame to the Earth's time-series overlay, e.g. a weather or
+    /// sea-surface temperature image, shown around `epoch` over the day map.
+    /// The globe shows the frame for the simulation time, cross-fading
+    /// between adjacent frames. Frames take any of the layouts
+    /// [`Viewer::load_globe_texture`] does; transparent pixels show the day
+    /// map through.
+    ///
+    /// Every frame stays on the GPU until [`Viewer::clear_overlay`], as a
+    /// full mipmapped cube map: six faces plus a third more for the mips,
+    /// about 32 MiB a frame with 1024-pixel RGBA faces. For long series,
+    /// use coarse frames or clear and re-add the ones near the simulation
+    /// time.
+    pub fn add_overlay_frame(&mut self, epoch: Epoch, path: &str) {
+        self.loading_total += 1;
+        let path = path.to_string();
+        let loader = self.loader.clone();
+        let sender = self.loading_sender.clone();
+        loader::spawn(async move {
+            let frame = loader.load_globe_texture(&path).await;
+            let _ = sender.unbounded_send((
+                path,
+                frame.map(|frame| LoadedAsset::OverlayFrame(epoch, frame)),
+            ));
+        });
+    }
+
+    /// Removes every overlay frame.
+    pub fn clear_overlay(&mut self) {
+        EarthSystem::clear_overlay(
+            &mut self
+                .world
+                .get_mut::<EarthSurfaceComponent>(self.earth_entity)
+                .unwrap(),
+        );
+        self.rebuild_earth_material();
+    }
+
+    /// How opaque the overlay is over the day map, from 0 to 1. Defaults to 1.
+    pub fn set_overlay_opacity(&mut self, opacity: f32) {
+        EarthSystem::set_overlay_opacity(
+            &self.queue,
+            &mut self
+                .world
+                .get_mut::<EarthSurfaceComponent>(self.earth_entity)
+                .unwrap(),
+            opacity,
+        );
+    }
+
+    /// Lights the Earth from the Sun, with relief, ocean glint and city
+    /// lights from whichever maps are loaded. On by default; when off the day
+    /// map is shown everywhere.
+    pub fn set_earth_lighting(&mut self, enabled: bool) {
+        EarthSystem::set_lighting(
+            &self.queue,
+            &mut self
+                .world
+                .get_mut::<EarthSurfaceComponent>(self.earth_entity)
+                .unwrap(),
+            enabled,
+        );
+    }
+
+    /// Shows or hides the Earth's atmosphere.
+    pub fn set_atmosphere_visible(&mut self, visible: bool) {
+        self.show_atmosphere = visible;
+    }
+
+    /// Shows or hides the cloud layer, along with its shadows. The clouds
+    /// come from the [`EarthMap::Clouds`] map and are clear until one loads.
+    pub fn set_clouds_visible(&mut self, visible: bool) {
+        self.cloud_component_mut().visible = visible;
+    }
+
+    /// Shows or hides the latitude/longitude grid. Its spacing follows the
+    /// camera's altitude, from 30° down to one arc-minute.
+    pub fn set_graticule_visible(&mut self, visible: bool) {
+        match (visible, self.graticule_entity) {
+            (true, None) => {
+                let highlight_entity = self.spawn_graticule_lines(GRATICULE_HIGHLIGHT_COLOR);
+                let entity = self.spawn_graticule_lines(GRATICULE_COLOR);
+                self.world.entity_mut(entity).insert(GraticuleComponent {
+                    spacing: 0.0,
+                    window: GraticuleWindow::default(),
+                    lines: Vec::new(),
+                    labels: Vec::new(),
+                    highlight_entity,
+                });
+                self.graticule_entity = Some(entity);
+                self.update_graticule();
+            }
+            (false, Some(entity)) => {
+                if let Some(graticule) = self.world.get::<GraticuleComponent>(entity) {
+                    let highlight_entity = graticule.highlight_entity;
+                    self.world.despawn(highlight_entity);
+                }
+                self.world.despawn(entity);
+                self.graticule_entity = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Where to draw the graticule's coordinate labels this frame. Empty while
+    /// the graticule is hidden.
+    pub fn graticule_labels(&self) -> &[GraticuleLabel] {
+        self.graticule_entity
+            .and_then(|entity| self.world.get::<GraticuleComponent>(entity))
+            .map_or(&[], |graticule| graticule.labels.as_slice())
+    }
+
+    // an empty set of lines in one color, filled in by `update_graticule`
+    fn spawn_graticule_lines(&mut self, color: [f32; 4]) -> Entity {
+        let camera_component = self
+            .world
+            .get::<CameraComponent>(self.camera_entity)
+            .unwrap();
+        let mesh = PolylineSystem::create_polyline_strips_mesh(&self.device, &[]);
+        let material = PolylineSystem::create_polyline_material(&self.device, color);
+        let render_pipeline = PolylineSystem::create_render_pipeline(
+            &self.device,
+            camera_component,
+            &material,
+            &mesh,
+            &self.config.format,
+        );
+        self.world.spawn((mesh, material, render_pipeline)).id()
+    }
+
+    // Lays the lines out again once the camera has moved far enough to need a
+    // new spacing or window, and places the labels for the current view.
+    fn update_graticule(&mut self) {
+        let Some(entity) = self.graticule_entity else {
+            return;
+        };
+        let camera = &self
+            .world
+            .get::<CameraComponent>(self.camera_entity)
+            .unwrap()
+            .camera;
+        let eye = cgmath::Vector3::new(camera.eye.x, camera.eye.y, camera.eye.z);
+        let (spacing, window) = GraticuleSystem::layout(eye, camera.fovy, self.earth_radius);
+
+        let mut graticule = self.world.get_mut::<GraticuleComponent>(entity).unwrap();
+        if graticule.spacing != spacing || graticule.window != window {
+            let (lines, highlights) = GraticuleSystem::lines(window, spacing, self.earth_radius);
+            let strips = |lines: &[GraticuleLine]| {
+                PolylineSystem::create_polyline_strips_mesh(
+                    &self.device,
+                    &lines
+                        .iter()
+                        .map(|line| line.positions.as_slice())
+                        .collect::<Vec<_>>(),
+                )
+            };
+            let (mesh, highlight_mesh) = (strips(&lines), strips(&highlights));
+            graticule.spacing = spacing;
+            graticule.window = window;
+            graticule.lines = lines;
+
+            let highlight_entity = graticule.highlight_entity;
+            self.world.entity_mut(entity).insert(mesh);
+            self.world
+                .entity_mut(highlight_entity)
+                .insert(highlight_mesh);
+        }
+
+        let camera_component = self
+            .world
+            .get::<CameraComponent>(self.camera_entity)
+            .unwrap();
+        let labels = GraticuleSystem::labels(
+            &self.world.get::<GraticuleComponent>(entity).unwrap().lines,
+            camera_component,
+            self.config.width as f32,
+            self.config.height as f32,
+        );
+        self.world
+            .get_mut::<GraticuleComponent>(entity)
+            .unwrap()
+            .labels = labels;
+    }
+
+    /// Whether the clouds shade the ground beneath them, on by default.
+    pub fn set_cloud_shadows(&mut self, enabled: bool) {
+        self.cloud_component_mut().shadows = enabled;
+    }
+
+    /// How fast the clouds drift east over the ground, in degrees of
+    /// longitude per simulated hour. Negative values drift west.
+    pub fn set_cloud_drift(&mut self, degrees_per_hour: f64) {
+        self.cloud_component_mut().drift_rate = CloudSystem::drift_rate(degrees_per_hour);
+    }
+
+    fn cloud_component_mut(&mut self) -> Mut<'_, CloudComponent> {
+        self.world
+            .get_mut::<CloudComponent>(self.cloud_entity)
+            .unwrap()
+    }
+
+    /// Replaces the built-in bright stars with the catalog at `path`, either a
+    /// `ra,dec,vmag,bv` CSV or the Yale Bright Star Catalogue's `catalog` file.
+    /// Stars fainter than magnitude 6.5 are left out.
+    pub fn load_star_catalog(&mut self, path: &str) {
+        self.loading_total += 1;
+        let path = path.to_string();
+        let loader = self.loader.clone();
+        let sender = self.loading_sender.clone();
+        loader::spawn(async move {
+            let stars = loader.load_star_catalog(&path).await;
+            let _ = sender.unbounded_send((path, stars.map(LoadedAsset::StarCatalog)));
+        });
+    }
+
+    /// Spawns the Sun and planets at their ephemeris positions, or updates the
+    /// options if solar-system mode is already on.
+    ///
+    /// `radius_scale` exaggerates the planets' IAU radii, since at true scale
+    /// they are far below a pixel. The Sun is always drawn at true size. Each body is drawn in a flat color
+    /// until its map at `textures/bodies/<name>.jpg` loads, which
+    /// `AssetLoader::with_base_url` fetches from `<base_url>/bodies/<name>.jpg`;
+    /// bodies without one keep the color and report an `AssetFailed` event.
+    ///
+    /// The scene stays Earth-fixed: the globe doesn't turn, and the Sun,
+    /// planets and orbits wheel around it with the Moon and stars once a
+    /// sidereal day, with the Sun over the longitudes it lights.
+    pub fn enable_solar_system(&mut self, radius_scale: f32, show_orbits: bool) {
+        if let Some(mut solar_system) = self.world.get_resource_mut::<SolarSystem>() {
+            solar_system.radius_scale = radius_scale;
+            if solar_system.show_orbits != show_orbits {
+                solar_system.show_orbits = show_orbits;
+                solar_system.orbits_built = false;
+                self.despawn_all::<OrbitComponent>();
+            }
+            return;
+        }
+
+        for body in BODIES.iter() {
+            let (body_component, body_mesh, body_material) =
+                BodySystem::create_body(&self.device, &self.queue, body);
+
+            if !self.world.contains_resource::<BodyPipeline>() {
+                let body_render_pipeline = BodySystem::create_render_pipeline(
+                    &self.device,
+                    self.config.format,
+                    self.world
+                        .get::<CameraComponent>(self.camera_entity)
+                        .unwrap(),
+                    &body_mesh,
+                    &body_material,
+                );
+                self.world
+                    .insert_resource(BodyPipeline(body_render_pipeline));
+            }
+
+            let imagery = ImagerySystem::create_imagery(&self.device, &self.queue);
+            let entity = self
+                .world
+                .spawn((body_component, body_mesh, body_material, imagery))
+                .id();
+
+            self.load_globe_texture(entity, &format!("{BODY_TEXTURES_PREFIX}/{}.jpg", body.name));
+        }
+
+        self.world.insert_resource(SolarSystem {
+            radius_scale,
+            show_orbits,
+            orbits_built: false,
+        });
+        self.set_zfar(SOLAR_SYSTEM_ZFAR);
+    }
+
+    /// Despawns the Sun, planets and orbits.
+    pub fn disable_solar_system(&mut self) {
+        self.despawn_all::<CelestialBodyComponent>();
+        self.despawn_all::<OrbitComponent>();
+        self.world.remove_resource::<SolarSystem>();
+        self.set_zfar(SCENE_ZFAR);
+    }
+
+    pub fn is_solar_system_enabled(&self) -> bool {
+        self.world.contains_resource::<SolarSystem>()
+    }
+
+    fn despawn_all<T: bevy_ecs::component::Component>(&mut self) {
+        let entities: Vec<Entity> = self
+            .world
+            .query_filtered::<Entity, With<T>>()
+            .iter(&self.world)
+            .collect();
+        for entity in entities {
+            self.world.despawn(entity);
+        }
+    }
+
+    fn set_zfar(&mut self, zfar: f32) {
+        if let Some(mut camera_component) =
+            self.world.get_mut::<CameraComponent>(self.camera_entity)
+        {
+            camera_component.camera.zfar = zfar;
+        }
+    }
+
+    /// Creates the viewer on an HTML canvas, requesting its own adapter and device.
+    #[cfg(target_arch = "wasm32")]
+    pub async fn from_canvas(
+        canvas: web_sys::HtmlCanvasElement,
+        loader: AssetLoader,
+        kernels: Vec<Kernel>,
+    ) -> Self {
+        let size = PhysicalSize::new(canvas.width(), canvas.height());
+        let instance = Viewer::create_instance();
+        let surface = instance.create_surface_from_canvas(canvas).unwrap();
+        let adapter = Viewer::create_adapter(&instance, &surface).await;
+        let (device, queue) = Viewer::create_device_and_queue(&adapter).await;
+        let config = Viewer::configure_surface(&adapter, &device, &surface, size);
+
+        Viewer::with_loader(device, queue, surface, config, loader, kernels)
+    }
+
+    pub fn create_instance() -> wgpu::Instance {
+        wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            dx12_shader_compiler: wgpu::Dx12Compiler::Dxc {
+                dxil_path: None,
+                dxc_path: None,
+            },
+        })
+    }
+
+    pub async fn create_adapter(
+        instance: &wgpu::Instance,
+        surface: &wgpu::Surface,
+    ) -> wgpu::Adapter {
+        instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: Some(surface),
+                force_fallback_adapter: false,
+            })
+            .await
+            .unwrap()
+    }
+
+    pub async fn create_device_and_queue(adapter: &wgpu::Adapter) -> (wgpu::Device, wgpu::Queue) {
+        adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    // whichever compressed formats the adapter has, for KTX2
+                    features: adapter.features()
+                        & (wgpu::Features::TEXTURE_COMPRESSION_BC
+                            | wgpu::Features::TEXTURE_COMPRESSION_ETC2
+                            | wgpu::Features::TEXTURE_COMPRESSION_ASTC),
+                    // WebGL doesn't support all of wgpu's features, so if
+                    // we're building for the web we'll have to disable some.
+                    limits: wgpu::Limits::downlevel_webgl2_defaults(),
+                    label: None,
+                },
+                None, // Trace path
+            )
+            .await
+            .unwrap()
+    }
+
+    /// Picks an sRGB format for the surface and configures it for `size`.
+    pub fn configure_surface(
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        surface: &wgpu::Surface,
+        size: PhysicalSize<u32>,
+    ) -> wgpu::SurfaceConfiguration {
+        let surface_caps = surface.get_capabilities(adapter);
+        // Shader code in this tutorial assumes an sRGB surface texture. Using a different
+        // one will result all the colors coming out darker. If you want to support non
+        // sRGB surfaces, you'll need to account for that when drawing to the frame.
+        let surface_format = surface_caps
+            .formats
+            .iter()
+            .copied()
+            .find(|f| f.is_srgb())
+            .unwrap_or(surface_caps.formats[0]);
+
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: surface_format,
+            width: size.width,
+            height: size.height,
+            present_mode: surface_caps.present_modes[0],
+            alpha_mode: surface_caps.alpha_modes[0],
+            view_formats: vec![],
+        };
+        surface.configure(device, &config);
+        config
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    pub fn surface_format(&self) -> wgpu::TextureFormat {
+        self.config.format
+    }
+
+    pub fn size(&self) -> PhysicalSize<u32> {
+        PhysicalSize::new(self.config.width, self.config.height)
+    }
+
+    pub fn camera_entity(&self) -> Entity {
+        self.camera_entity
+    }
+
+    pub fn earth_entity(&self) -> Entity {
+        self.earth_entity
+    }
+
+    pub fn moon_entity(&self) -> Entity {
+        self.moon_entity
+    }
+
+    pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
+        if new_size.width > 0 && new_size.height > 0 {
+            self.config.width = new_size.width;
+            self.config.height = new_size.height;
+            self.surface.configure(&self.device, &self.config);
+            self._depth_texture =
+                Texture::create_depth_texture(&self.device, &self.config, "depth texture");
+
+            if let Some(mut camera_component) =
+                self.world.get_mut::<CameraComponent>(self.camera_entity)
+            {
+                camera_component.camera.aspect = new_size.width as f32 / new_size.height as f32;
+            }
+        }
+    }
+
+    /// Whether selecting the globe, with a left click or a tap unless rebound,
+    /// drops a billboard. Defaults to `true`.
+    pub fn set_place_billboard_on_click(&mut self, enabled: bool) {
+        self.place_billboard_on_click = enabled;
+    }
+
+    /// Whether moving the cursor queues [`ViewerEvent::Hover`] events, which
+    /// costs a pick against the globe and every entity on each move. Defaults
+    /// to `false`.
+    pub fn set_hover_events(&mut self, enabled: bool) {
+        self.hover_events = enabled;
+        self.last_hover = None;
+    }
+
+    /// While on, selecting the globe adds points to the measurement instead
+    /// of dropping billboards, each one queuing a [`ViewerEvent::Measured`].
+    /// Turning it on starts a new measurement; turning it off leaves the last
+    /// one drawn until [`Viewer::clear_measurement`].
+    pub fn set_measure_mode(&mut self, enabled: bool) {
+        if enabled && !self.measure_mode {
+            self.clear_measurement();
+        }
+        self.measure_mode = enabled;
+    }
+
+    /// The points measured so far and the distances and area between them.
+    pub fn measurement(&self) -> &Measurement {
+        &self.measurement
+    }
+
+    /// Forgets the measured points and removes their path from the globe.
+    pub fn clear_measurement(&mut self) {
+        self.measurement = Measurement::default();
+        if let Some(entity) = self.measurement_entity.take() {
+            self.world.despawn(entity);
+        }
+    }
+
+    fn add_measurement_point(&mut self, lat: f32, lon: f32) {
+        let measurement = &mut self.measurement;
+        if let Some(&(last_lat, last_lon)) = measurement.points.last() {
+            let segment = GeodesicSystem::inverse(
+                (last_lat as f64, last_lon as f64),
+                (lat as f64, lon as f64),
+            );
+            measurement.distance += segment.distance;
+            measurement.segments.push(segment);
+        }
+        measurement.points.push((lat, lon));
+
+        let vertices: Vec<(f64, f64)> = measurement
+            .points
+            .iter()
+            .map(|&(lat, lon)| (lat as f64, lon as f64))
+            .collect();
+        measurement.area = GeodesicSystem::polygon_area(&vertices);
+
+        if let Some(entity) = self.measurement_entity.take() {
+            self.world.despawn(entity);
+        }
+        if self.measurement.points.len() >= 2 {
+            let path = self.measurement.points.clone();
+            self.measurement_entity = Some(self.add_polyline(path, MEASUREMENT_COLOR));
+        }
+        self.events
+            .push(ViewerEvent::Measured(self.measurement.clone()));
+    }
+
+    /// How [`Viewer::format_position`] and [`Viewer::cursor_position`] write
+    /// positions out. Defaults to decimal degrees.
+    pub fn set_coordinate_format(&mut self, format: CoordinateFormat) {
+        self.coordinate_format = format;
+    }
+
+    pub fn coordinate_format(&self) -> CoordinateFormat {
+        self.coordinate_format
+    }
+
+    /// A latitude and longitude written out in the viewer's coordinate format.
+    pub fn format_position(&self, lat: f32, lon: f32) -> String {
+        CoordinatesSystem::format(lat as f64, lon as f64, self.coordinate_format)
+    }
+
+    /// The position of the globe under the cursor in the viewer's coordinate
+    /// format, if the cursor is over the globe.
+    pub fn cursor_position(&self) -> Option<String> {
+        self.pick().map(|(lat, lon)| self.format_position(lat, lon))
+    }
+
+    /// Takes every event queued since the last call.
+    pub fn drain_events(&mut self) -> Vec<ViewerEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Feeds a window event to the scene. Returns `true` if the event was consumed.
+    pub fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.pointer_moved(position.x, position.y);
+                false
+            }
+            WindowEvent::MouseInput { button, state, .. } => {
+                self.pointer_button(*button, *state == ElementState::Pressed)
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => self.key_input(*keycode, *state == ElementState::Pressed),
+            WindowEvent::Touch(touch) => {
+                self.pointer_moved(touch.location.x, touch.location.y);
+                match touch.phase {
+                    TouchPhase::Started => self.touch_input(true),
+                    TouchPhase::Ended | TouchPhase::Cancelled => self.touch_input(false),
+                    TouchPhase::Moved => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// Cursor position in physical pixels relative to the surface.
+    pub fn pointer_moved(&mut self, x: f64, y: f64) {
+        self.screen_coords = Some(PhysicalPosition::new(x, y));
+        if !self.hover_events {
+            return;
+        }
+
+        let Some((lat, lon)) = self.pick() else {
+            self.last_hover = None;
+            return;
+        };
+        let entity = self.pick_entity();
+        if self.last_hover != Some((lat, lon, entity)) {
+            self.last_hover = Some((lat, lon, entity));
+            self.events.push(ViewerEvent::Hover { lat, lon, entity });
+        }
+    }
+
+    /// Returns `true` if the button press was consumed.
+    pub fn pointer_button(&mut self, button: MouseButton, pressed: bool) -> bool {
+        self.input(InputTrigger::Mouse(button), pressed)
+    }
+
+    /// Returns `true` if the key is bound to an action.
+    pub fn key_input(&mut self, keycode: VirtualKeyCode, pressed: bool) -> bool {
+        self.input(InputTrigger::Key(keycode), pressed)
+    }
+
+    /// A finger touching or leaving the surface at the pointer position, see
+    /// [`Viewer::pointer_moved`]. Every finger is the one
+    /// [`InputTrigger::Touch`], with no drag or pinch gestures. Returns
+    /// `true` if the touch was consumed.
+    pub fn touch_input(&mut self, pressed: bool) -> bool {
+        self.input(InputTrigger::Touch, pressed)
+    }
+
+    /// Performs the actions bound to `trigger`. Held actions start on the
+    /// first press of any input bound to them and stop once all of those
+    /// inputs are released; the others act on the point under the pointer
+    /// when pressed. Returns `true` if any of them was consumed.
+    pub fn input(&mut self, trigger: InputTrigger, pressed: bool) -> bool {
+        let actions = InputSystem::actions(self.world.resource::<InputBindings>(), trigger);
+        let mut handled = false;
+        for action in actions {
+            if action.is_held() {
+                let mut held = self.world.resource_mut::<HeldInputs>();
+                let changed = if pressed {
+                    InputSystem::hold(&mut held, trigger, action)
+                } else {
+                    InputSystem::release(&mut held, trigger, action)
+                };
+                // otherwise another input already holds it, or still does
+                handled |= !changed || self.perform(action, pressed);
+            } else {
+                handled |= self.perform(action, pressed);
+            }
+        }
+        handled
+    }
+
+    /// Which inputs trigger which actions. By default W/S and the up and down
+    /// arrows zoom, A/D and the left and right arrows orbit, and a left click
+    /// or a tap selects.
+    pub fn input_bindings(&self) -> &InputBindings {
+        self.world.resource::<InputBindings>()
+    }
+
+    /// Replaces the whole binding table, releasing any held action.
+    pub fn set_input_bindings(&mut self, bindings: InputBindings) {
+        self.release_held_actions();
+        self.world.insert_resource(bindings);
+    }
+
+    /// Makes `trigger` perform `action` too.
+    pub fn bind_input(&mut self, trigger: InputTrigger, action: InputAction) {
+        InputSystem::bind(
+            &mut self.world.resource_mut::<InputBindings>(),
+            trigger,
+            action,
+        );
+    }
+
+    /// Makes `trigger` perform nothing, releasing what only it held.
+    pub fn unbind_input(&mut self, trigger: InputTrigger) {
+        let actions = InputSystem::unbind(&mut self.world.resource_mut::<InputBindings>(), trigger);
+        for action in actions.into_iter().filter(InputAction::is_held) {
+            let mut held = self.world.resource_mut::<HeldInputs>();
+            if InputSystem::release(&mut held, trigger, action) {
+                self.perform(action, false);
+            }
+        }
+    }
+
+    // so an input unbound while held doesn't keep the camera moving
+    fn release_held_actions(&mut self) {
+        let mut held = self.world.resource_mut::<HeldInputs>();
+        let actions: HashSet<InputAction> = held.held.drain().map(|(_, action)| action).collect();
+        for action in actions {
+            self.perform(action, false);
+        }
+    }
+
+    // Returns `true` if the action did anything.
+    fn perform(&mut self, action: InputAction, pressed: bool) -> bool {
+        if action.is_held() {
+            return match self.world.get_mut::<CameraComponent>(self.camera_entity) {
+                Some(mut camera_component) => CameraSystem::process_action(
+                    &mut camera_component.camera_controller,
+                    action,
+                    pressed,
+                ),
+                None => false,
+            };
+        }
+        if !pressed {
+            return false;
+        }
+        let Some((lat, lon)) = self.pick() else {
+            return false;
+        };
+
+        match action {
+            InputAction::Select => {
+                let entity = self.pick_entity();
+                tracing::debug!("click at lat: {lat}, lon: {lon}, entity: {entity:?}");
+                self.events.push(ViewerEvent::Click { lat, lon, entity });
+
+                if self.measure_mode {
+                    self.add_measurement_point(lat, lon);
+                } else if self.place_billboard_on_click && entity.is_none() {
+                    self.add_billboard(lat, lon);
+                }
+            }
+            // not on top of another entity
+            InputAction::PlaceMarker if self.pick_entity().is_none() => {
+                self.add_billboard(lat, lon);
+            }
+            InputAction::Measure => self.add_measurement_point(lat, lon),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Latitude and longitude of the globe under the cursor, if any.
+    pub fn pick(&self) -> Option<(f32, f32)> {
+        let screen_coords = self.screen_coords?;
+        let camera_component = self.world.get::<CameraComponent>(self.camera_entity)?;
+
+        WindowSystem::handle_left_click(
+            self.config.width as f32,
+            self.config.height as f32,
+            screen_coords.x as f32,
+            screen_coords.y as f32,
+            self.earth_radius,
+            camera_component,
+        )
+    }
+
+    /// The billboard or polyline under the cursor, if any. Billboards win over lines.
+    pub fn pick_entity(&mut self) -> Option<Entity> {
+        let screen_coords = self.screen_coords?;
+        let (width, height) = (self.config.width as f32, self.config.height as f32);
+        let (cursor_x, cursor_y) = (screen_coords.x as f32, screen_coords.y as f32);
+        let mut billboards = self.world.query::<(Entity, &BillboardComponent)>();
+        let mut polylines = self.world.query::<(Entity, &PolylineComponent)>();
+        let camera_component = self.world.get::<CameraComponent>(self.camera_entity)?;
+
+        let (ray_origin, ray_direction) =
+            WindowSystem::cursor_ray(width, height, cursor_x, cursor_y, camera_component);
+        let origin = cgmath::Vector3::new(0.0, 0.0, 0.0);
+        let globe_distance = WindowSystem::ray_sphere_intersection(
+            ray_origin,
+            ray_direction,
+            origin,
+            self.earth_radius,
+        )
+        .unwrap_or(f32::INFINITY);
+
+        // billboards are treated as spheres around their anchor
+        let billboard = billboards
+            .iter(&self.world)
+            .filter_map(|(entity, billboard)| {
+                WindowSystem::ray_sphere_intersection(
+                    ray_origin,
+                    ray_direction,
+                    billboard.position.into(),
+                    billboard.size / 2.0,
+                )
+                .filter(|&t| t < globe_distance)
+                .map(|t| (entity, t))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(entity, _)| entity);
+        if billboard.is_some() {
+            return billboard;
+        }
+
+        let eye = ray_origin;
+        for (entity, polyline) in polylines.iter(&self.world) {
+            for segment in polyline.positions.windows(2) {
+                let (a, b) = (
+                    cgmath::Vector3::from(segment[0]),
+                    cgmath::Vector3::from(segment[1]),
+                );
+                let (Some(screen_a), Some(screen_b)) = (
+                    WindowSystem::world_to_screen(width, height, a, camera_component),
+                    WindowSystem::world_to_screen(width, height, b, camera_component),
+                ) else {
+                    continue;
+                };
+
+                // closest point on the projected segment
+                let (dx, dy) = (screen_b.0 - screen_a.0, screen_b.1 - screen_a.1);
+                let length_squared = dx * dx + dy * dy;
+                let t = if length_squared > 0.0 {
+                    (((cursor_x - screen_a.0) * dx + (cursor_y - screen_a.1) * dy) / length_squared)
+                        .clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let (closest_x, closest_y) = (screen_a.0 + dx * t, screen_a.1 + dy * t);
+                let pixel_distance =
+                    ((cursor_x - closest_x).powi(2) + (cursor_y - closest_y).powi(2)).sqrt();
+                if pixel_distance > POLYLINE_PICK_TOLERANCE {
+                    continue;
+                }
+
+                // skip lines hidden behind the globe
+                let point = a + (b - a) * t;
+                let to_point = point - eye;
+                let occluded = WindowSystem::ray_sphere_intersection(
+                    eye,
+                    to_point.normalize(),
+                    origin,
+                    self.earth_radius,
+                )
+                .is_some_and(|hit| hit < to_point.magnitude() - 1.0);
+                if !occluded {
+                    return Some(entity);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Places a billboard on the globe surface and returns its entity.
+    pub fn add_billboard(&mut self, lat: f32, lon: f32) -> Entity {
+        let camera_component = self
+            .world
+            .get::<CameraComponent>(self.camera_entity)
+            .unwrap();
+
+        let size = 500.0;
+        let billboard = BillboardSystem::create_billboard_component(
+            size,
+            lat,
+            lon,
+            self.earth_radius,
+            self.annotations_added,
+        );
+        self.annotations_added += 1;
+        let billboard_mesh =
+            BillboardSystem::create_billboard_mesh(&self.device, size, lat, lon, self.earth_radius);
+        let billboard_material = BillboardSystem::create_billboard_material(
+            &self.device,
+            &self.queue,
+            self.billboard_image.clone(),
+        );
+
+        // should 100% not be making a render pipeline for each billboard...
+        // probably should be reusing the material too. I don't think we can
+        // reuse the mesh due to coordinate differences.
+        let billboard_render_pipeline = BillboardSystem::create_render_pipeline(
+            &self.device,
+            camera_component,
+            &billboard_material,
+            &billboard_mesh,
+            &self.config.format,
+        );
+
+        self.world
+            .spawn((
+                billboard,
+                billboard_mesh,
+                billboard_render_pipeline,
+                billboard_material,
+            ))
+            .id()
+    }
+
+    /// Draws a line through the (lat, lon) `coordinates` following WGS84 geodesics.
+    pub fn add_polyline(&mut self, coordinates: Vec<(f32, f32)>, color: [f32; 4]) -> Entity {
+        self.spawn_polyline(coordinates, color, false)
+    }
+
+    /// Outlines the polygon with (lat, lon) vertices `coordinates`, closing it
+    /// back to the first vertex along WGS84 geodesics.
+    pub fn add_polygon(&mut self, coordinates: Vec<(f32, f32)>, color: [f32; 4]) -> Entity {
+        self.spawn_polyline(coordinates, color, true)
+    }
+
+    // a polygon is drawn as a line strip that runs back to its first vertex
+    fn spawn_polyline(
+        &mut self,
+        coordinates: Vec<(f32, f32)>,
+        color: [f32; 4],
+        closed: bool,
+    ) -> Entity {
+        let camera_component = self
+            .world
+            .get::<CameraComponent>(self.camera_entity)
+            .unwrap();
+
+        let polyline = PolylineSystem::create_polyline_component(
+            coordinates,
+            color,
+            closed,
+            self.earth_radius,
+            self.annotations_added,
+        );
+        self.annotations_added += 1;
+        let polyline_mesh = PolylineSystem::create_polyline_mesh(&self.device, &polyline.positions);
+        let polyline_material = PolylineSystem::create_polyline_material(&self.device, color);
+        let polyline_render_pipeline = PolylineSystem::create_render_pipeline(
+            &self.device,
+            camera_component,
+            &polyline_material,
+            &polyline_mesh,
+            &self.config.format,
+        );
+
+        self.world
+            .spawn((
+                polyline,
+                polyline_mesh,
+                polyline_material,
+                polyline_render_pipeline,
+            ))
+            .id()
+    }
+
+    /// Despawns an entity added by the host. The built-in camera, Earth, Moon,
+    /// starfield, atmosphere and cloud entities cannot be removed.
+    pub fn remove_entity(&mut self, entity: Entity) -> bool {
+        let builtin = [
+            self.camera_entity,
+            self.earth_entity,
+            self.moon_entity,
+            self.starfield_entity,
+            self.atmosphere_entity,
+            self.cloud_entity,
+        ];
+        if builtin.contains(&entity) {
+            return false;
+        }
+        self.world.despawn(entity)
+    }
+
+    /// Flies the camera to hover `altitude` kilometers above lat/lon.
+    pub fn fly_to(&mut self, lat: f32, lon: f32, altitude: f32, duration_seconds: f32) {
+        // keep clear of the poles, the camera's up vector is fixed to +y
+        let lat = lat.clamp(-89.0, 89.0);
+        let (x, y, z) = MeshSystem::lat_lon_to_cartesian(lat, lon, self.earth_radius + altitude);
+        if let Some(mut camera_component) =
+            self.world.get_mut::<CameraComponent>(self.camera_entity)
+        {
+            CameraSystem::fly_to(
+                &mut camera_component,
+                cgmath::Point3::new(x, y, z),
+                duration_seconds,
+            );
+        }
+    }
+
+    /// Current simulation time.
+    pub fn epoch(&self) -> Epoch {
+        self.world.resource::<SimulationClock>().epoch
+    }
+
+    pub fn set_epoch(&mut self, epoch: Epoch) {
+        ClockSystem::set_epoch(&mut self.world.resource_mut::<SimulationClock>(), epoch);
+    }
+
+    /// Simulated seconds per wall-clock second. Zero or negative values are allowed.
+    pub fn set_time_multiplier(&mut self, multiplier: f64) {
+        self.world.resource_mut::<SimulationClock>().multiplier = multiplier;
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.world.resource_mut::<SimulationClock>().paused = paused;
+    }
+
+    /// The camera, simulation time and everything the host added, to be
+    /// written out with [`SceneDocument::to_json`] or [`SceneDocument::to_ron`]
+    /// and restored with [`Viewer::load_scene`]. The measurement is left out.
+    pub fn save_scene(&mut self) -> SceneDocument {
+        let camera = &self
+            .world
+            .get::<CameraComponent>(self.camera_entity)
+            .unwrap()
+            .camera;
+        let camera = SceneCamera {
+            eye: camera.eye.into(),
+            target: camera.target.into(),
+            up: camera.up.into(),
+            fovy: camera.fovy,
+        };
+        let clock = self.world.resource::<SimulationClock>();
+        let time = SceneTime {
+            epoch: clock.epoch,
+            multiplier: clock.multiplier,
+            paused: clock.paused,
+        };
+
+        // in the order they were added
+        let mut billboards: Vec<(u64, SceneBillboard)> = self
+            .world
+            .query::<&BillboardComponent>()
+            .iter(&self.world)
+            .map(|billboard| {
+                let (lat, lon) = (billboard.lat, billboard.lon);
+                (billboard.order, SceneBillboard { lat, lon })
+            })
+            .collect();
+        billboards.sort_by_key(|(order, _)| *order);
+        let mut polylines: Vec<(u64, bool, ScenePolyline)> = self
+            .world
+            .query::<(Entity, &PolylineComponent)>()
+            .iter(&self.world)
+            .filter(|(entity, _)| Some(*entity) != self.measurement_entity)
+            .map(|(_, polyline)| {
+                let line = ScenePolyline {
+                    coordinates: polyline.coordinates.clone(),
+                    color: polyline.color,
+                };
+                (polyline.order, polyline.closed, line)
+            })
+            .collect();
+        polylines.sort_by_key(|(order, _, _)| *order);
+        let (polygons, polylines): (Vec<_>, Vec<_>) =
+            polylines.into_iter().partition(|(_, closed, _)| *closed);
+
+        let mut globes = vec![("earth", self.earth_entity), ("moon", self.moon_entity)];
+        globes.extend(
+            self.world
+                .query::<(Entity, &CelestialBodyComponent)>()
+                .iter(&self.world)
+                .map(|(entity, body)| (body.name, entity)),
+        );
+        let imagery_layers = globes
+            .into_iter()
+            .filter_map(|(name, entity)| Some((name, self.world.get::<ImageryComponent>(entity)?)))
+            .flat_map(|(name, imagery)| {
+                imagery.layers.iter().map(move |layer| SceneImageryLayer {
+                    globe: name.to_string(),
+                    source: layer.source.clone(),
+                    style: layer.style,
+                })
+            })
+            .collect();
+
+        SceneDocument {
+            version: SCENE_VERSION,
+            camera,
+            time,
+            billboards: billboards
+                .into_iter()
+                .map(|(_, billboard)| billboard)
+                .collect(),
+            polylines: polylines.into_iter().map(|(_, _, line)| line).collect(),
+            polygons: polygons.into_iter().map(|(_, _, line)| line).collect(),
+            imagery_layers,
+        }
+    }
+
+    /// Replaces the host's billboards, lines, polygons and imagery layers with
+    /// those of a saved scene, and moves the camera and clock to where they
+    /// were. Layers are fetched again; those on a globe that is not in the
+    /// scene, like a planet while the solar system is hidden, are skipped.
+    pub fn load_scene(&mut self, scene: &SceneDocument) -> Result<(), SceneError> {
+        if scene.version > SCENE_VERSION {
+            return Err(SceneError::UnsupportedVersion {
+                version: scene.version,
+            });
+        }
+
+        self.clear_measurement();
+        self.despawn_all::<BillboardComponent>();
+        self.despawn_all::<PolylineComponent>();
+        let layers: Vec<(Entity, ImageryLayerId)> = self
+            .world
+            .query::<(Entity, &ImageryComponent)>()
+            .iter(&self.world)
+            .flat_map(|(entity, imagery)| {
+                imagery.layers.iter().map(move |layer| (entity, layer.id))
+            })
+            .collect();
+        for (entity, id) in layers {
+            self.remove_imagery_layer(entity, id);
+        }
+
+        if let Some(mut camera_component) =
+            self.world.get_mut::<CameraComponent>(self.camera_entity)
+        {
+            let camera = &mut camera_component.camera;
+            camera.eye = scene.camera.eye.into();
+            camera.target = scene.camera.target.into();
+            camera.up = scene.camera.up.into();
+            camera.fovy = scene.camera.fovy;
+            camera_component.camera_controller.flight = None;
+        }
+        self.set_epoch(scene.time.epoch);
+        self.set_time_multiplier(scene.time.multiplier);
+        self.set_paused(scene.time.paused);
+
+        for billboard in &scene.billboards {
+            self.add_billboard(billboard.lat, billboard.lon);
+        }
+        for line in &scene.polylines {
+            self.add_polyline(line.coordinates.clone(), line.color);
+        }
+        for polygon in &scene.polygons {
+            self.add_polygon(polygon.coordinates.clone(), polygon.color);
+        }
+        for layer in &scene.imagery_layers {
+            let Some(entity) = self.globe_entity(&layer.globe) else {
+                tracing::warn!(
+                    "no globe {} for imagery layer {}",
+                    layer.globe,
+                    layer.source.location()
+                );
+                continue;
+            };
+            match &layer.source {
+                ImageryLayerSource::Image { path } => {
+                    self.add_imagery_layer(entity, path, layer.style);
+                }
+                ImageryLayerSource::Wms {
+                    capabilities_url,
+                    layer: name,
+                } => {
+                    self.add_wms_imagery(capabilities_url, name, layer.style);
+                }
+                ImageryLayerSource::Wmts {
+                    capabilities_url,
+                    layer: name,
+                } => {
+                    self.add_wmts_imagery(capabilities_url, name, layer.style);
+                }
+                ImageryLayerSource::Archive { path } => {
+                    self.add_archive_imagery(path, layer.style);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// `(completed, total)` requested assets, counting failures as completed.
+    pub fn loading_progress(&self) -> (usize, usize) {
+        (self.loading_completed, self.loading_total)
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.loading_completed < self.loading_total
+    }
+
+    // Puts whatever the loading task has finished since the last frame into the scene.
+    fn receive_assets(&mut self) {
+        while let Ok(Some((path, result))) = self.loading.try_next() {
+            self.loading_completed += 1;
+            let (completed, total) = self.loading_progress();
+
+            let asset = match result {
+                Ok(asset) => asset,
+                Err(e) => {
+                    tracing::error!("{e}");
+                    self.events.push(ViewerEvent::AssetFailed {
+                        path,
+                        error: e.to_string(),
+                        completed,
+                        total,
+                    });
+                    continue;
+                }
+            };
+
+            match asset {
+                LoadedAsset::Kernel(kernel) => {
+                    let ephemeris = self.world.get_resource::<Ephemeris>();
+                    match EphemerisSystem::add_kernel(ephemeris, &path, kernel) {
+                        Ok(ephemeris) => self.world.insert_resource(ephemeris),
+                        Err(error) => {
+                            tracing::error!("could not add kernel {path}: {error}");
+                            self.events.push(ViewerEvent::AssetFailed {
+                                path,
+                                error,
+                                completed,
+                                total,
+                            });
+                            continue;
+                        }
+                    }
+                }
+                LoadedAsset::GlobeTexture(entity, map, faces) => {
+                    // decoded here so a format the GPU lacks is reported
+                    let faces = match MaterialSystem::prepare_texture(&self.device, faces) {
+                        Ok(faces) => faces,
+                        Err(error) => {
+                            self.events.push(ViewerEvent::AssetFailed {
+                                path,
+                                error,
+                                completed,
+                                total,
+                            });
+                            continue;
+                        }
+                    };
+                    if entity == self.earth_entity {
+                        self.set_earth_map(map, faces);
+                    } else if entity == self.moon_entity {
+                        self.retexture_globe(entity, faces, MoonSystem::retexture);
+                    } else if self.world.get::<CelestialBodyComponent>(entity).is_some() {
+                        let body_material =
+                            BodySystem::create_material(&self.device, &self.queue, faces);
+                        self.world.entity_mut(entity).insert(body_material);
+                    } else {
+                        // solar-system mode was turned off meanwhile, or the
+                        // entity was never a globe
+                        let error = "not a globe".to_string();
+                        self.events.push(ViewerEvent::AssetFailed {
+                            path,
+                            error,
+                            completed,
+                            total,
+                        });
+                        continue;
+                    }
+                }
+                LoadedAsset::ImageryImage(entity, id, image) => {
+                    // the globe or the layer may have gone meanwhile
+                    let added = match self.world.get_mut::<ImageryComponent>(entity) {
+                        Some(mut imagery) => ImagerySystem::set_layer_image(
+                            &self.device,
+                            &self.queue,
+                            &mut imagery,
+                            id,
+                            image,
+                        ),
+                        None => false,
+                    };
+                    if !added {
+                        let error = "imagery layer was removed".to_string();
+                        self.events.push(ViewerEvent::AssetFailed {
+                            path,
+                            error,
+                            completed,
+                            total,
+                        });
+                        continue;
+                    }
+                }
+                LoadedAsset::ImageryProvider(layer, provider) => {
+                    // the layer may have been removed while its capabilities loaded
+                    let layer_exists = self
+                        .world
+                        .get::<ImageryComponent>(self.earth_entity)
+                        .is_some_and(|imagery| imagery.layers.iter().any(|l| l.id == layer));
+                    if !layer_exists {
+                        let error = "imagery layer was removed".to_string();
+                        self.events.push(ViewerEvent::AssetFailed {
+                            path,
+                            error,
+                            completed,
+                            total,
+                        });
+                        continue;
+                    }
+                    self.world.spawn(ImageryProviderComponent {
+                        layer,
+                        provider,
+                        shown: None,
+                        pending: false,
+                    });
+                }
+                LoadedAsset::ProviderImage(entity, view, image) => {
+                    let Some(mut provider) = self.world.get_mut::<ImageryProviderComponent>(entity)
+                    else {
+                        let error = "imagery layer was removed".to_string();
+                        self.events.push(ViewerEvent::AssetFailed {
+                            path,
+                            error,
+                            completed,
+                            total,
+                        });
+                        continue;
+                    };
+                    // failed views are only retried once the camera moves on
+                    provider.shown = Some(view);
+                    provider.pending = false;
+                    let layer = provider.layer;
+
+                    let imagery_result = match image {
+                        Ok(imagery_result) => imagery_result,
+                        Err(e) => {
+                            tracing::error!("{e}");
+                            self.events.push(ViewerEvent::AssetFailed {
+                                path,
+                                error: e.to_string(),
+                                completed,
+                                total,
+                            });
+                            continue;
+                        }
+                    };
+                    let rectangle = match &imagery_result {
+                        ProviderImagery::Image(rectangle, _)
+                        | ProviderImagery::Tiles(rectangle, _, _) => *rectangle,
+                    };
+                    let mut imagery = self
+                        .world
+                        .get_mut::<ImageryComponent>(self.earth_entity)
+                        .unwrap();
+                    let style = imagery
+                        .layers
+                        .iter()
+                        .find(|imagery_layer| imagery_layer.id == layer)
+                        .map(|imagery_layer| ImageryLayerStyle {
+                            rectangle,
+                            ..imagery_layer.style
+                        });
+                    if let Some(style) = style {
+                        ImagerySystem::set_layer_style(
+                            &self.device,
+                            &self.queue,
+                            &mut imagery,
+                            layer,
+                            style,
+                        );
+                        match imagery_result {
+                            ProviderImagery::Image(_, image) => ImagerySystem::set_layer_image(
+                                &self.device,
+                                &self.queue,
+                                &mut imagery,
+                                layer,
+                                image,
+                            ),
+                            ProviderImagery::Tiles(_, grid, tiles) => {
+                                ImagerySystem::set_layer_tiles(
+                                    &self.device,
+                                    &self.queue,
+                                    &mut imagery,
+                                    layer,
+                                    grid,
+                                    tiles,
+                                )
+                            }
+                        };
+                    }
+                }
+                LoadedAsset::OverlayFrame(epoch, frame) => {
+                    let frame = match MaterialSystem::prepare_texture(&self.device, frame) {
+                        Ok(frame) => frame,
+                        Err(error) => {
+                            self.events.push(ViewerEvent::AssetFailed {
+                                path,
+                                error,
+                                completed,
+                                total,
+                            });
+                            continue;
+                        }
+                    };
+                    EarthSystem::add_overlay_frame(
+                        &self.device,
+                        &self.queue,
+                        &mut self
+                            .world
+                            .get_mut::<EarthSurfaceComponent>(self.earth_entity)
+                            .unwrap(),
+                        epoch,
+                        frame,
+                    );
+                }
+                LoadedAsset::BillboardImage(image) => {
+                    self.billboard_image = image;
+                    self.retexture_billboards();
+                }
+                LoadedAsset::StarCatalog(stars) => {
+                    let starfield = StarSystem::create_starfield(&self.device, &stars);
+                    self.world
+                        .entity_mut(self.starfield_entity)
+                        .insert(starfield);
+                }
+            }
+            self.events.push(ViewerEvent::AssetLoaded {
+                path,
+                completed,
+                total,
+            });
+        }
+    }
+
+    fn set_earth_map(&mut self, map: EarthMap, faces: TextureSource) {
+        let mut earth_query = self
+            .world
+            .query::<(&mut EarthSurfaceComponent, &mut MaterialComponent)>();
+        let (mut surface_component, mut material_component) = earth_query
+            .get_mut(&mut self.world, self.earth_entity)
+            .unwrap();
+        EarthSystem::set_map(
+            &self.device,
+            &self.queue,
+            &mut surface_component,
+            &mut material_component,
+            map,
+            faces,
+        );
+        if map == EarthMap::Clouds {
+            let cloud_components = CloudSystem::retexture(
+                &self.device,
+                self.config.format,
+                self.world
+                    .get::<CameraComponent>(self.camera_entity)
+                    .unwrap(),
+                self.world.get::<MeshComponent>(self.cloud_entity).unwrap(),
+                self.world
+                    .get::<EarthSurfaceComponent>(self.earth_entity)
+                    .unwrap(),
+            );
+            self.world
+                .entity_mut(self.cloud_entity)
+                .insert(cloud_components);
+        }
+    }
+
+    fn rebuild_earth_material(&mut self) {
+        let mut earth_query = self
+            .world
+            .query::<(&EarthSurfaceComponent, &mut MaterialComponent)>();
+        let (surface_component, mut material_component) = earth_query
+            .get_mut(&mut self.world, self.earth_entity)
+            .unwrap();
+        EarthSystem::rebuild_material(&self.device, surface_component, &mut material_component);
+    }
+
+    fn retexture_globe(&mut self, entity: Entity, faces: TextureSource, retexture: RetextureFn) {
+        let camera_component = self
+            .world
+            .get::<CameraComponent>(self.camera_entity)
+            .unwrap();
+        let mesh_component = self.world.get::<MeshComponent>(entity).unwrap();
+        let components = retexture(
+            &self.device,
+            &self.queue,
+            self.config.format,
+            camera_component,
+            mesh_component,
+            faces,
+        );
+        self.world.entity_mut(entity).insert(components);
+    }
+
+    // billboards placed before their texture arrived were drawn with a placeholder
+    fn retexture_billboards(&mut self) {
+        let billboards: Vec<Entity> = self
+            .world
+            .query_filtered::<Entity, With<BillboardComponent>>()
+            .iter(&self.world)
+            .collect();
+
+        for entity in billboards {
+            let billboard_material = BillboardSystem::create_billboard_material(
+                &self.device,
+                &self.queue,
+                self.billboard_image.clone(),
+            );
+            let camera_component = self
+                .world
+                .get::<CameraComponent>(self.camera_entity)
+                .unwrap();
+            let billboard_render_pipeline = BillboardSystem::create_render_pipeline(
+                &self.device,
+                camera_component,
+                &billboard_material,
+                self.world.get::<MeshComponent>(entity).unwrap(),
+                &self.config.format,
+            );
+            self.world
+                .entity_mut(entity)
+                .insert((billboard_material, billboard_render_pipeline));
+        }
+    }
+
+    pub fn update(&mut self) {
+        self.receive_assets();
+
+        let dt = ClockSystem::tick(&mut self.world.resource_mut::<SimulationClock>());
+        let epoch = self.epoch();
+
+        let almanac = self
+            .world
+            .get_resource::<Ephemeris>()
+            .map(|ephemeris| &ephemeris.almanac);
+        StarSystem::update_orientation(
+            &self.queue,
+            self.world
+                .get::<MeshComponent>(self.starfield_entity)
+                .unwrap(),
+            OrientationSystem::j2000_to_scene(almanac, epoch),
+        );
+
+        if let Some(ephemeris) = self.world.get_resource::<Ephemeris>() {
+            self.moon_placed |= MoonSystem::update_position(
+                &self.queue,
+                self.world.get::<MeshComponent>(self.moon_entity).unwrap(),
+                &ephemeris.almanac,
+                epoch,
+            );
+
+            if let Some(sun_direction) = EarthSystem::sun_direction(&ephemeris.almanac, epoch) {
+                AtmosphereSystem::update_sun_direction(
+                    &self.queue,
+                    &mut self
+                        .world
+                        .get_mut::<AtmosphereComponent>(self.atmosphere_entity)
+                        .unwrap(),
+                    sun_direction,
+                );
+                EarthSystem::update_sun_direction(
+                    &self.queue,
+                    &mut self
+                        .world
+                        .get_mut::<EarthSurfaceComponent>(self.earth_entity)
+                        .unwrap(),
+                    sun_direction,
+                );
+            }
+        }
+        let cloud_component = *self.world.get::<CloudComponent>(self.cloud_entity).unwrap();
+        CloudSystem::update(
+            &self.queue,
+            &cloud_component,
+            &mut self
+                .world
+                .get_mut::<EarthSurfaceComponent>(self.earth_entity)
+                .unwrap(),
+            epoch,
+        );
+        let overlay_changed = EarthSystem::update_overlay(
+            &self.queue,
+            &mut self
+                .world
+                .get_mut::<EarthSurfaceComponent>(self.earth_entity)
+                .unwrap(),
+            epoch,
+        );
+        if overlay_changed {
+            self.rebuild_earth_material();
+        }
+        self.update_solar_system(epoch);
+
+        CameraSystem::update_camera(
+            &self.queue,
+            self.world
+                .get_mut::<CameraComponent>(self.camera_entity)
+                .unwrap(),
+            dt as f32,
+        );
+        self.update_imagery_providers();
+        self.update_graticule();
+    }
+
+    fn update_solar_system(&mut self, epoch: Epoch) {
+        let mut bodies_query = self
+            .world
+            .query::<(&CelestialBodyComponent, &MeshComponent)>();
+        let mut orbits_query = self
+            .world
+            .query_filtered::<&MeshComponent, With<OrbitComponent>>();
+
+        let (Some(solar_system), Some(ephemeris)) = (
+            self.world.get_resource::<SolarSystem>(),
+            self.world.get_resource::<Ephemeris>(),
+        ) else {
+            return;
+        };
+        let almanac = &ephemeris.almanac;
+
+        for (body, body_mesh) in bodies_query.iter(&self.world) {
+            BodySystem::update_position(
+                &self.queue,
+                body_mesh,
+                body,
+                solar_system.radius_scale,
+                almanac,
+                epoch,
+            );
+        }
+
+        let Some(sun_position) = BodySystem::scene_position(almanac, celestial_objects::SUN, epoch)
+        else {
+            return;
+        };
+        let j2000_to_scene = OrientationSystem::j2000_to_scene(Some(almanac), epoch);
+
+        // sampled once, then only moved and turned along with the Sun and sky
+        if solar_system.show_orbits && !solar_system.orbits_built {
+            let orbits: Vec<(i32, Vec<[f32; 3]>)> = BODIES
+                .iter()
+                .map(|body| {
+                    (
+                        body.naif_id,
+                        BodySystem::orbit_positions(almanac, body, epoch),
+                    )
+                })
+                .filter(|(_, positions)| positions.len() > 1)
+                .collect();
+            for (naif_id, positions) in orbits {
+                self.add_orbit(naif_id, &positions);
+            }
+            self.world.resource_mut::<SolarSystem>().orbits_built = true;
+        }
+
+        for orbit_mesh in orbits_query.iter(&self.world) {
+            BodySystem::update_orbit(&self.queue, orbit_mesh, sun_position, j2000_to_scene);
+        }
+    }
+
+    // Orbits reuse the polyline pipeline but not `PolylineComponent`, so they
+    // can't be picked.
+    fn add_orbit(&mut self, naif_id: i32, positions: &[[f32; 3]]) {
+        let camera_component = self
+            .world
+            .get::<CameraComponent>(self.camera_entity)
+            .unwrap();
+
+        let orbit_mesh = PolylineSystem::create_polyline_mesh(&self.device, positions);
+        let orbit_material = PolylineSystem::create_polyline_material(&self.device, ORBIT_COLOR);
+        let orbit_render_pipeline = PolylineSystem::create_render_pipeline(
+            &self.device,
+            camera_component,
+            &orbit_material,
+            &orbit_mesh,
+            &self.config.format,
+        );
+
+        self.world.spawn((
+            OrbitComponent { naif_id },
+            orbit_mesh,
+            orbit_material,
+            orbit_render_pipeline,
+        ));
+    }
+
+    /// Renders a frame to the viewer's surface and presents it.
+    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        let output = self.surface.get_current_texture()?;
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        self.render_to_view(&view);
+        output.present();
+        Ok(())
+    }
+
+    /// Renders a frame into `view`, which must match the surface format and size.
+    pub fn render_to_view(&mut self, view: &wgpu::TextureView) {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        r: 0.0,
+                        g: 0.0,
+                        b: 0.0,
+                        a: 1.0,
+                    }),
+                    store: true,
+                },
+            })],
+
+            // depth stencil not working on WSL + Nvidia
+            #[cfg(target_os = "linux")]
+            depth_stencil_attachment: None,
+            #[cfg(not(target_os = "linux"))]
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self._depth_texture.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+
+        let mut objects_query = self.world.query_filtered::<(
+            Entity,
+            &RenderPipelineComponent,
+            &MeshComponent,
+            &MaterialComponent,
+            Option<&ImageryComponent>,
+        ), (
+            Without<StarfieldComponent>,
+            Without<AtmosphereComponent>,
+            Without<CloudComponent>,
+        )>();
+        let mut bodies_query = self.world.query_filtered::<(
+            &MeshComponent,
+            &MaterialComponent,
+            &ImageryComponent,
+        ), With<CelestialBodyComponent>>();
+
+        let camera_component = self
+            .world
+            .get::<CameraComponent>(self.camera_entity)
+            .unwrap();
+        render_pass.set_bind_group(0, &camera_component.camera_bind_group, &[]);
+
+        // the starfield is the backdrop, everything else is drawn over it
+        let starfield = (
+            self.starfield_entity,
+            self.world
+                .get::<RenderPipelineComponent>(self.starfield_entity)
+                .unwrap(),
+            self.world
+                .get::<MeshComponent>(self.starfield_entity)
+                .unwrap(),
+            self.world
+                .get::<MaterialComponent>(self.starfield_entity)
+                .unwrap(),
+            None,
+        );
+        for (entity, render_pipeline, mesh, material, imagery) in
+            std::iter::once(starfield).chain(objects_query.iter(&self.world))
+        {
+            if entity == self.moon_entity && !self.moon_placed {
+                continue;
+            }
+            render_pass.set_pipeline(&render_pipeline.render_pipeline);
+            render_pass.set_bind_group(1, &material.bind_group, &[]);
+            render_pass.set_bind_group(2, &mesh.model_matrix_bind_group, &[]);
+            // globes composite their imagery layers
+            if let Some(imagery) = imagery {
+                render_pass.set_bind_group(3, &imagery.bind_group, &[]);
+            }
+
+            render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+            render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..mesh.num_indices, 0, 0..1);
+        }
+
+        // solar-system bodies all share one pipeline
+        if let Some(BodyPipeline(body_pipeline)) = self.world.get_resource::<BodyPipeline>() {
+            render_pass.set_pipeline(&body_pipeline.render_pipeline);
+            for (mesh, material, imagery) in bodies_query.iter(&self.world) {
+                render_pass.set_bind_group(1, &material.bind_group, &[]);
+                render_pass.set_bind_group(2, &mesh.model_matrix_bind_group, &[]);
+                render_pass.set_bind_group(3, &imagery.bind_group, &[]);
+
+                render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+                render_pass
+                    .set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                render_pass.draw_indexed(0..mesh.num_indices, 0, 0..1);
+            }
+        }
+
+        // the clouds are blended over the ground, so they follow it
+        if self
+            .world
+            .get::<CloudComponent>(self.cloud_entity)
+            .unwrap()
+            .visible
+        {
+            let render_pipeline = self
+                .world
+                .get::<RenderPipelineComponent>(self.cloud_entity)
+                .unwrap();
+            let mesh = self.world.get::<MeshComponent>(self.cloud_entity).unwrap();
+            let material = self
+                .world
+                .get::<MaterialComponent>(self.cloud_entity)
+                .unwrap();
+
+            render_pass.set_pipeline(&render_pipeline.render_pipeline);
+            render_pass.set_bind_group(1, &material.bind_group, &[]);
+            render_pass.set_bind_group(2, &mesh.model_matrix_bind_group, &[]);
+
+            render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+            render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..mesh.num_indices, 0, 0..1);
+        }
+
+        // the atmosphere tints whatever is behind it, so it goes last
+        if self.show_atmosphere {
+            let render_pipeline = self
+                .world
+                .get::<RenderPipelineComponent>(self.atmosphere_entity)
+                .unwrap();
+            let mesh = self
+                .world
+                .get::<MeshComponent>(self.atmosphere_entity)
+                .unwrap();
+            let material = self
+                .world
+                .get::<MaterialComponent>(self.atmosphere_entity)
+                .unwrap();
+
+            render_pass.set_pipeline(&render_pipeline.render_pipeline);
+            render_pass.set_bind_group(1, &material.bind_group, &[]);
+            render_pass.set_bind_group(2, &mesh.model_matrix_bind_group, &[]);
+
+            render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+            render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..mesh.num_indices, 0, 0..1);
+        }
+
+        drop(render_pass);
+
+        // submit will accept anything that implements IntoIter
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+}
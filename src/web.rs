@@ -0,0 +1,1205 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    str::FromStr,
+};
+
+use anise::prelude::Epoch;
+use bevy_ecs::entity::Entity;
+use js_sys::{Function, Object, Reflect};
+use wasm_bindgen::prelude::*;
+use web_sys::{HtmlCanvasElement, KeyboardEvent, MouseEvent, Touch, TouchEvent};
+use winit::event::{MouseButton, VirtualKeyCode};
+
+use crate::{
+    components::{
+        earth::EarthMap,
+        graticule::GraticuleAxis,
+        imagery::{GeoRectangle, ImageryLayerStyle},
+    },
+    loader::{AssetLoader, Kernel},
+    resources::input::{InputAction, InputBindings, InputTrigger},
+    scene::SceneDocument,
+    systems::geospatial::{
+        coordinates::{CoordinateFormat, CoordinatesSystem},
+        mgrs::MgrsSystem,
+        utm::UtmSystem,
+    },
+    Measurement, Viewer, ViewerEvent,
+};
+
+const DEFAULT_POLYLINE_COLOR: [f32; 4] = [1.0, 1.0, 0.0, 1.0];
+const DEFAULT_RADIUS_SCALE: f32 = 1000.0;
+
+// the `KeyboardEvent.code` of each key actions can be bound to
+const KEY_CODES: &[(&str, VirtualKeyCode)] = &[
+    ("KeyA", VirtualKeyCode::A),
+    ("KeyB", VirtualKeyCode::B),
+    ("KeyC", VirtualKeyCode::C),
+    ("KeyD", VirtualKeyCode::D),
+    ("KeyE", VirtualKeyCode::E),
+    ("KeyF", VirtualKeyCode::F),
+    ("KeyG", VirtualKeyCode::G),
+    ("KeyH", VirtualKeyCode::H),
+    ("KeyI", VirtualKeyCode::I),
+    ("KeyJ", VirtualKeyCode::J),
+    ("KeyK", VirtualKeyCode::K),
+    ("KeyL", VirtualKeyCode::L),
+    ("KeyM", VirtualKeyCode::M),
+    ("KeyN", VirtualKeyCode::N),
+    ("KeyO", VirtualKeyCode::O),
+    ("KeyP", VirtualKeyCode::P),
+    ("KeyQ", VirtualKeyCode::Q),
+    ("KeyR", VirtualKeyCode::R),
+    ("KeyS", VirtualKeyCode::S),
+    ("KeyT", VirtualKeyCode::T),
+    ("KeyU", VirtualKeyCode::U),
+    ("KeyV", VirtualKeyCode::V),
+    ("KeyW", VirtualKeyCode::W),
+    ("KeyX", VirtualKeyCode::X),
+    ("KeyY", VirtualKeyCode::Y),
+    ("KeyZ", VirtualKeyCode::Z),
+    ("Digit0", VirtualKeyCode::Key0),
+    ("Digit1", VirtualKeyCode::Key1),
+    ("Digit2", VirtualKeyCode::Key2),
+    ("Digit3", VirtualKeyCode::Key3),
+    ("Digit4", VirtualKeyCode::Key4),
+    ("Digit5", VirtualKeyCode::Key5),
+    ("Digit6", VirtualKeyCode::Key6),
+    ("Digit7", VirtualKeyCode::Key7),
+    ("Digit8", VirtualKeyCode::Key8),
+    ("Digit9", VirtualKeyCode::Key9),
+    ("ArrowUp", VirtualKeyCode::Up),
+    ("ArrowDown", VirtualKeyCode::Down),
+    ("ArrowLeft", VirtualKeyCode::Left),
+    ("ArrowRight", VirtualKeyCode::Right),
+    ("PageUp", VirtualKeyCode::PageUp),
+    ("PageDown", VirtualKeyCode::PageDown),
+    ("Home", VirtualKeyCode::Home),
+    ("End", VirtualKeyCode::End),
+    ("Insert", VirtualKeyCode::Insert),
+    ("Delete", VirtualKeyCode::Delete),
+    ("Escape", VirtualKeyCode::Escape),
+    ("Space", VirtualKeyCode::Space),
+    ("Enter", VirtualKeyCode::Return),
+    ("Tab", VirtualKeyCode::Tab),
+    ("Backspace", VirtualKeyCode::Back),
+    ("Minus", VirtualKeyCode::Minus),
+    ("Equal", VirtualKeyCode::Equals),
+    ("BracketLeft", VirtualKeyCode::LBracket),
+    ("BracketRight", VirtualKeyCode::RBracket),
+    ("Semicolon", VirtualKeyCode::Semicolon),
+    ("Quote", VirtualKeyCode::Apostrophe),
+    ("Comma", VirtualKeyCode::Comma),
+    ("Period", VirtualKeyCode::Period),
+    ("Slash", VirtualKeyCode::Slash),
+    ("Backslash", VirtualKeyCode::Backslash),
+    ("Backquote", VirtualKeyCode::Grave),
+    ("ShiftLeft", VirtualKeyCode::LShift),
+    ("ShiftRight", VirtualKeyCode::RShift),
+    ("ControlLeft", VirtualKeyCode::LControl),
+    ("ControlRight", VirtualKeyCode::RControl),
+    ("AltLeft", VirtualKeyCode::LAlt),
+    ("AltRight", VirtualKeyCode::RAlt),
+    ("NumpadAdd", VirtualKeyCode::NumpadAdd),
+    ("NumpadSubtract", VirtualKeyCode::NumpadSubtract),
+    ("F1", VirtualKeyCode::F1),
+    ("F2", VirtualKeyCode::F2),
+    ("F3", VirtualKeyCode::F3),
+    ("F4", VirtualKeyCode::F4),
+    ("F5", VirtualKeyCode::F5),
+    ("F6", VirtualKeyCode::F6),
+    ("F7", VirtualKeyCode::F7),
+    ("F8", VirtualKeyCode::F8),
+    ("F9", VirtualKeyCode::F9),
+    ("F10", VirtualKeyCode::F10),
+    ("F11", VirtualKeyCode::F11),
+    ("F12", VirtualKeyCode::F12),
+];
+const INPUT_ACTIONS: [(&str, InputAction); 7] = [
+    ("orbitLeft", InputAction::OrbitLeft),
+    ("orbitRight", InputAction::OrbitRight),
+    ("zoomIn", InputAction::ZoomIn),
+    ("zoomOut", InputAction::ZoomOut),
+    ("placeMarker", InputAction::PlaceMarker),
+    ("measure", InputAction::Measure),
+    ("select", InputAction::Select),
+];
+
+#[wasm_bindgen(start)]
+pub fn start() {
+    console_error_panic_hook::set_once();
+    tracing_wasm::set_as_global_default();
+}
+
+#[derive(Default)]
+struct Callbacks {
+    click: Option<Function>,
+    hover: Option<Function>,
+    load: Option<Function>,
+    measure: Option<Function>,
+}
+
+type Listener = Closure<dyn FnMut(web_sys::Event)>;
+type FrameLoop = Rc<RefCell<Option<Closure<dyn FnMut()>>>>;
+
+/// A globe rendering into a canvas element, driven by its own
+/// `requestAnimationFrame` loop.
+#[wasm_bindgen(js_name = HypersphereViewer)]
+pub struct WebViewer {
+    viewer: Rc<RefCell<Viewer>>,
+    callbacks: Rc<RefCell<Callbacks>>,
+    canvas: HtmlCanvasElement,
+    listeners: Vec<(&'static str, Listener)>,
+    frame: FrameLoop,
+    frame_id: Rc<Cell<i32>>,
+}
+
+#[wasm_bindgen(js_class = HypersphereViewer)]
+impl WebViewer {
+    /// Creates a viewer on `canvas`. The canvas' `width`/`height` attributes set
+    /// the drawing buffer size. Kernels and data layers are fetched from
+    /// `assetBaseUrl`, `http://localhost:3000` when omitted. `kernels` lists
+    /// kernel paths like `kernels/moon_pa_de440_200625.bpc`, typed by extension
+    /// (`.bsp`, `.bpc`, `.pca`), and defaults to `kernels/de440s.bsp`.
+    pub async fn create(
+        canvas: HtmlCanvasElement,
+        asset_base_url: Option<String>,
+        kernels: Option<Vec<String>>,
+    ) -> Result<WebViewer, JsValue> {
+        let loader = match asset_base_url {
+            Some(base_url) => AssetLoader::with_base_url(&base_url),
+            None => AssetLoader::default(),
+        };
+        let kernels = match kernels {
+            Some(paths) => paths
+                .iter()
+                .map(|path| WebViewer::kernel(path))
+                .collect::<Result<_, _>>()?,
+            None => Kernel::defaults(),
+        };
+        let viewer = Rc::new(RefCell::new(
+            Viewer::from_canvas(canvas.clone(), loader, kernels).await,
+        ));
+        let callbacks = Rc::new(RefCell::new(Callbacks::default()));
+
+        // keyboard events only reach focusable elements
+        canvas.set_attribute("tabindex", "0")?;
+
+        let mut web_viewer = WebViewer {
+            viewer,
+            callbacks,
+            canvas,
+            listeners: Vec::new(),
+            frame: Rc::new(RefCell::new(None)),
+            frame_id: Rc::new(Cell::new(0)),
+        };
+        web_viewer.add_listeners()?;
+        web_viewer.start_frame_loop()?;
+        Ok(web_viewer)
+    }
+
+    /// Loads another kernel, e.g. a spacecraft SPK, and merges it into the
+    /// ephemeris. Progress is reported through `onLoad`.
+    #[wasm_bindgen(js_name = loadKernel)]
+    pub fn load_kernel(&self, path: &str) -> Result<(), JsValue> {
+        let kernel = WebViewer::kernel(path)?;
+        self.viewer.borrow_mut().load_kernel(kernel);
+        Ok(())
+    }
+
+    /// Replaces the built-in bright stars with a catalog: a `ra,dec,vmag,bv`
+    /// CSV, or the Yale Bright Star Catalogue's `catalog` file.
+    #[wasm_bindgen(js_name = loadStarCatalog)]
+    pub fn load_star_catalog(&self, path: &str) {
+        self.viewer.borrow_mut().load_star_catalog(path);
+    }
+
+    /// Retextures the Earth from an equirectangular image or a directory of
+    /// six cube map faces.
+    #[wasm_bindgen(js_name = loadEarthTexture)]
+    pub fn load_earth_texture(&self, path: &str) {
+        let mut viewer = self.viewer.borrow_mut();
+        let earth = viewer.earth_entity();
+        viewer.load_globe_texture(earth, path);
+    }
+
+    /// Loads one layer of the Earth's material: `"day"`, `"normal"`, `"bump"`,
+    /// `"specular"` (an ocean mask), `"night"` (city lights) or `"clouds"`.
+    #[wasm_bindgen(js_name = loadEarthMap)]
+    pub fn load_earth_map(&self, map: &str, path: &str) -> Result<(), JsValue> {
+        let map = match map {
+            "day" => EarthMap::Day,
+            "normal" => EarthMap::Normal,
+            "bump" => EarthMap::Bump,
+            "specular" => EarthMap::Specular,
+            "night" => EarthMap::Night,
+            "clouds" => EarthMap::Clouds,
+            _ => return Err(JsValue::from_str(&format!("unknown Earth map {map}"))),
+        };
+        self.viewer.borrow_mut().load_earth_map(map, path);
+        Ok(())
+    }
+
+    /// Adds a frame to the Earth's time-series overlay, shown around
+    /// `timeMillis` (Unix milliseconds) and cross-faded with its neighbours.
+    #[wasm_bindgen(js_name = addOverlayFrame)]
+    pub fn add_overlay_frame(&self, time_millis: f64, path: &str) {
+        self.viewer
+            .borrow_mut()
+            .add_overlay_frame(Epoch::from_unix_milliseconds(time_millis), path);
+    }
+
+    #[wasm_bindgen(js_name = clearOverlay)]
+    pub fn clear_overlay(&self) {
+        self.viewer.borrow_mut().clear_overlay();
+    }
+
+    /// From 0 to 1, default 1.
+    #[wasm_bindgen(js_name = setOverlayOpacity)]
+    pub fn set_overlay_opacity(&self, opacity: f32) {
+        self.viewer.borrow_mut().set_overlay_opacity(opacity);
+    }
+
+    /// Lights the Earth from the Sun, on by default.
+    #[wasm_bindgen(js_name = setEarthLighting)]
+    pub fn set_earth_lighting(&self, enabled: bool) {
+        self.viewer.borrow_mut().set_earth_lighting(enabled);
+    }
+
+    /// Textures the Moon, which is a flat gray until given one.
+    #[wasm_bindgen(js_name = loadMoonTexture)]
+    pub fn load_moon_texture(&self, path: &str) {
+        let mut viewer = self.viewer.borrow_mut();
+        let moon = viewer.moon_entity();
+        viewer.load_globe_texture(moon, path);
+    }
+
+    /// Puts an imagery layer on top of a globe's stack: `"earth"`, `"moon"` or
+    /// a solar-system body. `options` may hold `alpha`, `brightness`,
+    /// `contrast`, `hue` (degrees), `gamma`, `show` and `rectangle`, a
+    /// `[west, south, east, north]` array in degrees the image is stretched
+    /// over. Returns the layer's id.
+    #[wasm_bindgen(js_name = addImageryLayer)]
+    pub fn add_imagery_layer(
+        &self,
+        globe: &str,
+        path: &str,
+        options: Option<Object>,
+    ) -> Result<u32, JsValue> {
+        let mut viewer = self.viewer.borrow_mut();
+        let entity = WebViewer::globe(&mut viewer, globe)?;
+        let style = WebViewer::imagery_style(ImageryLayerStyle::default(), options)?;
+        viewer
+            .add_imagery_layer(entity, path, style)
+            .ok_or_else(|| JsError::new("not a globe").into())
+    }
+
+    /// Drapes a WMS layer over the Earth, requested for the region in view as
+    /// the camera moves. `capabilitiesUrl` is the server's full GetCapabilities
+    /// URL; `options` are those of `addImageryLayer`, less `rectangle`. Returns
+    /// the id of the Earth imagery layer it fills.
+    #[wasm_bindgen(js_name = addWmsImagery)]
+    pub fn add_wms_imagery(
+        &self,
+        capabilities_url: &str,
+        layer: &str,
+        options: Option<Object>,
+    ) -> Result<u32, JsValue> {
+        let style = WebViewer::imagery_style(ImageryLayerStyle::default(), options)?;
+        Ok(self
+            .viewer
+            .borrow_mut()
+            .add_wms_imagery(capabilities_url, layer, style))
+    }
+
+    /// Like `addWmsImagery`, for a WMTS layer.
+    #[wasm_bindgen(js_name = addWmtsImagery)]
+    pub fn add_wmts_imagery(
+        &self,
+        capabilities_url: &str,
+        layer: &str,
+        options: Option<Object>,
+    ) -> Result<u32, JsValue> {
+        let style = WebViewer::imagery_style(ImageryLayerStyle::default(), options)?;
+        Ok(self
+            .viewer
+            .borrow_mut()
+            .add_wmts_imagery(capabilities_url, layer, style))
+    }
+
+    /// Drapes the raster tiles of an MBTiles or PMTiles archive over the Earth,
+    /// read with range requests from `url`, e.g. a file in `data/` served by
+    /// `server/server.js`. `options` are those of `addWmsImagery`.
+    #[wasm_bindgen(js_name = addArchiveImagery)]
+    pub fn add_archive_imagery(&self, url: &str, options: Option<Object>) -> Result<u32, JsValue> {
+        let style = WebViewer::imagery_style(ImageryLayerStyle::default(), options)?;
+        Ok(self.viewer.borrow_mut().add_archive_imagery(url, style))
+    }
+
+    /// Caps the memory streamed imagery tiles take, in megabytes: `gpu` in the
+    /// GPU tile atlas and `memory` of decoded tiles kept in memory.
+    #[wasm_bindgen(js_name = setTileCacheBudget)]
+    pub fn set_tile_cache_budget(&self, gpu: f64, memory: f64) {
+        let megabytes = |value: f64| (value.max(0.0) * 1024.0 * 1024.0) as u64;
+        self.viewer
+            .borrow_mut()
+            .set_tile_cache_budget(megabytes(gpu), megabytes(memory) as usize);
+    }
+
+    /// Changes the options given in `options`, leaving the rest as they are.
+    #[wasm_bindgen(js_name = setImageryLayerStyle)]
+    pub fn set_imagery_layer_style(
+        &self,
+        globe: &str,
+        id: u32,
+        options: Object,
+    ) -> Result<bool, JsValue> {
+        let mut viewer = self.viewer.borrow_mut();
+        let entity = WebViewer::globe(&mut viewer, globe)?;
+        let Some((_, style)) = viewer
+            .imagery_layers(entity)
+            .into_iter()
+            .find(|(layer, _)| *layer == id)
+        else {
+            return Ok(false);
+        };
+        let style = WebViewer::imagery_style(style, Some(options))?;
+        Ok(viewer.set_imagery_layer_style(entity, id, style))
+    }
+
+    /// Moves a layer to `index` in its stack, 0 being the bottom.
+    #[wasm_bindgen(js_name = moveImageryLayer)]
+    pub fn move_imagery_layer(&self, globe: &str, id: u32, index: usize) -> Result<bool, JsValue> {
+        let mut viewer = self.viewer.borrow_mut();
+        let entity = WebViewer::globe(&mut viewer, globe)?;
+        Ok(viewer.move_imagery_layer(entity, id, index))
+    }
+
+    #[wasm_bindgen(js_name = removeImageryLayer)]
+    pub fn remove_imagery_layer(&self, globe: &str, id: u32) -> Result<bool, JsValue> {
+        let mut viewer = self.viewer.borrow_mut();
+        let entity = WebViewer::globe(&mut viewer, globe)?;
+        Ok(viewer.remove_imagery_layer(entity, id))
+    }
+
+    /// The ids of a globe's imagery layers, bottom first.
+    #[wasm_bindgen(js_name = imageryLayers)]
+    pub fn imagery_layers(&self, globe: &str) -> Result<Vec<u32>, JsValue> {
+        let mut viewer = self.viewer.borrow_mut();
+        let entity = WebViewer::globe(&mut viewer, globe)?;
+        Ok(viewer
+            .imagery_layers(entity)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect())
+    }
+
+    #[wasm_bindgen(js_name = addBillboard)]
+    pub fn add_billboard(&self, lat: f32, lon: f32) -> u64 {
+        self.viewer.borrow_mut().add_billboard(lat, lon).to_bits()
+    }
+
+    /// `coordinates` is a flat `[lat, lon, lat, lon, ...]` array, `color` an
+    /// optional `[r, g, b, a]` in 0..1.
+    #[wasm_bindgen(js_name = addPolyline)]
+    pub fn add_polyline(
+        &self,
+        coordinates: Vec<f32>,
+        color: Option<Vec<f32>>,
+    ) -> Result<u64, JsValue> {
+        let (coordinates, color) = WebViewer::line_arguments(&coordinates, color, 2)?;
+        Ok(self
+            .viewer
+            .borrow_mut()
+            .add_polyline(coordinates, color)
+            .to_bits())
+    }
+
+    /// Outlines a polygon, closed back to its first vertex. Takes the same
+    /// arguments as `addPolyline`, with at least three vertices.
+    #[wasm_bindgen(js_name = addPolygon)]
+    pub fn add_polygon(
+        &self,
+        coordinates: Vec<f32>,
+        color: Option<Vec<f32>>,
+    ) -> Result<u64, JsValue> {
+        let (coordinates, color) = WebViewer::line_arguments(&coordinates, color, 3)?;
+        Ok(self
+            .viewer
+            .borrow_mut()
+            .add_polygon(coordinates, color)
+            .to_bits())
+    }
+
+    #[wasm_bindgen(js_name = removeEntity)]
+    pub fn remove_entity(&self, id: u64) -> bool {
+        self.viewer
+            .borrow_mut()
+            .remove_entity(Entity::from_bits(id))
+    }
+
+    /// Flies the camera to `altitude` kilometers above lat/lon.
+    #[wasm_bindgen(js_name = flyTo)]
+    pub fn fly_to(&self, lat: f32, lon: f32, altitude: f32, duration_seconds: Option<f32>) {
+        self.viewer
+            .borrow_mut()
+            .fly_to(lat, lon, altitude, duration_seconds.unwrap_or(2.0));
+    }
+
+    /// The camera, time, billboards, lines, polygons and imagery layers as a
+    /// document in `format`, `"json"` (the default) or `"ron"`, for `loadScene`.
+    #[wasm_bindgen(js_name = saveScene)]
+    pub fn save_scene(&self, format: Option<String>) -> Result<String, JsValue> {
+        let scene = self.viewer.borrow_mut().save_scene();
+        let text = match format.as_deref().unwrap_or("json") {
+            "json" => scene.to_json(),
+            "ron" => scene.to_ron(),
+            format => return Err(JsError::new(&format!("unknown scene format {format}")).into()),
+        };
+        text.map_err(|e| JsError::new(&e.to_string()).into())
+    }
+
+    /// Replaces what the page added with a document from `saveScene`.
+    #[wasm_bindgen(js_name = loadScene)]
+    pub fn load_scene(&self, text: &str, format: Option<String>) -> Result<(), JsValue> {
+        let scene = match format.as_deref().unwrap_or("json") {
+            "json" => SceneDocument::from_json(text),
+            "ron" => SceneDocument::from_ron(text),
+            format => return Err(JsError::new(&format!("unknown scene format {format}")).into()),
+        }
+        .map_err(|e| JsError::new(&e.to_string()))?;
+        self.viewer
+            .borrow_mut()
+            .load_scene(&scene)
+            .map_err(|e| JsError::new(&e.to_string()).into())
+    }
+
+    /// Sets the simulation time from an ISO 8601 / Gregorian string,
+    /// e.g. `2024-03-20T03:06:00 UTC`.
+    #[wasm_bindgen(js_name = setTime)]
+    pub fn set_time(&self, time: &str) -> Result<(), JsValue> {
+        let epoch = Epoch::from_str(time).map_err(|e| JsError::new(&e.to_string()))?;
+        self.viewer.borrow_mut().set_epoch(epoch);
+        Ok(())
+    }
+
+    /// Sets the simulation time from milliseconds since the Unix epoch, as
+    /// returned by `Date.now()`.
+    #[wasm_bindgen(js_name = setTimeMillis)]
+    pub fn set_time_millis(&self, millis: f64) {
+        self.viewer
+            .borrow_mut()
+            .set_epoch(Epoch::from_unix_milliseconds(millis));
+    }
+
+    #[wasm_bindgen(js_name = getTimeMillis)]
+    pub fn time_millis(&self) -> f64 {
+        self.viewer.borrow().epoch().to_unix_milliseconds()
+    }
+
+    #[wasm_bindgen(js_name = setTimeMultiplier)]
+    pub fn set_time_multiplier(&self, multiplier: f64) {
+        self.viewer.borrow_mut().set_time_multiplier(multiplier);
+    }
+
+    #[wasm_bindgen(js_name = setPaused)]
+    pub fn set_paused(&self, paused: bool) {
+        self.viewer.borrow_mut().set_paused(paused);
+    }
+
+    /// Shows the Sun and planets, with radii multiplied by `radiusScale`
+    /// (default 1000) and orbit paths unless `showOrbits` is false. Calling it
+    /// again updates the options.
+    #[wasm_bindgen(js_name = setSolarSystemMode)]
+    pub fn set_solar_system_mode(
+        &self,
+        enabled: bool,
+        radius_scale: Option<f32>,
+        show_orbits: Option<bool>,
+    ) {
+        let mut viewer = self.viewer.borrow_mut();
+        if enabled {
+            viewer.enable_solar_system(
+                radius_scale.unwrap_or(DEFAULT_RADIUS_SCALE),
+                show_orbits.unwrap_or(true),
+            );
+        } else {
+            viewer.disable_solar_system();
+        }
+    }
+
+    #[wasm_bindgen(js_name = setAtmosphereVisible)]
+    pub fn set_atmosphere_visible(&self, visible: bool) {
+        self.viewer.borrow_mut().set_atmosphere_visible(visible);
+    }
+
+    /// Shows or hides the clouds loaded with `loadEarthMap("clouds", ...)`.
+    #[wasm_bindgen(js_name = setCloudsVisible)]
+    pub fn set_clouds_visible(&self, visible: bool) {
+        self.viewer.borrow_mut().set_clouds_visible(visible);
+    }
+
+    #[wasm_bindgen(js_name = setCloudShadows)]
+    pub fn set_cloud_shadows(&self, enabled: bool) {
+        self.viewer.borrow_mut().set_cloud_shadows(enabled);
+    }
+
+    /// Eastward cloud drift in degrees of longitude per simulated hour.
+    #[wasm_bindgen(js_name = setCloudDrift)]
+    pub fn set_cloud_drift(&self, degrees_per_hour: f64) {
+        self.viewer.borrow_mut().set_cloud_drift(degrees_per_hour);
+    }
+
+    /// Shows or hides the latitude/longitude grid.
+    #[wasm_bindgen(js_name = setGraticuleVisible)]
+    pub fn set_graticule_visible(&self, visible: bool) {
+        self.viewer.borrow_mut().set_graticule_visible(visible);
+    }
+
+    /// `[{ text, axis, x, y }]` labels for the grid lines in view, as of the
+    /// last frame. `axis` is `"lat"` or `"lon"` and `x`, `y` are drawing buffer
+    /// pixels from the top left, for the page to draw the text over the canvas.
+    #[wasm_bindgen(js_name = graticuleLabels)]
+    pub fn graticule_labels(&self) -> js_sys::Array {
+        let labels = js_sys::Array::new();
+        for label in self.viewer.borrow().graticule_labels() {
+            let entry = Object::new();
+            let axis = match label.axis {
+                GraticuleAxis::Latitude => "lat",
+                GraticuleAxis::Longitude => "lon",
+            };
+            let _ = Reflect::set(&entry, &"text".into(), &label.text.as_str().into());
+            let _ = Reflect::set(&entry, &"axis".into(), &axis.into());
+            let _ = Reflect::set(&entry, &"x".into(), &label.x.into());
+            let _ = Reflect::set(&entry, &"y".into(), &label.y.into());
+            labels.push(&entry);
+        }
+        labels
+    }
+
+    #[wasm_bindgen(js_name = setPlaceBillboardOnClick)]
+    pub fn set_place_billboard_on_click(&self, enabled: bool) {
+        self.viewer
+            .borrow_mut()
+            .set_place_billboard_on_click(enabled);
+    }
+
+    /// While on, clicks on the globe add measurement points instead of billboards.
+    /// Turning it on starts a new measurement.
+    #[wasm_bindgen(js_name = setMeasureMode)]
+    pub fn set_measure_mode(&self, enabled: bool) {
+        self.viewer.borrow_mut().set_measure_mode(enabled);
+    }
+
+    #[wasm_bindgen(js_name = clearMeasurement)]
+    pub fn clear_measurement(&self) {
+        self.viewer.borrow_mut().clear_measurement();
+    }
+
+    /// The current measurement, shaped like the `onMeasure` payload.
+    pub fn measurement(&self) -> Object {
+        WebViewer::measurement_object(self.viewer.borrow().measurement())
+    }
+
+    /// `callback({ points, distance, bearing, area, segments })` as each point is
+    /// measured. `points` is a flat `[lat, lon, ...]` array, `distance` meters
+    /// along the whole path, `bearing` the initial bearing in degrees of the last
+    /// segment or `null`, `area` square meters of the closed polygon and
+    /// `segments` one `{ distance, initialBearing, finalBearing }` per segment.
+    #[wasm_bindgen(js_name = onMeasure)]
+    pub fn on_measure(&self, callback: Option<Function>) {
+        self.callbacks.borrow_mut().measure = callback;
+    }
+
+    /// Makes `input` perform `action` as well as whatever it already does.
+    /// `input` is a `KeyboardEvent.code` such as `"KeyM"`, or `"MouseLeft"`,
+    /// `"MouseMiddle"`, `"MouseRight"` or `"Touch"`. `action` is one of
+    /// `"orbitLeft"`, `"orbitRight"`, `"zoomIn"`, `"zoomOut"`, `"placeMarker"`,
+    /// `"measure"` or `"select"`.
+    #[wasm_bindgen(js_name = bindInput)]
+    pub fn bind_input(&self, input: &str, action: &str) -> Result<(), JsValue> {
+        let trigger = WebViewer::input_trigger(input)?;
+        let action = INPUT_ACTIONS
+            .iter()
+            .find(|(name, _)| *name == action)
+            .map(|&(_, action)| action)
+            .ok_or_else(|| JsError::new(&format!("unknown action {action}")))?;
+        self.viewer.borrow_mut().bind_input(trigger, action);
+        Ok(())
+    }
+
+    /// Makes `input` perform nothing.
+    #[wasm_bindgen(js_name = unbindInput)]
+    pub fn unbind_input(&self, input: &str) -> Result<(), JsValue> {
+        let trigger = WebViewer::input_trigger(input)?;
+        self.viewer.borrow_mut().unbind_input(trigger);
+        Ok(())
+    }
+
+    /// Restores the default bindings: W/S and the up and down arrows zoom,
+    /// A/D and the left and right arrows orbit, and a left click or a tap
+    /// selects.
+    #[wasm_bindgen(js_name = resetInputBindings)]
+    pub fn reset_input_bindings(&self) {
+        self.viewer
+            .borrow_mut()
+            .set_input_bindings(InputBindings::default());
+    }
+
+    /// `[{ input, action }]`, one per binding, named as in `bindInput`.
+    #[wasm_bindgen(js_name = inputBindings)]
+    pub fn input_bindings(&self) -> js_sys::Array {
+        let bindings = js_sys::Array::new();
+        for &(trigger, action) in &self.viewer.borrow().input_bindings().bindings {
+            let action = INPUT_ACTIONS
+                .iter()
+                .find(|(_, named)| *named == action)
+                .map_or("", |&(name, _)| name);
+            let entry = Object::new();
+            let input = WebViewer::input_name(trigger);
+            let _ = Reflect::set(&entry, &"input".into(), &input.into());
+            let _ = Reflect::set(&entry, &"action".into(), &action.into());
+            bindings.push(&entry);
+        }
+        bindings
+    }
+
+    /// How positions are written out: `"degrees"`, `"dms"`, `"utm"` or `"mgrs"`.
+    /// `precision` is decimals of degrees, seconds or meters, or MGRS digits
+    /// per axis.
+    #[wasm_bindgen(js_name = setCoordinateFormat)]
+    pub fn set_coordinate_format(
+        &self,
+        format: &str,
+        precision: Option<usize>,
+    ) -> Result<(), JsValue> {
+        let format = match format {
+            "degrees" => CoordinateFormat::DecimalDegrees {
+                decimals: precision.unwrap_or(5),
+            },
+            "dms" => CoordinateFormat::Dms {
+                decimals: precision.unwrap_or(1),
+            },
+            "utm" => CoordinateFormat::Utm {
+                decimals: precision.unwrap_or(0),
+            },
+            "mgrs" => CoordinateFormat::Mgrs {
+                precision: precision.unwrap_or(5),
+            },
+            _ => return Err(JsError::new(&format!("unknown coordinate format {format}")).into()),
+        };
+        self.viewer.borrow_mut().set_coordinate_format(format);
+        Ok(())
+    }
+
+    /// A position written out in the coordinate format.
+    #[wasm_bindgen(js_name = formatPosition)]
+    pub fn format_position(&self, lat: f32, lon: f32) -> String {
+        self.viewer.borrow().format_position(lat, lon)
+    }
+
+    /// The globe position under the cursor in the coordinate format, or
+    /// `undefined` off the globe.
+    #[wasm_bindgen(js_name = cursorPosition)]
+    pub fn cursor_position(&self) -> Option<String> {
+        self.viewer.borrow().cursor_position()
+    }
+
+    /// `[lat, lon]` of a UTM (`"33N 500000 4649776"`) or UPS
+    /// (`"N 2000000 2000000"`) coordinate.
+    #[wasm_bindgen(js_name = parseUtm)]
+    pub fn parse_utm(text: &str) -> Result<Vec<f64>, JsValue> {
+        let coordinate = UtmSystem::parse(text).map_err(|e| JsError::new(&e.to_string()))?;
+        let (lat, lon) = UtmSystem::to_lat_lon(&coordinate);
+        Ok(vec![lat, lon])
+    }
+
+    /// `[lat, lon]` of the south-west corner of an MGRS square.
+    #[wasm_bindgen(js_name = parseMgrs)]
+    pub fn parse_mgrs(text: &str) -> Result<Vec<f64>, JsValue> {
+        let coordinate = MgrsSystem::parse(text).map_err(|e| JsError::new(&e.to_string()))?;
+        let (lat, lon) = UtmSystem::to_lat_lon(&coordinate);
+        Ok(vec![lat, lon])
+    }
+
+    /// `callback({ lat, lon, entity, position })` on every left click over the
+    /// globe. `entity` is the picked entity id or `null`, `position` the point
+    /// in the coordinate format. Pass `undefined` to unsubscribe.
+    #[wasm_bindgen(js_name = onClick)]
+    pub fn on_click(&self, callback: Option<Function>) {
+        self.callbacks.borrow_mut().click = callback;
+    }
+
+    /// `callback({ lat, lon, entity, position })` whenever the cursor moves to
+    /// another point of the globe or onto another entity. The cursor is only
+    /// picked while a callback is set.
+    #[wasm_bindgen(js_name = onHover)]
+    pub fn on_hover(&self, callback: Option<Function>) {
+        self.viewer
+            .borrow_mut()
+            .set_hover_events(callback.is_some());
+        self.callbacks.borrow_mut().hover = callback;
+    }
+
+    /// `callback({ path, completed, total, error })` as each startup asset
+    /// finishes loading. `error` is a message if the asset failed, else `null`.
+    #[wasm_bindgen(js_name = onLoad)]
+    pub fn on_load(&self, callback: Option<Function>) {
+        self.callbacks.borrow_mut().load = callback;
+    }
+
+    /// `[completed, total]` startup assets, counting failures as completed.
+    #[wasm_bindgen(js_name = loadingProgress)]
+    pub fn loading_progress(&self) -> Vec<u32> {
+        let (completed, total) = self.viewer.borrow().loading_progress();
+        vec![completed as u32, total as u32]
+    }
+
+    /// Resizes the drawing buffer, in physical pixels.
+    pub fn resize(&self, width: u32, height: u32) {
+        self.canvas.set_width(width);
+        self.canvas.set_height(height);
+        self.viewer
+            .borrow_mut()
+            .resize(winit::dpi::PhysicalSize::new(width, height));
+    }
+}
+
+impl WebViewer {
+    fn kernel(path: &str) -> Result<Kernel, JsValue> {
+        Kernel::from_path(path).ok_or_else(|| {
+            JsError::new(&format!("{path} is not a .bsp, .bpc or .pca kernel")).into()
+        })
+    }
+
+    fn add_listeners(&mut self) -> Result<(), JsValue> {
+        let viewer = self.viewer.clone();
+        let canvas = self.canvas.clone();
+        self.listen("mousemove", move |event| {
+            if let Some(event) = event.dyn_ref::<MouseEvent>() {
+                let (x, y) = WebViewer::canvas_position(&canvas, event);
+                viewer.borrow_mut().pointer_moved(x, y);
+            }
+        })?;
+
+        let viewer = self.viewer.clone();
+        let canvas = self.canvas.clone();
+        self.listen("mousedown", move |event| {
+            if let Some(event) = event.dyn_ref::<MouseEvent>() {
+                let (x, y) = WebViewer::canvas_position(&canvas, event);
+                let mut viewer = viewer.borrow_mut();
+                viewer.pointer_moved(x, y);
+                viewer.pointer_button(WebViewer::mouse_button(event.button()), true);
+            }
+        })?;
+
+        let viewer = self.viewer.clone();
+        self.listen("mouseup", move |event| {
+            if let Some(event) = event.dyn_ref::<MouseEvent>() {
+                viewer
+                    .borrow_mut()
+                    .pointer_button(WebViewer::mouse_button(event.button()), false);
+            }
+        })?;
+
+        for (kind, pressed) in [
+            ("touchstart", true),
+            ("touchend", false),
+            ("touchcancel", false),
+        ] {
+            let viewer = self.viewer.clone();
+            let canvas = self.canvas.clone();
+            self.listen(kind, move |event| {
+                let Some(event) = event.dyn_ref::<TouchEvent>() else {
+                    return;
+                };
+                // the finger that touched or lifted
+                let Some(touch) = event.changed_touches().get(0) else {
+                    return;
+                };
+                let (x, y) = WebViewer::touch_position(&canvas, &touch);
+                let mut viewer = viewer.borrow_mut();
+                viewer.pointer_moved(x, y);
+                if viewer.touch_input(pressed) {
+                    // or the browser follows up with mouse events
+                    event.prevent_default();
+                }
+            })?;
+        }
+
+        // a right button bound to an action shouldn't open the context menu
+        let viewer = self.viewer.clone();
+        self.listen("contextmenu", move |event| {
+            let right = InputTrigger::Mouse(MouseButton::Right);
+            let bound = viewer
+                .borrow()
+                .input_bindings()
+                .bindings
+                .iter()
+                .any(|(trigger, _)| *trigger == right);
+            if bound {
+                event.prevent_default();
+            }
+        })?;
+
+        for (kind, pressed) in [("keydown", true), ("keyup", false)] {
+            let viewer = self.viewer.clone();
+            self.listen(kind, move |event| {
+                let Some(event) = event.dyn_ref::<KeyboardEvent>() else {
+                    return;
+                };
+                if let Some(keycode) = WebViewer::virtual_keycode(&event.code()) {
+                    if viewer.borrow_mut().key_input(keycode, pressed) {
+                        event.prevent_default();
+                    }
+                }
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn listen(
+        &mut self,
+        kind: &'static str,
+        handler: impl FnMut(web_sys::Event) + 'static,
+    ) -> Result<(), JsValue> {
+        let listener: Listener = Closure::new(handler);
+        self.canvas
+            .add_event_listener_with_callback(kind, listener.as_ref().unchecked_ref())?;
+        self.listeners.push((kind, listener));
+        Ok(())
+    }
+
+    fn start_frame_loop(&mut self) -> Result<(), JsValue> {
+        let viewer = self.viewer.clone();
+        let callbacks = self.callbacks.clone();
+        let frame = self.frame.clone();
+        let frame_id = self.frame_id.clone();
+
+        *self.frame.borrow_mut() = Some(Closure::new(move || {
+            let (events, format) = {
+                let mut viewer = viewer.borrow_mut();
+                viewer.update();
+                match viewer.render() {
+                    Ok(_) => {}
+                    // Reconfigure the surface if lost
+                    Err(wgpu::SurfaceError::Lost) => {
+                        let size = viewer.size();
+                        viewer.resize(size);
+                    }
+                    Err(e) => tracing::warn!("{:?}", e),
+                }
+                (viewer.drain_events(), viewer.coordinate_format())
+            };
+
+            // the viewer is released first so callbacks can call back into it
+            WebViewer::dispatch(&callbacks, events, format);
+
+            if let Some(closure) = frame.borrow().as_ref() {
+                if let Ok(id) = WebViewer::request_animation_frame(closure) {
+                    frame_id.set(id);
+                }
+            }
+        }));
+
+        let id = WebViewer::request_animation_frame(self.frame.borrow().as_ref().unwrap())?;
+        self.frame_id.set(id);
+        Ok(())
+    }
+
+    fn globe(viewer: &mut Viewer, name: &str) -> Result<Entity, JsValue> {
+        viewer
+            .globe_entity(name)
+            .ok_or_else(|| JsError::new(&format!("unknown globe {name}")).into())
+    }
+
+    // `options` overrides the fields of `style` it has
+    fn imagery_style(
+        mut style: ImageryLayerStyle,
+        options: Option<Object>,
+    ) -> Result<ImageryLayerStyle, JsValue> {
+        let Some(options) = options else {
+            return Ok(style);
+        };
+        let get = |key: &str| {
+            Reflect::get(&options, &key.into())
+                .ok()
+                .filter(|value| !value.is_undefined())
+        };
+        let number = |key: &str, value: &mut f32| -> Result<(), JsValue> {
+            if let Some(option) = get(key) {
+                *value = option
+                    .as_f64()
+                    .ok_or_else(|| JsError::new(&format!("{key} must be a number")))?
+                    as f32;
+            }
+            Ok(())
+        };
+        number("alpha", &mut style.alpha)?;
+        number("brightness", &mut style.brightness)?;
+        number("contrast", &mut style.contrast)?;
+        number("hue", &mut style.hue)?;
+        number("gamma", &mut style.gamma)?;
+        if let Some(show) = get("show") {
+            style.show = show
+                .as_bool()
+                .ok_or_else(|| JsError::new("show must be a boolean"))?;
+        }
+        if let Some(rectangle) = get("rectangle") {
+            let rectangle: Vec<f32> = js_sys::Array::from(&rectangle)
+                .iter()
+                .filter_map(|value| value.as_f64())
+                .map(|value| value as f32)
+                .collect();
+            style.rectangle = match rectangle[..] {
+                [west, south, east, north] => GeoRectangle {
+                    west,
+                    south,
+                    east,
+                    north,
+                },
+                _ => {
+                    return Err(JsError::new("rectangle must be [west, south, east, north]").into())
+                }
+            };
+        }
+        Ok(style)
+    }
+
+    fn request_animation_frame(closure: &Closure<dyn FnMut()>) -> Result<i32, JsValue> {
+        web_sys::window()
+            .ok_or_else(|| JsValue::from_str("no window"))?
+            .request_animation_frame(closure.as_ref().unchecked_ref())
+    }
+
+    // Each callback is cloned out before it runs, so handlers can subscribe,
+    // unsubscribe or swap themselves out without a nested borrow.
+    fn dispatch(
+        callbacks: &RefCell<Callbacks>,
+        events: Vec<ViewerEvent>,
+        format: CoordinateFormat,
+    ) {
+        for event in events {
+            // setting plain data properties on a fresh object cannot fail
+            let payload = Object::new();
+            let set = |key: &str, value: JsValue| {
+                let _ = Reflect::set(&payload, &key.into(), &value);
+            };
+            let entity_id = |entity: Option<Entity>| {
+                entity
+                    .map(|entity| JsValue::from(entity.to_bits()))
+                    .unwrap_or(JsValue::NULL)
+            };
+
+            let callback = match event {
+                ViewerEvent::Click { lat, lon, entity }
+                | ViewerEvent::Hover { lat, lon, entity } => {
+                    set("lat", lat.into());
+                    set("lon", lon.into());
+                    set("entity", entity_id(entity));
+                    set(
+                        "position",
+                        CoordinatesSystem::format(lat as f64, lon as f64, format).into(),
+                    );
+                    if matches!(event, ViewerEvent::Click { .. }) {
+                        callbacks.borrow().click.clone()
+                    } else {
+                        callbacks.borrow().hover.clone()
+                    }
+                }
+                ViewerEvent::AssetLoaded {
+                    path,
+                    completed,
+                    total,
+                } => {
+                    set("path", path.into());
+                    set("completed", completed.into());
+                    set("total", total.into());
+                    set("error", JsValue::NULL);
+                    callbacks.borrow().load.clone()
+                }
+                ViewerEvent::AssetFailed {
+                    path,
+                    error,
+                    completed,
+                    total,
+                } => {
+                    set("path", path.into());
+                    set("completed", completed.into());
+                    set("total", total.into());
+                    set("error", error.into());
+                    callbacks.borrow().load.clone()
+                }
+                ViewerEvent::Measured(measurement) => {
+                    let callback = callbacks.borrow().measure.clone();
+                    if let Some(callback) = callback {
+                        let payload = WebViewer::measurement_object(&measurement);
+                        if let Err(e) = callback.call1(&JsValue::NULL, &payload) {
+                            tracing::warn!("viewer callback threw: {:?}", e);
+                        }
+                    }
+                    continue;
+                }
+            };
+
+            if let Some(callback) = callback {
+                if let Err(e) = callback.call1(&JsValue::NULL, &payload) {
+                    tracing::warn!("viewer callback threw: {:?}", e);
+                }
+            }
+        }
+    }
+
+    // a flat `[lat, lon, ...]` array of at least `min_points` vertices and an
+    // optional `[r, g, b, a]`
+    fn line_arguments(
+        coordinates: &[f32],
+        color: Option<Vec<f32>>,
+        min_points: usize,
+    ) -> Result<(Vec<(f32, f32)>, [f32; 4]), JsValue> {
+        if coordinates.len() < 2 * min_points || !coordinates.len().is_multiple_of(2) {
+            let message = format!("expected at least {min_points} [lat, lon] pairs");
+            return Err(JsError::new(&message).into());
+        }
+        let coordinates = coordinates
+            .chunks_exact(2)
+            .map(|pair| (pair[0], pair[1]))
+            .collect();
+        let color = match color.as_deref() {
+            Some(&[r, g, b, a]) => [r, g, b, a],
+            Some(_) => return Err(JsError::new("color must be [r, g, b, a]").into()),
+            None => DEFAULT_POLYLINE_COLOR,
+        };
+        Ok((coordinates, color))
+    }
+
+    fn measurement_object(measurement: &Measurement) -> Object {
+        let object = Object::new();
+        let set = |object: &Object, key: &str, value: JsValue| {
+            let _ = Reflect::set(object, &key.into(), &value);
+        };
+
+        let points: Vec<f32> = measurement
+            .points
+            .iter()
+            .flat_map(|&(lat, lon)| [lat, lon])
+            .collect();
+        let segments = js_sys::Array::new();
+        for segment in &measurement.segments {
+            let entry = Object::new();
+            set(&entry, "distance", segment.distance.into());
+            set(&entry, "initialBearing", segment.initial_bearing.into());
+            set(&entry, "finalBearing", segment.final_bearing.into());
+            segments.push(&entry);
+        }
+        let bearing = measurement
+            .segments
+            .last()
+            .map(|segment| JsValue::from(segment.initial_bearing))
+            .unwrap_or(JsValue::NULL);
+
+        set(
+            &object,
+            "points",
+            js_sys::Float32Array::from(&points[..]).into(),
+        );
+        set(&object, "distance", measurement.distance.into());
+        set(&object, "bearing", bearing);
+        set(&object, "area", measurement.area.into());
+        set(&object, "segments", segments.into());
+        object
+    }
+
+    // CSS pixels to drawing buffer pixels
+    fn canvas_position(canvas: &HtmlCanvasElement, event: &MouseEvent) -> (f64, f64) {
+        let scale_x = canvas.width() as f64 / canvas.client_width().max(1) as f64;
+        let scale_y = canvas.height() as f64 / canvas.client_height().max(1) as f64;
+        (
+            event.offset_x() as f64 * scale_x,
+            event.offset_y() as f64 * scale_y,
+        )
+    }
+
+    // a touch's CSS pixels to drawing buffer pixels
+    fn touch_position(canvas: &HtmlCanvasElement, touch: &Touch) -> (f64, f64) {
+        let rect = canvas.get_bounding_client_rect();
+        let scale_x = canvas.width() as f64 / canvas.client_width().max(1) as f64;
+        let scale_y = canvas.height() as f64 / canvas.client_height().max(1) as f64;
+        (
+            (touch.client_x() as f64 - rect.left()) * scale_x,
+            (touch.client_y() as f64 - rect.top()) * scale_y,
+        )
+    }
+
+    fn mouse_button(button: i16) -> MouseButton {
+        match button {
+            0 => MouseButton::Left,
+            1 => MouseButton::Middle,
+            2 => MouseButton::Right,
+            other => MouseButton::Other(other as u16),
+        }
+    }
+
+    // maps `KeyboardEvent.code` to winit's key codes
+    fn virtual_keycode(code: &str) -> Option<VirtualKeyCode> {
+        KEY_CODES
+            .iter()
+            .find(|(name, _)| *name == code)
+            .map(|&(_, keycode)| keycode)
+    }
+
+    // `"KeyM"`, `"MouseLeft"`, `"Mouse3"`, `"Touch"` and so on
+    fn input_trigger(name: &str) -> Result<InputTrigger, JsValue> {
+        let trigger = match name {
+            "MouseLeft" => InputTrigger::Mouse(MouseButton::Left),
+            "MouseMiddle" => InputTrigger::Mouse(MouseButton::Middle),
+            "MouseRight" => InputTrigger::Mouse(MouseButton::Right),
+            "Touch" => InputTrigger::Touch,
+            _ => {
+                let button = name
+                    .strip_prefix("Mouse")
+                    .and_then(|button| button.parse().ok());
+                match (button, WebViewer::virtual_keycode(name)) {
+                    (Some(button), _) => InputTrigger::Mouse(WebViewer::mouse_button(button)),
+                    (None, Some(keycode)) => InputTrigger::Key(keycode),
+                    (None, None) => {
+                        return Err(JsError::new(&format!("unknown input {name}")).into())
+                    }
+                }
+            }
+        };
+        Ok(trigger)
+    }
+
+    fn input_name(trigger: InputTrigger) -> String {
+        match trigger {
+            InputTrigger::Key(keycode) => KEY_CODES
+                .iter()
+                .find(|(_, named)| *named == keycode)
+                .map_or_else(|| format!("{keycode:?}"), |(name, _)| name.to_string()),
+            InputTrigger::Mouse(MouseButton::Left) => "MouseLeft".to_string(),
+            InputTrigger::Mouse(MouseButton::Middle) => "MouseMiddle".to_string(),
+            InputTrigger::Mouse(MouseButton::Right) => "MouseRight".to_string(),
+            InputTrigger::Mouse(MouseButton::Other(button)) => format!("Mouse{button}"),
+            InputTrigger::Touch => "Touch".to_string(),
+        }
+    }
+}
+
+impl Drop for WebViewer {
+    fn drop(&mut self) {
+        if let Some(window) = web_sys::window() {
+            let _ = window.cancel_animation_frame(self.frame_id.get());
+        }
+        // breaks the closure's reference to itself
+        self.frame.borrow_mut().take();
+
+        for (kind, listener) in self.listeners.drain(..) {
+            let _ = self
+                .canvas
+                .remove_event_listener_with_callback(kind, listener.as_ref().unchecked_ref());
+        }
+    }
+}
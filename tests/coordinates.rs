@@ -0,0 +1,61 @@
+use hypersphere::systems::geospatial::{
+    coordinates::{CoordinateFormat, CoordinatesSystem},
+    mgrs::MgrsSystem,
+    utm::{Hemisphere, UtmSystem},
+};
+
+// the Eiffel Tower, the point the format docs write out
+const LAT: f64 = 48.85837;
+const LON: f64 = 2.29448;
+
+fn format(format: CoordinateFormat) -> String {
+    CoordinatesSystem::format(LAT, LON, format)
+}
+
+#[test]
+fn formats_documented_examples() {
+    assert_eq!(
+        format(CoordinateFormat::DecimalDegrees { decimals: 5 }),
+        "48.85837, 2.29448"
+    );
+    assert_eq!(
+        format(CoordinateFormat::Dms { decimals: 1 }),
+        "48°51'30.1\"N 2°17'40.1\"E"
+    );
+    assert_eq!(
+        format(CoordinateFormat::Utm { decimals: 0 }),
+        "31N 448251 5411952"
+    );
+    assert_eq!(
+        format(CoordinateFormat::Mgrs { precision: 5 }),
+        "31U DQ 48250 11951"
+    );
+}
+
+#[test]
+fn truncates_mgrs_digits() {
+    // the UTM position is 448250.50 E, 5411951.59 N, which UTM rounds
+    assert_eq!(MgrsSystem::format(LAT, LON, 0), "31U DQ");
+    assert_eq!(MgrsSystem::format(LAT, LON, 1), "31U DQ 4 1");
+    assert_eq!(MgrsSystem::format(LAT, LON, 3), "31U DQ 482 119");
+}
+
+#[test]
+fn parses_documented_examples() {
+    let spaced = MgrsSystem::parse("31U DQ 48250 11951").unwrap();
+    assert_eq!(MgrsSystem::parse("31UDQ4825011951").unwrap(), spaced);
+    assert_eq!((spaced.zone, spaced.hemisphere), (31, Hemisphere::North));
+    assert_eq!((spaced.easting, spaced.northing), (448_250.0, 5_411_951.0));
+
+    // the square's corner lies within a meter of the point
+    let (lat, lon) = UtmSystem::to_lat_lon(&spaced);
+    assert!((lat - LAT).abs() < 1e-5 && (lon - LON).abs() < 1e-5);
+    assert_eq!(
+        MgrsSystem::format(lat + 1e-6, lon + 1e-6, 5),
+        "31U DQ 48250 11951"
+    );
+
+    let polar = MgrsSystem::parse("ZGC 2000 3000").unwrap();
+    assert!(polar.is_ups());
+    assert_eq!(polar.hemisphere, Hemisphere::North);
+}
@@ -0,0 +1,97 @@
+"""Writes the MBTiles fixtures the loader tests read.
+
+Run from this directory with `python3 make_mbtiles.py`. Small 512-byte pages
+give the tables and indexes interior b-tree pages, and one large tile spills
+onto a chain of overflow pages.
+"""
+
+import os
+import sqlite3
+
+MAX_ZOOM = 4
+# the tile stored across overflow pages, in XYZ
+LARGE_TILE = (2, 1, 2)
+
+
+def tile_data(z, x, y):
+    if (z, x, y) == LARGE_TILE:
+        return bytes(i % 251 for i in range(3000))
+    return f"{z}/{x}/{y}".encode() * 2
+
+
+def tiles():
+    for z in range(MAX_ZOOM + 1):
+        for x in range(1 << z):
+            for y in range(1 << z):
+                # MBTiles counts rows up from the south
+                yield z, x, (1 << z) - 1 - y, tile_data(z, x, y)
+
+
+def create(path):
+    if os.path.exists(path):
+        os.remove(path)
+    db = sqlite3.connect(path)
+    db.execute("PRAGMA page_size = 512")
+    db.execute("CREATE TABLE metadata (name TEXT, value TEXT)")
+    return db
+
+
+def flat(path):
+    # no minzoom or maxzoom, so the zoom range comes from the index
+    db = create(path)
+    db.executemany(
+        "INSERT INTO metadata VALUES (?, ?)", [("name", "flat"), ("format", "png")]
+    )
+    db.execute(
+        "CREATE TABLE tiles (zoom_level INTEGER, tile_column INTEGER, "
+        "tile_row INTEGER, tile_data BLOB)"
+    )
+    db.execute(
+        "CREATE UNIQUE INDEX tile_index ON tiles (zoom_level, tile_column, tile_row)"
+    )
+    db.executemany("INSERT INTO tiles VALUES (?, ?, ?, ?)", tiles())
+    db.commit()
+    db.execute("VACUUM")
+    db.close()
+
+
+def map_images(path):
+    db = create(path)
+    db.executemany(
+        "INSERT INTO metadata VALUES (?, ?)",
+        [("name", "map_images"), ("format", "png"), ("minzoom", "1"), ("maxzoom", "4")],
+    )
+    db.execute(
+        "CREATE TABLE map (zoom_level INTEGER, tile_column INTEGER, "
+        "tile_row INTEGER, tile_id TEXT)"
+    )
+    db.execute("CREATE TABLE images (tile_data BLOB, tile_id TEXT)")
+    db.execute(
+        "CREATE UNIQUE INDEX map_index ON map (zoom_level, tile_column, tile_row)"
+    )
+    db.execute("CREATE UNIQUE INDEX images_id ON images (tile_id)")
+    db.execute(
+        "CREATE VIEW tiles AS SELECT map.zoom_level AS zoom_level, "
+        "map.tile_column AS tile_column, map.tile_row AS tile_row, "
+        "images.tile_data AS tile_data FROM map JOIN images "
+        "ON images.tile_id = map.tile_id"
+    )
+    images = {}
+    for z, x, row, data in tiles():
+        # identical tiles share one image, as deduplicating writers do
+        tile_id = "blank" if z == MAX_ZOOM and x % 2 == 0 else f"{z}-{x}-{row}"
+        if tile_id == "blank":
+            data = b"blank"
+        images[tile_id] = data
+        db.execute("INSERT INTO map VALUES (?, ?, ?, ?)", (z, x, row, tile_id))
+    db.executemany(
+        "INSERT INTO images VALUES (?, ?)", [(data, id) for id, data in images.items()]
+    )
+    db.commit()
+    db.execute("VACUUM")
+    db.close()
+
+
+if __name__ == "__main__":
+    flat("flat.mbtiles")
+    map_images("map_images.mbtiles")
@@ -0,0 +1,154 @@
+"""Writes the PMTiles fixtures the loader tests read.
+
+Run from this directory with `python3 make_pmtiles.py`. The tiles are those of
+make_mbtiles.py, except that every zoom 3 tile holds the same bytes and is
+stored once, as a single run-length entry. `root.pmtiles` keeps every entry in
+an uncompressed root directory; `leaves.pmtiles` gzips its directories and
+splits the entries across leaf directories.
+"""
+
+import gzip
+import struct
+
+from make_mbtiles import MAX_ZOOM, tile_data
+
+# every zoom 3 tile is this, one run of consecutive tile ids
+RUN_ZOOM = 3
+RUN_DATA = b"blank"
+# entries per leaf directory in leaves.pmtiles
+LEAF_SIZE = 20
+
+NONE = 1
+GZIP = 2
+PNG = 2
+
+
+def tile_id(z, x, y):
+    # every tile of the shallower zooms, then the place along the Hilbert curve
+    first = ((1 << (2 * z)) - 1) // 3
+    d = 0
+    s = (1 << z) // 2
+    while s > 0:
+        rx = 1 if x & s else 0
+        ry = 1 if y & s else 0
+        d += s * s * ((3 * rx) ^ ry)
+        if ry == 0:
+            if rx == 1:
+                x = s - 1 - (x & (s - 1))
+                y = s - 1 - (y & (s - 1))
+            x, y = y, x
+        s //= 2
+    return first + d
+
+
+def tiles():
+    for z in range(MAX_ZOOM + 1):
+        for x in range(1 << z):
+            for y in range(1 << z):
+                data = RUN_DATA if z == RUN_ZOOM else tile_data(z, x, y)
+                yield tile_id(z, x, y), data
+
+
+def varint(value):
+    out = bytearray()
+    while True:
+        byte = value & 0x7F
+        value >>= 7
+        if value:
+            out.append(byte | 0x80)
+        else:
+            out.append(byte)
+            return bytes(out)
+
+
+def directory(entries):
+    # entries are (tile_id, offset, length, run_length), sorted by tile id
+    out = bytearray(varint(len(entries)))
+    last = 0
+    for tile, _, _, _ in entries:
+        out += varint(tile - last)
+        last = tile
+    for _, _, _, run_length in entries:
+        out += varint(run_length)
+    for _, _, length, _ in entries:
+        out += varint(length)
+    for i, (_, offset, _, _) in enumerate(entries):
+        previous = entries[i - 1] if i > 0 else None
+        if previous and offset == previous[1] + previous[2]:
+            out += varint(0)
+        else:
+            out += varint(offset + 1)
+    return bytes(out)
+
+
+def tile_entries():
+    # consecutive identical tiles collapse into one entry
+    entries = []
+    data = bytearray()
+    for tile, content in sorted(tiles()):
+        last = entries[-1] if entries else None
+        if last and last[0] + last[3] == tile and data[last[1] :] == content:
+            entries[-1] = (last[0], last[1], last[2], last[3] + 1)
+            continue
+        entries.append((tile, len(data), len(content), 1))
+        data += content
+    return entries, bytes(data)
+
+
+def header(root, leaves, data, entries, internal_compression):
+    root_offset = 127
+    metadata_offset = root_offset + len(root)
+    leaves_offset = metadata_offset
+    data_offset = leaves_offset + len(leaves)
+    addressed = sum(entry[3] for entry in entries)
+    return (
+        b"PMTiles"
+        + bytes([3])
+        + struct.pack(
+            "<9Q",
+            root_offset,
+            len(root),
+            metadata_offset,
+            0,
+            leaves_offset,
+            len(leaves),
+            data_offset,
+            len(data),
+            addressed,
+        )
+        + struct.pack("<2Q", len(entries), len(entries))
+        + bytes([1, internal_compression, NONE, PNG, 0, MAX_ZOOM])
+        + struct.pack("<4i", -1800000000, -850511287, 1800000000, 850511287)
+        + bytes([0])
+        + struct.pack("<2i", 0, 0)
+    )
+
+
+def write(path, root, leaves, data, entries, internal_compression):
+    with open(path, "wb") as f:
+        f.write(header(root, leaves, data, entries, internal_compression))
+        f.write(root)
+        f.write(leaves)
+        f.write(data)
+
+
+def root_only(path):
+    entries, data = tile_entries()
+    write(path, directory(entries), b"", data, entries, NONE)
+
+
+def with_leaves(path):
+    entries, data = tile_entries()
+    leaves = bytearray()
+    root_entries = []
+    for i in range(0, len(entries), LEAF_SIZE):
+        leaf = gzip.compress(directory(entries[i : i + LEAF_SIZE]), mtime=0)
+        root_entries.append((entries[i][0], len(leaves), len(leaf), 0))
+        leaves += leaf
+    root = gzip.compress(directory(root_entries), mtime=0)
+    write(path, root, bytes(leaves), data, entries, GZIP)
+
+
+if __name__ == "__main__":
+    root_only("root.pmtiles")
+    with_leaves("leaves.pmtiles")
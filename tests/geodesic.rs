@@ -0,0 +1,99 @@
+use hypersphere::systems::geospatial::geodesic::GeodesicSystem;
+
+// WGS84 solutions are checked to the last digit they're published with
+const MILLIMETER: f64 = 1e-3;
+const TENTH_MILLIMETER: f64 = 1e-4;
+
+fn dms(degrees: f64, minutes: f64, seconds: f64) -> f64 {
+    degrees.signum() * (degrees.abs() + minutes / 60.0 + seconds / 3600.0)
+}
+
+fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+    assert!(
+        (actual - expected).abs() <= tolerance,
+        "{actual} is not within {tolerance} of {expected}"
+    );
+}
+
+#[test]
+fn solves_flinders_peak_to_buninyong() {
+    // the line Geoscience Australia works Vincenty's formulae through
+    let flinders_peak = (dms(-37.0, 57.0, 3.72030), dms(144.0, 25.0, 29.52440));
+    let buninyong = (dms(-37.0, 39.0, 10.15610), dms(143.0, 55.0, 35.38390));
+    let second = 1.0 / 3600.0;
+
+    let inverse = GeodesicSystem::inverse(flinders_peak, buninyong);
+    assert_close(inverse.distance, 54_972.271, MILLIMETER);
+    assert_close(
+        inverse.initial_bearing,
+        dms(306.0, 52.0, 5.37),
+        0.01 * second,
+    );
+    assert_close(
+        inverse.final_bearing,
+        dms(307.0, 10.0, 25.07),
+        0.01 * second,
+    );
+
+    let direct = GeodesicSystem::direct(flinders_peak, inverse.initial_bearing, inverse.distance);
+    assert_close(direct.lat, buninyong.0, 1e-9);
+    assert_close(direct.lon, buninyong.1, 1e-9);
+}
+
+#[test]
+fn solves_karney_examples() {
+    // the direct and inverse examples of Karney, "Algorithms for geodesics"
+    // (2013), the second nearly antipodal, where Vincenty's iteration fails
+    let direct = GeodesicSystem::direct((40.0, 0.0), 30.0, 10_000_000.0);
+    assert_close(direct.lat, 41.793_310_205_06, 1e-10);
+    assert_close(direct.lon, 137.844_900_043_77, 1e-9);
+    assert_close(direct.final_bearing, 149.090_169_318_07, 1e-10);
+
+    let inverse = GeodesicSystem::inverse((-30.0, 0.0), (29.9, 179.8));
+    assert_close(inverse.distance, 19_989_832.827_61, TENTH_MILLIMETER);
+    assert_close(inverse.initial_bearing, 161.890_524_736, 1e-7);
+    assert_close(inverse.final_bearing, 18.090_737_246, 1e-7);
+}
+
+#[test]
+fn solves_antipodal_equator() {
+    // the shortest way between equatorial antipodes is over a pole, half a
+    // meridian long
+    let inverse = GeodesicSystem::inverse((0.0, 0.0), (0.0, 180.0));
+    assert_close(inverse.distance, 20_003_931.458_62, TENTH_MILLIMETER);
+    assert_eq!(inverse.initial_bearing, 0.0);
+    assert_eq!(inverse.final_bearing, 180.0);
+}
+
+#[test]
+fn keeps_bearings_below_360() {
+    // both round a hair under 0° up to 360° before reducing
+    let direct = GeodesicSystem::direct((10.0, 20.0), 360.0, 1000.0);
+    assert_eq!(direct.final_bearing, 0.0);
+    let direct = GeodesicSystem::direct((10.0, 20.0), -1e-15, 1000.0);
+    assert_eq!(direct.final_bearing, 0.0);
+}
+
+#[test]
+fn spaces_intermediate_points() {
+    let (from, to) = ((0.0, 0.0), (0.0, 10.0));
+    let length = GeodesicSystem::inverse(from, to).distance;
+    let points = GeodesicSystem::intermediate_points(from, to, 100_000.0);
+    assert_eq!(points.len(), (length / 100_000.0).ceil() as usize + 1);
+    assert_eq!((points[0], points[points.len() - 1]), (from, to));
+    for pair in points.windows(2) {
+        assert!(GeodesicSystem::inverse(pair[0], pair[1]).distance <= 100_000.0);
+    }
+
+    // spacings that can't be met are widened to the most pieces allowed
+    for spacing in [0.0, -1.0, f64::NAN, 1e-9] {
+        let points = GeodesicSystem::intermediate_points(from, to, spacing);
+        assert_eq!(points.len(), (1 << 16) + 1, "spacing {spacing}");
+    }
+    for spacing in [0.0, f64::NAN] {
+        assert_eq!(
+            GeodesicSystem::intermediate_points(from, from, spacing),
+            vec![from, from]
+        );
+    }
+}
@@ -0,0 +1,43 @@
+use hypersphere::{
+    resources::input::{HeldInputs, InputAction, InputTrigger},
+    systems::input::InputSystem,
+};
+use winit::event::VirtualKeyCode;
+
+const W: InputTrigger = InputTrigger::Key(VirtualKeyCode::W);
+const UP: InputTrigger = InputTrigger::Key(VirtualKeyCode::Up);
+
+#[test]
+fn holds_an_action_until_its_last_input_is_released() {
+    let mut held = HeldInputs::default();
+    assert!(InputSystem::hold(&mut held, W, InputAction::ZoomIn));
+    // a second key, or W repeating, doesn't start it again
+    assert!(!InputSystem::hold(&mut held, UP, InputAction::ZoomIn));
+    assert!(!InputSystem::hold(&mut held, W, InputAction::ZoomIn));
+
+    assert!(!InputSystem::release(&mut held, W, InputAction::ZoomIn));
+    assert!(InputSystem::is_held(&held, InputAction::ZoomIn));
+    assert!(InputSystem::release(&mut held, UP, InputAction::ZoomIn));
+    assert!(!InputSystem::is_held(&held, InputAction::ZoomIn));
+}
+
+#[test]
+fn ignores_releases_of_inputs_not_holding_the_action() {
+    let mut held = HeldInputs::default();
+    assert!(!InputSystem::release(&mut held, W, InputAction::ZoomIn));
+
+    assert!(InputSystem::hold(&mut held, W, InputAction::ZoomIn));
+    assert!(InputSystem::hold(
+        &mut held,
+        InputTrigger::Touch,
+        InputAction::OrbitLeft
+    ));
+    assert!(!InputSystem::release(&mut held, UP, InputAction::ZoomIn));
+    assert!(!InputSystem::release(&mut held, W, InputAction::OrbitLeft));
+    assert!(InputSystem::release(
+        &mut held,
+        InputTrigger::Touch,
+        InputAction::OrbitLeft
+    ));
+    assert!(InputSystem::is_held(&held, InputAction::ZoomIn));
+}
@@ -0,0 +1,91 @@
+use hypersphere::loader::Ktx2Texture;
+use wgpu::{AstcBlock, AstcChannel, TextureFormat};
+
+// a single-level 4x4 texture holding one block
+fn texture(format: TextureFormat, block: Vec<u8>) -> Ktx2Texture {
+    Ktx2Texture {
+        format,
+        width: 4,
+        height: 4,
+        faces: 1,
+        levels: vec![block],
+    }
+}
+
+fn decompress(format: TextureFormat, block: Vec<u8>) -> (TextureFormat, Vec<u8>) {
+    let texture = texture(format, block).decompress().unwrap();
+    (texture.format, texture.levels.into_iter().next().unwrap())
+}
+
+fn texel(texels: &[u8], x: usize, y: usize) -> &[u8] {
+    &texels[(y * 4 + x) * 4..][..4]
+}
+
+// `indices` of `bits` each, texel 0 in the lowest bits
+fn pack_indices(indices: &[u64], bits: u32) -> u64 {
+    indices
+        .iter()
+        .enumerate()
+        .fold(0, |packed, (i, &index)| packed | index << (bits * i as u32))
+}
+
+#[test]
+fn decodes_bc1() {
+    // pure red then pure blue, the larger first for four colors
+    let mut block = [0xf800u16.to_le_bytes(), 0x001fu16.to_le_bytes()].concat();
+    block.extend((pack_indices(&[0, 1, 2, 3], 2) as u32).to_le_bytes());
+    let (format, texels) = decompress(TextureFormat::Bc1RgbaUnormSrgb, block);
+    assert_eq!(format, TextureFormat::Rgba8UnormSrgb);
+    assert_eq!(texel(&texels, 0, 0), [255, 0, 0, 255]);
+    assert_eq!(texel(&texels, 1, 0), [0, 0, 255, 255]);
+    assert_eq!(texel(&texels, 2, 0), [170, 0, 85, 255]);
+    assert_eq!(texel(&texels, 3, 0), [85, 0, 170, 255]);
+
+    // the smaller first gives three colors and transparent black
+    let mut block = [0x001fu16.to_le_bytes(), 0xf800u16.to_le_bytes()].concat();
+    block.extend((pack_indices(&[2, 3], 2) as u32).to_le_bytes());
+    let (_, texels) = decompress(TextureFormat::Bc1RgbaUnorm, block);
+    assert_eq!(texel(&texels, 0, 0), [127, 0, 127, 255]);
+    assert_eq!(texel(&texels, 1, 0), [0, 0, 0, 0]);
+}
+
+#[test]
+fn decodes_bc4_and_bc5() {
+    // 127 down to -127 in eight steps, as snorm bytes
+    let mut block = vec![0x7f, 0x81];
+    block.extend(&pack_indices(&[0, 1, 2], 3).to_le_bytes()[..6]);
+    let (format, texels) = decompress(TextureFormat::Bc4RSnorm, block);
+    assert_eq!(format, TextureFormat::Rgba8Snorm);
+    assert_eq!(texel(&texels, 0, 0), [127, 0, 0, 127]);
+    assert_eq!(texel(&texels, 1, 0), [(-127i8) as u8, 0, 0, 127]);
+    assert_eq!(texel(&texels, 2, 0), [90, 0, 0, 127]);
+
+    // red with eight steps, green with six and the 0 and 255 extremes
+    let mut block = vec![255, 0];
+    block.extend(&pack_indices(&[2], 3).to_le_bytes()[..6]);
+    block.extend([0, 255]);
+    block.extend(&pack_indices(&[2, 6, 7], 3).to_le_bytes()[..6]);
+    let (format, texels) = decompress(TextureFormat::Bc5RgUnorm, block);
+    assert_eq!(format, TextureFormat::Rgba8Unorm);
+    assert_eq!(texel(&texels, 0, 0), [218, 51, 0, 255]);
+    assert_eq!(texel(&texels, 1, 0), [255, 0, 0, 255]);
+    assert_eq!(texel(&texels, 2, 0), [255, 255, 0, 255]);
+}
+
+#[test]
+fn leaves_other_formats_to_the_gpu() {
+    let astc = TextureFormat::Astc {
+        block: AstcBlock::B4x4,
+        channel: AstcChannel::Unorm,
+    };
+    for format in [
+        TextureFormat::Bc6hRgbUfloat,
+        TextureFormat::Bc7RgbaUnorm,
+        TextureFormat::Etc2Rgb8Unorm,
+        TextureFormat::EacR11Unorm,
+        astc,
+    ] {
+        let block = vec![0; format.block_size(None).unwrap() as usize];
+        assert!(texture(format, block).decompress().is_none(), "{format:?}");
+    }
+}
@@ -0,0 +1,31 @@
+use hypersphere::loader::{AssetLoader, LoadError, MemorySource};
+
+fn source(path: &str, bytes: &[u8]) -> MemorySource {
+    let mut source = MemorySource::new();
+    source.insert(path, bytes);
+    source
+}
+
+fn load(loader: &AssetLoader, path: &str) -> Result<Vec<u8>, LoadError> {
+    pollster::block_on(loader.load(path))
+}
+
+#[test]
+fn routes_to_the_longest_mount() {
+    let mut loader = AssetLoader::new();
+    loader.mount("textures", source("earth.jpg", b"earth"));
+    loader.mount("textures/bodies/", source("mars.jpg", b"mars"));
+
+    assert_eq!(load(&loader, "textures/earth.jpg").unwrap(), b"earth");
+    assert_eq!(load(&loader, "/textures/bodies/mars.jpg").unwrap(), b"mars");
+    // nested mounts don't fall back to the shorter one
+    assert!(matches!(
+        load(&loader, "textures/bodies/earth.jpg"),
+        Err(LoadError::NotFound { .. })
+    ));
+    // prefixes match whole segments
+    assert!(matches!(
+        load(&loader, "textures2/earth.jpg"),
+        Err(LoadError::NoSource { .. })
+    ));
+}
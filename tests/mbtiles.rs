@@ -0,0 +1,125 @@
+use std::path::{Path, PathBuf};
+
+use hypersphere::loader::{ArchiveLocation, LoadError, TileArchive};
+
+// fixtures written by tests/fixtures/make_mbtiles.py with 512-byte pages
+const PAGE_SIZE: usize = 512;
+const MAX_ZOOM: u8 = 4;
+const LARGE_TILE: (u8, u32, u32) = (2, 1, 2);
+
+const LEAF_OR_OVERFLOW: u8 = 0;
+const INTERIOR_INDEX: u8 = 2;
+const INTERIOR_TABLE: u8 = 5;
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures")).join(name)
+}
+
+fn open(path: &Path) -> Result<TileArchive, LoadError> {
+    pollster::block_on(TileArchive::open(ArchiveLocation::File(path.to_path_buf())))
+}
+
+fn tile_data(zoom: u8, x: u32, y: u32) -> Vec<u8> {
+    if (zoom, x, y) == LARGE_TILE {
+        return (0..3000).map(|i| (i % 251) as u8).collect();
+    }
+    format!("{zoom}/{x}/{y}").repeat(2).into_bytes()
+}
+
+fn check_tiles(archive: &TileArchive, expected: impl Fn(u8, u32, u32) -> Vec<u8>) {
+    for zoom in 0..=MAX_ZOOM {
+        for x in 0..1 << zoom {
+            for y in 0..1 << zoom {
+                let tile = pollster::block_on(archive.tile(zoom, x, y)).unwrap();
+                let expected = (zoom >= archive.min_zoom).then(|| expected(zoom, x, y));
+                assert_eq!(tile, expected, "tile {zoom}/{x}/{y}");
+            }
+        }
+    }
+    assert_eq!(
+        pollster::block_on(archive.tile(MAX_ZOOM + 1, 0, 0)).unwrap(),
+        None
+    );
+}
+
+// the offset of the b-tree header on page `number`, past the file header on page 1
+fn header(number: usize) -> usize {
+    (number - 1) * PAGE_SIZE + if number == 1 { 100 } else { 0 }
+}
+
+// a copy of `name` with `edit` applied to its bytes, in the temp directory
+fn corrupt_copy(name: &str, label: &str, edit: impl FnOnce(&mut Vec<u8>)) -> PathBuf {
+    let mut bytes = std::fs::read(fixture(name)).unwrap();
+    edit(&mut bytes);
+    let path = std::env::temp_dir().join(format!("hypersphere-{label}-{name}"));
+    std::fs::write(&path, bytes).unwrap();
+    path
+}
+
+fn pages_of_kind(bytes: &[u8], kind: u8) -> Vec<usize> {
+    (1..=bytes.len() / PAGE_SIZE)
+        .filter(|&number| bytes[header(number)] == kind)
+        .collect()
+}
+
+fn set_u32(bytes: &mut [u8], offset: usize, value: u32) {
+    bytes[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+}
+
+#[test]
+fn reads_tiles_table() {
+    let archive = open(&fixture("flat.mbtiles")).unwrap();
+    assert_eq!((archive.min_zoom, archive.max_zoom), (0, MAX_ZOOM));
+    check_tiles(&archive, tile_data);
+}
+
+#[test]
+fn reads_map_and_images_tables() {
+    let archive = open(&fixture("map_images.mbtiles")).unwrap();
+    assert_eq!((archive.min_zoom, archive.max_zoom), (1, MAX_ZOOM));
+    check_tiles(&archive, |zoom, x, y| {
+        if zoom == MAX_ZOOM && x % 2 == 0 {
+            b"blank".to_vec()
+        } else {
+            tile_data(zoom, x, y)
+        }
+    });
+}
+
+#[test]
+fn rejects_child_pointer_to_page_zero() {
+    let path = corrupt_copy("flat.mbtiles", "page-zero", |bytes| {
+        for number in pages_of_kind(bytes, INTERIOR_TABLE) {
+            set_u32(bytes, header(number) + 8, 0);
+        }
+    });
+    let archive = open(&path).unwrap();
+    // the last tile written has the largest rowid, under the right-most child
+    assert!(pollster::block_on(archive.tile(MAX_ZOOM, 15, 15)).is_err());
+}
+
+#[test]
+fn rejects_b_tree_cycle() {
+    let path = corrupt_copy("flat.mbtiles", "b-tree-cycle", |bytes| {
+        for number in pages_of_kind(bytes, INTERIOR_INDEX) {
+            set_u32(bytes, header(number) + 8, number as u32);
+        }
+    });
+    // the zoom range comes from the last index entry, down the right-most children
+    assert!(open(&path).is_err());
+}
+
+#[test]
+fn rejects_overflow_cycle() {
+    let path = corrupt_copy("flat.mbtiles", "overflow-cycle", |bytes| {
+        for number in pages_of_kind(bytes, LEAF_OR_OVERFLOW) {
+            let offset = (number - 1) * PAGE_SIZE;
+            if bytes[offset..offset + 4] != [0; 4] {
+                set_u32(bytes, offset, number as u32);
+            }
+        }
+    });
+    let archive = open(&path).unwrap();
+    let (zoom, x, y) = LARGE_TILE;
+    assert!(pollster::block_on(archive.tile(zoom, x, y)).is_err());
+}
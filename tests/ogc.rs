@@ -0,0 +1,247 @@
+use std::path::PathBuf;
+
+use hypersphere::{
+    components::{
+        imagery::GeoRectangle,
+        imagery_provider::{ImageryProvider, ImageryView},
+    },
+    loader::{with_query, ImageryCrs, LoadError, WmsCapabilities, WmtsCapabilities},
+    systems::imagery_provider::ImageryProviderSystem,
+};
+
+// trimmed capabilities documents, in tests/fixtures
+fn fixture(name: &str) -> Vec<u8> {
+    let path = PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures")).join(name);
+    std::fs::read(path).unwrap()
+}
+
+fn wms(name: &str, url: &str) -> WmsCapabilities {
+    WmsCapabilities::parse(&fixture(name), url).unwrap()
+}
+
+// the GetMap URL asked for to cover 10°W to 30°E and 20°N to 50°N
+fn get_map_url(capabilities: &WmsCapabilities, layer: &str) -> String {
+    let provider = ImageryProviderSystem::wms_provider(capabilities, layer).unwrap();
+    let view = ImageryView {
+        rectangle: GeoRectangle {
+            west: -10.0,
+            south: 20.0,
+            east: 30.0,
+            north: 50.0,
+        },
+        resolution: 0.1,
+    };
+    let request = ImageryProviderSystem::plan(&provider, &view).unwrap();
+    assert_eq!(request.pieces.len(), 1);
+    request.pieces[0].url.clone()
+}
+
+#[test]
+fn reads_wms_1_1_1_capabilities() {
+    let url = "https://demo.example.com/geoserver/wms?SERVICE=WMS&REQUEST=GetCapabilities";
+    let capabilities = wms("wms_1_1_1.xml", url);
+    assert_eq!(capabilities.version, "1.1.1");
+    // absolute links are kept as they are
+    assert_eq!(
+        capabilities.get_map_url,
+        "https://demo.example.com/geoserver/wms?SERVICE=WMS&"
+    );
+    assert_eq!(
+        capabilities.formats,
+        [
+            "image/png",
+            "application/atom+xml",
+            "image/gif",
+            "image/jpeg"
+        ]
+    );
+
+    // the unnamed root layer is only a container
+    let names: Vec<&str> = capabilities
+        .layers
+        .iter()
+        .map(|l| l.name.as_str())
+        .collect();
+    assert_eq!(names, ["topp:states", "nurc:Img_Sample"]);
+    let states = capabilities.layer("topp:states").unwrap();
+    assert_eq!(states.title, "USA Population");
+    assert_eq!(states.crs, ["EPSG:4326", "EPSG:3857"]);
+    assert_eq!(
+        states.bounds,
+        GeoRectangle {
+            west: -124.731_42,
+            south: 24.955967,
+            east: -66.969_85,
+            north: 49.371735,
+        }
+    );
+    // projections are inherited
+    let sample = capabilities.layer("nurc:Img_Sample").unwrap();
+    assert_eq!(sample.crs, ["EPSG:4326", "EPSG:3857"]);
+}
+
+#[test]
+fn reads_wms_1_3_0_capabilities() {
+    let url = "https://maps.example.org/cgi-bin/mapserv?map=/maps/global.map&SERVICE=WMS&REQUEST=GetCapabilities";
+    let capabilities = wms("wms_1_3_0.xml", url);
+    assert_eq!(capabilities.version, "1.3.0");
+    // the endpoint is given from the server's root
+    assert_eq!(
+        capabilities.get_map_url,
+        "https://maps.example.org/cgi-bin/mapserv?map=/maps/global.map&"
+    );
+    assert_eq!(
+        capabilities.formats,
+        ["image/jpeg", "image/png", "image/png; mode=8bit"]
+    );
+
+    let names: Vec<&str> = capabilities
+        .layers
+        .iter()
+        .map(|l| l.name.as_str())
+        .collect();
+    assert_eq!(names, ["global", "bluemarble", "sea_ice"]);
+    let sea_ice = capabilities.layer("sea_ice").unwrap();
+    assert_eq!(
+        sea_ice.crs,
+        ["EPSG:4326", "EPSG:3857", "EPSG:900913", "EPSG:3413"]
+    );
+    assert_eq!(sea_ice.bounds.south, 30.98);
+    assert_eq!(sea_ice.bounds.north, 90.0);
+}
+
+#[test]
+fn orders_wms_bbox_axes_by_version() {
+    // 1.1.1 takes EPSG:4326 longitude first
+    let capabilities = wms(
+        "wms_1_1_1.xml",
+        "https://demo.example.com/geoserver/wms?REQUEST=GetCapabilities",
+    );
+    let url = get_map_url(&capabilities, "topp:states");
+    assert!(
+        url.starts_with("https://demo.example.com/geoserver/wms?SERVICE=WMS&"),
+        "{url}"
+    );
+    assert!(url.contains("&VERSION=1.1.1&"), "{url}");
+    assert!(url.contains("&SRS=EPSG%3A4326&BBOX=-10,20,30,50&"), "{url}");
+
+    // and 1.3.0 latitude first
+    let capabilities = wms(
+        "wms_1_3_0.xml",
+        "https://maps.example.org/cgi-bin/mapserv?map=/maps/global.map&REQUEST=GetCapabilities",
+    );
+    let url = get_map_url(&capabilities, "bluemarble");
+    assert!(url.contains("&VERSION=1.3.0&"), "{url}");
+    assert!(url.contains("&CRS=EPSG%3A4326&BBOX=20,-10,50,30&"), "{url}");
+}
+
+#[test]
+fn reads_wmts_capabilities() {
+    let url = "https://demo.example.com/geoserver/gwc/service/wmts?REQUEST=GetCapabilities";
+    let capabilities = WmtsCapabilities::parse(&fixture("wmts.xml"), url).unwrap();
+    // the KVP endpoint, not the RESTful one
+    assert_eq!(
+        capabilities.get_tile_url.as_deref(),
+        Some("https://demo.example.com/geoserver/gwc/service/wmts?")
+    );
+
+    let layer = capabilities.layer("ne:world").unwrap();
+    assert_eq!(layer.title, "Natural Earth");
+    // the style marked default, not the first
+    assert_eq!(layer.style, "ne:world");
+    assert_eq!(layer.formats, ["image/png", "image/jpeg"]);
+    assert_eq!(layer.tile_matrix_sets, ["EPSG:4326", "EPSG:900913"]);
+    assert_eq!(layer.bounds, GeoRectangle::WORLD);
+    // the template is relative to the document
+    assert_eq!(
+        layer.resource_url.as_deref(),
+        Some(
+            "https://demo.example.com/geoserver/gwc/service/rest/ne:world/{style}/\
+             {TileMatrixSet}/{TileMatrixSet}:{TileMatrix}/{TileRow}/{TileCol}?format=image/png"
+        )
+    );
+}
+
+#[test]
+fn reads_wmts_tile_matrices() {
+    let url = "https://demo.example.com/geoserver/gwc/service/wmts?REQUEST=GetCapabilities";
+    let capabilities = WmtsCapabilities::parse(&fixture("wmts.xml"), url).unwrap();
+
+    // EPSG:4326 corners are written latitude first, and swapped to longitude first
+    let geographic = capabilities.tile_matrix_set("EPSG:4326").unwrap();
+    assert_eq!(geographic.crs, "urn:ogc:def:crs:EPSG::4326");
+    let identifiers: Vec<&str> = geographic
+        .matrices
+        .iter()
+        .map(|matrix| matrix.identifier.as_str())
+        .collect();
+    assert_eq!(identifiers, ["EPSG:4326:0", "EPSG:4326:1"]);
+    let matrix = &geographic.matrices[1];
+    assert_eq!(matrix.top_left, [-180.0, 90.0]);
+    assert_eq!(matrix.scale_denominator, 1.3977056600717944e8);
+    assert_eq!((matrix.tile_width, matrix.tile_height), (256, 256));
+    assert_eq!((matrix.matrix_width, matrix.matrix_height), (4, 2));
+
+    // projected corners are easting first already
+    let mercator = capabilities.tile_matrix_set("EPSG:900913").unwrap();
+    let matrix = &mercator.matrices[0];
+    assert_eq!(matrix.top_left, [-2.003750834e7, 2.0037508e7]);
+    assert_eq!(matrix.scale_denominator, 5.590822639508929e8);
+    assert_eq!((matrix.matrix_width, matrix.matrix_height), (1, 1));
+
+    // geographic tiles need no reprojecting, so they are preferred
+    let provider = ImageryProviderSystem::wmts_provider(&capabilities, "ne:world").unwrap();
+    let ImageryProvider::Wmts(provider) = provider else {
+        panic!("not a WMTS provider");
+    };
+    assert_eq!(provider.crs, ImageryCrs::Geographic);
+    assert_eq!(provider.tile_matrix_set.identifier, "EPSG:4326");
+}
+
+#[test]
+fn reports_bad_documents_as_decode_errors() {
+    let url = "https://example.com/wms?REQUEST=GetCapabilities";
+    for result in [
+        WmsCapabilities::parse(b"<WMS_Capabilities version=\"1.3.0\">", url).map(|_| ()),
+        WmsCapabilities::parse(&fixture("wmts.xml"), url).map(|_| ()),
+        WmtsCapabilities::parse(&fixture("wms_1_3_0.xml"), url).map(|_| ()),
+    ] {
+        match result {
+            Err(LoadError::Decode { path, .. }) => assert_eq!(path, url),
+            other => panic!("{other:?} is not a decode error"),
+        }
+    }
+}
+
+#[test]
+fn appends_encoded_query_parameters() {
+    let params = [
+        ("LAYERS", "a,b"),
+        ("STYLES", ""),
+        ("FORMAT", "image/png; mode=8bit"),
+    ];
+    let query = "LAYERS=a,b&STYLES=&FORMAT=image%2Fpng%3B%20mode%3D8bit";
+    assert_eq!(
+        with_query("https://example.com/wms", &params),
+        format!("https://example.com/wms?{query}")
+    );
+    assert_eq!(
+        with_query("https://example.com/wms?", &params),
+        format!("https://example.com/wms?{query}")
+    );
+    assert_eq!(
+        with_query("https://example.com/wms?map=a.map&", &params),
+        format!("https://example.com/wms?map=a.map&{query}")
+    );
+    assert_eq!(
+        with_query("https://example.com/wms?map=a.map", &params),
+        format!("https://example.com/wms?map=a.map&{query}")
+    );
+    assert_eq!(
+        with_query(
+            "https://example.com/wms",
+            &[("TIME", "2024-03-20T03:06:00Z")]
+        ),
+        "https://example.com/wms?TIME=2024-03-20T03%3A06%3A00Z"
+    );
+}
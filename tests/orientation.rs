@@ -0,0 +1,35 @@
+use anise::prelude::Epoch;
+use cgmath::{InnerSpace, Vector3};
+use hypersphere::systems::orientation::OrientationSystem;
+
+// where a direction over `longitude` degrees east on the equator points in
+// the scene: 0° lon faces -z and 90°E -x
+fn over_longitude(longitude: f64) -> Vector3<f64> {
+    let longitude = longitude.to_radians();
+    Vector3::new(-longitude.sin(), 0.0, -longitude.cos())
+}
+
+fn assert_close(actual: Vector3<f64>, expected: Vector3<f64>) {
+    assert!(
+        (actual - expected).magnitude() < 1e-6,
+        "{actual:?} is not {expected:?}"
+    );
+}
+
+#[test]
+fn turns_j2000_into_the_earth_fixed_scene() {
+    // at the J2000 epoch the IAU model puts the prime meridian 190.147° east
+    // of the equator's node at 90° right ascension, so the equinox lies over
+    // 79.853°E, and the pole hasn't yet moved
+    let j2000_to_scene = OrientationSystem::j2000_to_scene(None, Epoch::from_tdb_seconds(0.0));
+    assert_close(j2000_to_scene * Vector3::unit_x(), over_longitude(79.853));
+    assert_close(j2000_to_scene * Vector3::unit_z(), Vector3::unit_y());
+
+    // the sky moves west as the Earth turns east
+    let hour_later = OrientationSystem::j2000_to_scene(None, Epoch::from_tdb_seconds(3600.0));
+    let turned = 360.985_623_5 / 24.0;
+    assert_close(
+        hour_later * Vector3::unit_x(),
+        over_longitude(79.853 - turned),
+    );
+}
@@ -0,0 +1,160 @@
+use std::path::{Path, PathBuf};
+
+use hypersphere::loader::{tile_id, ArchiveLocation, LoadError, TileArchive};
+
+// fixtures written by tests/fixtures/make_pmtiles.py
+const MAX_ZOOM: u8 = 4;
+const LARGE_TILE: (u8, u32, u32) = (2, 1, 2);
+// every tile of this zoom is one run-length entry
+const RUN_ZOOM: u8 = 3;
+const RUN_DATA: &[u8] = b"blank";
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures")).join(name)
+}
+
+fn open(path: &Path) -> Result<TileArchive, LoadError> {
+    pollster::block_on(TileArchive::open(ArchiveLocation::File(path.to_path_buf())))
+}
+
+fn tile(archive: &TileArchive, (zoom, x, y): (u8, u32, u32)) -> Result<Option<Vec<u8>>, LoadError> {
+    pollster::block_on(archive.tile(zoom, x, y))
+}
+
+fn tile_data(zoom: u8, x: u32, y: u32) -> Vec<u8> {
+    if zoom == RUN_ZOOM {
+        return RUN_DATA.to_vec();
+    }
+    if (zoom, x, y) == LARGE_TILE {
+        return (0..3000).map(|i| (i % 251) as u8).collect();
+    }
+    format!("{zoom}/{x}/{y}").repeat(2).into_bytes()
+}
+
+fn check_tiles(archive: &TileArchive) {
+    assert_eq!((archive.min_zoom, archive.max_zoom), (0, MAX_ZOOM));
+    assert_eq!(archive.format, "png");
+    for zoom in 0..=MAX_ZOOM {
+        for x in 0..1 << zoom {
+            for y in 0..1 << zoom {
+                let expected = Some(tile_data(zoom, x, y));
+                assert_eq!(
+                    tile(archive, (zoom, x, y)).unwrap(),
+                    expected,
+                    "tile {zoom}/{x}/{y}"
+                );
+            }
+        }
+    }
+    assert_eq!(tile(archive, (MAX_ZOOM + 1, 0, 0)).unwrap(), None);
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+fn set_u64(bytes: &mut [u8], offset: usize, value: u64) {
+    bytes[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+// a copy of `name` with `edit` applied to its bytes, in the temp directory
+fn corrupt_copy(name: &str, label: &str, edit: impl FnOnce(&mut Vec<u8>)) -> PathBuf {
+    let mut bytes = std::fs::read(fixture(name)).unwrap();
+    edit(&mut bytes);
+    let path = std::env::temp_dir().join(format!("hypersphere-{label}-{name}"));
+    std::fs::write(&path, bytes).unwrap();
+    path
+}
+
+// header fields, as byte offsets
+const ROOT_OFFSET: usize = 8;
+const ROOT_LENGTH: usize = 16;
+const LEAVES_OFFSET: usize = 40;
+const LEAVES_LENGTH: usize = 48;
+
+#[test]
+fn numbers_tiles_along_the_hilbert_curve() {
+    assert_eq!(tile_id(0, 0, 0), 0);
+    // zoom 1 runs down the west side and back up the east
+    assert_eq!(tile_id(1, 0, 0), 1);
+    assert_eq!(tile_id(1, 0, 1), 2);
+    assert_eq!(tile_id(1, 1, 1), 3);
+    assert_eq!(tile_id(1, 1, 0), 4);
+    assert_eq!(tile_id(2, 0, 0), 5);
+    assert_eq!(tile_id(2, 3, 0), 20);
+    assert_eq!(tile_id(3, 0, 0), 21);
+}
+
+#[test]
+fn reads_tiles_from_the_root_directory() {
+    check_tiles(&open(&fixture("root.pmtiles")).unwrap());
+}
+
+#[test]
+fn reads_tiles_through_leaf_directories() {
+    let archive = open(&fixture("leaves.pmtiles")).unwrap();
+    check_tiles(&archive);
+    // again, from the cached leaves
+    check_tiles(&archive);
+}
+
+#[test]
+fn reads_every_tile_of_a_run() {
+    let archive = open(&fixture("root.pmtiles")).unwrap();
+    let size = 1 << RUN_ZOOM;
+    for x in 0..size {
+        for y in 0..size {
+            let data = tile(&archive, (RUN_ZOOM, x, y)).unwrap();
+            assert_eq!(data.as_deref(), Some(RUN_DATA), "tile {RUN_ZOOM}/{x}/{y}");
+        }
+    }
+    // the tiles either side of the run have their own data
+    for (zoom, x, y) in [(RUN_ZOOM - 1, 3, 0), (RUN_ZOOM + 1, 0, 0)] {
+        let data = tile(&archive, (zoom, x, y)).unwrap();
+        assert_eq!(data, Some(tile_data(zoom, x, y)));
+    }
+    assert_eq!(tile_id(RUN_ZOOM - 1, 3, 0) + 1, tile_id(RUN_ZOOM, 0, 0));
+    assert_eq!(tile_id(RUN_ZOOM + 1, 0, 0), tile_id(RUN_ZOOM, 7, 0) + 1);
+}
+
+#[test]
+fn rejects_truncated_directory() {
+    let path = corrupt_copy("root.pmtiles", "truncated", |bytes| {
+        let length = u64_at(bytes, ROOT_LENGTH);
+        set_u64(bytes, ROOT_LENGTH, length / 2);
+    });
+    assert!(matches!(open(&path), Err(LoadError::Decode { .. })));
+}
+
+#[test]
+fn rejects_corrupt_leaf_directory() {
+    let path = corrupt_copy("leaves.pmtiles", "corrupt-leaf", |bytes| {
+        let offset = u64_at(bytes, LEAVES_OFFSET) as usize;
+        let length = u64_at(bytes, LEAVES_LENGTH) as usize;
+        bytes[offset..offset + length].fill(0xff);
+    });
+    // the root is intact, but no leaf decompresses
+    let archive = open(&path).unwrap();
+    assert!(matches!(
+        tile(&archive, (0, 0, 0)),
+        Err(LoadError::Decode { .. })
+    ));
+}
+
+#[test]
+fn rejects_leaf_directory_loop() {
+    let path = corrupt_copy("root.pmtiles", "leaf-loop", |bytes| {
+        // a root holding one leaf entry for every tile, pointing back at itself
+        let offset = u64_at(bytes, ROOT_OFFSET);
+        let directory = [1, 0, 0, 5, 1];
+        let start = offset as usize;
+        bytes[start..start + directory.len()].copy_from_slice(&directory);
+        set_u64(bytes, ROOT_LENGTH, directory.len() as u64);
+        set_u64(bytes, LEAVES_OFFSET, offset);
+    });
+    let archive = open(&path).unwrap();
+    assert!(matches!(
+        tile(&archive, (0, 0, 0)),
+        Err(LoadError::Decode { .. })
+    ));
+}
@@ -0,0 +1,134 @@
+use std::str::FromStr;
+
+use anise::prelude::Epoch;
+use hypersphere::{
+    components::imagery::{GeoRectangle, ImageryLayerSource, ImageryLayerStyle},
+    scene::{
+        SceneBillboard, SceneCamera, SceneDocument, SceneError, SceneImageryLayer, ScenePolyline,
+        SceneTime, SCENE_VERSION,
+    },
+};
+
+// one of everything a scene holds, with values that don't survive a lossy
+// float or epoch round trip
+fn scene() -> SceneDocument {
+    let layer = |globe: &str, source| SceneImageryLayer {
+        globe: globe.to_string(),
+        source,
+        style: ImageryLayerStyle::default(),
+    };
+    SceneDocument {
+        version: SCENE_VERSION,
+        camera: SceneCamera {
+            eye: [12_345.678, -0.1, 6_378.137],
+            target: [0.0, 0.0, 0.0],
+            up: [0.0, 1.0, 0.0],
+            fovy: 45.0,
+        },
+        time: SceneTime {
+            epoch: Epoch::from_str("2024-03-20T03:06:00.123 UTC").unwrap(),
+            multiplier: -3600.5,
+            paused: true,
+        },
+        billboards: vec![
+            SceneBillboard {
+                lat: 51.4779,
+                lon: -0.0015,
+            },
+            SceneBillboard {
+                lat: -33.8568,
+                lon: 151.2153,
+            },
+        ],
+        polylines: vec![ScenePolyline {
+            coordinates: vec![(40.6413, -73.7781), (51.47, -0.4543)],
+            color: [1.0, 0.5, 0.0, 1.0],
+        }],
+        polygons: vec![ScenePolyline {
+            coordinates: vec![(0.0, 0.0), (0.0, 10.0), (10.0, 10.0)],
+            color: [0.1, 0.2, 0.3, 0.4],
+        }],
+        imagery_layers: vec![
+            layer(
+                "earth",
+                ImageryLayerSource::Image {
+                    path: "textures/overlay.png".to_string(),
+                },
+            ),
+            layer(
+                "earth",
+                ImageryLayerSource::Wms {
+                    capabilities_url: "https://example.com/wms?SERVICE=WMS".to_string(),
+                    layer: "clouds".to_string(),
+                },
+            ),
+            layer(
+                "moon",
+                ImageryLayerSource::Wmts {
+                    capabilities_url: "https://example.com/wmts".to_string(),
+                    layer: "lro".to_string(),
+                },
+            ),
+            SceneImageryLayer {
+                globe: "mars".to_string(),
+                source: ImageryLayerSource::Archive {
+                    path: "tiles/mars.pmtiles".to_string(),
+                },
+                style: ImageryLayerStyle {
+                    alpha: 0.75,
+                    hue: 12.5,
+                    show: false,
+                    rectangle: GeoRectangle {
+                        west: -10.0,
+                        south: -5.0,
+                        east: 20.0,
+                        north: 15.0,
+                    },
+                    ..ImageryLayerStyle::default()
+                },
+            },
+        ],
+    }
+}
+
+#[test]
+fn round_trips_through_json() {
+    let scene = scene();
+    let text = scene.to_json().unwrap();
+    assert_eq!(SceneDocument::from_json(&text).unwrap(), scene);
+}
+
+#[test]
+fn round_trips_through_ron() {
+    let scene = scene();
+    let text = scene.to_ron().unwrap();
+    assert_eq!(SceneDocument::from_ron(&text).unwrap(), scene);
+}
+
+#[test]
+fn reads_documents_without_annotations() {
+    let text = r#"{
+        "version": 1,
+        "camera": { "eye": [0, 0, 20000], "target": [0, 0, 0], "up": [0, 1, 0], "fovy": 45 },
+        "time": { "epoch": "2000-01-01T12:00:00 UTC", "multiplier": 1, "paused": false }
+    }"#;
+    let scene = SceneDocument::from_json(text).unwrap();
+    assert!(scene.billboards.is_empty());
+    assert!(scene.polylines.is_empty());
+    assert!(scene.polygons.is_empty());
+    assert!(scene.imagery_layers.is_empty());
+}
+
+#[test]
+fn reports_the_format_of_bad_text() {
+    let json = SceneDocument::from_json("{").unwrap_err();
+    assert!(
+        matches!(json, SceneError::Format { format: "JSON", .. }),
+        "{json}"
+    );
+    let ron = SceneDocument::from_ron("(version: 1").unwrap_err();
+    assert!(
+        matches!(ron, SceneError::Format { format: "RON", .. }),
+        "{ron}"
+    );
+}